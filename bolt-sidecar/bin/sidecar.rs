@@ -1,6 +1,7 @@
 use std::time::Duration;
 
 use alloy_rpc_types_beacon::events::HeadEvent;
+use beacon_api_client::BlockId;
 use tokio::sync::mpsc;
 
 use bolt_sidecar::{
@@ -11,7 +12,9 @@ use bolt_sidecar::{
         SignedConstraints,
     },
     start_builder_proxy_server, start_rpc_server,
-    state::{ConsensusState, ExecutionState, HeadTracker, StateClient},
+    state::{
+        CommitmentLog, CommitmentRecord, ConsensusState, ExecutionState, HeadTracker, StateClient,
+    },
     BeaconClient, BuilderProxyConfig, Config, ConstraintsApi, LocalBuilder, MevBoostClient,
 };
 
@@ -33,6 +36,25 @@ async fn main() -> eyre::Result<()> {
     let mevboost_client = MevBoostClient::new(config.mevboost_url.clone());
     let beacon_client = BeaconClient::new(config.beacon_api_url.clone());
 
+    // Commitments loaded from the log are replayed before serving any request,
+    // for the slots after the current head.
+    let mut commitment_log = match &config.commitment_log_path {
+        Some(path) => {
+            let (log, records) = CommitmentLog::open(path)?;
+            tracing::info!(?path, count = records.len(), "Loaded commitment log");
+
+            let head = beacon_client.get_beacon_header(BlockId::Head).await?;
+            let head_slot = head.header.message.slot;
+            let replayed = execution_state
+                .replay_commitments(&records, head_slot)
+                .await;
+            tracing::info!(replayed, head_slot, "Replayed commitments from the log");
+
+            Some(log)
+        }
+        None => None,
+    };
+
     let (api_events, mut api_events_rx) = mpsc::channel(1024);
     let shutdown_tx = start_rpc_server(&config, api_events).await?;
     let mut consensus_state = ConsensusState::new(
@@ -49,6 +71,7 @@ async fn main() -> eyre::Result<()> {
     let builder_proxy_config = BuilderProxyConfig {
         mevboost_url: config.mevboost_url.clone(),
         server_port: config.mevboost_proxy_port,
+        constraint_signers: config.constraint_signers.clone(),
     };
 
     let (payload_tx, mut payload_rx) = mpsc::channel(16);
@@ -95,9 +118,28 @@ async fn main() -> eyre::Result<()> {
                 );
 
                 // parse the request into constraints and sign them with the sidecar signer
-                let message = ConstraintsMessage::build(validator_index, request.slot, request);
+                let message = ConstraintsMessage::build(validator_index, request.slot, request.clone());
 
                 let signature = signer.sign(&message.digest())?.to_string();
+
+                if let Some(mut log) = commitment_log.take() {
+                    let record = CommitmentRecord::new(&request, signature.clone());
+
+                    // Flushing the log to disk blocks, so keep it off the async workers
+                    let (log, res) = tokio::task::spawn_blocking(move || {
+                        let res = log.append(&record);
+                        (log, res)
+                    })
+                    .await?;
+                    commitment_log = Some(log);
+
+                    // A commitment that can't be replayed after a restart isn't issued
+                    if let Err(e) = res {
+                        tracing::error!(err = ?e, "Failed to persist commitment");
+                        let _ = response_tx.send(Err(ApiError::Custom(e.to_string())));
+                        continue;
+                    }
+                }
                 let signed_constraints = vec![SignedConstraints { message, signature }];
 
                 // TODO: fix retry logic
@@ -1,7 +1,7 @@
 use axum::{
-    body::{self, Body},
+    body::{self, Body, Bytes},
     extract::{Path, Request, State},
-    http::StatusCode,
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
     response::Html,
     routing::{get, post},
     Json, Router,
@@ -20,12 +20,16 @@ use std::{sync::Arc, time::Duration};
 use tokio::net::TcpListener;
 
 use super::spec::{
-    BuilderApi, BuilderApiError, ConstraintsApi, GET_HEADER_PATH, GET_PAYLOAD_PATH,
-    REGISTER_VALIDATORS_PATH, STATUS_PATH,
+    BuilderApi, BuilderApiError, ConstraintsApi, SpecError, GET_HEADER_PATH, GET_PAYLOAD_PATH,
+    REGISTER_VALIDATORS_PATH, STATUS_PATH, SUBMIT_CONSTRAINTS_PATH,
 };
 use crate::{
     client::mevboost::MevBoostClient,
-    primitives::{GetPayloadResponse, PayloadFetcher, SignedBuilderBid},
+    crypto::bls::BlsPublicKey as ConstraintSignerKey,
+    primitives::{
+        BatchedSignedConstraints, GetPayloadResponse, PayloadFetcher, SignedBuilderBid,
+        SignedConstraints,
+    },
 };
 
 const MAX_BLINDED_BLOCK_LENGTH: usize = 1024 * 1024;
@@ -43,6 +47,8 @@ pub struct BuilderProxyServer<T: BuilderApi, P> {
     local_payload: Mutex<Option<GetPayloadResponse>>,
     /// The payload fetcher to get locally built payloads.
     payload_fetcher: P,
+    /// The public keys allowed to sign the submitted constraints.
+    constraint_signers: Vec<ConstraintSignerKey>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -63,9 +69,17 @@ where
             proxy_target,
             local_payload: Mutex::new(None),
             payload_fetcher,
+            constraint_signers: Vec::new(),
         }
     }
 
+    /// Set the public keys allowed to sign the submitted constraints. Without any,
+    /// every submission is rejected.
+    pub fn with_constraint_signers(mut self, signers: Vec<ConstraintSignerKey>) -> Self {
+        self.constraint_signers = signers;
+        self
+    }
+
     /// Gets the status. Just forwards the request to mev-boost and returns the status.
     pub async fn status(State(server): State<Arc<BuilderProxyServer<T, P>>>) -> StatusCode {
         let start = std::time::Instant::now();
@@ -92,7 +106,7 @@ where
     pub async fn register_validators(
         State(server): State<Arc<BuilderProxyServer<T, P>>>,
         Json(registrations): Json<Vec<SignedValidatorRegistration>>,
-    ) -> Result<StatusCode, BuilderApiError> {
+    ) -> Result<StatusCode, SpecError> {
         let start = std::time::Instant::now();
         tracing::debug!("Received register validators request");
 
@@ -101,7 +115,7 @@ where
         let elapsed = start.elapsed();
         tracing::debug!(?elapsed, "Returning response: {:?}", response);
 
-        response.map(|_| StatusCode::OK)
+        response.map(|_| StatusCode::OK).map_err(SpecError::from)
     }
 
     /// Gets the header. NOTE: converts this request to a get_header_with_proofs
@@ -112,7 +126,7 @@ where
     pub async fn get_header(
         State(server): State<Arc<BuilderProxyServer<T, P>>>,
         Path(params): Path<GetHeaderParams>,
-    ) -> Result<Json<VersionedValue<SignedBuilderBid>>, BuilderApiError> {
+    ) -> Result<Json<VersionedValue<SignedBuilderBid>>, SpecError> {
         let start = std::time::Instant::now();
 
         tracing::debug!("Received get_header request");
@@ -147,7 +161,7 @@ where
                 // which means we haven't made any commitments. This means the beacon client should
                 // fallback to local block building.
                 tracing::error!("No local payload produced for slot {slot}");
-                return Err(BuilderApiError::FailedToFetchLocalPayload(slot));
+                return Err(SpecError::NoPayload(slot));
             }
         };
 
@@ -171,10 +185,63 @@ where
         Ok(Json(versioned_bid))
     }
 
+    /// Accepts the signed constraints of the proposers, as JSON or as SSZ with the
+    /// `application/octet-stream` content type, and forwards them to mev-boost so that
+    /// builders can honor them.
+    ///
+    /// Every message must be signed by one of the constraint signers, otherwise the
+    /// whole batch is rejected.
+    pub async fn submit_constraints(
+        State(server): State<Arc<BuilderProxyServer<T, P>>>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Result<StatusCode, SpecError> {
+        let start = std::time::Instant::now();
+        tracing::debug!("Received submit constraints request");
+
+        let is_ssz = headers
+            .get(CONTENT_TYPE)
+            .is_some_and(|value| value.as_bytes().starts_with(b"application/octet-stream"));
+
+        let constraints = if is_ssz {
+            SignedConstraints::decode_ssz_batch(&body)
+                .map_err(|e| SpecError::InvalidRequest(format!("invalid SSZ: {e}")))?
+        } else {
+            serde_json::from_slice::<BatchedSignedConstraints>(&body)
+                .map_err(|e| SpecError::InvalidRequest(e.to_string()))?
+        };
+
+        if let Some(invalid) = constraints.iter().find(|signed| {
+            !server
+                .constraint_signers
+                .iter()
+                .any(|signer| signed.verify_signature(signer))
+        }) {
+            tracing::warn!(
+                slot = invalid.message.slot,
+                "Rejecting constraints with an invalid signature"
+            );
+            return Err(SpecError::InvalidSignature);
+        }
+
+        server
+            .proxy_target
+            .submit_constraints(&constraints)
+            .await
+            .map_err(|e| {
+                tracing::error!(error = %e, "Failed to submit constraints to mev-boost");
+                SpecError::from(e)
+            })?;
+
+        tracing::debug!(elapsed = ?start.elapsed(), count = constraints.len(), "Submitted constraints");
+
+        Ok(StatusCode::OK)
+    }
+
     pub async fn get_payload(
         State(server): State<Arc<BuilderProxyServer<T, P>>>,
         req: Request<Body>,
-    ) -> Result<Json<GetPayloadResponse>, BuilderApiError> {
+    ) -> Result<Json<GetPayloadResponse>, SpecError> {
         let start = std::time::Instant::now();
         tracing::debug!("Received get_payload request");
 
@@ -182,14 +249,14 @@ where
             .await
             .map_err(|e| {
                 tracing::error!(error = %e, "Failed to read request body");
-                e
+                SpecError::InvalidRequest(e.to_string())
             })?;
 
         // Convert to signed blinded beacon block
         let signed_blinded_block = serde_json::from_slice::<SignedBlindedBeaconBlock>(&body_bytes)
             .map_err(|e| {
                 tracing::error!(error = %e, "Failed to parse signed blinded block");
-                e
+                SpecError::InvalidRequest(e.to_string())
             })?;
 
         // If we have a locally built payload, return it and clear the cache.
@@ -212,7 +279,8 @@ where
                 return Err(BuilderApiError::InvalidLocalPayloadBlockHash {
                     expected: requested_block.to_string(),
                     have: payload.block_hash().to_string(),
-                });
+                }
+                .into());
             };
 
             tracing::info!("Local block found, returning: {payload:?}");
@@ -228,7 +296,7 @@ where
             .map(Json)
             .map_err(|e| {
                 tracing::error!(elapsed = ?start.elapsed(), error = %e, "Failed to get payload from mev-boost");
-                e
+                SpecError::from(e)
             })?;
 
         tracing::debug!(elapsed = ?start.elapsed(), "Returning payload");
@@ -244,6 +312,8 @@ pub struct BuilderProxyConfig {
     pub mevboost_url: Url,
     /// The port on which the builder proxy should listen.
     pub server_port: u16,
+    /// The public keys allowed to sign the constraints submitted to the proxy.
+    pub constraint_signers: Vec<ConstraintSignerKey>,
 }
 
 /// Start the builder proxy with the given payload fetcher and configuration.
@@ -261,9 +331,24 @@ where
     );
 
     let mev_boost = MevBoostClient::new(config.mevboost_url);
-    let server = Arc::new(BuilderProxyServer::new(mev_boost, payload_fetcher));
+    let server = Arc::new(
+        BuilderProxyServer::new(mev_boost, payload_fetcher)
+            .with_constraint_signers(config.constraint_signers),
+    );
 
-    let router = Router::new()
+    let listener = TcpListener::bind(format!("0.0.0.0:{}", config.server_port)).await?;
+    axum::serve(listener, builder_proxy_router(server)).await?;
+
+    Ok(())
+}
+
+/// The routes of the builder proxy, served by the given server.
+fn builder_proxy_router<T, P>(server: Arc<BuilderProxyServer<T, P>>) -> Router
+where
+    T: ConstraintsApi + Send + Sync + 'static,
+    P: PayloadFetcher + Send + Sync + 'static,
+{
+    Router::new()
         .route("/", get(index))
         .route(STATUS_PATH, get(BuilderProxyServer::status))
         .route(
@@ -272,14 +357,321 @@ where
         )
         .route(GET_HEADER_PATH, get(BuilderProxyServer::get_header))
         .route(GET_PAYLOAD_PATH, post(BuilderProxyServer::get_payload))
-        .with_state(server);
-
-    let listener = TcpListener::bind(format!("0.0.0.0:{}", config.server_port)).await?;
-    axum::serve(listener, router).await?;
-
-    Ok(())
+        .route(
+            SUBMIT_CONSTRAINTS_PATH,
+            post(BuilderProxyServer::submit_constraints),
+        )
+        .with_state(server)
 }
 
 async fn index() -> Html<&'static str> {
     Html("Hello")
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy_primitives::U256;
+    use axum::{
+        body::{Body, Bytes},
+        extract::State,
+        http::{header::CONTENT_TYPE, HeaderMap, Request, StatusCode},
+    };
+    use beacon_api_client::VersionedValue;
+    use ethereum_consensus::{
+        builder::SignedValidatorRegistration,
+        deneb::mainnet::SignedBlindedBeaconBlock,
+        primitives::BlsPublicKey,
+        ssz::prelude::{ssz_rs, List},
+        Fork,
+    };
+    use parking_lot::Mutex;
+    use tower::ServiceExt;
+
+    use super::{builder_proxy_router, BuilderProxyServer, GetHeaderParams};
+    use crate::{
+        api::spec::{
+            BuilderApi, BuilderApiError, ConstraintsApi, ErrorResponse, SpecError,
+            SUBMIT_CONSTRAINTS_PATH,
+        },
+        crypto::{
+            bls::{from_bls_signature_to_consensus_signature, random_bls_secret},
+            SignableBLS,
+        },
+        primitives::{
+            constraint::{
+                Constraint, SszBatchedSignedConstraints, SszConstraint, SszConstraintsMessage,
+                SszSignedConstraints,
+            },
+            BatchedSignedConstraints, BuilderBid, ConstraintsMessage, GetPayloadResponse,
+            NoopPayloadFetcher, SignedBuilderBid, SignedConstraints,
+        },
+        test_util::test_bls_secret_key,
+    };
+
+    /// A mev-boost recording the submitted constraints.
+    #[derive(Debug, Default)]
+    struct MockMevBoost {
+        constraints: Mutex<Vec<BatchedSignedConstraints>>,
+    }
+
+    #[async_trait::async_trait]
+    impl BuilderApi for MockMevBoost {
+        async fn status(&self) -> Result<StatusCode, BuilderApiError> {
+            Ok(StatusCode::OK)
+        }
+
+        async fn register_validators(
+            &self,
+            _registrations: Vec<SignedValidatorRegistration>,
+        ) -> Result<(), BuilderApiError> {
+            Err(BuilderApiError::FailedRegisteringValidators(unsupported()))
+        }
+
+        async fn get_header(
+            &self,
+            _params: GetHeaderParams,
+        ) -> Result<SignedBuilderBid, BuilderApiError> {
+            Err(BuilderApiError::FailedGettingHeader(unsupported()))
+        }
+
+        async fn get_payload(
+            &self,
+            _signed_block: SignedBlindedBeaconBlock,
+        ) -> Result<GetPayloadResponse, BuilderApiError> {
+            Err(BuilderApiError::FailedGettingPayload(unsupported()))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ConstraintsApi for MockMevBoost {
+        async fn submit_constraints(
+            &self,
+            constraints: &BatchedSignedConstraints,
+        ) -> Result<(), BuilderApiError> {
+            self.constraints.lock().push(constraints.clone());
+            Ok(())
+        }
+
+        async fn get_header_with_proofs(
+            &self,
+            _params: GetHeaderParams,
+        ) -> Result<VersionedValue<SignedBuilderBid>, BuilderApiError> {
+            Err(BuilderApiError::FailedGettingHeader(unsupported()))
+        }
+    }
+
+    /// The error of the endpoints not covered by the mock.
+    fn unsupported() -> ErrorResponse {
+        ErrorResponse::new(501, "not supported by the mock")
+    }
+
+    type TestServer = Arc<BuilderProxyServer<MockMevBoost, NoopPayloadFetcher>>;
+
+    fn test_server() -> TestServer {
+        let signer = test_bls_secret_key().sk_to_pk();
+        let server = BuilderProxyServer::new(MockMevBoost::default(), NoopPayloadFetcher)
+            .with_constraint_signers(vec![signer]);
+
+        Arc::new(server)
+    }
+
+    /// Sign constraints for the given slot with the given key.
+    fn signed_constraints(slot: u64, key: &blst::min_pk::SecretKey) -> SignedConstraints {
+        let message = ConstraintsMessage {
+            validator_index: 42,
+            slot,
+            constraints: vec![
+                Constraint {
+                    tx: "0x02deadbeef".to_string(),
+                    index: None,
+                },
+                Constraint {
+                    tx: "0x02c0ffee".to_string(),
+                    index: Some(1),
+                },
+            ],
+        };
+        let signature = format!("0x{}", hex::encode(message.sign(key).to_bytes()));
+
+        SignedConstraints { message, signature }
+    }
+
+    /// The SSZ encoding of the given signed constraints.
+    fn to_ssz(constraints: &[SignedConstraints]) -> Vec<u8> {
+        let batch = constraints
+            .iter()
+            .map(|signed| {
+                let constraints = signed
+                    .message
+                    .constraints
+                    .iter()
+                    .map(|constraint| SszConstraint {
+                        tx: hex::decode(&constraint.tx[2..])
+                            .unwrap()
+                            .as_slice()
+                            .try_into()
+                            .unwrap(),
+                        index: List::try_from(constraint.index.into_iter().collect::<Vec<_>>())
+                            .unwrap(),
+                    })
+                    .collect::<Vec<_>>();
+
+                SszSignedConstraints {
+                    message: SszConstraintsMessage {
+                        validator_index: signed.message.validator_index,
+                        slot: signed.message.slot,
+                        constraints: List::try_from(constraints).unwrap(),
+                    },
+                    signature: from_bls_signature_to_consensus_signature(
+                        hex::decode(&signed.signature[2..]).unwrap(),
+                    ),
+                }
+            })
+            .collect::<Vec<_>>();
+        let batch = SszBatchedSignedConstraints::try_from(batch).unwrap();
+
+        ssz_rs::serialize(&batch).unwrap()
+    }
+
+    async fn submit(
+        server: &TestServer,
+        content_type: &str,
+        body: Vec<u8>,
+    ) -> Result<StatusCode, SpecError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, content_type.parse().unwrap());
+
+        BuilderProxyServer::submit_constraints(State(server.clone()), headers, Bytes::from(body))
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_submit_constraints_json_and_ssz() {
+        let server = test_server();
+        let key = test_bls_secret_key();
+        let constraints = vec![signed_constraints(10, &key), signed_constraints(11, &key)];
+
+        let json = serde_json::to_vec(&constraints).unwrap();
+        let status = submit(&server, "application/json", json).await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+
+        let ssz = to_ssz(&constraints);
+        let status = submit(&server, "application/octet-stream", ssz)
+            .await
+            .unwrap();
+        assert_eq!(status, StatusCode::OK);
+
+        // Both encodings are forwarded as the same constraints
+        let submitted = server.proxy_target.constraints.lock().clone();
+        assert_eq!(submitted, [constraints.clone(), constraints]);
+    }
+
+    #[tokio::test]
+    async fn test_submit_constraints_route() {
+        let server = test_server();
+        let key = test_bls_secret_key();
+
+        let post = |content_type: &str, body: Vec<u8>| {
+            let request = Request::post(SUBMIT_CONSTRAINTS_PATH)
+                .header(CONTENT_TYPE, content_type)
+                .body(Body::from(body))
+                .unwrap();
+            builder_proxy_router(server.clone()).oneshot(request)
+        };
+
+        let constraints = vec![signed_constraints(10, &key)];
+        let response = post("application/octet-stream", to_ssz(&constraints))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(*server.proxy_target.constraints.lock(), [constraints]);
+
+        let unknown = vec![signed_constraints(10, &random_bls_secret())];
+        let json = serde_json::to_vec(&unknown).unwrap();
+        let response = post("application/json", json).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(server.proxy_target.constraints.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_submit_constraints_rejects_invalid_signatures() {
+        let server = test_server();
+        let key = test_bls_secret_key();
+
+        // Signed by an unknown key
+        let constraints = vec![
+            signed_constraints(10, &key),
+            signed_constraints(11, &random_bls_secret()),
+        ];
+        let json = serde_json::to_vec(&constraints).unwrap();
+        let err = submit(&server, "application/json", json).await.unwrap_err();
+        assert_eq!(err, SpecError::InvalidSignature);
+
+        // Tampered with after signing
+        let mut tampered = signed_constraints(10, &key);
+        tampered.message.slot = 12;
+        let err = submit(&server, "application/octet-stream", to_ssz(&[tampered]))
+            .await
+            .unwrap_err();
+        assert_eq!(err, SpecError::InvalidSignature);
+
+        // Malformed signature
+        let mut malformed = signed_constraints(10, &key);
+        malformed.signature = "0x1234".to_string();
+        let json = serde_json::to_vec(&[malformed]).unwrap();
+        let err = submit(&server, "application/json", json).await.unwrap_err();
+        assert_eq!(err, SpecError::InvalidSignature);
+
+        assert!(server.proxy_target.constraints.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_submit_constraints_rejects_malformed_bodies() {
+        let server = test_server();
+
+        let err = submit(&server, "application/json", b"{}".to_vec())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SpecError::InvalidRequest(_)));
+
+        let err = submit(&server, "application/octet-stream", vec![1, 2, 3])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SpecError::InvalidRequest(_)));
+    }
+
+    #[test]
+    fn test_get_header_response_roundtrip() {
+        let key = test_bls_secret_key();
+        let public_key = BlsPublicKey::try_from(key.sk_to_pk().to_bytes().as_ref()).unwrap();
+        let signature = key.sign(b"bid", &[], &[]);
+
+        let bid = VersionedValue::<SignedBuilderBid> {
+            version: Fork::Deneb,
+            data: SignedBuilderBid {
+                message: BuilderBid {
+                    value: U256::from(1_000_000_000u64),
+                    public_key,
+                    ..Default::default()
+                },
+                signature: from_bls_signature_to_consensus_signature(signature.to_bytes()),
+            },
+            meta: Default::default(),
+        };
+
+        // The builder-specs encode the fork in lowercase and the bid value as a decimal string
+        let json = serde_json::to_value(&bid).unwrap();
+        assert_eq!(json["version"], "deneb");
+        assert_eq!(json["data"]["message"]["value"], "1000000000");
+        assert!(json["data"]["message"]["header"]["block_hash"].is_string());
+
+        let decoded: VersionedValue<SignedBuilderBid> =
+            serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(decoded.version, Fork::Deneb);
+        assert_eq!(decoded.data.message.value, bid.data.message.value);
+        assert_eq!(decoded.data.message.public_key, bid.data.message.public_key);
+        assert_eq!(serde_json::to_value(&decoded).unwrap(), json);
+    }
+}
@@ -7,7 +7,7 @@ use beacon_api_client::VersionedValue;
 use ethereum_consensus::{
     builder::SignedValidatorRegistration, deneb::mainnet::SignedBlindedBeaconBlock,
 };
-use serde::{Deserialize, Serialize, Serializer};
+use serde::{Deserialize, Serialize};
 
 use crate::primitives::{BatchedSignedConstraints, GetPayloadResponse, SignedBuilderBid};
 
@@ -23,21 +23,122 @@ pub const GET_HEADER_PATH: &str = "/eth/v1/builder/header/:slot/:parent_hash/:pu
 pub const GET_PAYLOAD_PATH: &str = "/eth/v1/builder/blinded_blocks";
 /// The path to the constraints API submit constraints endpoint.
 pub const CONSTRAINTS_PATH: &str = "/eth/v1/builder/constraints";
+/// The path to the endpoint of the constraints API receiving the signed constraints
+/// of the proposers, as JSON or SSZ.
+pub const SUBMIT_CONSTRAINTS_PATH: &str = "/constraints/v1/builder/constraints";
 
-/// A response object for errors.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A response object for errors, as defined by the builder-specs:
+/// `{ "code": <u16>, "message": <string> }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ErrorResponse {
-    #[serde(serialize_with = "serialize_status_code")]
     code: u16,
     message: String,
 }
 
-/// Helper to serialize a status code as a string using the provided serializer.
-pub fn serialize_status_code<S>(value: &u16, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    serializer.serialize_str(&value.to_string())
+impl ErrorResponse {
+    /// Create a new error response with the given HTTP status code and message.
+    pub fn new(code: u16, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// The HTTP status code of the error.
+    pub fn code(&self) -> u16 {
+        self.code
+    }
+
+    /// The error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Errors returned by the sidecar API handlers. They are rendered as an [ErrorResponse]
+/// with the matching HTTP status code, except for [SpecError::NoPayload] which has no body.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum SpecError {
+    /// The requested slot is invalid, e.g. in the past.
+    #[error("invalid slot: {0}")]
+    InvalidSlot(u64),
+    /// The validator with the given public key is not known.
+    #[error("unknown validator: {0}")]
+    UnknownValidator(String),
+    /// The signature of the request is invalid.
+    #[error("invalid signature")]
+    InvalidSignature,
+    /// The request could not be parsed or is inconsistent.
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+    /// No payload is available for the given slot.
+    #[error("no payload available for slot {0}")]
+    NoPayload(u64),
+    /// The upstream request timed out.
+    #[error("request timed out")]
+    Timeout,
+    /// The error returned by the upstream builder API, forwarded as is.
+    #[error("{}", .0.message)]
+    Upstream(ErrorResponse),
+    /// An internal error.
+    #[error("internal error: {0}")]
+    Internal(String),
+}
+
+impl SpecError {
+    /// The HTTP status code of the error.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::InvalidSlot(_)
+            | Self::UnknownValidator(_)
+            | Self::InvalidSignature
+            | Self::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            Self::NoPayload(_) => StatusCode::NO_CONTENT,
+            Self::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            Self::Upstream(error) => {
+                StatusCode::from_u16(error.code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// The error response sent in the body.
+    pub fn to_error_response(&self) -> ErrorResponse {
+        ErrorResponse::new(self.status_code().as_u16(), self.to_string())
+    }
+}
+
+impl IntoResponse for SpecError {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        if status == StatusCode::NO_CONTENT {
+            return status.into_response();
+        }
+
+        (status, Json(self.to_error_response())).into_response()
+    }
+}
+
+impl From<BuilderApiError> for SpecError {
+    fn from(err: BuilderApiError) -> Self {
+        match err {
+            BuilderApiError::FailedRegisteringValidators(error)
+            | BuilderApiError::FailedGettingHeader(error)
+            | BuilderApiError::FailedGettingPayload(error)
+            | BuilderApiError::FailedSubmittingConstraints(error) => Self::Upstream(error),
+            BuilderApiError::FailedToFetchLocalPayload(slot) => Self::NoPayload(slot),
+            BuilderApiError::AxumError(err) => Self::InvalidRequest(err.to_string()),
+            BuilderApiError::JsonError(err) => Self::InvalidRequest(err.to_string()),
+            BuilderApiError::Timeout(_) => Self::Timeout,
+            err @ BuilderApiError::InvalidLocalPayloadBlockHash { .. } => {
+                Self::InvalidRequest(err.to_string())
+            }
+            err @ (BuilderApiError::ReqwestError(_) | BuilderApiError::InvalidFork(_)) => {
+                Self::Internal(err.to_string())
+            }
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -70,47 +171,7 @@ pub enum BuilderApiError {
 
 impl IntoResponse for BuilderApiError {
     fn into_response(self) -> Response {
-        match self {
-            BuilderApiError::FailedRegisteringValidators(error) => {
-                (StatusCode::from_u16(error.code).unwrap(), Json(error)).into_response()
-            }
-            BuilderApiError::FailedGettingHeader(error) => {
-                (StatusCode::from_u16(error.code).unwrap(), Json(error)).into_response()
-            }
-            BuilderApiError::FailedGettingPayload(error) => {
-                (StatusCode::from_u16(error.code).unwrap(), Json(error)).into_response()
-            }
-            BuilderApiError::FailedSubmittingConstraints(error) => {
-                (StatusCode::from_u16(error.code).unwrap(), Json(error)).into_response()
-            }
-            BuilderApiError::AxumError(err) => {
-                (StatusCode::BAD_REQUEST, err.to_string()).into_response()
-            }
-            BuilderApiError::JsonError(err) => {
-                (StatusCode::BAD_REQUEST, err.to_string()).into_response()
-            }
-            BuilderApiError::FailedToFetchLocalPayload(_) => {
-                (StatusCode::NO_CONTENT, self.to_string()).into_response()
-            }
-            BuilderApiError::ReqwestError(_) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                StatusCode::INTERNAL_SERVER_ERROR
-                    .canonical_reason()
-                    .unwrap(),
-            )
-                .into_response(),
-            BuilderApiError::Timeout(_) => (
-                StatusCode::GATEWAY_TIMEOUT,
-                StatusCode::GATEWAY_TIMEOUT.canonical_reason().unwrap(),
-            )
-                .into_response(),
-            BuilderApiError::InvalidFork(err) => {
-                (StatusCode::BAD_REQUEST, Json(err)).into_response()
-            }
-            BuilderApiError::InvalidLocalPayloadBlockHash { .. } => {
-                (StatusCode::BAD_REQUEST, self.to_string()).into_response()
-            }
-        }
+        SpecError::from(self).into_response()
     }
 }
 
@@ -151,3 +212,68 @@ pub trait ConstraintsApi: BuilderApi {
         params: GetHeaderParams,
     ) -> Result<VersionedValue<SignedBuilderBid>, BuilderApiError>;
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::{body, http::StatusCode, response::IntoResponse};
+
+    use super::{ErrorResponse, SpecError};
+
+    /// Render the error and return its status and body.
+    async fn render(err: SpecError) -> (StatusCode, Vec<u8>) {
+        let response = err.into_response();
+        let status = response.status();
+        let body = body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        (status, body.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_spec_error_envelope() {
+        let (status, body) = render(SpecError::InvalidSignature).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            serde_json::json!({ "code": 400, "message": "invalid signature" })
+        );
+
+        let (status, body) = render(SpecError::InvalidSlot(42)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            serde_json::from_slice::<ErrorResponse>(&body).unwrap(),
+            ErrorResponse::new(400, "invalid slot: 42")
+        );
+
+        let (status, _) = render(SpecError::Internal("boom".to_string())).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+
+        // No content responses have no body
+        let (status, body) = render(SpecError::NoPayload(42)).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_upstream_error_is_forwarded() {
+        let upstream = ErrorResponse::new(503, "relay unavailable");
+
+        let (status, body) = render(SpecError::Upstream(upstream.clone())).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            serde_json::from_slice::<ErrorResponse>(&body).unwrap(),
+            upstream
+        );
+    }
+
+    #[test]
+    fn test_error_response_roundtrip() {
+        let json = r#"{"code":400,"message":"unknown validator: 0xab"}"#;
+
+        let error = serde_json::from_str::<ErrorResponse>(json).unwrap();
+        assert_eq!(error.code(), 400);
+        assert_eq!(error.message(), "unknown validator: 0xab");
+        assert_eq!(serde_json::to_string(&error).unwrap(), json);
+    }
+}
@@ -1,31 +1,208 @@
 //! Module that defines the [CallTraceManager] actor, which is responsible for
 //! handling trace requests for transactions and accumulating the state diffs
 //! for each block that is traced.
+//!
+//! With the `metrics` feature, the actor exposes the following gauges:
+//!
+//! - `bolt_sidecar_trace_in_flight`: the number of trace calls in flight.
+//! - `bolt_sidecar_trace_queued_transactions`: the number of transactions waiting to be
+//!   traced, across all blocks (including the ones buffered for future blocks).
+//! - `bolt_sidecar_trace_tracked_blocks`: the number of blocks with diffs, requests or
+//!   errors held by the actor.
+//! - `bolt_sidecar_trace_waiting_fetchers`: the number of fetch requests waiting for diffs.
+//!
+//! And the following counter:
+//!
+//! - `bolt_sidecar_trace_diff_copies_total`: the number of times the accumulated diffs of a
+//!   block were copied on merge, because a trace in flight or a snapshot still shared them.
 
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    num::NonZeroUsize,
+    ops::RangeInclusive,
     pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
+    time::{Duration, Instant},
 };
 
-use alloy_primitives::{BlockNumber, U64};
+use alloy_eips::BlockNumberOrTag;
+use alloy_json_rpc::RpcError;
+use alloy_primitives::{keccak256, Address, BlockNumber, Bytes, B256, U256, U64};
 use alloy_rpc_types::{
     state::{AccountOverride, StateOverride},
-    TransactionRequest,
+    BlockOverrides, TransactionRequest,
 };
 use alloy_rpc_types_trace::geth::{
-    AccountState, GethDebugBuiltInTracerType, GethDebugTracerType, GethDebugTracingCallOptions,
-    GethDebugTracingOptions, GethDefaultTracingOptions, GethTrace, PreStateFrame,
+    AccountState, DiffMode, GethDebugBuiltInTracerType, GethDebugTracerType,
+    GethDebugTracingCallOptions, GethDebugTracingOptions, GethDefaultTracingOptions, GethTrace,
+    PreStateConfig, PreStateFrame, PreStateMode, UnexpectedTracerError,
+};
+use alloy_transport::{TransportError, TransportErrorKind, TransportResult};
+use futures::{
+    stream::{self, FuturesUnordered},
+    Future, FutureExt, Stream, StreamExt,
 };
-use alloy_transport::TransportResult;
-use futures::{stream::FuturesOrdered, Future, StreamExt};
+use lru::LruCache;
 use reqwest::Url;
+use serde::Serialize;
 use tokio::{
-    sync::{mpsc, oneshot},
-    task::JoinHandle,
+    sync::{
+        mpsc::{self, error::TrySendError},
+        oneshot,
+    },
+    task::{AbortHandle, JoinHandle},
+    time::Interval,
 };
+use tracing::{Instrument, Span};
+
+#[cfg(feature = "diff-store")]
+use std::sync::{Mutex, PoisonError};
+
+#[cfg(feature = "diff-store")]
+use super::diff_store::DiffStore;
+use crate::{BlockHashes, BlockTicker, ExecutionRpc, RevertReason, RpcClient};
+
+/// The default maximum number of blocks tracked by the [CallTraceManager].
+pub const DEFAULT_MAX_TRACKED_BLOCKS: usize = 64;
+
+/// The default capacity of the command channel of the [CallTraceManager].
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 512;
+
+/// The number of snapshots buffered for a [CallTraceHandle::subscribe_diffs] subscriber.
+pub const DIFF_SUBSCRIPTION_CAPACITY: usize = 64;
+
+/// The default maximum number of trace calls in flight at once in the [CallTraceManager].
+pub const DEFAULT_MAX_CONCURRENT_TRACES: usize = 16;
+
+/// The default number of trace results cached by the [CallTraceManager].
+pub const DEFAULT_TRACE_CACHE_SIZE: usize = 1024;
+
+/// The gauge of the number of trace calls in flight.
+#[cfg(feature = "metrics")]
+pub const TRACE_IN_FLIGHT: &str = "bolt_sidecar_trace_in_flight";
+/// The gauge of the number of transactions waiting to be traced.
+#[cfg(feature = "metrics")]
+pub const TRACE_QUEUED_TRANSACTIONS: &str = "bolt_sidecar_trace_queued_transactions";
+/// The gauge of the number of blocks tracked by the actor.
+#[cfg(feature = "metrics")]
+pub const TRACE_TRACKED_BLOCKS: &str = "bolt_sidecar_trace_tracked_blocks";
+/// The gauge of the number of fetch requests waiting for diffs.
+#[cfg(feature = "metrics")]
+pub const TRACE_WAITING_FETCHERS: &str = "bolt_sidecar_trace_waiting_fetchers";
+/// The counter of the copies of the accumulated diffs of a block made on merge.
+#[cfg(feature = "metrics")]
+pub const TRACE_DIFF_COPIES_TOTAL: &str = "bolt_sidecar_trace_diff_copies_total";
+
+/// The tracer used by the [CallTraceManager] when tracing transactions.
+///
+/// Only [TracerKind::PreStateDiff] produces results that are compatible with
+/// [GethTrace::try_into_pre_state_frame], and thus can be accumulated into
+/// the per-block state diffs. The other tracers return arbitrary JSON values,
+/// which fail the block with [TraceError::InvalidTrace] unless they happen to
+/// be pre-state frames.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TracerKind {
+    /// The built-in `prestateTracer`, which returns the state of all accounts
+    /// and storage slots touched by the transaction.
+    PreStateDiff,
+    /// A JS tracer that returns the storage root of the transaction's `from` address.
+    #[default]
+    StorageRoot,
+    /// A custom JS tracer with the given code.
+    Custom(String),
+}
+
+impl TracerKind {
+    /// Returns the [GethDebugTracerType] for this tracer, given the transaction to trace.
+    fn tracer_type(&self, transaction: &TransactionRequest) -> GethDebugTracerType {
+        match self {
+            Self::PreStateDiff => {
+                GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::PreStateTracer)
+            }
+            Self::StorageRoot => GethDebugTracerType::JsTracer(format!(
+                r#"{{
+                    data: [],
+                    result: function(ctx, db) {{
+                        var root = db.GetStorageRoot("{:x}");
+                        return root;
+                    }},
+                }}"#,
+                transaction.from.unwrap_or_default()
+            )),
+            Self::Custom(code) => GethDebugTracerType::JsTracer(code.clone()),
+        }
+    }
+}
+
+/// The options of the `debug_traceCall` requests of the [CallTraceManager].
+///
+/// The defaults only capture the storage, which is all the `prestateTracer` needs.
+/// Enabling the other captures is mostly useful with a struct-log [TracerKind::Custom]
+/// tracer, e.g. to debug a reverting commitment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceOptionsConfig {
+    /// Capture the EVM memory. Defaults to `false`.
+    pub memory: bool,
+    /// Capture the EVM stack. Defaults to `false`.
+    pub stack: bool,
+    /// Capture the storage. Defaults to `true`.
+    pub storage: bool,
+    /// Capture the return data. Defaults to `false`.
+    pub return_data: bool,
+    /// The maximum gas of the traced transactions. Transactions without a gas limit, or
+    /// with a higher one, are traced with this one instead. Defaults to no cap.
+    pub gas_cap: Option<u64>,
+    /// The timeout of JS tracers, as a duration string like "60s". Defaults to the
+    /// node's own timeout.
+    pub timeout: Option<String>,
+    /// Run the `prestateTracer` in diff mode, which reports the state after the
+    /// transaction as well. The accumulated diffs then hold the state left by the
+    /// transactions, rather than the state they touched. Defaults to `false`.
+    pub diff_mode: bool,
+}
+
+impl Default for TraceOptionsConfig {
+    fn default() -> Self {
+        Self {
+            memory: false,
+            stack: false,
+            storage: true,
+            return_data: false,
+            gas_cap: None,
+            timeout: None,
+            diff_mode: false,
+        }
+    }
+}
+
+impl TraceOptionsConfig {
+    /// Caps the gas of the given transaction to [TraceOptionsConfig::gas_cap], if set.
+    fn cap_gas(&self, transaction: &mut TransactionRequest) {
+        if let Some(cap) = self.gas_cap.map(u128::from) {
+            transaction.gas = Some(transaction.gas.map_or(cap, |gas| gas.min(cap)));
+        }
+    }
+}
+
+/// The block a trace request targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockTarget {
+    /// The given block number.
+    Number(BlockNumber),
+    /// The next block, i.e. the current head + 1. It is resolved when the request
+    /// is received, and the trace is then keyed by the resolved number.
+    Pending,
+}
 
-use crate::RpcClient;
+impl From<BlockNumber> for BlockTarget {
+    fn from(block: BlockNumber) -> Self {
+        Self::Number(block)
+    }
+}
 
 /// Commands to interact with the [CallTraceManager] actor
 #[derive(Debug)]
@@ -37,272 +214,5642 @@ pub enum TraceCommand {
         /// The transaction to trace
         transaction: TransactionRequest,
         /// The block in which the transaction should be simulated on
+        block: BlockTarget,
+        /// The tracer to use for this transaction. If `None`, the
+        /// default tracer of the [CallTraceManager] is used.
+        tracer: Option<TracerKind>,
+        /// Overrides of the block header fields (e.g. the base fee) to apply
+        /// while tracing the transaction.
+        block_overrides: Option<BlockOverrides>,
+    },
+    /// Request to trace a transaction's execution on the current simulation
+    /// target block, i.e. `head + head_offset`. A target after the head is traced
+    /// right away on the state of the head, with the number of the target block.
+    ///
+    /// The resolved block is sent back through the response channel, or `None`
+    /// if the head of the chain is not known yet.
+    AddTraceAtHead {
+        /// The transaction to trace
+        transaction: TransactionRequest,
+        /// The tracer to use for this transaction. If `None`, the
+        /// default tracer of the [CallTraceManager] is used.
+        tracer: Option<TracerKind>,
+        /// The oneshot channel to receive the resolved target block
+        res: oneshot::Sender<Option<BlockNumber>>,
+    },
+    /// Request to trace a transaction's execution on the pending block, i.e. the current
+    /// head + 1, like [TraceCommand::AddTrace] with [BlockTarget::Pending].
+    ///
+    /// The block resolved for this request is sent back through the response channel,
+    /// or the reason why the request was dropped.
+    AddPendingTrace {
+        /// The transaction to trace
+        transaction: TransactionRequest,
+        /// The tracer to use for this transaction. If `None`, the
+        /// default tracer of the [CallTraceManager] is used.
+        tracer: Option<TracerKind>,
+        /// The oneshot channel to receive the resolved block
+        res: oneshot::Sender<Result<BlockNumber, PendingTraceError>>,
+    },
+    /// Seed the accumulated diffs of the given block with externally supplied state, e.g.
+    /// the state left by a prior slot, so that the next traces on the block run on top of it.
+    ///
+    /// The overrides are merged into the diffs accumulated so far, taking precedence over
+    /// them. Traces already in flight on the block don't see the seeded state.
+    SeedDiff {
+        /// The block of the accumulated diffs to seed
         block: BlockNumber,
+        /// The state to merge into the accumulated diffs
+        overrides: StateOverride,
+    },
+    /// Update the current head of the chain, used to resolve the simulation target block.
+    UpdateHead {
+        /// The new head block number
+        head: BlockNumber,
     },
     /// Request to get the accumulated state diffs for a bundle of transactions
     /// that were previously simulated on the given block.
     ///
     /// The result is sent back through a response channel as soon as the last
-    /// pending trace request for that block has been processed.
+    /// pending trace request for that block has been processed. If any trace on
+    /// the block failed, the error is sent instead.
+    ///
+    /// This consumes the accumulated diffs: the next trace on the block starts from
+    /// the block state again. Use [TraceCommand::PeekAccumulatedDiffs] to keep them.
+    /// Concurrent fetches of the same block all receive the same result.
     FetchAccumulatedDiffs {
         /// The block of the accumulated diffs to fetch
         block: BlockNumber,
+        /// The id of the request, to cancel it with [TraceCommand::CancelFetch]
+        id: u64,
+        /// The oneshot channel to receive the accumulated diffs
+        res: FetchSender,
+    },
+    /// Request the state diffs accumulated on every block of the given inclusive range,
+    /// as a consistent snapshot across blocks.
+    ///
+    /// The result is sent once no trace is pending on any block of the range anymore.
+    /// Blocks without diffs, e.g. because a trace failed, are omitted from the map.
+    ///
+    /// Unlike [TraceCommand::FetchAccumulatedDiffs], this doesn't consume the diffs.
+    FetchAccumulatedDiffsRange {
+        /// The first block of the range
+        from: BlockNumber,
+        /// The last block of the range, included
+        to: BlockNumber,
+        /// The oneshot channel to receive the accumulated diffs of each block
+        res: oneshot::Sender<HashMap<BlockNumber, StateOverride>>,
+    },
+    /// Request a copy of the state diffs accumulated so far on the given block, without
+    /// consuming them, so that more transactions can be traced on top of them afterwards.
+    ///
+    /// Unlike [TraceCommand::FetchAccumulatedDiffs], the result is sent immediately, even
+    /// if traces are still in flight: it then only contains the traces completed so far.
+    /// It's empty if nothing was traced on the block, or if a trace on the block failed.
+    PeekAccumulatedDiffs {
+        /// The block of the accumulated diffs to peek
+        block: BlockNumber,
         /// The oneshot channel to receive the accumulated diffs
-        res: oneshot::Sender<Option<StateOverride>>,
+        res: oneshot::Sender<StateOverride>,
+    },
+    /// Subscribe to the state diffs accumulated on the given block: a snapshot is sent
+    /// after each trace is merged, and the channel is closed once no trace is left
+    /// pending on the block. It's closed right away if none is pending.
+    SubscribeDiffs {
+        /// The block of the accumulated diffs to subscribe to
+        block: BlockNumber,
+        /// The channel to receive the accumulated diffs snapshots
+        tx: mpsc::Sender<StateOverride>,
+    },
+    /// Request the progress of the traces of the given block, e.g. for a status endpoint.
+    /// The result is sent immediately, without consuming the accumulated diffs.
+    BlockStatus {
+        /// The block to inspect
+        block: BlockNumber,
+        /// The oneshot channel to receive the status of the block
+        res: oneshot::Sender<BlockTraceStatus>,
+    },
+    /// Request the addresses touched by the transactions traced so far on the given block,
+    /// without cloning the accumulated state diffs.
+    TouchedAddresses {
+        /// The block of the accumulated diffs to inspect
+        block: BlockNumber,
+        /// The oneshot channel to receive the touched addresses
+        res: oneshot::Sender<Vec<Address>>,
+    },
+    /// Request the accounts touched by the transactions traced so far on the given block,
+    /// split between contracts and externally owned accounts, e.g. to detect conflicts
+    /// with other bundles targeting the same block.
+    TouchedAccounts {
+        /// The block of the accumulated diffs to inspect
+        block: BlockNumber,
+        /// The oneshot channel to receive the touched accounts
+        res: oneshot::Sender<TouchedAccounts>,
+    },
+    /// Check the transactions traced on the given block for nonce collisions, nonce gaps and
+    /// senders that can't pay for all their transactions, which would make the bundle
+    /// unincludable.
+    ///
+    /// Like [TraceCommand::FetchAccumulatedDiffs], the result is sent once the pending
+    /// traces of the block have completed. `None` is sent instead if a trace on the
+    /// block failed, or if the block is removed before its traces complete.
+    ValidateBundle {
+        /// The block of the bundle to validate
+        block: BlockNumber,
+        /// The oneshot channel to receive the validation result
+        res: oneshot::Sender<Option<BundleValidation>>,
     },
+    /// Summarize the transactions traced on the given block per sender: their cumulative
+    /// value and maximum gas cost, and the nonce of the sender after them, e.g. to check
+    /// that each sender can pay for its transactions before admitting the bundle.
+    ///
+    /// Like [TraceCommand::ValidateBundle], the result is sent once the pending traces
+    /// of the block have completed, and `None` is sent instead if a trace on the block
+    /// failed or if the block is removed before its traces complete.
+    SummarizeBundle {
+        /// The block of the bundle to summarize
+        block: BlockNumber,
+        /// The oneshot channel to receive the summaries, in order of first transaction
+        res: oneshot::Sender<Option<Vec<SenderSummary>>>,
+    },
+    /// Cancel the trace of a transaction on the given block, e.g. after its bundle was
+    /// withdrawn. The transaction is removed from the queues, and its trace is aborted
+    /// if it is in flight, without merging its result in the accumulated diffs.
+    CancelTrace {
+        /// The block the transaction was traced on
+        block: BlockNumber,
+        /// The [trace_request_hash] of the transaction to cancel
+        request_hash: B256,
+    },
+    /// Remove a transaction from the given block. If its trace was already merged, the
+    /// traces merged after it ran on top of it: their diffs are dropped, and they are
+    /// traced again in order without it, along with the trace in flight on the block.
+    /// Otherwise, its trace is cancelled like with [TraceCommand::CancelTrace].
+    RemoveTrace {
+        /// The block the transaction was traced on
+        block: BlockNumber,
+        /// The [trace_request_hash] of the transaction to remove
+        request_hash: B256,
+    },
+    /// Cancel a pending [TraceCommand::FetchAccumulatedDiffs] request for the given block,
+    /// dropping its response channel. The accumulated diffs and the other fetch requests
+    /// of the block are kept untouched.
+    CancelFetch {
+        /// The block of the pending fetch request to cancel
+        block: BlockNumber,
+        /// The id of the fetch request to cancel
+        id: u64,
+    },
+    /// Discard the accumulated diffs and pending trace requests of the given block,
+    /// e.g. to start fresh after a bundle was rejected. A waiting fetcher receives
+    /// [TraceError::Cleared].
+    ClearBlock {
+        /// The block to clear
+        block: BlockNumber,
+    },
+    /// Invalidate the given block and all the blocks above it, e.g. after a reorg:
+    /// their accumulated diffs and pending trace requests are dropped, and any
+    /// waiting fetcher receives [TraceError::Reorged].
+    InvalidateFrom {
+        /// The lowest block to invalidate
+        block: BlockNumber,
+    },
+    /// Gracefully shut down the actor: new trace requests are rejected, the pending
+    /// traces are completed, and the outstanding fetch requests are answered with
+    /// whatever diffs are available before the actor future resolves.
+    Shutdown,
 }
 
-/// The handle to control the [CallTraceManager] actor in a
-/// thread-safe, non-blocking way.
-#[derive(Debug, Clone)]
-pub struct CallTraceHandle {
-    cmd_tx: mpsc::Sender<TraceCommand>,
-}
+/// Error returned when the [CallTraceManager] actor is not running anymore,
+/// and thus can't receive commands or answer requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("the call trace manager actor is not running")]
+pub struct TraceActorGone;
 
-impl CallTraceHandle {
-    /// Request the trace for the given transaction on the provided block
-    pub async fn add_trace(&self, transaction: TransactionRequest, block: BlockNumber) {
-        let _ = self
-            .cmd_tx
-            .send(TraceCommand::AddTrace { transaction, block })
-            .await;
-    }
+/// Errors returned when a trace request on the pending block is dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum PendingTraceError {
+    /// The head of the chain is not known yet, so the pending block can't be resolved.
+    #[error("the head of the chain is not known yet")]
+    UnknownHead,
+    /// A trace on the resolved block failed, so the request would run on incomplete diffs.
+    #[error("a trace on block {block} failed")]
+    FailedBlock {
+        /// The resolved block
+        block: BlockNumber,
+    },
+    /// The actor is shutting down and rejects new trace requests.
+    #[error("the call trace manager is shutting down")]
+    ShuttingDown,
+    /// The actor is not running anymore.
+    #[error(transparent)]
+    ActorGone(#[from] TraceActorGone),
+}
 
-    /// Request the accumulated state diffs for a given block from previously
-    /// traced transactions.
+/// Errors that can occur when fetching the accumulated state diffs of a block.
+#[derive(Debug, thiserror::Error)]
+pub enum TraceError {
+    /// A trace call on the block failed. The accumulated diffs of the block are
+    /// incomplete, so they were discarded along with the remaining trace requests.
+    #[error("failed to trace transaction on block {block}: {source}")]
+    Rpc {
+        /// The block of the failed trace
+        block: BlockNumber,
+        /// The underlying RPC error
+        #[source]
+        source: TransportError,
+    },
+    /// A trace on the block returned a result that is not a pre-state frame, e.g. because
+    /// its tracer isn't [TracerKind::PreStateDiff]. Its state diff can't be accumulated,
+    /// so the block was failed like on an RPC error.
+    #[error("invalid trace result on block {block}: {reason}")]
+    InvalidTrace {
+        /// The block of the invalid trace
+        block: BlockNumber,
+        /// Why the trace result couldn't be parsed
+        reason: String,
+    },
+    /// A trace on the block reported that the transaction reverted or failed, e.g. in the
+    /// frame of the `callTracer` or of a custom tracer returning one. The trace doesn't
+    /// carry a state diff, so the block was failed like on an RPC error.
     ///
-    /// If the diffs are not available yet, this function
-    /// will hang until the last transaction has been processed and the diffs are ready.
-    pub async fn fetch_accumulated_diffs(&self, block: BlockNumber) -> Option<StateOverride> {
-        let (res_tx, res_rx) = oneshot::channel();
-        let _ = self
-            .cmd_tx
-            .send(TraceCommand::FetchAccumulatedDiffs { block, res: res_tx })
-            .await;
+    /// The built-in `prestateTracer` doesn't report reverts: the state left by a
+    /// reverted transaction is accumulated like any other.
+    #[error(
+        "transaction reverted on block {block}: {}",
+        .reason.as_ref().map_or_else(|| "unknown reason".to_string(), ToString::to_string)
+    )]
+    Reverted {
+        /// The block of the reverted trace
+        block: BlockNumber,
+        /// The reason decoded from the output of the trace, if any
+        reason: Option<RevertReason>,
+    },
+    /// The block was evicted before its accumulated diffs were fetched.
+    #[error("block {block} was evicted before its state diffs were fetched")]
+    Evicted {
+        /// The evicted block
+        block: BlockNumber,
+    },
+    /// The block was cleared before its accumulated diffs were fetched.
+    #[error("block {block} was cleared before its state diffs were fetched")]
+    Cleared {
+        /// The cleared block
+        block: BlockNumber,
+    },
+    /// The block was reorged, so its accumulated diffs were stale and discarded.
+    #[error("block {block} was reorged, its state diffs were discarded")]
+    Reorged {
+        /// The reorged block
+        block: BlockNumber,
+    },
+    /// The accumulated diffs were not available before the given timeout.
+    #[error("timed out while fetching accumulated state diffs for block {block}")]
+    Timeout {
+        /// The block of the accumulated diffs that timed out
+        block: BlockNumber,
+    },
+    /// The actor is not running anymore.
+    #[error(transparent)]
+    ActorGone(#[from] TraceActorGone),
+}
+
+impl TraceError {
+    /// Returns the decoded revert reason of the failed trace, if its result reported
+    /// that the traced transaction reverted. `debug_traceCall` doesn't fail on reverts,
+    /// so the RPC errors never carry one.
+    pub fn revert_reason(&self) -> Option<RevertReason> {
+        match self {
+            Self::Reverted { reason, .. } => reason.clone(),
+            _ => None,
+        }
+    }
 
-        res_rx.await.unwrap()
+    /// Returns a copy of the error for another fetcher of the same block. Transport
+    /// errors can't be cloned, so they are copied as their message, while JSON-RPC error
+    /// responses are kept as is, with their revert data.
+    fn duplicate(&self) -> Self {
+        match self {
+            Self::Rpc { block, source } => Self::Rpc {
+                block: *block,
+                source: match source {
+                    RpcError::ErrorResp(payload) => RpcError::ErrorResp(payload.clone()),
+                    source => TransportErrorKind::custom_str(&source.to_string()),
+                },
+            },
+            Self::InvalidTrace { block, reason } => Self::InvalidTrace {
+                block: *block,
+                reason: reason.clone(),
+            },
+            Self::Reverted { block, reason } => Self::Reverted {
+                block: *block,
+                reason: reason.clone(),
+            },
+            Self::Evicted { block } => Self::Evicted { block: *block },
+            Self::Cleared { block } => Self::Cleared { block: *block },
+            Self::Reorged { block } => Self::Reorged { block: *block },
+            Self::Timeout { block } => Self::Timeout { block: *block },
+            Self::ActorGone(err) => Self::ActorGone(*err),
+        }
     }
 }
 
-/// The [CallTraceManager] actor is responsible for handling trace requests for transactions
-/// and accumulating the state diffs for each block that is traced. It listens for incoming
-/// trace requests and processes them in the background using the given RPC client.
-///
-/// The actor is implemented as a future that can be polled in the background.
-#[derive(Debug)]
-#[must_use = "CallTraceManager does nothing unless polled"]
-pub struct CallTraceManager {
-    rpc: RpcClient,
-    cmd_rx: mpsc::Receiver<TraceCommand>,
-    pending_traces: FuturesOrdered<TraceFuture>,
-    trace_request_queue: HashMap<BlockNumber, VecDeque<TransactionRequest>>,
-    response_queue: HashMap<BlockNumber, oneshot::Sender<Option<StateOverride>>>,
-    accumulated_state_diffs: HashMap<BlockNumber, StateOverride>,
+/// The result of a [TraceCommand::ValidateBundle] request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BundleValidation {
+    /// No conflict was found between the transactions of the bundle, and all of
+    /// them could be checked.
+    Valid,
+    /// The bundle can't be included, for the given reasons, in bundle order.
+    Invalid(Vec<BundleConflict>),
 }
 
-type TraceFuture = JoinHandle<(BlockNumber, TransportResult<GethTrace>)>;
+/// A reason why a bundle of transactions can't be included in a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BundleConflict {
+    /// A transaction reuses the nonce of a previous transaction of the same sender.
+    NonceCollision {
+        /// The sender of the transactions
+        sender: Address,
+        /// The reused nonce
+        nonce: u64,
+    },
+    /// A transaction doesn't use the next nonce of its sender.
+    NonceGap {
+        /// The sender of the transaction
+        sender: Address,
+        /// The next nonce of the sender
+        expected: u64,
+        /// The nonce of the transaction
+        found: u64,
+    },
+    /// The sender can't pay for the value and maximum gas cost of its transactions.
+    InsufficientBalance {
+        /// The sender of the transactions
+        sender: Address,
+        /// The total cost of the sender's transactions up to the failing one
+        required: U256,
+        /// The balance of the sender before its first transaction in the bundle
+        available: U256,
+    },
+    /// A transaction uses the maximum nonce, after which the nonce of its sender can't be
+    /// incremented anymore (EIP-2681).
+    NonceOverflow {
+        /// The sender of the transaction
+        sender: Address,
+    },
+    /// The transaction couldn't be checked, because it has no sender or its trace didn't
+    /// report the state of its sender before the bundle.
+    Unvalidated {
+        /// The sender of the transaction, if set
+        sender: Option<Address>,
+        /// The [trace_request_hash] of the transaction
+        request_hash: B256,
+    },
+}
 
-impl Future for CallTraceManager {
-    type Output = ();
+/// The progress of the traces of a block, as returned by a [TraceCommand::BlockStatus]
+/// request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockTraceStatus {
+    /// The number of traces in flight, at most 1 as the traces of a block run in sequence
+    pub in_flight: usize,
+    /// The number of traces waiting for the previous traces of the block, or for the
+    /// head of the chain to reach the block
+    pub queued: usize,
+    /// Whether the accumulated diffs of the block are not empty
+    pub has_diffs: bool,
+    /// The number of fetch, validation and summary requests waiting for the traces of
+    /// the block to complete
+    pub waiters: usize,
+    /// Whether a trace on the block failed
+    pub failed: bool,
+    /// Whether the block was resolved from a [BlockTarget::Pending] target
+    pub pending: bool,
+}
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = self.get_mut();
+impl BlockTraceStatus {
+    /// Returns `true` if no trace is left to run on the block.
+    pub fn is_complete(&self) -> bool {
+        self.in_flight == 0 && self.queued == 0
+    }
+}
 
-        loop {
-            match this.cmd_rx.poll_recv(cx) {
-                Poll::Ready(Some(cmd)) => this.handle_new_trace_command(cmd),
-                Poll::Ready(None) => return Poll::Ready(()),
-                Poll::Pending => return Poll::Pending,
-            }
+/// The accounts present in the accumulated diffs of a block, as returned by a
+/// [TraceCommand::TouchedAccounts] request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TouchedAccounts {
+    /// The accounts whose storage or code is in the diffs, i.e. the contracts
+    pub contracts: HashSet<Address>,
+    /// The accounts with only balance or nonce changes, i.e. the externally owned accounts
+    pub eoas: HashSet<Address>,
+}
 
-            match this.pending_traces.poll_next_unpin(cx) {
-                Poll::Ready(Some(Ok((block, trace_result)))) => {
-                    this.handle_trace_result(block, trace_result)
-                }
-                Poll::Ready(Some(Err(e))) => {
-                    tracing::error!(err = ?e, "Error while tracing transaction");
-                }
-                Poll::Ready(None) => return Poll::Ready(()),
-                Poll::Pending => return Poll::Pending,
+impl TouchedAccounts {
+    /// Classifies the accounts of the given diffs.
+    fn from_diffs(diffs: &StateOverride) -> Self {
+        let mut accounts = Self::default();
+        for (address, account_override) in diffs {
+            let is_contract = account_override
+                .code
+                .as_ref()
+                .is_some_and(|code| !code.is_empty())
+                || account_override.state.is_some()
+                || account_override
+                    .state_diff
+                    .as_ref()
+                    .is_some_and(|state_diff| !state_diff.is_empty());
+
+            if is_contract {
+                accounts.contracts.insert(*address);
+            } else {
+                accounts.eoas.insert(*address);
             }
         }
-    }
-}
-
-impl CallTraceManager {
-    /// Creates a new [CallTraceManager] instance, which will listen for incoming
-    /// trace requests and process them in the background using the given RPC client.
-    pub fn new<U: Into<Url>>(url: U) -> (Self, CallTraceHandle) {
-        let rpc = RpcClient::new(url);
-        let (cmd_tx, cmd_rx) = mpsc::channel(512);
 
-        (
-            Self {
-                rpc,
-                cmd_rx,
-                trace_request_queue: Default::default(),
-                pending_traces: Default::default(),
-                response_queue: Default::default(),
-                accumulated_state_diffs: Default::default(),
-            },
-            CallTraceHandle { cmd_tx },
-        )
+        accounts
     }
 
-    fn handle_new_trace_command(&mut self, cmd: TraceCommand) {
-        match cmd {
-            TraceCommand::AddTrace { transaction, block } => {
-                tracing::debug!(block = block, "Received new transaction trace request");
+    /// Returns all the touched accounts.
+    pub fn all(&self) -> HashSet<Address> {
+        self.contracts.union(&self.eoas).copied().collect()
+    }
 
-                // TODO: handle the case where the block is in the future.
-                // Requires a execution block interval ticker.
+    /// Returns `true` if no account was touched.
+    pub fn is_empty(&self) -> bool {
+        self.contracts.is_empty() && self.eoas.is_empty()
+    }
+}
 
-                // Try to start the trace call in the background if
-                // there is no pending task
-                if self.pending_traces.is_empty() {
-                    self.start_new_trace_call_with_overrides(transaction, block);
-                } else {
-                    // Otherwise, add the transaction to the queue to be processed
-                    // in order for the given block
-                    self.trace_request_queue
-                        .entry(block)
-                        .or_default()
-                        .push_back(transaction);
-                }
-            }
-            TraceCommand::FetchAccumulatedDiffs { block, res } => {
-                tracing::debug!(block = block, "Fetching accumulated state diffs");
+/// The transactions of a sender in a bundle, as returned by a
+/// [TraceCommand::SummarizeBundle] request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SenderSummary {
+    /// The sender of the transactions
+    pub sender: Address,
+    /// The number of transactions of the sender in the bundle
+    pub transactions: usize,
+    /// The cumulative value of the transactions
+    pub value: U256,
+    /// The cumulative gas limit times maximum fee per gas of the transactions
+    pub max_gas_cost: U256,
+    /// The balance of the sender before its first transaction, if reported by its trace
+    pub balance: Option<U256>,
+    /// The nonce of the sender after its last transaction, if known
+    pub next_nonce: Option<u64>,
+}
 
-                if self.pending_traces.is_empty() {
-                    // If there are no pending traces for the given block, and the
-                    // accumulated state diffs are already available, send the result
-                    if let Some(diffs) = self.accumulated_state_diffs.remove(&block) {
-                        let _ = res.send(Some(diffs));
-                    } else {
-                        let _ = res.send(None);
-                    }
-                } else {
-                    // Otherwise, store the response channel to be used later once the last
-                    // pending trace request for that block has been processed and the diffs
-                    // are available.
-                    self.response_queue.insert(block, res);
-                }
-            }
-        }
+impl SenderSummary {
+    /// Returns the balance the sender needs to pay for all its transactions.
+    pub fn required_balance(&self) -> U256 {
+        self.value.saturating_add(self.max_gas_cost)
     }
+}
 
-    fn handle_trace_result(&mut self, block: BlockNumber, result: TransportResult<GethTrace>) {
-        match result {
-            Ok(trace) => {
-                tracing::debug!(block = block, "RPC trace call completed");
+/// The handle to control the [CallTraceManager] actor in a
+/// thread-safe, non-blocking way.
+///
+/// Commands are sent through a bounded channel (see
+/// [CallTraceManager::with_client_and_capacity]). When the actor is backed up and the
+/// channel is full, the async methods wait for room in the channel before sending their
+/// command, which slows the caller down. [CallTraceHandle::try_add_trace] never waits,
+/// so that callers under time pressure can shed load instead.
+#[derive(Debug, Clone)]
+pub struct CallTraceHandle {
+    cmd_tx: mpsc::Sender<TraceCommand>,
+    /// The id of the next fetch request, shared by the clones of the handle.
+    next_fetch_id: Arc<AtomicU64>,
+}
 
-                let Ok(PreStateFrame::Default(trace_state)) = trace.try_into_pre_state_frame()
-                else {
-                    tracing::error!("Failed to extract pre-state frame from trace result");
-                    return;
-                };
+impl CallTraceHandle {
+    /// Request the trace for the given transaction on the provided block.
+    ///
+    /// A transaction with a sender and nonce already submitted on the block is ignored,
+    /// until the accumulated diffs of the block are fetched. Requests missing either are
+    /// always traced: without a nonce, two identical requests may be two distinct
+    /// transactions, e.g. the same contract call made twice. Waits for room in the command
+    /// channel if it is full.
+    pub async fn add_trace(
+        &self,
+        transaction: TransactionRequest,
+        block: BlockNumber,
+    ) -> Result<(), TraceActorGone> {
+        self.cmd_tx
+            .send(TraceCommand::AddTrace {
+                transaction,
+                block: BlockTarget::Number(block),
+                tracer: None,
+                block_overrides: None,
+            })
+            .await
+            .map_err(|_| TraceActorGone)
+    }
 
-                // Store the updated accumulated state diffs for the given block
-                let acc_state_diffs = self.accumulated_state_diffs.entry(block).or_default();
-                for (address, account_state) in trace_state.0 {
-                    let account_override = acc_state_diffs.entry(address).or_default();
-                    merge_account_state_in_overrides(account_override, account_state);
-                }
+    /// Request the trace for the given transaction on the provided block, without
+    /// waiting: if the command channel is full, [TrySendError::Full] is returned with
+    /// the command, so that the caller can drop or retry the request later.
+    /// [TrySendError::Closed] is returned if the actor is not running anymore.
+    pub fn try_add_trace(
+        &self,
+        transaction: TransactionRequest,
+        block: BlockNumber,
+    ) -> Result<(), TrySendError<TraceCommand>> {
+        self.cmd_tx.try_send(TraceCommand::AddTrace {
+            transaction,
+            block: BlockTarget::Number(block),
+            tracer: None,
+            block_overrides: None,
+        })
+    }
 
-                // If there are more pending trace requests for the same block, process the next one
-                if let Some(transactions) = self.trace_request_queue.get_mut(&block) {
-                    if let Some(transaction) = transactions.pop_front() {
-                        self.start_new_trace_call_with_overrides(transaction, block);
-                        return;
-                    }
-                }
+    /// Request the trace for the given transaction on the provided block,
+    /// using the given tracer instead of the manager's default one.
+    pub async fn add_trace_with_tracer(
+        &self,
+        transaction: TransactionRequest,
+        block: BlockNumber,
+        tracer: TracerKind,
+    ) -> Result<(), TraceActorGone> {
+        self.cmd_tx
+            .send(TraceCommand::AddTrace {
+                transaction,
+                block: BlockTarget::Number(block),
+                tracer: Some(tracer),
+                block_overrides: None,
+            })
+            .await
+            .map_err(|_| TraceActorGone)
+    }
 
-                // If there are no more transactions to process for this block,
-                // send the accumulated state diffs to the response channel if there is
-                // one waiting for it
-                if let Some(res) = self.response_queue.remove(&block) {
-                    let _ = res.send(Some(acc_state_diffs.clone()));
-                    self.accumulated_state_diffs.remove(&block);
-                }
-            }
-            Err(err) => {
-                tracing::error!(err = ?err, "RPC error while tracing transaction");
+    /// Request the trace for the given transaction on the provided block,
+    /// overriding the given block header fields (e.g. the base fee).
+    pub async fn add_trace_with_block_overrides(
+        &self,
+        transaction: TransactionRequest,
+        block: BlockNumber,
+        block_overrides: BlockOverrides,
+    ) -> Result<(), TraceActorGone> {
+        self.cmd_tx
+            .send(TraceCommand::AddTrace {
+                transaction,
+                block: BlockTarget::Number(block),
+                tracer: None,
+                block_overrides: Some(block_overrides),
+            })
+            .await
+            .map_err(|_| TraceActorGone)
+    }
 
-                // For now, just log the error and continue processing the next trace request
-                // for the same block, if there is one.
-                if let Some(transactions) = self.trace_request_queue.get_mut(&block) {
-                    if let Some(transaction) = transactions.pop_front() {
-                        self.start_new_trace_call_with_overrides(transaction, block);
-                    }
-                }
+    /// Request the trace for the given transaction on the pending block, i.e. the block
+    /// after the current head, resolved when the request is received.
+    ///
+    /// Returns the block resolved for this request, or an error if the request was
+    /// dropped, e.g. because the head of the chain is not known yet.
+    pub async fn add_pending_trace(
+        &self,
+        transaction: TransactionRequest,
+    ) -> Result<BlockNumber, PendingTraceError> {
+        let (res_tx, res_rx) = oneshot::channel();
+        self.cmd_tx
+            .send(TraceCommand::AddPendingTrace {
+                transaction,
+                tracer: None,
+                res: res_tx,
+            })
+            .await
+            .map_err(|_| TraceActorGone)?;
 
-                if let Some(res) = self.response_queue.remove(&block) {
-                    let _ = res.send(None);
-                    self.accumulated_state_diffs.remove(&block);
-                }
-            }
-        }
+        res_rx.await.map_err(|_| TraceActorGone)?
     }
 
-    fn start_new_trace_call_with_overrides(
-        &mut self,
+    /// Request the trace for the given transaction on the current simulation target
+    /// block (`head + head_offset`). Returns the resolved block, or `None` if the
+    /// head of the chain is not known yet or the actor is shutting down.
+    ///
+    /// Unlike [CallTraceHandle::add_trace] on a future block, the trace isn't buffered
+    /// until the head reaches the target: it runs on the state of the current head,
+    /// with the block number overridden to the target block.
+    pub async fn add_trace_at_head(
+        &self,
         transaction: TransactionRequest,
-        block: BlockNumber,
-    ) {
-        let rpc = self.rpc.clone();
-        let state_override = self
-            .accumulated_state_diffs
-            .get(&block)
-            .cloned()
-            .unwrap_or_default();
+    ) -> Result<Option<BlockNumber>, TraceActorGone> {
+        let (res_tx, res_rx) = oneshot::channel();
+        self.cmd_tx
+            .send(TraceCommand::AddTraceAtHead {
+                transaction,
+                tracer: None,
+                res: res_tx,
+            })
+            .await
+            .map_err(|_| TraceActorGone)?;
 
-        let mut tracing_options = get_trace_options_with_override(state_override);
-        tracing_options.tracing_options.tracer = Some(GethDebugTracerType::JsTracer(format!(
-            r#"{{
-                data: [],
-                result: function(ctx, db) {{
-                    var root = db.GetStorageRoot("{:x}");
-                    return root;
-                }},
-            }}"#,
-            transaction.from.unwrap_or_default()
-        )));
-
-        self.pending_traces.push_back(tokio::spawn(async move {
-            (
-                block,
-                rpc.debug_trace_call(transaction, Some(block), Some(tracing_options))
-                    .await,
-            )
-        }));
+        res_rx.await.map_err(|_| TraceActorGone)
     }
-}
 
-fn get_trace_options_with_override(state_override: StateOverride) -> GethDebugTracingCallOptions {
-    let mut opts = GethDebugTracingOptions::default().with_tracer(
-        GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::PreStateTracer),
-    );
+    /// Update the head of the chain known to the actor. The simulation target
+    /// block is recomputed from this value as `head + head_offset`.
+    pub async fn update_head(&self, head: BlockNumber) -> Result<(), TraceActorGone> {
+        self.cmd_tx
+            .send(TraceCommand::UpdateHead { head })
+            .await
+            .map_err(|_| TraceActorGone)
+    }
 
-    opts.config = GethDefaultTracingOptions::default()
-        .with_disable_storage(false)
-        .with_disable_memory(true)
-        .with_disable_return_data(true)
-        .with_disable_stack(true);
+    /// Seed the accumulated diffs of the given block with the given state overrides,
+    /// so that the transactions traced afterwards on the block run on top of them.
+    pub async fn seed_diff(
+        &self,
+        block: BlockNumber,
+        overrides: StateOverride,
+    ) -> Result<(), TraceActorGone> {
+        self.cmd_tx
+            .send(TraceCommand::SeedDiff { block, overrides })
+            .await
+            .map_err(|_| TraceActorGone)
+    }
 
-    GethDebugTracingCallOptions::default()
-        .with_tracing_options(opts)
-        .with_state_overrides(state_override)
-}
+    /// Inject the given bytecode and storage entries at the given address in the
+    /// accumulated diffs of the block, e.g. to simulate against a patched or not yet
+    /// deployed contract. The transactions traced afterwards on the block run against
+    /// the injected code, which is kept by the following traces.
+    pub async fn inject_code(
+        &self,
+        block: BlockNumber,
+        address: Address,
+        code: Bytes,
+        storage: HashMap<B256, B256>,
+    ) -> Result<(), TraceActorGone> {
+        let account = AccountOverride {
+            code: Some(code),
+            state_diff: (!storage.is_empty()).then_some(storage),
+            ..Default::default()
+        };
 
-fn merge_account_state_in_overrides(account_override: &mut AccountOverride, value: AccountState) {
-    account_override.balance = value.balance;
-    account_override.nonce = value.nonce.map(U64::from);
-    account_override.code = value.code;
-    for (key, value) in value.storage {
-        if let Some(ref mut state) = account_override.state_diff {
-            state.insert(key, value);
-        } else {
-            account_override.state_diff = Some(HashMap::from_iter(vec![(key, value)]));
-        }
+        self.seed_diff(block, StateOverride::from([(address, account)]))
+            .await
+    }
+
+    /// Request the accumulated state diffs for a given block from previously
+    /// traced transactions.
+    ///
+    /// If the diffs are not available yet, this function
+    /// will hang until the last transaction has been processed and the diffs are ready.
+    ///
+    /// Returns an empty override if nothing was traced on the given block, and an
+    /// error if any trace on the block failed or the actor is not running anymore.
+    ///
+    /// The diffs are consumed: use [CallTraceHandle::peek_accumulated_diffs] to inspect
+    /// them while continuing to trace transactions on top of them.
+    pub async fn fetch_accumulated_diffs(
+        &self,
+        block: BlockNumber,
+    ) -> Result<StateOverride, TraceError> {
+        let (res_tx, res_rx) = oneshot::channel();
+        let id = self.next_fetch_id.fetch_add(1, Ordering::Relaxed);
+        self.cmd_tx
+            .send(TraceCommand::FetchAccumulatedDiffs {
+                block,
+                id,
+                res: res_tx,
+            })
+            .await
+            .map_err(|_| TraceActorGone)?;
+
+        res_rx.await.unwrap_or(Err(TraceActorGone.into()))
+    }
+
+    /// Request the accumulated state diffs of every block from `from` to `to` included,
+    /// waiting until no trace is pending on any of them.
+    ///
+    /// Blocks without diffs are omitted from the returned map. Unlike
+    /// [CallTraceHandle::fetch_accumulated_diffs], the diffs are not consumed.
+    pub async fn fetch_accumulated_diffs_range(
+        &self,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> Result<HashMap<BlockNumber, StateOverride>, TraceActorGone> {
+        let (res_tx, res_rx) = oneshot::channel();
+        self.cmd_tx
+            .send(TraceCommand::FetchAccumulatedDiffsRange {
+                from,
+                to,
+                res: res_tx,
+            })
+            .await
+            .map_err(|_| TraceActorGone)?;
+
+        res_rx.await.map_err(|_| TraceActorGone)
+    }
+
+    /// Request a copy of the state diffs accumulated so far on the given block.
+    ///
+    /// Unlike [CallTraceHandle::fetch_accumulated_diffs], this returns immediately with
+    /// the traces completed so far and doesn't consume the diffs, so that following
+    /// traces on the block still build on top of them.
+    pub async fn peek_accumulated_diffs(
+        &self,
+        block: BlockNumber,
+    ) -> Result<StateOverride, TraceActorGone> {
+        let (res_tx, res_rx) = oneshot::channel();
+        self.cmd_tx
+            .send(TraceCommand::PeekAccumulatedDiffs { block, res: res_tx })
+            .await
+            .map_err(|_| TraceActorGone)?;
+
+        res_rx.await.map_err(|_| TraceActorGone)
+    }
+
+    /// Subscribe to the state diffs accumulated on the given block. The stream yields a
+    /// snapshot of the accumulated diffs after each trace on the block is merged, and ends
+    /// once the traces submitted before subscribing and while subscribed are all done.
+    ///
+    /// The stream is empty if no trace is pending on the block, and ends early if the
+    /// actor stops. Snapshots are dropped if the subscriber falls
+    /// [DIFF_SUBSCRIPTION_CAPACITY] behind.
+    pub async fn subscribe_diffs(
+        &self,
+        block: BlockNumber,
+    ) -> Result<impl Stream<Item = StateOverride>, TraceActorGone> {
+        let (tx, rx) = mpsc::channel(DIFF_SUBSCRIPTION_CAPACITY);
+        self.cmd_tx
+            .send(TraceCommand::SubscribeDiffs { block, tx })
+            .await
+            .map_err(|_| TraceActorGone)?;
+
+        Ok(stream::unfold(rx, |mut rx| async move {
+            let diffs = rx.recv().await?;
+            Some((diffs, rx))
+        }))
+    }
+
+    /// Request the progress of the traces of the given block, without waiting for them
+    /// to complete and without consuming the accumulated diffs.
+    pub async fn block_status(
+        &self,
+        block: BlockNumber,
+    ) -> Result<BlockTraceStatus, TraceActorGone> {
+        let (res_tx, res_rx) = oneshot::channel();
+        self.cmd_tx
+            .send(TraceCommand::BlockStatus { block, res: res_tx })
+            .await
+            .map_err(|_| TraceActorGone)?;
+
+        res_rx.await.map_err(|_| TraceActorGone)
+    }
+
+    /// Request the addresses touched by the transactions traced so far on the given block.
+    ///
+    /// Unlike [CallTraceHandle::fetch_accumulated_diffs], this returns immediately with
+    /// the current state of the accumulated diffs and doesn't consume them.
+    pub async fn touched_addresses(
+        &self,
+        block: BlockNumber,
+    ) -> Result<Vec<Address>, TraceActorGone> {
+        let (res_tx, res_rx) = oneshot::channel();
+        self.cmd_tx
+            .send(TraceCommand::TouchedAddresses { block, res: res_tx })
+            .await
+            .map_err(|_| TraceActorGone)?;
+
+        res_rx.await.map_err(|_| TraceActorGone)
+    }
+
+    /// Request the accounts touched by the transactions traced so far on the given block,
+    /// split between contracts and externally owned accounts.
+    ///
+    /// Like [CallTraceHandle::touched_addresses], this returns immediately with the
+    /// current state of the accumulated diffs and doesn't consume them.
+    pub async fn touched_accounts(
+        &self,
+        block: BlockNumber,
+    ) -> Result<TouchedAccounts, TraceActorGone> {
+        let (res_tx, res_rx) = oneshot::channel();
+        self.cmd_tx
+            .send(TraceCommand::TouchedAccounts { block, res: res_tx })
+            .await
+            .map_err(|_| TraceActorGone)?;
+
+        res_rx.await.map_err(|_| TraceActorGone)
+    }
+
+    /// Cancel the trace of the transaction with the given [trace_request_hash] on the
+    /// given block. Other transactions of the block are traced as if it was never added.
+    pub async fn cancel_trace(
+        &self,
+        block: BlockNumber,
+        request_hash: B256,
+    ) -> Result<(), TraceActorGone> {
+        self.cmd_tx
+            .send(TraceCommand::CancelTrace {
+                block,
+                request_hash,
+            })
+            .await
+            .map_err(|_| TraceActorGone)
+    }
+
+    /// Remove the transaction with the given [trace_request_hash] from the given block,
+    /// even if its trace was already merged: the transactions traced after it are then
+    /// traced again without it.
+    pub async fn remove_trace(
+        &self,
+        block: BlockNumber,
+        request_hash: B256,
+    ) -> Result<(), TraceActorGone> {
+        self.cmd_tx
+            .send(TraceCommand::RemoveTrace {
+                block,
+                request_hash,
+            })
+            .await
+            .map_err(|_| TraceActorGone)
+    }
+
+    /// Check the bundle traced on the given block for nonce and balance conflicts, once
+    /// its pending traces have completed. Returns `None` if a trace on the block failed
+    /// or the block was removed before its traces completed.
+    pub async fn validate_bundle(
+        &self,
+        block: BlockNumber,
+    ) -> Result<Option<BundleValidation>, TraceActorGone> {
+        let (res_tx, res_rx) = oneshot::channel();
+        self.cmd_tx
+            .send(TraceCommand::ValidateBundle { block, res: res_tx })
+            .await
+            .map_err(|_| TraceActorGone)?;
+
+        res_rx.await.map_err(|_| TraceActorGone)
+    }
+
+    /// Summarize the bundle traced on the given block per sender, once its pending traces
+    /// have completed. Returns `None` if a trace on the block failed or the block was
+    /// removed before its traces completed.
+    pub async fn summarize_bundle(
+        &self,
+        block: BlockNumber,
+    ) -> Result<Option<Vec<SenderSummary>>, TraceActorGone> {
+        let (res_tx, res_rx) = oneshot::channel();
+        self.cmd_tx
+            .send(TraceCommand::SummarizeBundle { block, res: res_tx })
+            .await
+            .map_err(|_| TraceActorGone)?;
+
+        res_rx.await.map_err(|_| TraceActorGone)
+    }
+
+    /// Discard the accumulated diffs and pending trace requests of the given block.
+    /// A trace in flight for the block is ignored when it returns.
+    pub async fn clear_block(&self, block: BlockNumber) -> Result<(), TraceActorGone> {
+        self.cmd_tx
+            .send(TraceCommand::ClearBlock { block })
+            .await
+            .map_err(|_| TraceActorGone)
+    }
+
+    /// Invalidate the given block and all the blocks above it, e.g. after a reorg
+    /// detected by an external slot manager.
+    pub async fn invalidate_from(&self, block: BlockNumber) -> Result<(), TraceActorGone> {
+        self.cmd_tx
+            .send(TraceCommand::InvalidateFrom { block })
+            .await
+            .map_err(|_| TraceActorGone)
+    }
+
+    /// Gracefully shut down the actor. Pending traces are completed and outstanding
+    /// fetch requests are answered, while new requests are rejected.
+    pub async fn shutdown(&self) -> Result<(), TraceActorGone> {
+        self.cmd_tx
+            .send(TraceCommand::Shutdown)
+            .await
+            .map_err(|_| TraceActorGone)
+    }
+
+    /// Request the accumulated state diffs for a given block from previously
+    /// traced transactions, waiting at most `timeout` for them to be ready.
+    ///
+    /// On timeout, the pending request is cancelled in the actor so that
+    /// no stale response channel is left behind.
+    pub async fn fetch_accumulated_diffs_timeout(
+        &self,
+        block: BlockNumber,
+        timeout: Duration,
+    ) -> Result<StateOverride, TraceError> {
+        let (res_tx, mut res_rx) = oneshot::channel();
+        let id = self.next_fetch_id.fetch_add(1, Ordering::Relaxed);
+        self.cmd_tx
+            .send(TraceCommand::FetchAccumulatedDiffs {
+                block,
+                id,
+                res: res_tx,
+            })
+            .await
+            .map_err(|_| TraceActorGone)?;
+
+        if let Ok(res) = tokio::time::timeout(timeout, &mut res_rx).await {
+            return res.unwrap_or(Err(TraceActorGone.into()));
+        }
+
+        let _ = self
+            .cmd_tx
+            .send(TraceCommand::CancelFetch { block, id })
+            .await;
+
+        // The last trace may have completed right as the timeout fired, in which case the
+        // diffs were already sent and removed from the actor. Commands are processed in order,
+        // so the channel resolves as soon as the cancellation has been handled.
+        res_rx.await.unwrap_or(Err(TraceError::Timeout { block }))
+    }
+}
+
+/// The [CallTraceManager] actor is responsible for handling trace requests for transactions
+/// and accumulating the state diffs for each block that is traced. It listens for incoming
+/// trace requests and processes them in the background using the given RPC client.
+///
+/// Transactions targeting the same block depend on each other's state diffs, so they are
+/// traced in sequence: at most one trace per block is in flight at any time. Traces for
+/// different blocks are independent and run concurrently, up to
+/// [CallTraceManager::with_max_concurrent_traces] at once.
+///
+/// The actor is implemented as a future that can be polled in the background.
+#[derive(Debug)]
+#[must_use = "CallTraceManager does nothing unless polled"]
+pub struct CallTraceManager {
+    rpc: Arc<dyn ExecutionRpc>,
+    tracer: TracerKind,
+    /// The options of the `debug_traceCall` requests.
+    trace_options: TraceOptionsConfig,
+    /// The latest known head of the chain, if any.
+    head: Option<BlockNumber>,
+    /// The number of blocks on top of the head to simulate against.
+    head_offset: u64,
+    /// The interval at which the head of the chain is polled, if enabled.
+    head_poll_interval: Option<Duration>,
+    /// The ticker driving the head polling. Created lazily on the first poll.
+    head_ticker: Option<Interval>,
+    /// The in-flight request for the latest head block, if any.
+    head_request: Option<JoinHandle<TransportResult<BlockHashes>>>,
+    /// The stream of new block numbers advancing the head, if any.
+    block_ticker: Option<BlockTicker>,
+    /// The hashes of the recently polled head blocks, used to detect reorgs.
+    block_hashes: BTreeMap<BlockNumber, B256>,
+    /// The maximum number of blocks to keep diffs and queues for. Once exceeded,
+    /// the oldest blocks are evicted.
+    max_tracked_blocks: usize,
+    /// Whether traces complete immediately with an empty state diff, without calling the RPC.
+    dry_run: bool,
+    /// The store the accumulated diffs are persisted to, if any, shared with the
+    /// snapshot being saved in the background.
+    #[cfg(feature = "diff-store")]
+    diff_store: Option<Arc<Mutex<SharedDiffStore>>>,
+    /// The snapshot of the accumulated diffs being saved in the background, if any.
+    #[cfg(feature = "diff-store")]
+    diff_snapshot_task: Option<JoinHandle<()>>,
+    /// The interval at which the accumulated diffs are saved to the store.
+    #[cfg(feature = "diff-store")]
+    diff_snapshot_interval: Option<Duration>,
+    /// The ticker driving the snapshots. Created lazily on the first poll.
+    #[cfg(feature = "diff-store")]
+    diff_snapshot_ticker: Option<Interval>,
+    /// The number of blocks below the head to keep the restored diffs for, until the
+    /// head is known and the stale restored diffs are pruned.
+    #[cfg(feature = "diff-store")]
+    diff_store_prune_margin: Option<u64>,
+    /// Whether a graceful shutdown was requested.
+    shutting_down: bool,
+    cmd_rx: mpsc::Receiver<TraceCommand>,
+    pending_traces: FuturesUnordered<TraceFuture>,
+    /// The blocks that currently have a trace in flight, with the id of that trace.
+    in_flight_blocks: HashMap<BlockNumber, u64>,
+    /// The maximum number of trace calls in flight at once, across all blocks.
+    max_concurrent_traces: usize,
+    /// The blocks whose next queued trace is waiting for a free slot, in order.
+    ready_blocks: VecDeque<BlockNumber>,
+    /// The cache keys of the traces in flight, by trace id.
+    in_flight_cache_keys: HashMap<u64, TraceCacheKey>,
+    /// The results of previous traces. Disabled if `None`.
+    trace_cache: Option<LruCache<TraceCacheKey, GethTrace>>,
+    /// The id of the next trace, used to tell apart the results of traces that were
+    /// invalidated while in flight from the ones that replaced them.
+    next_trace_id: u64,
+    trace_request_queue: HashMap<BlockNumber, VecDeque<QueuedTrace>>,
+    /// Trace requests targeting blocks after the current head. They are moved
+    /// to the trace request queue in order once the head reaches their block.
+    future_queue: HashMap<BlockNumber, VecDeque<QueuedTrace>>,
+    /// The pending fetch requests of each block by request id, answered once the traces
+    /// of the block complete.
+    response_queue: HashMap<BlockNumber, Vec<(u64, FetchSender)>>,
+    /// The pending bundle validation requests, answered once the traces of the block complete.
+    validation_queue: HashMap<BlockNumber, Vec<oneshot::Sender<Option<BundleValidation>>>>,
+    /// The pending bundle summary requests, answered once the traces of the block complete.
+    summary_queue: HashMap<BlockNumber, Vec<oneshot::Sender<Option<Vec<SenderSummary>>>>>,
+    /// The pending range fetch requests, answered once the traces of all their blocks complete.
+    range_fetch_queue: Vec<(RangeInclusive<BlockNumber>, RangeFetchSender)>,
+    /// The subscribers to the accumulated diffs of each block, dropped once no trace is
+    /// left pending on the block.
+    diff_subscribers: HashMap<BlockNumber, Vec<mpsc::Sender<StateOverride>>>,
+    /// The accumulated diffs of each block, shared with the trace in flight on the block
+    /// instead of copied into its request, and with the snapshot being saved. Merging a
+    /// trace result only copies them if they are still shared, see
+    /// [CallTraceManager::block_diffs_mut].
+    accumulated_state_diffs: HashMap<BlockNumber, Arc<StateOverride>>,
+    /// The diffs merged in the accumulated diffs of each block, in order, to be able
+    /// to rebuild them without one of the transactions.
+    tx_diffs: HashMap<BlockNumber, Vec<TxDiff>>,
+    /// The transactions traced on each block, in order, used to validate the bundle.
+    bundles: HashMap<BlockNumber, Vec<BundleEntry>>,
+    /// The bundle entries of the traces in flight, by trace id.
+    in_flight_bundle_entries: HashMap<u64, BundleEntry>,
+    /// The traces in flight, by trace id, to be able to cancel them.
+    in_flight_traces: HashMap<u64, InFlightTrace>,
+    /// The blocks on which a trace failed, with the error to report to the fetcher.
+    failed_blocks: HashMap<BlockNumber, TraceError>,
+    /// The [trace_request_hash]es of the transactions with a sender and nonce queued, in
+    /// flight or merged on each block, to skip re-submitted transactions instead of
+    /// applying them twice.
+    seen_traces: HashMap<BlockNumber, HashSet<B256>>,
+    /// The blocks resolved from a [BlockTarget::Pending] target.
+    pending_blocks: BTreeSet<BlockNumber>,
+}
+
+type FetchSender = oneshot::Sender<Result<StateOverride, TraceError>>;
+
+type RangeFetchSender = oneshot::Sender<HashMap<BlockNumber, StateOverride>>;
+
+type TraceFuture = JoinHandle<(BlockNumber, u64, TransportResult<GethTrace>)>;
+
+/// The [DiffStore] of the actor, locked while a snapshot is saved so that saves never
+/// overlap.
+#[cfg(feature = "diff-store")]
+#[derive(Debug)]
+struct SharedDiffStore {
+    store: Box<dyn DiffStore>,
+    /// Whether the last snapshot was saved when the actor stopped. A snapshot still
+    /// being saved in the background is older, so it must not overwrite it.
+    closed: bool,
+}
+
+/// Saves the given snapshot of the accumulated diffs to the store, unless the last
+/// snapshot was saved already. `last` marks the snapshot saved when the actor stops.
+#[cfg(feature = "diff-store")]
+fn save_snapshot(
+    store: &Mutex<SharedDiffStore>,
+    diffs: &HashMap<BlockNumber, Arc<StateOverride>>,
+    last: bool,
+) {
+    // A poisoned lock only means that a previous save panicked
+    let mut store = store.lock().unwrap_or_else(PoisonError::into_inner);
+    if store.closed {
+        return;
+    }
+    store.closed = last;
+
+    match store.store.save(diffs) {
+        Ok(()) => tracing::debug!("Saved accumulated state diffs"),
+        Err(err) => tracing::error!(?err, "Failed to save accumulated state diffs"),
+    }
+}
+
+/// A trace request waiting for the previous traces of its block to complete.
+#[derive(Debug, Clone)]
+struct QueuedTrace {
+    transaction: TransactionRequest,
+    /// The [trace_request_hash] of the transaction, computed once when it is requested.
+    request_hash: B256,
+    tracer: TracerKind,
+    block_overrides: Option<BlockOverrides>,
+    /// The block whose state the trace runs on, if it's not the traced block itself,
+    /// see [CallTraceHandle::add_trace_at_head].
+    state_block: Option<BlockNumber>,
+    /// The span following the trace from its request to the merge of its result.
+    span: Span,
+}
+
+/// A trace in flight, which can be cancelled.
+#[derive(Debug)]
+struct InFlightTrace {
+    /// The [trace_request_hash] of the traced transaction.
+    request_hash: B256,
+    abort: AbortHandle,
+    /// The request of the trace, to run it again if a previous trace of its block is removed.
+    trace: QueuedTrace,
+}
+
+/// The diff contributed to the accumulated diffs of a block by a traced transaction,
+/// or by seeded or restored state if `request_hash` is `None`.
+#[derive(Debug, Clone)]
+struct TxDiff {
+    /// The [trace_request_hash] of the transaction, if any.
+    request_hash: Option<B256>,
+    /// The request of the trace, to run it again if a previous trace of its block is removed.
+    trace: Option<QueuedTrace>,
+    diff: StateOverride,
+}
+
+/// A transaction of a bundle, with the state of its sender before it was executed
+/// as reported by its trace.
+#[derive(Debug, Clone, Copy)]
+struct BundleEntry {
+    /// The [trace_request_hash] of the transaction.
+    request_hash: B256,
+    /// The sender of the transaction, if set.
+    sender: Option<Address>,
+    /// The nonce of the transaction, if set.
+    nonce: Option<u64>,
+    /// The value of the transaction.
+    value: U256,
+    /// The gas limit of the transaction times its maximum fee per gas.
+    gas_cost: U256,
+    /// The nonce of the sender before the transaction, if reported by the trace.
+    pre_nonce: Option<u64>,
+    /// The balance of the sender before the transaction, if reported by the trace.
+    pre_balance: Option<U256>,
+}
+
+impl BundleEntry {
+    /// Creates the entry of the given transaction.
+    fn new(transaction: &TransactionRequest, request_hash: B256) -> Self {
+        let gas_price = transaction.max_fee_per_gas.or(transaction.gas_price);
+        let gas_cost = match (transaction.gas, gas_price) {
+            (Some(gas), Some(price)) => U256::from(gas).saturating_mul(U256::from(price)),
+            _ => U256::ZERO,
+        };
+
+        Self {
+            request_hash,
+            sender: transaction.from,
+            nonce: transaction.nonce,
+            value: transaction.value.unwrap_or_default(),
+            gas_cost,
+            pre_nonce: None,
+            pre_balance: None,
+        }
+    }
+
+    /// Returns the value of the transaction plus its maximum gas cost.
+    fn cost(&self) -> U256 {
+        self.value.saturating_add(self.gas_cost)
+    }
+
+    /// Returns the nonce of the sender after the transaction, given the one before it,
+    /// or `None` if it is unknown or can't be incremented.
+    fn next_nonce(&self, nonce: Option<u64>) -> Option<u64> {
+        // Without an explicit nonce, the transaction uses the next one
+        self.nonce.or(nonce).and_then(|nonce| nonce.checked_add(1))
+    }
+}
+
+/// Walks the transactions of a bundle in order, and reports the conflicts that
+/// would make it unincludable.
+///
+/// The expected nonce and available balance of each sender are the ones reported by
+/// the trace of its first transaction. Incoming transfers within the bundle are not
+/// accounted for, so the balance check is conservative. If that trace didn't report
+/// the state of the sender, its transactions are reported as unvalidated, as are the
+/// transactions without a sender.
+fn validate_bundle(entries: &[BundleEntry]) -> BundleValidation {
+    #[derive(Default)]
+    struct SenderState {
+        next_nonce: Option<u64>,
+        used_nonces: HashSet<u64>,
+        required: U256,
+        available: Option<U256>,
+        insufficient: bool,
+        unchecked: bool,
+    }
+
+    let mut senders = HashMap::<Address, SenderState>::new();
+    let mut conflicts = Vec::new();
+
+    for entry in entries {
+        let Some(sender) = entry.sender else {
+            conflicts.push(BundleConflict::Unvalidated {
+                sender: None,
+                request_hash: entry.request_hash,
+            });
+            continue;
+        };
+        let state = senders.entry(sender).or_insert_with(|| SenderState {
+            next_nonce: entry.pre_nonce,
+            available: entry.pre_balance,
+            unchecked: entry.pre_nonce.is_none() && entry.pre_balance.is_none(),
+            ..Default::default()
+        });
+
+        if state.unchecked {
+            conflicts.push(BundleConflict::Unvalidated {
+                sender: Some(sender),
+                request_hash: entry.request_hash,
+            });
+            continue;
+        }
+
+        if entry.nonce.or(state.next_nonce) == Some(u64::MAX) {
+            conflicts.push(BundleConflict::NonceOverflow { sender });
+        }
+
+        match entry.nonce {
+            Some(nonce) if !state.used_nonces.insert(nonce) => {
+                conflicts.push(BundleConflict::NonceCollision { sender, nonce });
+            }
+            Some(nonce) => {
+                if let Some(expected) = state.next_nonce.filter(|expected| *expected != nonce) {
+                    conflicts.push(BundleConflict::NonceGap {
+                        sender,
+                        expected,
+                        found: nonce,
+                    });
+                }
+                state.next_nonce = entry.next_nonce(state.next_nonce);
+            }
+            None => state.next_nonce = entry.next_nonce(state.next_nonce),
+        }
+
+        state.required = state.required.saturating_add(entry.cost());
+        if let Some(available) = state.available {
+            if !state.insufficient && state.required > available {
+                state.insufficient = true;
+                conflicts.push(BundleConflict::InsufficientBalance {
+                    sender,
+                    required: state.required,
+                    available,
+                });
+            }
+        }
+    }
+
+    if conflicts.is_empty() {
+        BundleValidation::Valid
+    } else {
+        BundleValidation::Invalid(conflicts)
+    }
+}
+
+/// Aggregates the transactions of a bundle per sender, in order of first transaction.
+/// The transactions without a sender are skipped.
+///
+/// The balance and nonce of each sender before the bundle are the ones reported by the
+/// trace of its first transaction.
+fn summarize_bundle(entries: &[BundleEntry]) -> Vec<SenderSummary> {
+    let mut summaries = Vec::<SenderSummary>::new();
+    let mut indices = HashMap::<Address, usize>::new();
+
+    for entry in entries {
+        let Some(sender) = entry.sender else {
+            continue;
+        };
+        let index = *indices.entry(sender).or_insert_with(|| {
+            summaries.push(SenderSummary {
+                sender,
+                transactions: 0,
+                value: U256::ZERO,
+                max_gas_cost: U256::ZERO,
+                balance: entry.pre_balance,
+                next_nonce: entry.pre_nonce,
+            });
+            summaries.len() - 1
+        });
+
+        let summary = &mut summaries[index];
+        summary.transactions += 1;
+        summary.value = summary.value.saturating_add(entry.value);
+        summary.max_gas_cost = summary.max_gas_cost.saturating_add(entry.gas_cost);
+        summary.next_nonce = entry.next_nonce(summary.next_nonce);
+    }
+
+    summaries
+}
+
+/// The key of a cached trace result. Tracing the same transaction with the same tracer
+/// on top of the same block and state overrides always yields the same result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct TraceCacheKey {
+    block: BlockNumber,
+    /// The hash of the transaction request, tracer and block overrides.
+    call_hash: B256,
+    /// The hash of the state overrides the transaction is traced on top of.
+    state_override_hash: B256,
+}
+
+impl TraceCacheKey {
+    /// Computes the cache key of the given trace, or `None` if it can't be serialized.
+    fn new(
+        block: BlockNumber,
+        transaction: &TransactionRequest,
+        tracer: &GethDebugTracerType,
+        state_override: &StateOverride,
+        block_overrides: Option<&BlockOverrides>,
+    ) -> Option<Self> {
+        Some(Self {
+            block,
+            call_hash: canonical_hash(&(transaction, tracer, block_overrides))?,
+            state_override_hash: canonical_hash(state_override)?,
+        })
+    }
+}
+
+/// Returns the hash identifying a transaction request in the [CallTraceManager],
+/// e.g. to cancel its trace with [CallTraceHandle::cancel_trace].
+pub fn trace_request_hash(transaction: &TransactionRequest) -> B256 {
+    canonical_hash(transaction).expect("transaction requests are serializable")
+}
+
+/// Hashes the JSON encoding of the given value. Going through [serde_json::Value]
+/// sorts the object keys, so that the hash doesn't depend on the `HashMap` order.
+fn canonical_hash<T: Serialize>(value: &T) -> Option<B256> {
+    let value = serde_json::to_value(value).ok()?;
+    serde_json::to_vec(&value).ok().map(keccak256)
+}
+
+impl Future for CallTraceManager {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        loop {
+            match this.cmd_rx.poll_recv(cx) {
+                Poll::Ready(Some(cmd)) => {
+                    this.handle_new_trace_command(cmd);
+                    continue;
+                }
+                // While shutting down, the channel is closed on purpose: keep going
+                // until the pending traces are completed.
+                Poll::Ready(None) if !this.shutting_down => {
+                    this.save_diffs();
+                    return Poll::Ready(());
+                }
+                Poll::Ready(None) | Poll::Pending => {}
+            }
+
+            // Note: an empty `pending_traces` stream returns `Ready(None)`, which
+            // simply means there is nothing in flight right now.
+            match this.pending_traces.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok((block, id, trace_result)))) => {
+                    this.handle_trace_result(block, id, trace_result);
+                    continue;
+                }
+                Poll::Ready(Some(Err(e))) if e.is_cancelled() => {
+                    tracing::debug!("Trace task was cancelled");
+                    this.update_gauges();
+                    continue;
+                }
+                Poll::Ready(Some(Err(e))) => {
+                    tracing::error!(err = ?e, "Error while tracing transaction");
+                    this.update_gauges();
+                    continue;
+                }
+                Poll::Ready(None) | Poll::Pending => {}
+            }
+
+            if this.shutting_down && this.pending_traces.is_empty() {
+                this.finish_shutdown();
+                return Poll::Ready(());
+            }
+
+            if let Some(ticker) = this.block_ticker.as_mut() {
+                match ticker.poll_next_unpin(cx) {
+                    Poll::Ready(Some(block)) => {
+                        this.handle_new_head(block);
+                        continue;
+                    }
+                    Poll::Ready(None) => {
+                        tracing::warn!("Block ticker terminated");
+                        this.block_ticker = None;
+                    }
+                    Poll::Pending => {}
+                }
+            }
+
+            #[cfg(feature = "diff-store")]
+            if let Some(interval) = this.diff_snapshot_interval {
+                let ticker = this.diff_snapshot_ticker.get_or_insert_with(|| {
+                    tokio::time::interval_at(tokio::time::Instant::now() + interval, interval)
+                });
+
+                if ticker.poll_tick(cx).is_ready() {
+                    this.snapshot_diffs();
+                    continue;
+                }
+            }
+
+            if let Some(interval) = this.head_poll_interval {
+                let ticker = this
+                    .head_ticker
+                    .get_or_insert_with(|| tokio::time::interval(interval));
+
+                if ticker.poll_tick(cx).is_ready() && this.head_request.is_none() {
+                    let rpc = this.rpc.clone();
+                    this.head_request = Some(tokio::spawn(async move {
+                        rpc.get_block_hash(BlockNumberOrTag::Latest).await
+                    }));
+                    continue;
+                }
+            }
+
+            if let Some(head_request) = this.head_request.as_mut() {
+                if let Poll::Ready(result) = head_request.poll_unpin(cx) {
+                    this.head_request = None;
+
+                    match result {
+                        Ok(Ok(head)) => this.handle_new_head_block(head),
+                        Ok(Err(e)) => tracing::error!(err = ?e, "Failed to fetch head block"),
+                        Err(e) => tracing::error!(err = ?e, "Error while fetching head block"),
+                    }
+
+                    continue;
+                }
+            }
+
+            return Poll::Pending;
+        }
+    }
+}
+
+impl CallTraceManager {
+    /// Creates a new [CallTraceManager] instance, which will listen for incoming
+    /// trace requests and process them in the background using the given RPC client.
+    ///
+    /// Transactions are traced with the default [TracerKind::StorageRoot] tracer.
+    pub fn new<U: Into<Url>>(url: U) -> (Self, CallTraceHandle) {
+        Self::with_client(RpcClient::new(url))
+    }
+
+    /// Creates a new [CallTraceManager] instance that traces transactions
+    /// with the given tracer, unless overridden by the trace request.
+    pub fn new_with_tracer<U: Into<Url>>(url: U, tracer: TracerKind) -> (Self, CallTraceHandle) {
+        let (mut manager, handle) = Self::with_client(RpcClient::new(url));
+        manager.tracer = tracer;
+
+        (manager, handle)
+    }
+
+    /// Creates a new [CallTraceManager] instance that uses the given RPC client, e.g.
+    /// an [RpcClient] configured with custom timeouts, headers, JWT authentication or
+    /// failover, or any other [ExecutionRpc] backend.
+    ///
+    /// Transactions are traced with the default [TracerKind::StorageRoot] tracer.
+    pub fn with_client<R: ExecutionRpc + 'static>(rpc: R) -> (Self, CallTraceHandle) {
+        Self::with_client_and_capacity(rpc, DEFAULT_CHANNEL_CAPACITY)
+    }
+
+    /// Creates a new [CallTraceManager] instance that uses the given RPC client, with a
+    /// command channel holding up to `capacity` commands. Once full, the handle methods
+    /// wait for the actor to catch up, except [CallTraceHandle::try_add_trace].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is 0.
+    pub fn with_client_and_capacity<R: ExecutionRpc + 'static>(
+        rpc: R,
+        capacity: usize,
+    ) -> (Self, CallTraceHandle) {
+        let (cmd_tx, cmd_rx) = mpsc::channel(capacity);
+
+        (
+            Self {
+                rpc: Arc::new(rpc),
+                tracer: TracerKind::default(),
+                trace_options: TraceOptionsConfig::default(),
+                head: None,
+                head_offset: 0,
+                head_poll_interval: None,
+                head_ticker: None,
+                block_ticker: None,
+                head_request: None,
+                block_hashes: Default::default(),
+                max_tracked_blocks: DEFAULT_MAX_TRACKED_BLOCKS,
+                dry_run: false,
+                #[cfg(feature = "diff-store")]
+                diff_store: None,
+                #[cfg(feature = "diff-store")]
+                diff_snapshot_task: None,
+                #[cfg(feature = "diff-store")]
+                diff_snapshot_interval: None,
+                #[cfg(feature = "diff-store")]
+                diff_snapshot_ticker: None,
+                #[cfg(feature = "diff-store")]
+                diff_store_prune_margin: None,
+                shutting_down: false,
+                cmd_rx,
+                trace_request_queue: Default::default(),
+                future_queue: Default::default(),
+                pending_traces: Default::default(),
+                in_flight_blocks: Default::default(),
+                max_concurrent_traces: DEFAULT_MAX_CONCURRENT_TRACES,
+                ready_blocks: Default::default(),
+                in_flight_cache_keys: Default::default(),
+                trace_cache: NonZeroUsize::new(DEFAULT_TRACE_CACHE_SIZE).map(LruCache::new),
+                next_trace_id: 0,
+                response_queue: Default::default(),
+                validation_queue: Default::default(),
+                summary_queue: Default::default(),
+                range_fetch_queue: Default::default(),
+                diff_subscribers: Default::default(),
+                accumulated_state_diffs: Default::default(),
+                tx_diffs: Default::default(),
+                bundles: Default::default(),
+                in_flight_bundle_entries: Default::default(),
+                in_flight_traces: Default::default(),
+                failed_blocks: Default::default(),
+                seen_traces: Default::default(),
+                pending_blocks: Default::default(),
+            },
+            CallTraceHandle {
+                cmd_tx,
+                next_fetch_id: Default::default(),
+            },
+        )
+    }
+
+    /// Sets the options of the `debug_traceCall` requests, e.g. to capture the memory
+    /// and stack with a struct-log tracer.
+    ///
+    /// Defaults to [TraceOptionsConfig::default].
+    pub fn with_trace_options(mut self, trace_options: TraceOptionsConfig) -> Self {
+        self.trace_options = trace_options;
+        self
+    }
+
+    /// Sets the number of blocks on top of the current head that transactions
+    /// requested with [CallTraceHandle::add_trace_at_head] are simulated against.
+    ///
+    /// Defaults to 0, i.e. simulating against the head block itself.
+    pub fn with_head_offset(mut self, head_offset: u64) -> Self {
+        self.head_offset = head_offset;
+        self
+    }
+
+    /// Enables polling the head block of the chain with `eth_getBlockByNumber` at the
+    /// given interval.
+    ///
+    /// Trace requests for blocks after the current head are buffered until the head
+    /// reaches them. Without polling, the head is only updated through
+    /// [CallTraceHandle::update_head].
+    ///
+    /// If the hash of a polled block number changes, the chain reorged: the block and
+    /// all the blocks above it are invalidated, as with [TraceCommand::InvalidateFrom].
+    pub fn with_head_poll_interval(mut self, interval: Duration) -> Self {
+        self.head_poll_interval = Some(interval);
+        self
+    }
+
+    /// Advances the head of the chain with the blocks yielded by the given [BlockTicker],
+    /// flushing the trace requests buffered for them.
+    ///
+    /// Unlike [Self::with_head_poll_interval], this doesn't detect reorgs.
+    pub fn with_block_ticker(mut self, ticker: BlockTicker) -> Self {
+        self.block_ticker = Some(ticker);
+        self
+    }
+
+    /// Sets the maximum number of blocks to keep accumulated diffs and pending
+    /// requests for. Once exceeded, the lowest blocks are evicted, and any fetch
+    /// request waiting on them receives [TraceError::Evicted].
+    ///
+    /// Defaults to [DEFAULT_MAX_TRACKED_BLOCKS].
+    pub fn with_max_tracked_blocks(mut self, max_tracked_blocks: usize) -> Self {
+        self.max_tracked_blocks = max_tracked_blocks;
+        self
+    }
+
+    /// Enables the dry-run mode, e.g. for local testing and demos without an execution
+    /// node: every trace request completes immediately with an empty state diff instead
+    /// of calling `debug_traceCall`. The queues and response channels work as usual, and
+    /// fetching the accumulated diffs of a block returns an empty override.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Persists the accumulated diffs to the given store, so that they survive restarts.
+    ///
+    /// The diffs saved by a previous run are restored right away, except the ones of the
+    /// blocks more than `prune_margin` blocks below the head, which are stale: they are
+    /// pruned as soon as the head is known. The diffs are then saved every
+    /// `snapshot_interval` on a blocking thread, and when the actor stops.
+    #[cfg(feature = "diff-store")]
+    pub fn with_diff_store<S: DiffStore + 'static>(
+        mut self,
+        store: S,
+        snapshot_interval: Duration,
+        prune_margin: u64,
+    ) -> Self {
+        match store.load() {
+            Ok(diffs) => {
+                tracing::info!(blocks = diffs.len(), "Restored accumulated state diffs");
+                for (block, diff) in diffs {
+                    self.tx_diffs.insert(
+                        block,
+                        vec![TxDiff {
+                            request_hash: None,
+                            trace: None,
+                            diff: diff.clone(),
+                        }],
+                    );
+                    self.accumulated_state_diffs.insert(block, Arc::new(diff));
+                }
+            }
+            Err(err) => tracing::error!(?err, "Failed to restore accumulated state diffs"),
+        }
+
+        self.diff_store = Some(Arc::new(Mutex::new(SharedDiffStore {
+            store: Box::new(store),
+            closed: false,
+        })));
+        self.diff_snapshot_interval = Some(snapshot_interval);
+        self.diff_store_prune_margin = Some(prune_margin);
+
+        if let Some(head) = self.head {
+            self.prune_restored_diffs(head);
+        }
+        self.evict_old_blocks();
+
+        self
+    }
+
+    /// Sets the maximum number of `debug_traceCall` requests in flight at once, across
+    /// all blocks. Traces ready to start beyond that wait for a slot to free up, and
+    /// the traces of a block still run in order. Values below 1 are treated as 1.
+    ///
+    /// Defaults to [DEFAULT_MAX_CONCURRENT_TRACES].
+    pub fn with_max_concurrent_traces(mut self, max_concurrent_traces: usize) -> Self {
+        self.max_concurrent_traces = max_concurrent_traces.max(1);
+        self
+    }
+
+    /// Sets the number of trace results to cache. Re-submitting a transaction traced
+    /// with the same tracer on top of the same block and accumulated diffs then
+    /// reuses the cached result instead of calling the RPC again. 0 disables the cache.
+    ///
+    /// Defaults to [DEFAULT_TRACE_CACHE_SIZE].
+    pub fn with_trace_cache_size(mut self, size: usize) -> Self {
+        self.trace_cache = NonZeroUsize::new(size).map(LruCache::new);
+        self
+    }
+
+    /// Returns the block against which transactions should be simulated,
+    /// computed as `head + head_offset`, or `None` if the head is not known yet.
+    pub fn simulation_target(&self) -> Option<BlockNumber> {
+        self.head.map(|head| head.saturating_add(self.head_offset))
+    }
+
+    fn handle_new_trace_command(&mut self, cmd: TraceCommand) {
+        self.process_trace_command(cmd);
+        self.update_gauges();
+    }
+
+    fn process_trace_command(&mut self, cmd: TraceCommand) {
+        match cmd {
+            TraceCommand::AddTrace { block, .. } if self.shutting_down => {
+                tracing::warn!(block = ?block, "Rejecting trace request while shutting down");
+            }
+            TraceCommand::AddTraceAtHead { res, .. } if self.shutting_down => {
+                tracing::warn!("Rejecting trace request at head while shutting down");
+                let _ = res.send(None);
+            }
+            TraceCommand::AddPendingTrace { res, .. } if self.shutting_down => {
+                tracing::warn!("Rejecting pending trace request while shutting down");
+                let _ = res.send(Err(PendingTraceError::ShuttingDown));
+            }
+            TraceCommand::AddTrace {
+                transaction,
+                block,
+                tracer,
+                block_overrides,
+            } => {
+                let _ = self.add_trace(transaction, block, tracer, block_overrides, None);
+            }
+            TraceCommand::AddPendingTrace {
+                transaction,
+                tracer,
+                res,
+            } => {
+                let _ =
+                    res.send(self.add_trace(transaction, BlockTarget::Pending, tracer, None, None));
+            }
+            TraceCommand::AddTraceAtHead {
+                transaction,
+                tracer,
+                res,
+            } => {
+                let (Some(head), Some(block)) = (self.head, self.simulation_target()) else {
+                    tracing::warn!("Received trace request at head, but the head is unknown");
+                    let _ = res.send(None);
+                    return;
+                };
+
+                // The target block isn't produced yet, so trace on the state of the head
+                let (state_block, block_overrides) = if block > head {
+                    let overrides = BlockOverrides {
+                        number: Some(U256::from(block)),
+                        ..Default::default()
+                    };
+                    (Some(head), Some(overrides))
+                } else {
+                    (None, None)
+                };
+
+                let _ = res.send(Some(block));
+                let _ = self.add_trace(
+                    transaction,
+                    BlockTarget::Number(block),
+                    tracer,
+                    block_overrides,
+                    state_block,
+                );
+            }
+            TraceCommand::SeedDiff { block, overrides } => {
+                tracing::debug!(block = block, "Seeding accumulated state diffs");
+
+                // The block failed, so any further trace would run on incomplete diffs
+                if self.failed_blocks.contains_key(&block) {
+                    tracing::warn!(block = block, "Dropping seed for failed block");
+                    return;
+                }
+
+                self.tx_diffs.entry(block).or_default().push(TxDiff {
+                    request_hash: None,
+                    trace: None,
+                    diff: overrides.clone(),
+                });
+                let acc_state_diffs = self.block_diffs_mut(block);
+                for (address, seed) in overrides {
+                    let account_override = acc_state_diffs.entry(address).or_default();
+                    merge_account_override(account_override, seed);
+                }
+
+                self.evict_old_blocks();
+            }
+            TraceCommand::UpdateHead { head } => self.set_head(head),
+            TraceCommand::FetchAccumulatedDiffs { block, id, res } => {
+                tracing::debug!(block = block, "Fetching accumulated state diffs");
+
+                if !self.is_block_pending(block) {
+                    // If there are no pending traces for the given block,
+                    // the result is already available
+                    let _ = res.send(self.take_result(block));
+                } else {
+                    // Otherwise, store the response channel to be used later once the last
+                    // pending trace request for that block has been processed and the diffs
+                    // are available.
+                    self.response_queue
+                        .entry(block)
+                        .or_default()
+                        .push((id, res));
+                }
+            }
+            TraceCommand::FetchAccumulatedDiffsRange { from, to, res } => {
+                tracing::debug!(
+                    from = from,
+                    to = to,
+                    "Fetching accumulated state diffs range"
+                );
+
+                self.range_fetch_queue.push((from..=to, res));
+                self.answer_range_fetches();
+            }
+            TraceCommand::ValidateBundle { block, res } => {
+                tracing::debug!(block = block, "Validating bundle");
+
+                if self.failed_blocks.contains_key(&block) {
+                    // The bundle can't be validated
+                    let _ = res.send(None);
+                    return;
+                }
+
+                if !self.is_block_pending(block) {
+                    let entries = self.bundles.get(&block).map_or(&[][..], Vec::as_slice);
+                    let _ = res.send(Some(validate_bundle(entries)));
+                } else {
+                    self.validation_queue.entry(block).or_default().push(res);
+                }
+            }
+            TraceCommand::SummarizeBundle { block, res } => {
+                tracing::debug!(block = block, "Summarizing bundle");
+
+                if self.failed_blocks.contains_key(&block) {
+                    // The bundle can't be summarized
+                    let _ = res.send(None);
+                    return;
+                }
+
+                if !self.is_block_pending(block) {
+                    let entries = self.bundles.get(&block).map_or(&[][..], Vec::as_slice);
+                    let _ = res.send(Some(summarize_bundle(entries)));
+                } else {
+                    self.summary_queue.entry(block).or_default().push(res);
+                }
+            }
+            TraceCommand::PeekAccumulatedDiffs { block, res } => {
+                let diffs = self
+                    .accumulated_state_diffs
+                    .get(&block)
+                    .map(|diffs| StateOverride::clone(diffs))
+                    .unwrap_or_default();
+
+                let _ = res.send(diffs);
+            }
+            TraceCommand::SubscribeDiffs { block, tx } => {
+                // Nothing left to merge, so the subscription ends right away
+                if !self.is_block_pending(block) {
+                    tracing::debug!(block = block, "No pending trace to subscribe to");
+                    return;
+                }
+
+                let subscribers = self.diff_subscribers.entry(block).or_default();
+                subscribers.retain(|tx| !tx.is_closed());
+                subscribers.push(tx);
+            }
+            TraceCommand::BlockStatus { block, res } => {
+                let _ = res.send(self.block_status(block));
+            }
+            TraceCommand::TouchedAddresses { block, res } => {
+                let addresses = self
+                    .accumulated_state_diffs
+                    .get(&block)
+                    .map(|diffs| diffs.keys().copied().collect())
+                    .unwrap_or_default();
+
+                let _ = res.send(addresses);
+            }
+            TraceCommand::TouchedAccounts { block, res } => {
+                let accounts = self
+                    .accumulated_state_diffs
+                    .get(&block)
+                    .map(|diffs| TouchedAccounts::from_diffs(diffs))
+                    .unwrap_or_default();
+
+                let _ = res.send(accounts);
+            }
+            TraceCommand::CancelTrace {
+                block,
+                request_hash,
+            } => self.cancel_trace(block, request_hash),
+            TraceCommand::RemoveTrace {
+                block,
+                request_hash,
+            } => self.remove_trace(block, request_hash),
+            TraceCommand::CancelFetch { block, id } => {
+                tracing::debug!(block = block, "Cancelling fetch of accumulated state diffs");
+
+                // Dropping the sender notifies the waiting fetcher
+                if let Some(fetchers) = self.response_queue.get_mut(&block) {
+                    fetchers.retain(|(fetch_id, _)| *fetch_id != id);
+                    if fetchers.is_empty() {
+                        self.response_queue.remove(&block);
+                    }
+                }
+            }
+            TraceCommand::ClearBlock { block } => {
+                tracing::debug!(block = block, "Clearing block");
+                self.remove_block(block, TraceError::Cleared { block });
+            }
+            TraceCommand::InvalidateFrom { block } => self.invalidate_from(block),
+            TraceCommand::Shutdown => {
+                tracing::info!("Shutting down the call trace manager");
+                self.shutting_down = true;
+
+                // Stop receiving new commands, while still processing the buffered ones
+                self.cmd_rx.close();
+
+                // Buffered traces for future blocks will never run
+                self.future_queue.clear();
+                self.head_poll_interval = None;
+                self.block_ticker = None;
+            }
+        }
+    }
+
+    /// Answers all the outstanding validation, summary and fetch requests with the diffs available so far.
+    /// Called once all the pending traces have completed during a shutdown.
+    fn finish_shutdown(&mut self) {
+        let waiting = self
+            .validation_queue
+            .keys()
+            .chain(self.summary_queue.keys())
+            .chain(self.response_queue.keys())
+            .copied()
+            .collect::<BTreeSet<_>>();
+        for block in waiting {
+            self.answer_waiters(block);
+        }
+
+        // Traces buffered for future blocks never run, so answer with what is available
+        for (range, res) in std::mem::take(&mut self.range_fetch_queue) {
+            self.send_range_diffs(range, res);
+        }
+
+        self.save_diffs();
+        self.update_gauges();
+        tracing::info!("Call trace manager shut down");
+    }
+
+    /// Returns the accumulated diffs of the given block to merge into, copying them
+    /// first if a trace in flight or a snapshot still shares them.
+    fn block_diffs_mut(&mut self, block: BlockNumber) -> &mut StateOverride {
+        let diffs = self.accumulated_state_diffs.entry(block).or_default();
+        if Arc::strong_count(diffs) > 1 {
+            #[cfg(feature = "metrics")]
+            metrics::counter!(TRACE_DIFF_COPIES_TOTAL).increment(1);
+            tracing::trace!(
+                block = block,
+                "Copying accumulated diffs shared with a trace"
+            );
+        }
+
+        Arc::make_mut(diffs)
+    }
+
+    /// Removes and returns the result of the given block: the trace error if any
+    /// trace failed, otherwise the accumulated diffs (empty if nothing was traced).
+    fn take_result(&mut self, block: BlockNumber) -> Result<StateOverride, TraceError> {
+        self.bundles.remove(&block);
+        self.seen_traces.remove(&block);
+        self.tx_diffs.remove(&block);
+        self.pending_blocks.remove(&block);
+
+        if let Some(err) = self.failed_blocks.remove(&block) {
+            return Err(err);
+        }
+
+        Ok(self
+            .accumulated_state_diffs
+            .remove(&block)
+            .map(Arc::unwrap_or_clone)
+            .unwrap_or_default())
+    }
+
+    /// Starts the trace call in the background if there is no pending task for the
+    /// same block and a slot is free, otherwise adds it to the queue to be processed
+    /// in order.
+    fn enqueue_trace(&mut self, trace: QueuedTrace, block: BlockNumber) {
+        let is_waiting = self.in_flight_blocks.contains_key(&block)
+            || self.trace_request_queue.contains_key(&block);
+
+        if !is_waiting && self.has_free_slot() {
+            self.start_new_trace_call_with_overrides(trace, block);
+            return;
+        }
+
+        trace
+            .span
+            .in_scope(|| tracing::debug!("Queueing trace request"));
+        self.trace_request_queue
+            .entry(block)
+            .or_default()
+            .push_back(trace);
+        if !is_waiting {
+            self.ready_blocks.push_back(block);
+        }
+    }
+
+    /// Queues the trace of a transaction on the given block. A pending target is resolved
+    /// once to the current head + 1, so that it doesn't move with the head.
+    ///
+    /// The trace runs on the state of `state_block` if given, and is buffered otherwise
+    /// while the block is in the future.
+    ///
+    /// Returns the resolved block, or why the request was dropped.
+    fn add_trace(
+        &mut self,
+        transaction: TransactionRequest,
+        block: BlockTarget,
+        tracer: Option<TracerKind>,
+        block_overrides: Option<BlockOverrides>,
+        state_block: Option<BlockNumber>,
+    ) -> Result<BlockNumber, PendingTraceError> {
+        let (block, pending) = match block {
+            BlockTarget::Number(block) => (block, false),
+            BlockTarget::Pending => {
+                let Some(head) = self.head else {
+                    tracing::warn!("Received pending trace request, but the head is unknown");
+                    return Err(PendingTraceError::UnknownHead);
+                };
+                (head + 1, true)
+            }
+        };
+        tracing::debug!(
+            block = block,
+            pending = pending,
+            "Received new transaction trace request"
+        );
+
+        // The block failed, so any further trace would run on incomplete diffs
+        if self.failed_blocks.contains_key(&block) {
+            tracing::warn!(block = block, "Dropping trace request for failed block");
+            return Err(PendingTraceError::FailedBlock { block });
+        }
+
+        // Tracing a re-submitted transaction again would apply its diff twice.
+        // Without a sender and nonce, repeating a call is legitimate (e.g. the
+        // same contract call made twice), so it is traced again.
+        let request_hash = trace_request_hash(&transaction);
+        let is_transaction = transaction.from.is_some() && transaction.nonce.is_some();
+        if is_transaction
+            && !self
+                .seen_traces
+                .entry(block)
+                .or_default()
+                .insert(request_hash)
+        {
+            tracing::debug!(block = block, %request_hash, "Skipping duplicate trace request");
+            return Ok(block);
+        }
+
+        let span = tracing::debug_span!(
+            "trace",
+            block = block,
+            request_hash = %request_hash,
+            from = ?transaction.from,
+        );
+        let trace = QueuedTrace {
+            transaction,
+            request_hash,
+            tracer: tracer.unwrap_or_else(|| self.tracer.clone()),
+            block_overrides,
+            state_block,
+            span,
+        };
+
+        if pending {
+            self.pending_blocks.insert(block);
+        }
+
+        // If the block is in the future, buffer the request until the head reaches it.
+        // A trace on the state of another block is buffered only behind earlier ones.
+        let is_future = trace.state_block.is_none() && self.head.is_some_and(|head| block > head);
+        if is_future || self.future_queue.contains_key(&block) {
+            let _guard = trace.span.enter();
+            tracing::debug!("Buffering trace request for future block");
+            self.future_queue.entry(block).or_default().push_back(trace);
+        } else {
+            self.enqueue_trace(trace, block);
+        }
+
+        self.evict_old_blocks();
+
+        Ok(block)
+    }
+
+    /// Returns the progress of the traces of the given block.
+    fn block_status(&self, block: BlockNumber) -> BlockTraceStatus {
+        let queued = [&self.trace_request_queue, &self.future_queue]
+            .iter()
+            .filter_map(|queue| queue.get(&block))
+            .map(VecDeque::len)
+            .sum();
+
+        let waiters = self.response_queue.get(&block).map_or(0, Vec::len)
+            + self.validation_queue.get(&block).map_or(0, Vec::len)
+            + self.summary_queue.get(&block).map_or(0, Vec::len)
+            + self
+                .range_fetch_queue
+                .iter()
+                .filter(|(range, _)| range.contains(&block))
+                .count();
+
+        BlockTraceStatus {
+            in_flight: usize::from(self.in_flight_blocks.contains_key(&block)),
+            queued,
+            has_diffs: self
+                .accumulated_state_diffs
+                .get(&block)
+                .is_some_and(|diffs| !diffs.is_empty()),
+            waiters,
+            failed: self.failed_blocks.contains_key(&block),
+            pending: self.pending_blocks.contains(&block),
+        }
+    }
+
+    /// Returns whether traces are still to be run for the given block.
+    fn is_block_pending(&self, block: BlockNumber) -> bool {
+        self.in_flight_blocks.contains_key(&block)
+            || self.trace_request_queue.contains_key(&block)
+            || self.future_queue.contains_key(&block)
+    }
+
+    /// Returns whether another trace call can be started without exceeding the
+    /// maximum number of traces in flight. Aborted traces don't take a slot.
+    fn has_free_slot(&self) -> bool {
+        self.in_flight_traces.len() < self.max_concurrent_traces
+    }
+
+    /// Starts the next queued trace of the blocks waiting for a slot, in order,
+    /// as long as slots are free.
+    fn start_ready_traces(&mut self) {
+        while self.has_free_slot() {
+            let Some(block) = self.ready_blocks.pop_front() else {
+                break;
+            };
+
+            // The block may have been removed or cancelled while waiting
+            if self.in_flight_blocks.contains_key(&block) {
+                continue;
+            }
+            let Some(transactions) = self.trace_request_queue.get_mut(&block) else {
+                continue;
+            };
+            let Some(trace) = transactions.pop_front() else {
+                continue;
+            };
+            if transactions.is_empty() {
+                self.trace_request_queue.remove(&block);
+            }
+
+            self.start_new_trace_call_with_overrides(trace, block);
+        }
+    }
+
+    /// Updates the head of the chain, and moves the buffered trace requests
+    /// for the blocks it reached to the normal trace path, in order.
+    fn set_head(&mut self, head: BlockNumber) {
+        if self.head.is_some_and(|current| current >= head) {
+            return;
+        }
+
+        tracing::debug!(head = head, "Updating head block");
+        self.head = Some(head);
+
+        #[cfg(feature = "diff-store")]
+        self.prune_restored_diffs(head);
+
+        let mut ready = self
+            .future_queue
+            .keys()
+            .copied()
+            .filter(|block| *block <= head)
+            .collect::<Vec<_>>();
+        ready.sort_unstable();
+
+        for block in ready {
+            let Some(transactions) = self.future_queue.remove(&block) else {
+                continue;
+            };
+
+            tracing::debug!(
+                block = block,
+                count = transactions.len(),
+                "Flushing future traces"
+            );
+            for trace in transactions {
+                self.enqueue_trace(trace, block);
+            }
+        }
+    }
+
+    /// Updates the head of the chain from a polled head block, invalidating the tracked
+    /// blocks from the fork point upwards on a reorg: if the hash of the head changed, or
+    /// if its parent isn't the block previously seen at its height, e.g. when the reorg
+    /// and the next block happened between two polls.
+    fn set_head_block(&mut self, head: BlockHashes) {
+        let number = head.number.to::<BlockNumber>();
+        let replaced = |block: BlockNumber, hash: B256| {
+            self.block_hashes
+                .get(&block)
+                .is_some_and(|known| *known != hash)
+        };
+
+        let fork_point = if replaced(number, head.hash) {
+            Some(number)
+        } else if number > 0 && replaced(number - 1, head.parent_hash) {
+            Some(number - 1)
+        } else {
+            None
+        };
+
+        if let Some(from) = fork_point {
+            tracing::warn!(block = from, hash = %head.hash, "Reorg detected");
+            self.invalidate_from(from);
+
+            // The new head may be lower than the previous one
+            self.head = self.head.map(|current| current.min(from));
+        }
+
+        if number > 0 {
+            self.block_hashes.insert(number - 1, head.parent_hash);
+        }
+        self.block_hashes.insert(number, head.hash);
+        while self.block_hashes.len() > self.max_tracked_blocks {
+            self.block_hashes.pop_first();
+        }
+
+        self.set_head(number);
+    }
+
+    /// Drops the accumulated diffs and pending requests of the given block and all
+    /// the blocks above it, answering the waiting fetchers with [TraceError::Reorged].
+    fn invalidate_from(&mut self, from: BlockNumber) {
+        for block in self.tracked_blocks().split_off(&from) {
+            tracing::warn!(block = block, "Invalidating block");
+            self.remove_block(block, TraceError::Reorged { block });
+        }
+
+        self.block_hashes.retain(|block, _| *block < from);
+
+        // Cached traces were computed on top of the old chain
+        if let Some(cache) = self.trace_cache.as_mut() {
+            let stale = cache
+                .iter()
+                .map(|(key, _)| *key)
+                .filter(|key| key.block >= from)
+                .collect::<Vec<_>>();
+
+            for key in stale {
+                cache.pop(&key);
+            }
+        }
+    }
+
+    /// Evicts the lowest tracked blocks until at most `max_tracked_blocks` remain.
+    fn evict_old_blocks(&mut self) {
+        let tracked = self.tracked_blocks();
+
+        let excess = tracked.len().saturating_sub(self.max_tracked_blocks);
+        for block in tracked.into_iter().take(excess) {
+            tracing::warn!(
+                block = block,
+                max_tracked_blocks = self.max_tracked_blocks,
+                "Evicting old block from the call trace manager"
+            );
+
+            self.remove_block(block, TraceError::Evicted { block });
+        }
+    }
+
+    /// Returns all the blocks the actor holds diffs, requests or errors for.
+    fn tracked_blocks(&self) -> BTreeSet<BlockNumber> {
+        self.accumulated_state_diffs
+            .keys()
+            .chain(self.trace_request_queue.keys())
+            .chain(self.future_queue.keys())
+            .chain(self.response_queue.keys())
+            .chain(self.in_flight_blocks.keys())
+            .chain(self.failed_blocks.keys())
+            .chain(self.seen_traces.keys())
+            .copied()
+            .collect()
+    }
+
+    /// Drops everything the actor holds for the given block, answering the waiting
+    /// fetcher with the given error. The result of a trace in flight for the block
+    /// is ignored when it returns.
+    fn remove_block(&mut self, block: BlockNumber, err: TraceError) {
+        self.accumulated_state_diffs.remove(&block);
+        self.tx_diffs.remove(&block);
+        self.bundles.remove(&block);
+        self.diff_subscribers.remove(&block);
+        self.trace_request_queue.remove(&block);
+        self.future_queue.remove(&block);
+        self.in_flight_blocks.remove(&block);
+        self.failed_blocks.remove(&block);
+        self.seen_traces.remove(&block);
+        self.pending_blocks.remove(&block);
+
+        // Don't leave the waiters hanging: the fetchers receive the error like for a
+        // failed block, and the validation and summary requests get `None`
+        self.failed_blocks.insert(block, err);
+        self.answer_waiters(block);
+        self.failed_blocks.remove(&block);
+        self.answer_range_fetches();
+    }
+
+    /// Answers the range fetch requests without pending traces left in their range,
+    /// and drops the ones whose requester is gone.
+    fn answer_range_fetches(&mut self) {
+        for (range, res) in std::mem::take(&mut self.range_fetch_queue) {
+            if res.is_closed() {
+                continue;
+            }
+
+            if range.clone().any(|block| self.is_block_pending(block)) {
+                self.range_fetch_queue.push((range, res));
+            } else {
+                self.send_range_diffs(range, res);
+            }
+        }
+    }
+
+    /// Sends the non-empty accumulated diffs of the blocks of the given range.
+    fn send_range_diffs(&self, range: RangeInclusive<BlockNumber>, res: RangeFetchSender) {
+        if res.is_closed() {
+            return;
+        }
+
+        let diffs = self
+            .accumulated_state_diffs
+            .iter()
+            .filter(|(block, diffs)| range.contains(block) && !diffs.is_empty())
+            .map(|(block, diffs)| (*block, StateOverride::clone(diffs)))
+            .collect();
+        let _ = res.send(diffs);
+    }
+
+    fn handle_trace_result(
+        &mut self,
+        block: BlockNumber,
+        id: u64,
+        result: TransportResult<GethTrace>,
+    ) {
+        self.process_trace_result(block, id, result);
+        self.start_ready_traces();
+        self.update_gauges();
+    }
+
+    fn handle_new_head(&mut self, head: BlockNumber) {
+        self.set_head(head);
+        self.update_gauges();
+    }
+
+    fn handle_new_head_block(&mut self, head: BlockHashes) {
+        self.set_head_block(head);
+        self.update_gauges();
+    }
+
+    /// Saves the accumulated diffs to the store, if any, once the snapshot being saved in
+    /// the background is done. Called when the actor stops. Does nothing without the
+    /// `diff-store` feature.
+    fn save_diffs(&self) {
+        #[cfg(feature = "diff-store")]
+        if let Some(store) = &self.diff_store {
+            save_snapshot(store, &self.diffs_snapshot(), true);
+        }
+    }
+
+    /// Saves a snapshot of the accumulated diffs on a blocking thread, so that writing it
+    /// doesn't hold up the actor. Skipped while the previous snapshot is still being saved.
+    #[cfg(feature = "diff-store")]
+    fn snapshot_diffs(&mut self) {
+        let Some(store) = &self.diff_store else {
+            return;
+        };
+
+        if self
+            .diff_snapshot_task
+            .as_ref()
+            .is_some_and(|task| !task.is_finished())
+        {
+            tracing::debug!("Skipping snapshot, the previous one is still being saved");
+            return;
+        }
+
+        let store = Arc::clone(store);
+        let diffs = self.diffs_snapshot();
+        self.diff_snapshot_task = Some(tokio::task::spawn_blocking(move || {
+            save_snapshot(&store, &diffs, false)
+        }));
+    }
+
+    /// Returns the accumulated diffs of every block to save to the store. They are shared
+    /// with the actor rather than copied: a merge copies the diffs of a block first if a
+    /// snapshot still holds them.
+    #[cfg(feature = "diff-store")]
+    fn diffs_snapshot(&self) -> HashMap<BlockNumber, Arc<StateOverride>> {
+        self.accumulated_state_diffs.clone()
+    }
+
+    /// Drops the restored diffs of the blocks more than the configured margin below the
+    /// given head, once after they were restored.
+    #[cfg(feature = "diff-store")]
+    fn prune_restored_diffs(&mut self, head: BlockNumber) {
+        let Some(margin) = self.diff_store_prune_margin.take() else {
+            return;
+        };
+
+        let min_block = head.saturating_sub(margin);
+        self.tx_diffs.retain(|block, _| *block >= min_block);
+        self.accumulated_state_diffs.retain(|block, _| {
+            let keep = *block >= min_block;
+            if !keep {
+                tracing::debug!(block = block, "Pruning stale restored state diffs");
+            }
+            keep
+        });
+    }
+
+    /// Updates the queue-depth gauges. Does nothing without the `metrics` feature.
+    fn update_gauges(&self) {
+        #[cfg(feature = "metrics")]
+        {
+            let queued = self
+                .trace_request_queue
+                .values()
+                .chain(self.future_queue.values())
+                .map(VecDeque::len)
+                .sum::<usize>();
+
+            metrics::gauge!(TRACE_IN_FLIGHT).set(self.pending_traces.len() as f64);
+            metrics::gauge!(TRACE_QUEUED_TRANSACTIONS).set(queued as f64);
+            metrics::gauge!(TRACE_TRACKED_BLOCKS).set(self.tracked_blocks().len() as f64);
+            let fetchers = self.response_queue.values().map(Vec::len).sum::<usize>();
+            metrics::gauge!(TRACE_WAITING_FETCHERS).set(fetchers as f64);
+        }
+    }
+
+    /// Removes the given transaction from the queues of the block, and aborts its trace
+    /// if it is in flight. The aborted trace is never merged in the accumulated diffs.
+    fn cancel_trace(&mut self, block: BlockNumber, request_hash: B256) {
+        let mut removed = false;
+        for queue in [&mut self.trace_request_queue, &mut self.future_queue] {
+            if let Some(transactions) = queue.get_mut(&block) {
+                let len = transactions.len();
+                transactions.retain(|trace| trace.request_hash != request_hash);
+                removed |= transactions.len() < len;
+
+                if transactions.is_empty() {
+                    queue.remove(&block);
+                }
+            }
+        }
+
+        // The transaction can be submitted again, unless it was already merged
+        if removed {
+            self.forget_trace(block, request_hash);
+        }
+
+        let Some(id) = self.in_flight_blocks.get(&block).copied() else {
+            // The block may have been waiting for a slot with only the cancelled trace
+            if !self.is_block_pending(block) {
+                self.advance_block(block);
+            }
+            return;
+        };
+        if !self
+            .in_flight_traces
+            .get(&id)
+            .is_some_and(|trace| trace.request_hash == request_hash)
+        {
+            return;
+        }
+
+        if let Some(in_flight) = self.in_flight_traces.remove(&id) {
+            in_flight
+                .trace
+                .span
+                .in_scope(|| tracing::debug!(block = block, "Aborting trace in flight"));
+            in_flight.abort.abort();
+        }
+        self.in_flight_cache_keys.remove(&id);
+        self.in_flight_bundle_entries.remove(&id);
+        self.in_flight_blocks.remove(&block);
+        self.forget_trace(block, request_hash);
+
+        self.advance_block(block);
+        self.start_ready_traces();
+    }
+
+    /// Removes the given transaction from the ones seen on the block.
+    fn forget_trace(&mut self, block: BlockNumber, request_hash: B256) {
+        if let Some(seen) = self.seen_traces.get_mut(&block) {
+            seen.remove(&request_hash);
+            if seen.is_empty() {
+                self.seen_traces.remove(&block);
+            }
+        }
+    }
+
+    /// Removes the given transaction from the block. If its diff was merged, the traces
+    /// merged after it ran on top of it: their diffs are dropped, and they are queued again
+    /// in order, followed by the trace in flight (aborted) and the queued ones. The seeded
+    /// diffs are kept. Otherwise, its trace is cancelled if it is still pending.
+    fn remove_trace(&mut self, block: BlockNumber, request_hash: B256) {
+        let Some(tx_diffs) = self.tx_diffs.get_mut(&block) else {
+            self.cancel_trace(block, request_hash);
+            return;
+        };
+        let Some(index) = tx_diffs
+            .iter()
+            .position(|tx_diff| tx_diff.request_hash == Some(request_hash))
+        else {
+            self.cancel_trace(block, request_hash);
+            return;
+        };
+
+        tracing::debug!(
+            block = block,
+            %request_hash,
+            "Removing merged trace from accumulated diffs"
+        );
+        let later = tx_diffs.split_off(index + 1);
+        tx_diffs.truncate(index);
+
+        let (seeds, traced): (Vec<_>, Vec<_>) = later
+            .into_iter()
+            .partition(|tx_diff| tx_diff.trace.is_none());
+        tx_diffs.extend(seeds);
+
+        let acc_state_diffs = accumulate_tx_diffs(tx_diffs);
+        if tx_diffs.is_empty() {
+            self.tx_diffs.remove(&block);
+        }
+        if acc_state_diffs.is_empty() {
+            self.accumulated_state_diffs.remove(&block);
+        } else {
+            self.accumulated_state_diffs
+                .insert(block, Arc::new(acc_state_diffs));
+        }
+
+        let requeued = traced
+            .iter()
+            .filter_map(|tx_diff| tx_diff.request_hash)
+            .collect::<HashSet<_>>();
+        if let Some(bundle) = self.bundles.get_mut(&block) {
+            bundle.retain(|entry| {
+                entry.request_hash != request_hash && !requeued.contains(&entry.request_hash)
+            });
+        }
+        self.forget_trace(block, request_hash);
+
+        let traces = traced
+            .into_iter()
+            .filter_map(|tx_diff| tx_diff.trace)
+            .collect();
+        self.requeue_traces(block, traces);
+        self.notify_diff_subscribers(block);
+        self.start_ready_traces();
+    }
+
+    /// Queues the given traces of the block again, followed by the trace in flight on the
+    /// block, which is aborted, and the queued ones, e.g. after the state they ran on top
+    /// of changed.
+    fn requeue_traces(&mut self, block: BlockNumber, mut traces: VecDeque<QueuedTrace>) {
+        if let Some(id) = self.in_flight_blocks.remove(&block) {
+            self.in_flight_cache_keys.remove(&id);
+            self.in_flight_bundle_entries.remove(&id);
+            if let Some(in_flight) = self.in_flight_traces.remove(&id) {
+                in_flight.abort.abort();
+                traces.push_back(in_flight.trace);
+            }
+        }
+        traces.extend(self.trace_request_queue.remove(&block).unwrap_or_default());
+
+        if !traces.is_empty() {
+            self.trace_request_queue.insert(block, traces);
+            self.ready_blocks.push_back(block);
+        }
+    }
+
+    fn process_trace_result(
+        &mut self,
+        block: BlockNumber,
+        id: u64,
+        result: TransportResult<GethTrace>,
+    ) {
+        // The block was evicted or invalidated while the trace was in flight,
+        // so the result is stale
+        let cache_key = self.in_flight_cache_keys.remove(&id);
+        let bundle_entry = self.in_flight_bundle_entries.remove(&id);
+        let (request_hash, trace_request) = match self.in_flight_traces.remove(&id) {
+            Some(in_flight) => (Some(in_flight.request_hash), Some(in_flight.trace)),
+            None => (None, None),
+        };
+        let span = trace_request
+            .as_ref()
+            .map_or_else(Span::none, |trace| trace.span.clone());
+        let guard = span.enter();
+        if self.in_flight_blocks.get(&block) != Some(&id) {
+            tracing::debug!(block = block, "Dropping stale trace result");
+            return;
+        }
+        self.in_flight_blocks.remove(&block);
+
+        if let (Ok(trace), Some(key), Some(cache)) = (&result, cache_key, self.trace_cache.as_mut())
+        {
+            cache.put(key, trace.clone());
+        }
+
+        let frame = match result {
+            Ok(GethTrace::CallTracer(frame)) if frame.error.is_some() => {
+                tracing::debug!(block = block, error = ?frame.error, "Traced transaction reverted");
+                Err(TraceError::Reverted {
+                    block,
+                    reason: frame.output.as_deref().and_then(RevertReason::decode),
+                })
+            }
+            Ok(trace) => {
+                tracing::debug!(block = block, "RPC trace call completed");
+                pre_state_frame(trace).map_err(|err| TraceError::InvalidTrace {
+                    block,
+                    reason: err.to_string(),
+                })
+            }
+            Err(source) => Err(TraceError::Rpc { block, source }),
+        };
+
+        match frame {
+            Ok(frame) => {
+                // In diff mode, the state left by the transaction is accumulated
+                let (pre_state, post_state) = match frame {
+                    PreStateFrame::Default(PreStateMode(pre)) => (pre, None),
+                    PreStateFrame::Diff(DiffMode { pre, post }) => (pre, Some(post)),
+                };
+
+                if let Some(mut entry) = bundle_entry {
+                    if let Some(sender) = entry.sender.and_then(|sender| pre_state.get(&sender)) {
+                        // Nodes omit the nonce of accounts that never sent a transaction
+                        entry.pre_nonce = Some(sender.nonce.unwrap_or_default());
+                        entry.pre_balance = sender.balance;
+                    }
+                    self.bundles.entry(block).or_default().push(entry);
+                }
+
+                let tx_diff = match post_state {
+                    Some(post_state) => post_state_override(pre_state, post_state),
+                    None => {
+                        let mut tx_diff = StateOverride::default();
+                        for (address, account_state) in pre_state {
+                            let account_override = tx_diff.entry(address).or_default();
+                            merge_account_state_in_overrides(account_override, account_state);
+                        }
+                        tx_diff
+                    }
+                };
+
+                // Store the updated accumulated state diffs for the given block
+                let acc_state_diffs = self.block_diffs_mut(block);
+                for (address, account_override) in &tx_diff {
+                    let acc_override = acc_state_diffs.entry(*address).or_default();
+                    merge_account_override(acc_override, account_override.clone());
+                }
+                self.tx_diffs.entry(block).or_default().push(TxDiff {
+                    request_hash,
+                    trace: trace_request,
+                    diff: tx_diff,
+                });
+                tracing::debug!(block = block, "Merged trace result in accumulated diffs");
+
+                self.notify_diff_subscribers(block);
+            }
+            Err(err) => {
+                // The following transactions of the bundle depend on this one, so their traces
+                // would run on incomplete diffs: fail the whole block instead.
+                tracing::error!(block = block, err = ?err, "Failed to trace transaction");
+
+                self.accumulated_state_diffs.remove(&block);
+                self.tx_diffs.remove(&block);
+                self.bundles.remove(&block);
+                self.trace_request_queue.remove(&block);
+                self.failed_blocks.insert(block, err);
+            }
+        }
+
+        // The next trace of the block runs in its own span
+        drop(guard);
+        self.advance_block(block);
+    }
+
+    /// Sends a snapshot of the accumulated diffs of the block to its subscribers, and
+    /// drops the subscribers that are gone.
+    fn notify_diff_subscribers(&mut self, block: BlockNumber) {
+        let Some(subscribers) = self.diff_subscribers.get_mut(&block) else {
+            return;
+        };
+
+        let diffs = self
+            .accumulated_state_diffs
+            .get(&block)
+            .map(|diffs| StateOverride::clone(diffs))
+            .unwrap_or_default();
+        subscribers.retain(|tx| match tx.try_send(diffs.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                tracing::warn!(block = block, "Diff subscriber lagging, dropping snapshot");
+                true
+            }
+            Err(TrySendError::Closed(_)) => false,
+        });
+
+        if subscribers.is_empty() {
+            self.diff_subscribers.remove(&block);
+        }
+    }
+
+    /// Starts the next queued trace of the given block once the previous one completed,
+    /// or answers the waiting requests if there is none left.
+    fn advance_block(&mut self, block: BlockNumber) {
+        // If there are more pending trace requests for the same block, process the next one
+        // once a slot is free, after the blocks already waiting for one
+        if self.trace_request_queue.contains_key(&block) {
+            self.ready_blocks.push_back(block);
+            self.start_ready_traces();
+            return;
+        }
+
+        // If there are no more transactions to process for this block, end the
+        // subscriptions and answer the pending validation, summary and range fetches before
+        // the fetch consumes the bundle and diffs
+        self.diff_subscribers.remove(&block);
+        self.answer_range_fetches();
+        self.answer_waiters(block);
+    }
+
+    /// Answers the requests waiting for the traces of the given block to complete: the
+    /// validation and summary requests with its bundle, unless the block failed, and the
+    /// fetchers with its result, which is consumed.
+    fn answer_waiters(&mut self, block: BlockNumber) {
+        let failed = self.failed_blocks.contains_key(&block);
+        let entries = self.bundles.get(&block).map_or(&[][..], Vec::as_slice);
+        // The bundle of a failed block can't be validated nor summarized
+        for res in self.validation_queue.remove(&block).unwrap_or_default() {
+            let _ = res.send((!failed).then(|| validate_bundle(entries)));
+        }
+        for res in self.summary_queue.remove(&block).unwrap_or_default() {
+            let _ = res.send((!failed).then(|| summarize_bundle(entries)));
+        }
+
+        // If the fetchers are gone, keep the result around for a later request
+        let fetchers = self
+            .response_queue
+            .remove(&block)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(_, res)| !res.is_closed())
+            .map(|(_, res)| res)
+            .collect::<Vec<_>>();
+        if fetchers.is_empty() {
+            return;
+        }
+
+        match self.take_result(block) {
+            Ok(diffs) => {
+                for res in fetchers {
+                    let _ = res.send(Ok(diffs.clone()));
+                }
+            }
+            Err(err) => {
+                for res in fetchers {
+                    let _ = res.send(Err(err.duplicate()));
+                }
+            }
+        }
+    }
+
+    fn start_new_trace_call_with_overrides(&mut self, trace: QueuedTrace, block: BlockNumber) {
+        let span = trace.span.clone();
+        let _guard = span.enter();
+        let mut transaction = trace.transaction.clone();
+        let block_overrides = trace.block_overrides.clone();
+
+        let rpc = self.rpc.clone();
+        // Shared with the trace rather than copied, see `block_diffs_mut`
+        let state_override = self
+            .accumulated_state_diffs
+            .get(&block)
+            .cloned()
+            .unwrap_or_default();
+
+        let tracer = trace.tracer.tracer_type(&transaction);
+        let request_hash = trace.request_hash;
+        let state_block = trace.state_block.unwrap_or(block);
+
+        let id = self.next_trace_id;
+        self.next_trace_id += 1;
+        self.in_flight_blocks.insert(block, id);
+
+        self.in_flight_bundle_entries
+            .insert(id, BundleEntry::new(&transaction, request_hash));
+
+        if self.dry_run {
+            tracing::debug!("Completing trace with an empty diff in dry-run mode");
+
+            // Go through the same path as the RPC results, to keep the per-block ordering
+            let result =
+                GethTrace::PreStateTracer(PreStateFrame::Default(PreStateMode(Default::default())));
+            let task = tokio::spawn(async move { (block, id, Ok(result)) });
+            self.push_trace(id, request_hash, trace, task);
+            return;
+        }
+
+        if let Some(cache) = self.trace_cache.as_mut() {
+            if let Some(key) = TraceCacheKey::new(
+                block,
+                &transaction,
+                &tracer,
+                &state_override,
+                block_overrides.as_ref(),
+            ) {
+                if let Some(result) = cache.get(&key).cloned() {
+                    tracing::debug!(block = block, "Reusing cached trace result");
+
+                    // Go through the same path as the RPC results, to keep the per-block ordering
+                    let task = tokio::spawn(async move { (block, id, Ok(result)) });
+                    self.push_trace(id, request_hash, trace, task);
+                    return;
+                }
+
+                self.in_flight_cache_keys.insert(id, key);
+            }
+        }
+
+        // The options are the same for all the traces, so the cache keys don't need them
+        self.trace_options.cap_gas(&mut transaction);
+        let tracing_options =
+            get_trace_options_with_override(tracer, &self.trace_options, block_overrides);
+        tracing::debug!("Starting trace call");
+        let task = tokio::spawn(
+            async move {
+                let start = Instant::now();
+                let result = rpc
+                    .debug_trace_call_with_shared_state(
+                        transaction,
+                        BlockNumberOrTag::Number(state_block),
+                        state_override,
+                        Some(tracing_options),
+                    )
+                    .await;
+                tracing::debug!(elapsed = ?start.elapsed(), "debug_traceCall completed");
+
+                (block, id, result)
+            }
+            .instrument(span.clone()),
+        );
+        self.push_trace(id, request_hash, trace, task);
+    }
+
+    /// Tracks the given trace task as in flight.
+    fn push_trace(&mut self, id: u64, request_hash: B256, trace: QueuedTrace, task: TraceFuture) {
+        let abort = task.abort_handle();
+        self.in_flight_traces.insert(
+            id,
+            InFlightTrace {
+                request_hash,
+                abort,
+                trace,
+            },
+        );
+        self.pending_traces.push(task);
+    }
+}
+
+fn get_trace_options_with_override(
+    tracer: GethDebugTracerType,
+    options: &TraceOptionsConfig,
+    block_overrides: Option<BlockOverrides>,
+) -> GethDebugTracingCallOptions {
+    let mut opts = GethDebugTracingOptions::default().with_tracer(tracer);
+
+    opts.config = GethDefaultTracingOptions::default()
+        .with_disable_storage(!options.storage)
+        .with_disable_memory(!options.memory)
+        .with_disable_return_data(!options.return_data)
+        .with_disable_stack(!options.stack);
+
+    // Recent nodes ignore the deprecated `disable*` flags of the memory and return data
+    if options.memory {
+        opts.config = opts.config.with_enable_memory(true);
+    }
+    if options.return_data {
+        opts.config = opts.config.with_enable_return_data(true);
+    }
+    opts.timeout = options.timeout.clone();
+
+    let is_pre_state = opts.tracer
+        == Some(GethDebugTracerType::BuiltInTracer(
+            GethDebugBuiltInTracerType::PreStateTracer,
+        ));
+    if options.diff_mode && is_pre_state {
+        opts = opts.with_prestate_config(PreStateConfig {
+            diff_mode: Some(true),
+        });
+    }
+
+    let call_opts = GethDebugTracingCallOptions::default().with_tracing_options(opts);
+
+    match block_overrides {
+        Some(block_overrides) => call_opts.with_block_overrides(block_overrides),
+        None => call_opts,
+    }
+}
+
+/// Extracts the pre-state frame of a trace result. An empty JSON object is deserialized
+/// as the frame of another tracer, so it is read as an empty pre-state instead.
+fn pre_state_frame(trace: GethTrace) -> Result<PreStateFrame, UnexpectedTracerError> {
+    match trace {
+        GethTrace::NoopTracer(_) => Ok(PreStateFrame::Default(Default::default())),
+        GethTrace::FourByteTracer(frame) if frame.0.is_empty() => {
+            Ok(PreStateFrame::Default(Default::default()))
+        }
+        trace => trace.try_into_pre_state_frame(),
+    }
+}
+
+/// Merges the state of an account reported by a trace into the accumulated override.
+///
+/// Fields that are not reported by the trace (`None`) keep their previously accumulated
+/// value. Storage slots set back to zero are kept as explicit zero entries rather than
+/// removed, so that they keep shadowing the (possibly non-zero) value on chain.
+fn merge_account_state_in_overrides(account_override: &mut AccountOverride, value: AccountState) {
+    if let Some(balance) = value.balance {
+        account_override.balance = Some(balance);
+    }
+    if let Some(nonce) = value.nonce {
+        account_override.nonce = Some(U64::from(nonce));
+    }
+    if let Some(code) = value.code {
+        account_override.code = Some(code);
+    }
+
+    if !value.storage.is_empty() {
+        // Nodes reject overrides with both a full state and a state diff
+        let storage = match account_override.state.as_mut() {
+            Some(state) => state,
+            None => account_override.state_diff.get_or_insert_with(HashMap::new),
+        };
+        storage.extend(value.storage);
+    }
+}
+
+/// Builds the override of the state left by a transaction from its diff-mode trace.
+///
+/// The post-state only reports the fields changed by the transaction. It omits the
+/// storage slots cleared to zero and the accounts deleted by the transaction, which
+/// are only reported in the pre-state.
+fn post_state_override(
+    pre: BTreeMap<Address, AccountState>,
+    post: BTreeMap<Address, AccountState>,
+) -> StateOverride {
+    let mut state_override = StateOverride::default();
+
+    for (address, pre_state) in pre {
+        let account_override = state_override.entry(address).or_default();
+
+        let Some(mut post_state) = post.get(&address).cloned() else {
+            *account_override = AccountOverride {
+                balance: Some(U256::ZERO),
+                nonce: Some(U64::ZERO),
+                code: Some(Bytes::new()),
+                state: Some(HashMap::new()),
+                ..Default::default()
+            };
+            continue;
+        };
+
+        for slot in pre_state.storage.into_keys() {
+            post_state.storage.entry(slot).or_insert(B256::ZERO);
+        }
+        merge_account_state_in_overrides(account_override, post_state);
+    }
+
+    // Accounts created by the transaction are not in the pre-state
+    for (address, post_state) in post {
+        if !state_override.contains_key(&address) {
+            let account_override = state_override.entry(address).or_default();
+            merge_account_state_in_overrides(account_override, post_state);
+        }
+    }
+
+    state_override
+}
+
+/// Merges the given diffs into a new accumulated override, in order.
+fn accumulate_tx_diffs(tx_diffs: &[TxDiff]) -> StateOverride {
+    let mut acc_state_diffs = StateOverride::default();
+    for (address, account_override) in tx_diffs.iter().flat_map(|tx_diff| &tx_diff.diff) {
+        let acc_override = acc_state_diffs.entry(*address).or_default();
+        merge_account_override(acc_override, account_override.clone());
+    }
+
+    acc_state_diffs
+}
+
+/// Merges the given seeded account override into the accumulated one, the seed taking
+/// precedence. A full storage override replaces the accumulated storage.
+fn merge_account_override(account_override: &mut AccountOverride, seed: AccountOverride) {
+    if let Some(balance) = seed.balance {
+        account_override.balance = Some(balance);
+    }
+    if let Some(nonce) = seed.nonce {
+        account_override.nonce = Some(nonce);
+    }
+    if let Some(code) = seed.code {
+        account_override.code = Some(code);
+    }
+
+    if let Some(state) = seed.state {
+        account_override.state = Some(state);
+        account_override.state_diff = None;
+    }
+    if let Some(state_diff) = seed.state_diff {
+        let storage = match account_override.state.as_mut() {
+            Some(state) => state,
+            None => account_override.state_diff.get_or_insert_with(HashMap::new),
+        };
+        storage.extend(state_diff);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use alloy_json_rpc::{ErrorPayload, RpcError};
+    use alloy_primitives::{address, hex, Bytes, B256, U256};
+    use alloy_rpc_types::TransactionRequest;
+    use alloy_rpc_types_trace::geth::PreStateMode;
+    use reqwest::header::HeaderMap;
+    use serde_json::{json, Value};
+
+    use crate::test_util::{launch_anvil, MockResponse, MockRpc, MockRpcServer};
+
+    use super::*;
+
+    /// The address of the mock counter contract
+    const COUNTER: Address = address!("00000000000000000000000000000000000c0de0");
+
+    /// Calldata of `increment()`: increments the counter in slot 0
+    const INCREMENT: [u8; 4] = [0xd0, 0x9d, 0xe0, 0x8a];
+
+    /// Calldata of `reset()`: sets the counter in slot 0 back to zero
+    const RESET: [u8; 4] = [0xd8, 0x26, 0xf8, 0x8f];
+
+    /// The sender of the bundles in the validation tests.
+    const SENDER: Address = address!("0000000000000000000000000000000000005e4d");
+
+    /// Reads the counter value from the state overrides in the `debug_traceCall` params.
+    fn counter_override(params: &Value) -> Option<u64> {
+        let value = params[2]["stateOverrides"][COUNTER.to_string().to_lowercase()]["stateDiff"]
+            [B256::ZERO.to_string()]
+        .as_str()?;
+
+        Some(u64::from_str_radix(value.trim_start_matches("0x"), 16).unwrap())
+    }
+
+    /// Spawns a mock RPC emulating a counter contract in slot 0, executed on top of the
+    /// given state overrides. Like the `prestateTracer`, each trace reports the value of
+    /// the counter before the transaction, and in diff mode its value after it too.
+    async fn spawn_counter_rpc() -> MockRpcServer {
+        spawn_counter_rpc_with_delay(Duration::ZERO).await
+    }
+
+    /// Same as [spawn_counter_rpc], answering every request after the given delay.
+    async fn spawn_counter_rpc_with_delay(delay: Duration) -> MockRpcServer {
+        MockRpcServer::spawn_with_delay(delay, |method, params| {
+            assert_eq!(method, "debug_traceCall");
+
+            let current = counter_override(params).unwrap_or(0);
+            let input = params[0]["input"]
+                .as_str()
+                .or(params[0]["data"].as_str())
+                .unwrap_or_default();
+
+            let next = if input == Bytes::from(RESET).to_string() {
+                0
+            } else {
+                current + 1
+            };
+
+            let counter = COUNTER.to_string().to_lowercase();
+            let slot = B256::ZERO.to_string();
+            let pre = json!({
+                counter: {
+                    "balance": "0x0",
+                    "nonce": 1,
+                    "storage": { &slot: B256::from(U256::from(current)) },
+                },
+            });
+
+            if params[2]["tracerConfig"]["diffMode"] != json!(true) {
+                return Ok(pre);
+            }
+
+            // Like geth, the post-state omits the slots cleared by the transaction
+            let storage = match next {
+                0 => json!({}),
+                next => json!({ slot: B256::from(U256::from(next)) }),
+            };
+            Ok(json!({
+                "pre": pre,
+                "post": { counter: { "storage": storage } },
+            }))
+        })
+        .await
+    }
+
+    /// Creates a manager tracing with the `prestateTracer` in diff mode, so that the
+    /// counter value after each transaction is accumulated.
+    fn counter_manager(rpc: &MockRpcServer) -> (CallTraceManager, CallTraceHandle) {
+        let (manager, handle) =
+            CallTraceManager::new_with_tracer(rpc.url(), TracerKind::PreStateDiff);
+        let options = TraceOptionsConfig {
+            diff_mode: true,
+            ..Default::default()
+        };
+
+        (manager.with_trace_options(options), handle)
+    }
+
+    /// Spawns a mock RPC answering after 10 seconds, so that the traces stay in flight.
+    async fn stalled_rpc() -> MockRpcServer {
+        MockRpcServer::spawn_with_delay(Duration::from_secs(10), |_, _| Ok(Value::Null)).await
+    }
+
+    /// Adds the trace of the transaction on the given block, like [CallTraceHandle::add_trace]
+    /// but synchronously, to inspect the manager right after.
+    fn add_trace(
+        manager: &mut CallTraceManager,
+        transaction: TransactionRequest,
+        block: BlockNumber,
+    ) {
+        manager.handle_new_trace_command(TraceCommand::AddTrace {
+            transaction,
+            block: BlockTarget::Number(block),
+            tracer: None,
+            block_overrides: None,
+        });
+    }
+
+    /// Sends the request every 10 milliseconds until its result satisfies the condition,
+    /// and returns that result.
+    async fn wait_until<T, E, Fut>(
+        mut request: impl FnMut() -> Fut,
+        condition: impl Fn(&T) -> bool,
+    ) -> T
+    where
+        E: std::fmt::Debug,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        loop {
+            let res = request().await.unwrap();
+            if condition(&res) {
+                return res;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    fn in_flight_blocks(manager: &CallTraceManager) -> BTreeSet<BlockNumber> {
+        manager.in_flight_blocks.keys().copied().collect()
+    }
+
+    fn counter_call(input: [u8; 4]) -> TransactionRequest {
+        TransactionRequest::default()
+            .to(COUNTER)
+            .input(Bytes::from(input).into())
+    }
+
+    #[tokio::test]
+    async fn test_storage_overrides_layer_across_traces() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        let block = 1;
+        for _ in 0..3 {
+            handle
+                .add_trace(counter_call(INCREMENT), block)
+                .await
+                .unwrap();
+        }
+
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+
+        // (a) each trace sees the prior increments through the state overrides
+        let overrides = rpc
+            .calls("debug_traceCall")
+            .iter()
+            .map(counter_override)
+            .collect::<Vec<_>>();
+        assert_eq!(overrides, vec![None, Some(1), Some(2)]);
+
+        // (b) the accumulated state diff reflects the last value, not the first
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(3)));
+    }
+
+    #[tokio::test]
+    async fn test_manager_with_client() {
+        let rpc = spawn_counter_rpc().await;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "secret".parse().unwrap());
+        let client = RpcClient::new_with_headers(rpc.url(), headers);
+
+        let (manager, handle) = CallTraceManager::with_client(client);
+        tokio::spawn(manager);
+
+        let block = 1;
+        handle
+            .add_trace(counter_call(INCREMENT), block)
+            .await
+            .unwrap();
+        handle.fetch_accumulated_diffs(block).await.unwrap();
+
+        // The trace went through the configured client
+        assert_eq!(rpc.call_count("debug_traceCall"), 1);
+        assert_eq!(rpc.headers()[0]["x-api-key"], "secret");
+    }
+
+    #[tokio::test]
+    async fn test_storage_override_reset_to_zero() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        let block = 1;
+        for _ in 0..3 {
+            handle
+                .add_trace(counter_call(INCREMENT), block)
+                .await
+                .unwrap();
+        }
+        handle.add_trace(counter_call(RESET), block).await.unwrap();
+
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+
+        let overrides = rpc
+            .calls("debug_traceCall")
+            .iter()
+            .map(counter_override)
+            .collect::<Vec<_>>();
+        assert_eq!(overrides, vec![None, Some(1), Some(2), Some(3)]);
+
+        // The override must reflect the reset to zero, not the stale value
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_storage_overrides_layer_on_anvil() {
+        // The runtime code of a counter in slot 0, with `increment()` and `reset()`
+        let code = Bytes::from_static(&hex!(
+            "60003560e01c8063d09de08a14601d5763d826f88f14602857600080fd"
+            "5b600054600101600055005b600060005500"
+        ));
+
+        let anvil = launch_anvil();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url.clone());
+        client
+            .request::<_, Value>("anvil_setCode", (COUNTER, &code))
+            .await
+            .unwrap();
+        let block = client.get_head().await.unwrap();
+
+        let (manager, handle) =
+            CallTraceManager::new_with_tracer(anvil_url, TracerKind::PreStateDiff);
+        let options = TraceOptionsConfig {
+            diff_mode: true,
+            ..Default::default()
+        };
+        tokio::spawn(manager.with_trace_options(options));
+
+        for _ in 0..3 {
+            handle
+                .add_trace(counter_call(INCREMENT), block)
+                .await
+                .unwrap();
+        }
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(3)));
+
+        handle.add_trace(counter_call(RESET), block).await.unwrap();
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_block_overrides_are_applied() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        let block_overrides = BlockOverrides {
+            base_fee: Some(U256::from(7_000_000_000u64)),
+            ..Default::default()
+        };
+        handle
+            .add_trace_with_block_overrides(counter_call(INCREMENT), 1, block_overrides)
+            .await
+            .unwrap();
+        handle.add_trace(counter_call(INCREMENT), 2).await.unwrap();
+
+        handle.fetch_accumulated_diffs(1).await.unwrap();
+        handle.fetch_accumulated_diffs(2).await.unwrap();
+
+        let calls = rpc.calls("debug_traceCall");
+        let call_on = |block: &str| calls.iter().find(|params| params[1] == block).unwrap();
+
+        assert_eq!(
+            call_on("0x1")[2]["blockOverrides"],
+            json!({ "baseFee": "0x1a13b8600" })
+        );
+
+        // Traces without overrides don't send any block overrides
+        assert_eq!(call_on("0x2")[2].get("blockOverrides"), None);
+    }
+
+    /// Build a bundle entry for the given sender state, as reported by the trace.
+    fn bundle_entry(nonce: u64, cost: u64, pre_nonce: u64, pre_balance: u64) -> BundleEntry {
+        BundleEntry {
+            request_hash: B256::ZERO,
+            sender: Some(SENDER),
+            nonce: Some(nonce),
+            value: U256::from(cost),
+            gas_cost: U256::ZERO,
+            pre_nonce: Some(pre_nonce),
+            pre_balance: Some(U256::from(pre_balance)),
+        }
+    }
+
+    #[test]
+    fn test_validate_bundle_nonce_conflicts() {
+        // The second transaction reuses nonce 5, the third one skips nonce 6
+        let entries = [
+            bundle_entry(5, 0, 5, 100),
+            bundle_entry(5, 0, 6, 100),
+            bundle_entry(7, 0, 6, 100),
+        ];
+
+        assert_eq!(
+            validate_bundle(&entries),
+            BundleValidation::Invalid(vec![
+                BundleConflict::NonceCollision {
+                    sender: SENDER,
+                    nonce: 5
+                },
+                BundleConflict::NonceGap {
+                    sender: SENDER,
+                    expected: 6,
+                    found: 7
+                },
+            ])
+        );
+
+        let entries = [bundle_entry(5, 0, 5, 100), bundle_entry(6, 0, 6, 100)];
+        assert_eq!(validate_bundle(&entries), BundleValidation::Valid);
+    }
+
+    #[test]
+    fn test_validate_bundle_nonce_overflow() {
+        let entries = [
+            bundle_entry(u64::MAX - 1, 0, u64::MAX - 1, 100),
+            bundle_entry(u64::MAX, 0, u64::MAX - 1, 100),
+        ];
+
+        assert_eq!(
+            validate_bundle(&entries),
+            BundleValidation::Invalid(vec![BundleConflict::NonceOverflow { sender: SENDER }])
+        );
+    }
+
+    #[test]
+    fn test_validate_bundle_insufficient_balance() {
+        let entries = [
+            bundle_entry(0, 60, 0, 100),
+            bundle_entry(1, 60, 1, 40),
+            bundle_entry(2, 60, 2, 0),
+        ];
+
+        // Reported once, when the cumulative cost exceeds the initial balance
+        assert_eq!(
+            validate_bundle(&entries),
+            BundleValidation::Invalid(vec![BundleConflict::InsufficientBalance {
+                sender: SENDER,
+                required: U256::from(120),
+                available: U256::from(100),
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_bundle_through_handle() {
+        let rpc = MockRpcServer::spawn(|_, _| {
+            let sender = SENDER.to_string().to_lowercase();
+            Ok(json!({ sender: { "balance": "0x64", "nonce": 3 } }))
+        })
+        .await;
+        let (manager, handle) =
+            CallTraceManager::new_with_tracer(rpc.url(), TracerKind::PreStateDiff);
+        tokio::spawn(manager);
+
+        let block = 1;
+        for input in [INCREMENT, RESET] {
+            let transaction = counter_call(input).from(SENDER).nonce(3);
+            handle.add_trace(transaction, block).await.unwrap();
+        }
+
+        assert_eq!(
+            handle.validate_bundle(block).await.unwrap(),
+            Some(BundleValidation::Invalid(vec![
+                BundleConflict::NonceCollision {
+                    sender: SENDER,
+                    nonce: 3
+                }
+            ]))
+        );
+
+        // Validating doesn't consume the diffs
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+        assert!(diffs.contains_key(&SENDER));
+    }
+
+    #[test]
+    fn test_validate_bundle_without_sender_state() {
+        // The trace of the first transaction didn't report the state of the sender
+        let unreported = BundleEntry {
+            request_hash: B256::repeat_byte(1),
+            pre_nonce: None,
+            pre_balance: None,
+            ..bundle_entry(0, 1_000, 0, 0)
+        };
+        let entries = [
+            unreported,
+            BundleEntry {
+                request_hash: B256::repeat_byte(2),
+                ..bundle_entry(1, 1_000, 1, 0)
+            },
+        ];
+
+        // Neither transaction can be checked, instead of the bundle passing as valid
+        assert_eq!(
+            validate_bundle(&entries),
+            BundleValidation::Invalid(vec![
+                BundleConflict::Unvalidated {
+                    sender: Some(SENDER),
+                    request_hash: B256::repeat_byte(1),
+                },
+                BundleConflict::Unvalidated {
+                    sender: Some(SENDER),
+                    request_hash: B256::repeat_byte(2),
+                },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_bundle_without_from() {
+        let rpc = MockRpcServer::spawn(|_, _| {
+            let sender = SENDER.to_string().to_lowercase();
+            Ok(json!({ sender: { "balance": "0x64", "nonce": 3 } }))
+        })
+        .await;
+        let (manager, handle) =
+            CallTraceManager::new_with_tracer(rpc.url(), TracerKind::PreStateDiff);
+        tokio::spawn(manager);
+
+        // The second transaction has no sender, so its nonce and cost can't be checked
+        let block = 1;
+        let anonymous = counter_call(RESET).nonce(3).value(U256::from(1_000));
+        handle
+            .add_trace(counter_call(INCREMENT).from(SENDER).nonce(3), block)
+            .await
+            .unwrap();
+        handle.add_trace(anonymous.clone(), block).await.unwrap();
+
+        assert_eq!(
+            handle.validate_bundle(block).await.unwrap(),
+            Some(BundleValidation::Invalid(vec![
+                BundleConflict::Unvalidated {
+                    sender: None,
+                    request_hash: trace_request_hash(&anonymous),
+                }
+            ]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unparseable_trace_fails_the_block() {
+        // The storage root tracer returns a hash rather than a pre-state frame
+        let rpc = MockRpcServer::spawn(|_, _| Ok(json!(B256::repeat_byte(0xaa)))).await;
+        let (manager, handle) =
+            CallTraceManager::new_with_tracer(rpc.url(), TracerKind::StorageRoot);
+        tokio::spawn(manager);
+
+        let block = 1;
+        let transaction = counter_call(INCREMENT).from(SENDER).nonce(0);
+        handle.add_trace(transaction, block).await.unwrap();
+
+        assert_eq!(handle.validate_bundle(block).await, Ok(None));
+        assert!(matches!(
+            handle.fetch_accumulated_diffs(block).await,
+            Err(TraceError::InvalidTrace { block: 1, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reverted_trace_fails_the_block() {
+        // A custom tracer returning the call frame of the reverted transaction
+        let rpc = MockRpcServer::spawn(|_, _| {
+            Ok(json!({
+                "from": SENDER,
+                "to": COUNTER,
+                "input": "0x",
+                "output": concat!(
+                    "0x08c379a0",
+                    "0000000000000000000000000000000000000000000000000000000000000020",
+                    "0000000000000000000000000000000000000000000000000000000000000002",
+                    "6e6f000000000000000000000000000000000000000000000000000000000000",
+                ),
+                "error": "execution reverted",
+                "type": "CALL",
+            }))
+        })
+        .await;
+        let (manager, handle) = CallTraceManager::new_with_tracer(
+            rpc.url(),
+            TracerKind::Custom("callTracer".to_string()),
+        );
+        tokio::spawn(manager);
+
+        let block = 1;
+        handle
+            .add_trace(counter_call(INCREMENT), block)
+            .await
+            .unwrap();
+
+        let err = handle.fetch_accumulated_diffs(block).await.unwrap_err();
+        assert!(matches!(err, TraceError::Reverted { block: 1, .. }));
+        assert_eq!(err.revert_reason(), Some(RevertReason::Error("no".into())));
+
+        // RPC errors are not reverts, whatever data they carry
+        let err = TraceError::Rpc {
+            block,
+            source: RpcError::ErrorResp(ErrorPayload {
+                code: 3,
+                message: "execution reverted".into(),
+                data: None,
+            }),
+        };
+        assert_eq!(err.revert_reason(), None);
+    }
+
+    #[test]
+    fn test_summarize_bundle() {
+        let other = Address::repeat_byte(0x22);
+        let entries = [
+            bundle_entry(5, 10, 5, 100),
+            BundleEntry {
+                sender: Some(other),
+                nonce: None,
+                gas_cost: U256::from(3),
+                ..bundle_entry(0, 1, 7, 50)
+            },
+            BundleEntry {
+                gas_cost: U256::from(4),
+                ..bundle_entry(6, 20, 6, 90)
+            },
+            BundleEntry {
+                sender: Some(other),
+                nonce: None,
+                ..bundle_entry(0, 2, 8, 40)
+            },
+            bundle_entry(7, 30, 7, 70),
+        ];
+
+        // In order of first transaction, with the state reported by that first trace
+        assert_eq!(
+            summarize_bundle(&entries),
+            vec![
+                SenderSummary {
+                    sender: SENDER,
+                    transactions: 3,
+                    value: U256::from(60),
+                    max_gas_cost: U256::from(4),
+                    balance: Some(U256::from(100)),
+                    next_nonce: Some(8),
+                },
+                SenderSummary {
+                    sender: other,
+                    transactions: 2,
+                    value: U256::from(3),
+                    max_gas_cost: U256::from(3),
+                    balance: Some(U256::from(50)),
+                    next_nonce: Some(9),
+                },
+            ]
+        );
+        assert_eq!(
+            summarize_bundle(&entries)[0].required_balance(),
+            U256::from(64)
+        );
+        assert!(summarize_bundle(&[]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_summarize_bundle_through_handle() {
+        let other = Address::repeat_byte(0x22);
+        let rpc = MockRpcServer::spawn(move |_, _| {
+            let sender = SENDER.to_string().to_lowercase();
+            let other = other.to_string().to_lowercase();
+            Ok(json!({
+                sender: { "balance": "0x3e8", "nonce": 3 },
+                other: { "balance": "0x64", "nonce": 9 },
+            }))
+        })
+        .await;
+        let (manager, handle) =
+            CallTraceManager::new_with_tracer(rpc.url(), TracerKind::PreStateDiff);
+        tokio::spawn(manager);
+
+        let block = 1;
+        let transactions = [
+            counter_call(INCREMENT)
+                .from(SENDER)
+                .value(U256::from(100))
+                .gas_limit(10)
+                .max_fee_per_gas(2),
+            counter_call(INCREMENT)
+                .from(other)
+                .nonce(9)
+                .gas_limit(5)
+                .max_fee_per_gas(3),
+            counter_call(RESET)
+                .from(SENDER)
+                .value(U256::from(50))
+                .gas_limit(20)
+                .max_fee_per_gas(2),
+            counter_call(RESET)
+                .from(other)
+                .nonce(10)
+                .value(U256::from(7)),
+            counter_call(INCREMENT).from(SENDER).value(U256::from(1)),
+        ];
+        for transaction in transactions {
+            handle.add_trace(transaction, block).await.unwrap();
+        }
+
+        assert_eq!(
+            handle.summarize_bundle(block).await.unwrap(),
+            Some(vec![
+                SenderSummary {
+                    sender: SENDER,
+                    transactions: 3,
+                    value: U256::from(151),
+                    max_gas_cost: U256::from(60),
+                    balance: Some(U256::from(1000)),
+                    next_nonce: Some(6),
+                },
+                SenderSummary {
+                    sender: other,
+                    transactions: 2,
+                    value: U256::from(7),
+                    max_gas_cost: U256::from(15),
+                    balance: Some(U256::from(100)),
+                    next_nonce: Some(11),
+                },
+            ])
+        );
+
+        // Summarizing doesn't consume the diffs
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+        assert!(diffs.contains_key(&SENDER));
+        assert!(diffs.contains_key(&other));
+
+        // Nothing traced on the block
+        assert_eq!(handle.summarize_bundle(block + 1).await, Ok(Some(vec![])));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_queued_trace() {
+        let rpc = spawn_counter_rpc_with_delay(Duration::from_millis(50)).await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        // Three increments, the second one being withdrawn while queued
+        let block = 1;
+        let withdrawn = counter_call(INCREMENT).from(SENDER);
+        handle
+            .add_trace(counter_call(INCREMENT), block)
+            .await
+            .unwrap();
+        handle.add_trace(withdrawn.clone(), block).await.unwrap();
+        handle
+            .add_trace(counter_call(INCREMENT).nonce(1), block)
+            .await
+            .unwrap();
+        handle
+            .cancel_trace(block, trace_request_hash(&withdrawn))
+            .await
+            .unwrap();
+
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(2)));
+        assert_eq!(rpc.call_count("debug_traceCall"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_trace_in_flight() {
+        let rpc = spawn_counter_rpc_with_delay(Duration::from_millis(50)).await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        // The first increment is withdrawn while its trace is in flight
+        let block = 1;
+        let withdrawn = counter_call(INCREMENT);
+        handle.add_trace(withdrawn.clone(), block).await.unwrap();
+        handle
+            .add_trace(counter_call(INCREMENT).from(SENDER), block)
+            .await
+            .unwrap();
+        handle
+            .cancel_trace(block, trace_request_hash(&withdrawn))
+            .await
+            .unwrap();
+
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+
+        // The aborted trace was not merged: the second increment ran on the initial state
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(1)));
+        assert_eq!(
+            counter_override(rpc.calls("debug_traceCall").last().unwrap()),
+            None
+        );
+    }
+
+    /// Build a pre-state trace touching the counter contract with the given value in slot 0.
+    fn counter_trace(value: u64) -> GethTrace {
+        let state = AccountState {
+            storage: [(B256::ZERO, B256::from(U256::from(value)))].into(),
+            ..Default::default()
+        };
+
+        GethTrace::PreStateTracer(PreStateFrame::Default(PreStateMode(
+            [(COUNTER, state)].into(),
+        )))
+    }
+
+    #[test]
+    fn test_merge_account_state_keeps_previous_fields() {
+        let slot_0 = B256::ZERO;
+        let slot_1 = B256::with_last_byte(1);
+
+        // First transaction: bumps the nonce and sets slot 0
+        let first = AccountState {
+            balance: Some(U256::from(100)),
+            nonce: Some(1),
+            code: Some(Bytes::from_static(&[0x60, 0x00])),
+            storage: [(slot_0, B256::from(U256::from(5)))].into(),
+        };
+
+        // Second transaction: only reports the balance, clears slot 0 and sets slot 1
+        let second = AccountState {
+            balance: Some(U256::from(90)),
+            nonce: None,
+            code: None,
+            storage: [(slot_0, B256::ZERO), (slot_1, B256::from(U256::from(7)))].into(),
+        };
+
+        let mut account_override = AccountOverride::default();
+        merge_account_state_in_overrides(&mut account_override, first);
+        merge_account_state_in_overrides(&mut account_override, second);
+
+        assert_eq!(account_override.balance, Some(U256::from(90)));
+        assert_eq!(account_override.nonce, Some(U64::from(1)));
+        assert_eq!(
+            account_override.code,
+            Some(Bytes::from_static(&[0x60, 0x00]))
+        );
+
+        let state_diff = account_override.state_diff.unwrap();
+        assert_eq!(state_diff.len(), 2);
+        assert_eq!(state_diff[&slot_0], B256::ZERO);
+        assert_eq!(state_diff[&slot_1], B256::from(U256::from(7)));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_accumulated_diffs_timeout() {
+        let rpc = stalled_rpc().await;
+        let (manager, handle) = CallTraceManager::new(rpc.url());
+        tokio::spawn(manager);
+
+        let block = 1;
+        handle
+            .add_trace(counter_call(INCREMENT), block)
+            .await
+            .unwrap();
+
+        let res = handle
+            .fetch_accumulated_diffs_timeout(block, Duration::from_millis(100))
+            .await;
+
+        assert!(matches!(res, Err(TraceError::Timeout { block: 1 })));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_fetch_drops_response_channel() {
+        let rpc = stalled_rpc().await;
+        let (mut manager, _handle) = CallTraceManager::new(rpc.url());
+
+        let block = 1;
+        add_trace(&mut manager, counter_call(INCREMENT), block);
+
+        let (res_tx, res_rx) = oneshot::channel();
+        manager.handle_new_trace_command(TraceCommand::FetchAccumulatedDiffs {
+            block,
+            id: 0,
+            res: res_tx,
+        });
+        assert!(manager.response_queue.contains_key(&block));
+
+        manager.handle_new_trace_command(TraceCommand::CancelFetch { block, id: 0 });
+        assert!(manager.response_queue.is_empty());
+        assert!(res_rx.await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_fetches_and_validations() {
+        let rpc = stalled_rpc().await;
+        let (mut manager, _handle) = CallTraceManager::new(rpc.url());
+
+        let block = 1;
+        add_trace(&mut manager, counter_call(INCREMENT), block);
+
+        let mut fetches = Vec::new();
+        for id in 0..3 {
+            let (res_tx, res_rx) = oneshot::channel();
+            manager.handle_new_trace_command(TraceCommand::FetchAccumulatedDiffs {
+                block,
+                id,
+                res: res_tx,
+            });
+            fetches.push(res_rx);
+        }
+        let mut validations = Vec::new();
+        for _ in 0..2 {
+            let (res_tx, res_rx) = oneshot::channel();
+            manager.handle_new_trace_command(TraceCommand::ValidateBundle { block, res: res_tx });
+            validations.push(res_rx);
+        }
+
+        // Only the cancelled fetch is dropped
+        manager.handle_new_trace_command(TraceCommand::CancelFetch { block, id: 1 });
+        assert_eq!(manager.response_queue[&block].len(), 2);
+
+        manager.handle_trace_result(block, 0, Ok(counter_trace(1)));
+
+        let cancelled = fetches.remove(1);
+        assert!(cancelled.await.is_err());
+        for res_rx in fetches {
+            let diffs = res_rx.await.unwrap().unwrap();
+            assert!(diffs.contains_key(&COUNTER));
+        }
+        for res_rx in validations {
+            assert!(res_rx.await.unwrap().is_some());
+        }
+        assert!(manager.response_queue.is_empty());
+        assert!(manager.validation_queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_fetch_after_last_trace_completed() {
+        let rpc = stalled_rpc().await;
+        let (mut manager, _handle) = CallTraceManager::new(rpc.url());
+
+        let block = 1;
+        add_trace(&mut manager, counter_call(INCREMENT), block);
+
+        let (res_tx, res_rx) = oneshot::channel();
+        manager.handle_new_trace_command(TraceCommand::FetchAccumulatedDiffs {
+            block,
+            id: 0,
+            res: res_tx,
+        });
+
+        // The last trace completes right as the fetcher times out and cancels
+        manager.handle_trace_result(block, 0, Ok(counter_trace(1)));
+        manager.handle_new_trace_command(TraceCommand::CancelFetch { block, id: 0 });
+
+        // The diffs were delivered, and nothing is left dangling in the actor
+        let diffs = res_rx.await.unwrap().unwrap();
+        assert!(diffs.contains_key(&COUNTER));
+        assert!(manager.response_queue.is_empty());
+        assert!(manager.accumulated_state_diffs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_traces_for_different_blocks_run_concurrently() {
+        let rpc = stalled_rpc().await;
+        let (mut manager, _handle) = CallTraceManager::new(rpc.url());
+
+        for block in [1, 2, 1, 2, 1] {
+            add_trace(&mut manager, counter_call(INCREMENT), block);
+        }
+
+        // One trace per block is in flight, the rest is queued in order
+        assert_eq!(manager.pending_traces.len(), 2);
+        assert_eq!(in_flight_blocks(&manager), BTreeSet::from([1, 2]));
+        assert_eq!(manager.trace_request_queue[&1].len(), 2);
+        assert_eq!(manager.trace_request_queue[&2].len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_traces() {
+        let rpc = spawn_counter_rpc_with_delay(Duration::from_millis(50)).await;
+        let (manager, _handle) = counter_manager(&rpc);
+        let mut manager = manager.with_max_concurrent_traces(2);
+
+        let blocks = [1, 2, 3, 1, 4, 5, 1, 6];
+        for block in blocks {
+            add_trace(&mut manager, counter_call(INCREMENT), block);
+        }
+
+        // Only two traces start, the other blocks wait for a slot
+        assert_eq!(manager.pending_traces.len(), 2);
+        assert_eq!(in_flight_blocks(&manager), BTreeSet::from([1, 2]));
+
+        let mut fetches = Vec::new();
+        for block in 1..=6 {
+            let (res_tx, res_rx) = oneshot::channel();
+            manager.handle_new_trace_command(TraceCommand::FetchAccumulatedDiffs {
+                block,
+                id: 0,
+                res: res_tx,
+            });
+            fetches.push((block, res_rx));
+        }
+
+        let mut max_in_flight = 0;
+        while !manager.in_flight_blocks.is_empty() || !manager.trace_request_queue.is_empty() {
+            let _ = tokio::time::timeout(Duration::from_millis(5), &mut manager).await;
+            max_in_flight = max_in_flight.max(manager.pending_traces.len());
+            assert!(manager.pending_traces.len() <= 2);
+        }
+        assert_eq!(max_in_flight, 2);
+        assert_eq!(rpc.call_count("debug_traceCall"), blocks.len());
+
+        // The traces of block 1 still ran in sequence, on top of each other
+        for (block, res_rx) in fetches {
+            let diffs = res_rx.await.unwrap().unwrap();
+            let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+            let expected = if block == 1 { 3 } else { 1 };
+            assert_eq!(slot, B256::from(U256::from(expected)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_old_blocks_are_evicted() {
+        let rpc = stalled_rpc().await;
+        let (manager, _handle) = CallTraceManager::new(rpc.url());
+        let mut manager = manager.with_max_tracked_blocks(2);
+
+        add_trace(&mut manager, counter_call(INCREMENT), 1);
+        manager
+            .accumulated_state_diffs
+            .insert(2, Default::default());
+
+        let (res_tx, res_rx) = oneshot::channel();
+        manager.handle_new_trace_command(TraceCommand::FetchAccumulatedDiffs {
+            block: 1,
+            id: 0,
+            res: res_tx,
+        });
+
+        for block in [1, 3, 4] {
+            add_trace(&mut manager, counter_call(INCREMENT), block);
+        }
+
+        // Blocks 1 and 2 are gone, and the pending fetch for block 1 is answered
+        assert_eq!(in_flight_blocks(&manager), BTreeSet::from([3, 4]));
+        assert!(manager.trace_request_queue.is_empty());
+        assert!(manager.accumulated_state_diffs.is_empty());
+        assert!(manager.response_queue.is_empty());
+        assert!(matches!(
+            res_rx.await.unwrap(),
+            Err(TraceError::Evicted { block: 1 })
+        ));
+
+        // A late result for an evicted block is dropped
+        manager.handle_trace_result(1, 0, Ok(counter_trace(1)));
+        assert!(manager.accumulated_state_diffs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_from() {
+        let rpc = stalled_rpc().await;
+        let (mut manager, _handle) = CallTraceManager::new(rpc.url());
+
+        // Trace ids 0, 1 and 2
+        for block in [1, 2, 3] {
+            add_trace(&mut manager, counter_call(INCREMENT), block);
+        }
+        manager
+            .accumulated_state_diffs
+            .insert(2, Default::default());
+
+        let (res_tx, res_rx) = oneshot::channel();
+        manager.handle_new_trace_command(TraceCommand::FetchAccumulatedDiffs {
+            block: 2,
+            id: 0,
+            res: res_tx,
+        });
+
+        manager.handle_new_trace_command(TraceCommand::InvalidateFrom { block: 2 });
+
+        // Blocks 2 and 3 are gone, and the fetcher gets an error instead of stale diffs
+        assert_eq!(in_flight_blocks(&manager), BTreeSet::from([1]));
+        assert!(manager.accumulated_state_diffs.is_empty());
+        assert!(manager.response_queue.is_empty());
+        assert!(matches!(
+            res_rx.await.unwrap(),
+            Err(TraceError::Reorged { block: 2 })
+        ));
+
+        // Trace id 3, on top of the new chain
+        add_trace(&mut manager, counter_call(INCREMENT), 2);
+
+        // The late result of the invalidated trace is dropped, but not the new one
+        manager.handle_trace_result(2, 1, Ok(counter_trace(1)));
+        assert!(manager.accumulated_state_diffs.is_empty());
+        assert_eq!(in_flight_blocks(&manager), BTreeSet::from([1, 2]));
+
+        manager.handle_trace_result(2, 3, Ok(counter_trace(1)));
+        assert!(manager.accumulated_state_diffs.contains_key(&2));
+    }
+
+    #[tokio::test]
+    async fn test_clear_block_with_trace_in_flight() {
+        let rpc = stalled_rpc().await;
+        let (mut manager, _handle) = CallTraceManager::new(rpc.url());
+
+        // Trace id 0 in flight, and another one queued behind it
+        let block = 1;
+        for _ in 0..2 {
+            add_trace(&mut manager, counter_call(INCREMENT), block);
+        }
+        manager
+            .accumulated_state_diffs
+            .insert(block, Default::default());
+
+        let (res_tx, res_rx) = oneshot::channel();
+        manager.handle_new_trace_command(TraceCommand::FetchAccumulatedDiffs {
+            block,
+            id: 0,
+            res: res_tx,
+        });
+
+        manager.handle_new_trace_command(TraceCommand::ClearBlock { block });
+
+        assert!(manager.accumulated_state_diffs.is_empty());
+        assert!(manager.trace_request_queue.is_empty());
+        assert!(manager.in_flight_blocks.is_empty());
+        assert!(matches!(
+            res_rx.await.unwrap(),
+            Err(TraceError::Cleared { block: 1 })
+        ));
+
+        // The trace in flight returns after the clear, and doesn't repopulate the diffs
+        manager.handle_trace_result(block, 0, Ok(counter_trace(1)));
+        assert!(manager.accumulated_state_diffs.is_empty());
+        assert!(manager.in_flight_blocks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reorg_detected_from_head_hash() {
+        let rpc = stalled_rpc().await;
+        let (mut manager, _handle) = CallTraceManager::new(rpc.url());
+
+        let head = |number: u64, hash: u8| BlockHashes {
+            number: U64::from(number),
+            hash: B256::repeat_byte(hash),
+            parent_hash: B256::repeat_byte(1),
+        };
+
+        manager.set_head_block(head(4, 1));
+        manager.set_head_block(head(5, 1));
+        for block in [4, 5] {
+            manager
+                .accumulated_state_diffs
+                .insert(block, Default::default());
+        }
+
+        // Polling the same block again is not a reorg
+        manager.set_head_block(head(5, 1));
+        assert_eq!(manager.accumulated_state_diffs.len(), 2);
+
+        // Block 5 was replaced: its diffs are stale, while block 4 is untouched
+        manager.set_head_block(head(5, 2));
+        assert_eq!(manager.head, Some(5));
+        assert!(manager.accumulated_state_diffs.contains_key(&4));
+        assert!(!manager.accumulated_state_diffs.contains_key(&5));
+        assert_eq!(manager.block_hashes[&5], B256::repeat_byte(2));
+
+        // A reorg to a lower head invalidates the blocks above it as well
+        manager
+            .accumulated_state_diffs
+            .insert(5, Default::default());
+        manager.set_head_block(head(4, 3));
+        assert_eq!(manager.head, Some(4));
+        assert!(manager.accumulated_state_diffs.is_empty());
+        assert!(!manager.block_hashes.contains_key(&5));
+    }
+
+    #[tokio::test]
+    async fn test_reorg_detected_from_parent_hash() {
+        let rpc = stalled_rpc().await;
+        let (mut manager, _handle) = CallTraceManager::new(rpc.url());
+
+        let head = |number: u64, hash: u8, parent_hash: u8| BlockHashes {
+            number: U64::from(number),
+            hash: B256::repeat_byte(hash),
+            parent_hash: B256::repeat_byte(parent_hash),
+        };
+
+        manager.set_head_block(head(4, 4, 3));
+        manager.set_head_block(head(5, 5, 4));
+        for block in [4, 5, 6] {
+            manager
+                .accumulated_state_diffs
+                .insert(block, Default::default());
+        }
+
+        // Block 5 was reorged and block 6 built on the new one before the next poll:
+        // the head is new, but its parent isn't the block 5 seen before
+        manager.set_head_block(head(6, 6, 0x55));
+        assert_eq!(manager.head, Some(6));
+        assert!(manager.accumulated_state_diffs.contains_key(&4));
+        assert!(!manager.accumulated_state_diffs.contains_key(&5));
+        assert!(!manager.accumulated_state_diffs.contains_key(&6));
+        assert_eq!(manager.block_hashes[&5], B256::repeat_byte(0x55));
+
+        // Advancing on top of the new chain is not a reorg
+        manager
+            .accumulated_state_diffs
+            .insert(6, Default::default());
+        manager.set_head_block(head(7, 7, 6));
+        assert_eq!(manager.head, Some(7));
+        assert!(manager.accumulated_state_diffs.contains_key(&6));
+    }
+
+    #[tokio::test]
+    async fn test_identical_traces_are_cached() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        let block = 1;
+        for _ in 0..2 {
+            handle
+                .add_trace(counter_call(INCREMENT), block)
+                .await
+                .unwrap();
+            let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+
+            let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+            assert_eq!(slot, B256::from(U256::from(1)));
+        }
+
+        // The second submission on the same state hits the cache
+        assert_eq!(rpc.call_count("debug_traceCall"), 1);
+
+        // A different tracer is a different trace
+        handle
+            .add_trace_with_tracer(counter_call(INCREMENT), block, TracerKind::StorageRoot)
+            .await
+            .unwrap();
+        let _ = handle.fetch_accumulated_diffs(block).await;
+        assert_eq!(rpc.call_count("debug_traceCall"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_trace_cache_invalidated_on_reorg() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        let block = 1;
+        for _ in 0..2 {
+            handle
+                .add_trace(counter_call(INCREMENT), block)
+                .await
+                .unwrap();
+            handle.fetch_accumulated_diffs(block).await.unwrap();
+            handle.invalidate_from(block).await.unwrap();
+        }
+
+        assert_eq!(rpc.call_count("debug_traceCall"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_trace_cache_invalidation_keeps_earlier_blocks() {
+        let rpc = spawn_counter_rpc().await;
+        let (mut manager, _handle) = counter_manager(&rpc);
+
+        for block in [1, 2, 3] {
+            add_trace(&mut manager, counter_call(INCREMENT), block);
+        }
+        while let Some(res) = manager.pending_traces.next().await {
+            let (block, id, result) = res.unwrap();
+            manager.handle_trace_result(block, id, result);
+        }
+
+        let cached_blocks = |manager: &CallTraceManager| {
+            let mut blocks = manager
+                .trace_cache
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|(key, _)| key.block)
+                .collect::<Vec<_>>();
+            blocks.sort_unstable();
+            blocks
+        };
+        assert_eq!(cached_blocks(&manager), vec![1, 2, 3]);
+
+        // Only the traces of the invalidated block and the following ones are evicted
+        manager.invalidate_from(2);
+        assert_eq!(cached_blocks(&manager), vec![1]);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn test_queue_gauges() {
+        use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+        let rpc = stalled_rpc().await;
+        let (mut manager, _handle) = CallTraceManager::new(rpc.url());
+
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+
+        // One trace in flight per block, and the rest queued behind them
+        metrics::with_local_recorder(&recorder, || {
+            for block in [1, 1, 1, 2, 2] {
+                add_trace(&mut manager, counter_call(INCREMENT), block);
+            }
+        });
+
+        let gauges = || {
+            snapshotter
+                .snapshot()
+                .into_vec()
+                .into_iter()
+                .filter_map(|(key, _, _, value)| match value {
+                    DebugValue::Gauge(value) => Some((key.key().name().to_string(), value.0)),
+                    _ => None,
+                })
+                .collect::<HashMap<_, _>>()
+        };
+
+        let snapshot = gauges();
+        assert_eq!(snapshot[TRACE_IN_FLIGHT], 2.0);
+        assert_eq!(snapshot[TRACE_QUEUED_TRANSACTIONS], 3.0);
+        assert_eq!(snapshot[TRACE_TRACKED_BLOCKS], 2.0);
+        assert_eq!(snapshot[TRACE_WAITING_FETCHERS], 0.0);
+
+        // The gauges follow the queues when they shrink outside of the commands, e.g. when
+        // the head advances past a buffered block or a reorg drops the tracked blocks
+        metrics::with_local_recorder(&recorder, || {
+            manager.set_head(2);
+            add_trace(&mut manager, counter_call(INCREMENT), 3);
+        });
+        assert_eq!(gauges()[TRACE_QUEUED_TRANSACTIONS], 4.0);
+
+        metrics::with_local_recorder(&recorder, || manager.handle_new_head(3));
+        let snapshot = gauges();
+        assert_eq!(snapshot[TRACE_IN_FLIGHT], 3.0);
+        assert_eq!(snapshot[TRACE_QUEUED_TRANSACTIONS], 3.0);
+
+        metrics::with_local_recorder(&recorder, || {
+            manager.handle_new_head_block(BlockHashes {
+                number: U64::from(3),
+                hash: B256::repeat_byte(3),
+                parent_hash: B256::repeat_byte(2),
+            });
+            manager.handle_new_head_block(BlockHashes {
+                number: U64::from(3),
+                hash: B256::repeat_byte(4),
+                parent_hash: B256::repeat_byte(2),
+            });
+        });
+        let snapshot = gauges();
+        assert_eq!(snapshot[TRACE_TRACKED_BLOCKS], 2.0);
+    }
+
+    #[tokio::test]
+    async fn test_interleaved_blocks_make_progress() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        for block in [1, 2, 1, 2, 1] {
+            handle
+                .add_trace(counter_call(INCREMENT), block)
+                .await
+                .unwrap();
+        }
+
+        let (diffs_1, diffs_2) = tokio::join!(
+            handle.fetch_accumulated_diffs(1),
+            handle.fetch_accumulated_diffs(2)
+        );
+
+        let slot_1 = diffs_1.unwrap()[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        let slot_2 = diffs_2.unwrap()[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+
+        // Each block accumulates its own overrides, independently of the other
+        assert_eq!(slot_1, B256::from(U256::from(3)));
+        assert_eq!(slot_2, B256::from(U256::from(2)));
+    }
+
+    #[tokio::test]
+    async fn test_actor_gone() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = CallTraceManager::new(rpc.url());
+        drop(manager);
+
+        let block = 1;
+        assert_eq!(
+            handle.add_trace(counter_call(INCREMENT), block).await,
+            Err(TraceActorGone)
+        );
+        assert!(matches!(
+            handle.fetch_accumulated_diffs(block).await,
+            Err(TraceError::ActorGone(TraceActorGone))
+        ));
+        assert_eq!(handle.touched_addresses(block).await, Err(TraceActorGone));
+        assert_eq!(handle.touched_accounts(block).await, Err(TraceActorGone));
+        assert_eq!(handle.validate_bundle(block).await, Err(TraceActorGone));
+        assert_eq!(handle.summarize_bundle(block).await, Err(TraceActorGone));
+        assert_eq!(
+            handle.add_trace_at_head(counter_call(INCREMENT)).await,
+            Err(TraceActorGone)
+        );
+        assert!(handle.subscribe_diffs(block).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_trace_error_fails_the_block() {
+        // Fails the second trace of the bundle, i.e. the one seeing the first increment
+        let rpc = MockRpcServer::spawn(|_, params| {
+            if counter_override(params).is_some() {
+                return Err(json!({ "code": -32000, "message": "execution aborted" }));
+            }
+
+            let counter = COUNTER.to_string().to_lowercase();
+            let slot = B256::ZERO.to_string();
+            let value = B256::from(U256::from(1)).to_string();
+
+            Ok(json!({ counter: { "storage": { slot: value } } }))
+        })
+        .await;
+        let (manager, handle) =
+            CallTraceManager::new_with_tracer(rpc.url(), TracerKind::PreStateDiff);
+        tokio::spawn(manager);
+
+        let block = 1;
+        for _ in 0..3 {
+            handle
+                .add_trace(counter_call(INCREMENT), block)
+                .await
+                .unwrap();
+        }
+
+        assert!(matches!(
+            handle.fetch_accumulated_diffs(block).await,
+            Err(TraceError::Rpc { block: 1, .. })
+        ));
+
+        // The rest of the bundle was dropped, and the failure is only reported once
+        assert_eq!(rpc.call_count("debug_traceCall"), 2);
+        assert!(handle
+            .fetch_accumulated_diffs(block)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_trace_error_with_mock_backend() {
+        // The first trace succeeds, the following ones fail
+        let counter = COUNTER.to_string().to_lowercase();
+        let rpc = Arc::new(MockRpc::new());
+        rpc.respond(
+            "debug_traceCall",
+            MockResponse::Ok(json!({ counter: { "balance": "0x0", "nonce": 1 } })),
+        )
+        .respond(
+            "debug_traceCall",
+            MockResponse::Rpc(-32000, "execution timeout".to_string()),
+        );
+        let (manager, handle) = CallTraceManager::with_client(rpc.clone());
+        tokio::spawn(manager);
+
+        // The second trace of the bundle fails, and so does the trace of the next block
+        for (block, inputs) in [(1, vec![INCREMENT, RESET]), (2, vec![INCREMENT])] {
+            for input in inputs {
+                handle.add_trace(counter_call(input), block).await.unwrap();
+            }
+
+            let err = handle.fetch_accumulated_diffs(block).await.unwrap_err();
+            assert!(
+                matches!(
+                    &err,
+                    TraceError::Rpc { block: failed, source: RpcError::ErrorResp(payload) }
+                        if *failed == block && payload.message == "execution timeout"
+                ),
+                "{err}"
+            );
+        }
+        assert_eq!(rpc.call_count("debug_traceCall"), 3);
+    }
+
+    #[tokio::test]
+    async fn test_graceful_shutdown() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        let actor = tokio::spawn(manager);
+
+        let block = 1;
+        for _ in 0..2 {
+            handle
+                .add_trace(counter_call(INCREMENT), block)
+                .await
+                .unwrap();
+        }
+
+        let (res_tx, res_rx) = oneshot::channel();
+        handle
+            .cmd_tx
+            .send(TraceCommand::FetchAccumulatedDiffs {
+                block,
+                id: 0,
+                res: res_tx,
+            })
+            .await
+            .unwrap();
+        handle.shutdown().await.unwrap();
+
+        // The pending traces are completed and the fetch still resolves
+        let diffs = res_rx.await.unwrap().unwrap();
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(2)));
+
+        // The actor future completes, even though the handle is still alive
+        tokio::time::timeout(Duration::from_secs(1), actor)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(handle
+            .add_trace(counter_call(INCREMENT), block)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_answers_range_fetches() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        let actor = tokio::spawn(manager);
+
+        // The trace on block 3 is buffered until the head reaches it
+        handle.update_head(1).await.unwrap();
+        for block in [1, 3] {
+            handle
+                .add_trace(counter_call(INCREMENT), block)
+                .await
+                .unwrap();
+        }
+
+        let (res_tx, res_rx) = oneshot::channel();
+        handle
+            .cmd_tx
+            .send(TraceCommand::FetchAccumulatedDiffsRange {
+                from: 1,
+                to: 3,
+                res: res_tx,
+            })
+            .await
+            .unwrap();
+        handle.shutdown().await.unwrap();
+
+        // The range fetch resolves with the diffs available on shutdown
+        let diffs = res_rx.await.unwrap();
+        assert_eq!(diffs.keys().copied().collect::<Vec<_>>(), vec![1]);
+
+        tokio::time::timeout(Duration::from_secs(1), actor)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_rejects_new_traces() {
+        let rpc = stalled_rpc().await;
+        let (mut manager, _handle) = CallTraceManager::new(rpc.url());
+
+        manager.handle_new_trace_command(TraceCommand::Shutdown);
+        add_trace(&mut manager, counter_call(INCREMENT), 1);
+
+        assert!(manager.pending_traces.is_empty());
+        assert!(manager.trace_request_queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_touched_addresses() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        let block = 1;
+        assert!(handle.touched_addresses(block).await.unwrap().is_empty());
+
+        handle
+            .add_trace(counter_call(INCREMENT), block)
+            .await
+            .unwrap();
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+        assert_eq!(diffs.keys().copied().collect::<Vec<_>>(), vec![COUNTER]);
+
+        handle
+            .add_trace(counter_call(INCREMENT), block)
+            .await
+            .unwrap();
+
+        // Wait until the trace has been merged without consuming the diffs
+        let touched = wait_until(
+            || handle.touched_addresses(block),
+            |touched| !touched.is_empty(),
+        )
+        .await;
+
+        assert_eq!(touched, vec![COUNTER]);
+        assert!(!handle
+            .fetch_accumulated_diffs(block)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_touched_accounts() {
+        // The caller pays for the gas and bumps its nonce, the counter is incremented
+        let rpc = MockRpcServer::spawn(|_, _| {
+            let counter = COUNTER.to_string().to_lowercase();
+            let sender = SENDER.to_string().to_lowercase();
+            let slot = B256::ZERO.to_string();
+            let value = B256::from(U256::from(1)).to_string();
+
+            Ok(json!({
+                counter: { "balance": "0x0", "nonce": 1, "storage": { slot: value } },
+                sender: { "balance": "0x64", "nonce": 3 },
+            }))
+        })
+        .await;
+        let (manager, handle) =
+            CallTraceManager::new_with_tracer(rpc.url(), TracerKind::PreStateDiff);
+        tokio::spawn(manager);
+
+        let block = 1;
+        assert!(handle.touched_accounts(block).await.unwrap().is_empty());
+
+        handle
+            .add_trace(counter_call(INCREMENT).from(SENDER), block)
+            .await
+            .unwrap();
+
+        // Wait until the trace has been merged without consuming the diffs
+        let touched = wait_until(
+            || handle.touched_accounts(block),
+            |touched| !touched.is_empty(),
+        )
+        .await;
+
+        assert_eq!(touched.contracts, HashSet::from([COUNTER]));
+        assert_eq!(touched.eoas, HashSet::from([SENDER]));
+        assert_eq!(touched.all(), HashSet::from([COUNTER, SENDER]));
+    }
+
+    /// A log writer keeping everything in memory, to inspect the emitted logs.
+    #[derive(Clone, Default)]
+    struct LogCapture(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for LogCapture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl LogCapture {
+        fn lines(&self) -> Vec<String> {
+            let logs = self.0.lock().unwrap();
+            String::from_utf8_lossy(&logs)
+                .lines()
+                .map(str::to_owned)
+                .collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trace_span_fields() {
+        let logs = LogCapture::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        let block = 1;
+        let transaction = counter_call(INCREMENT).from(SENDER);
+        let request_hash = trace_request_hash(&transaction);
+        handle.add_trace(transaction, block).await.unwrap();
+        handle.fetch_accumulated_diffs(block).await.unwrap();
+
+        let fields = [
+            format!("block={block}"),
+            format!("request_hash={request_hash}"),
+            format!("from={:?}", Some(SENDER)),
+        ];
+        let lines = logs.lines();
+        let find = |message: &str| {
+            lines
+                .iter()
+                .find(|line| line.contains(message))
+                .unwrap_or_else(|| panic!("missing log: {message}"))
+        };
+
+        // The RPC call in the spawned task and the merge in the actor share the trace context
+        let call = find("debug_traceCall completed");
+        assert!(call.contains("trace{"));
+        assert!(call.contains("elapsed="));
+        assert!(fields.iter().all(|field| call.contains(field.as_str())));
+
+        let merge = find("Merged trace result");
+        assert!(fields.iter().all(|field| merge.contains(field.as_str())));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(Value::Null)).await;
+        let (manager, handle) = CallTraceManager::new(rpc.url());
+        tokio::spawn(manager.with_dry_run(true));
+
+        let block = 1;
+        for _ in 0..3 {
+            handle
+                .add_trace(counter_call(INCREMENT), block)
+                .await
+                .unwrap();
+        }
+
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+        assert!(diffs.is_empty());
+        assert!(rpc.calls("debug_traceCall").is_empty());
+    }
+
+    #[cfg(feature = "diff-store")]
+    #[tokio::test]
+    async fn test_diffs_restored_from_store() {
+        use crate::builder::diff_store::FileDiffStore;
+
+        let path =
+            std::env::temp_dir().join(format!("bolt-trace-diffs-{}.json", std::process::id()));
+        let block = 10;
+
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        let manager =
+            manager.with_diff_store(FileDiffStore::new(&path), Duration::from_secs(60), 8);
+        let actor = tokio::spawn(manager);
+
+        handle
+            .add_trace(counter_call(INCREMENT), block)
+            .await
+            .unwrap();
+        wait_until(
+            || handle.peek_accumulated_diffs(block),
+            |diffs| !diffs.is_empty(),
+        )
+        .await;
+
+        // The diffs are saved on shutdown
+        handle.shutdown().await.unwrap();
+        actor.await.unwrap();
+
+        // A new manager restores them, without tracing anything again
+        let rpc = MockRpcServer::spawn(|_, _| Ok(Value::Null)).await;
+        let (manager, handle) = CallTraceManager::new(rpc.url());
+        tokio::spawn(manager.with_diff_store(
+            FileDiffStore::new(&path),
+            Duration::from_secs(60),
+            8,
+        ));
+
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(1)));
+        assert_eq!(rpc.call_count("debug_traceCall"), 0);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(feature = "diff-store")]
+    #[tokio::test]
+    async fn test_snapshots_are_saved_in_the_background() {
+        use crate::builder::diff_store::DiffStore;
+
+        /// A store that takes a while to save, and counts the saves.
+        #[derive(Debug, Default)]
+        struct SlowStore {
+            saves: Arc<AtomicU64>,
+        }
+
+        impl DiffStore for SlowStore {
+            fn save(&self, _diffs: &HashMap<BlockNumber, Arc<StateOverride>>) -> io::Result<()> {
+                std::thread::sleep(Duration::from_millis(200));
+                self.saves.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+
+            fn load(&self) -> io::Result<HashMap<BlockNumber, StateOverride>> {
+                Ok(HashMap::new())
+            }
+        }
+
+        let store = SlowStore::default();
+        let saves = Arc::clone(&store.saves);
+
+        let rpc = MockRpcServer::spawn(|_, _| Ok(Value::Null)).await;
+        let (manager, handle) = CallTraceManager::new(rpc.url());
+        let actor = tokio::spawn(manager.with_diff_store(store, Duration::from_millis(10), 8));
+
+        // The actor keeps answering while a snapshot is being saved
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        tokio::time::timeout(Duration::from_millis(100), handle.block_status(1))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(saves.load(Ordering::Relaxed), 0);
+
+        // The last snapshot is saved when the actor stops, after the one in progress
+        handle.shutdown().await.unwrap();
+        actor.await.unwrap();
+        assert!(saves.load(Ordering::Relaxed) >= 2);
+    }
+
+    #[cfg(feature = "diff-store")]
+    #[tokio::test]
+    async fn test_stale_restored_diffs_are_pruned() {
+        use crate::builder::diff_store::{DiffStore, FileDiffStore};
+
+        let path =
+            std::env::temp_dir().join(format!("bolt-stale-diffs-{}.json", std::process::id()));
+        let store = FileDiffStore::new(&path);
+        let diff = StateOverride::from([(COUNTER, Default::default())]);
+        let diff = Arc::new(diff);
+        store
+            .save(&HashMap::from([(1, Arc::clone(&diff)), (9, diff)]))
+            .unwrap();
+
+        let rpc = MockRpcServer::spawn(|_, _| Ok(Value::Null)).await;
+        let (manager, _handle) = CallTraceManager::new(rpc.url());
+        let mut manager = manager.with_diff_store(store, Duration::from_secs(60), 2);
+
+        // The head is not known yet, so everything is restored
+        assert_eq!(manager.tracked_blocks(), BTreeSet::from([1, 9]));
+
+        // Once it is, the blocks more than 2 blocks below it are dropped
+        manager.set_head(10);
+        assert_eq!(manager.tracked_blocks(), BTreeSet::from([9]));
+
+        // Only the restored diffs are pruned
+        manager.set_head(20);
+        assert_eq!(manager.tracked_blocks(), BTreeSet::from([9]));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_merge_seeded_account_override() {
+        let slot_0 = B256::ZERO;
+        let slot_1 = B256::with_last_byte(1);
+        let value = |v: u8| B256::with_last_byte(v);
+
+        let mut account = AccountOverride {
+            balance: Some(U256::from(1)),
+            nonce: Some(U64::from(1)),
+            state_diff: Some([(slot_0, value(1))].into()),
+            ..Default::default()
+        };
+
+        // A seeded state diff is layered on top
+        merge_account_override(
+            &mut account,
+            AccountOverride {
+                balance: Some(U256::from(2)),
+                state_diff: Some([(slot_1, value(2))].into()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(account.balance, Some(U256::from(2)));
+        assert_eq!(account.nonce, Some(U64::from(1)));
+        assert_eq!(
+            account.state_diff,
+            Some([(slot_0, value(1)), (slot_1, value(2))].into())
+        );
+
+        // A seeded full state replaces the storage, and the next diffs are applied to it
+        merge_account_override(
+            &mut account,
+            AccountOverride {
+                state: Some([(slot_1, value(3))].into()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(account.state, Some([(slot_1, value(3))].into()));
+        assert_eq!(account.state_diff, None);
+
+        merge_account_state_in_overrides(
+            &mut account,
+            AccountState {
+                storage: [(slot_0, value(4))].into(),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            account.state,
+            Some([(slot_0, value(4)), (slot_1, value(3))].into())
+        );
+        assert_eq!(account.state_diff, None);
+    }
+
+    #[tokio::test]
+    async fn test_seed_diff_then_trace() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        // Prime the block with the counter state and another account from a prior slot
+        let block = 1;
+        let other = Address::repeat_byte(0x11);
+        let seed = StateOverride::from([
+            (
+                COUNTER,
+                AccountOverride {
+                    state_diff: Some([(B256::ZERO, B256::from(U256::from(5)))].into()),
+                    ..Default::default()
+                },
+            ),
+            (
+                other,
+                AccountOverride {
+                    balance: Some(U256::from(100)),
+                    ..Default::default()
+                },
+            ),
+        ]);
+        handle.seed_diff(block, seed).await.unwrap();
+
+        for _ in 0..2 {
+            handle
+                .add_trace(counter_call(INCREMENT), block)
+                .await
+                .unwrap();
+        }
+
+        // The traces ran on top of the seeded state
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(7)));
+        assert_eq!(diffs[&other].balance, Some(U256::from(100)));
+
+        let calls = rpc.calls("debug_traceCall");
+        assert_eq!(counter_override(&calls[0]), Some(5));
+        assert!(calls[0][2]["stateOverrides"]
+            .get(other.to_string().to_lowercase())
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_try_add_trace_with_full_channel() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(Value::Null)).await;
+        let (manager, handle) =
+            CallTraceManager::with_client_and_capacity(RpcClient::new(rpc.url()), 2);
+
+        // The actor is not polled, so the channel fills up
+        for _ in 0..2 {
+            handle.try_add_trace(counter_call(INCREMENT), 1).unwrap();
+        }
+
+        let err = handle
+            .try_add_trace(counter_call(INCREMENT), 1)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            TrySendError::Full(TraceCommand::AddTrace {
+                block: BlockTarget::Number(1),
+                ..
+            })
+        ));
+
+        // While the async variant waits for room in the channel
+        let res = tokio::time::timeout(
+            Duration::from_millis(50),
+            handle.add_trace(counter_call(INCREMENT), 1),
+        )
+        .await;
+        assert!(res.is_err());
+
+        drop(manager);
+        let err = handle
+            .try_add_trace(counter_call(INCREMENT), 1)
+            .unwrap_err();
+        assert!(matches!(err, TrySendError::Closed(_)));
+    }
+
+    #[tokio::test]
+    async fn test_injected_code_is_traced() {
+        let target = Address::repeat_byte(0x42);
+        // PUSH1 1 SLOAD PUSH1 1 ADD PUSH1 1 SSTORE: increments slot 1
+        let code = Bytes::from_static(&[0x60, 0x01, 0x54, 0x60, 0x01, 0x01, 0x60, 0x01, 0x55]);
+        let slot = B256::with_last_byte(1);
+
+        // Emulates the execution of the injected code, and reports the touched storage
+        // without the code, as a prestate of an account with overridden code may do
+        let injected = code.clone();
+        let rpc = MockRpcServer::spawn(move |_, params| {
+            let account = &params[2]["stateOverrides"][target.to_string().to_lowercase()];
+            if account["code"] != json!(injected) {
+                return Ok(json!({}));
+            }
+
+            let current = account["stateDiff"][slot.to_string()]
+                .as_str()
+                .map_or(0, |value| {
+                    u64::from_str_radix(value.trim_start_matches("0x"), 16).unwrap()
+                });
+            let value = B256::from(U256::from(current + 1));
+
+            Ok(json!({
+                target.to_string().to_lowercase(): { "storage": { slot.to_string(): value } },
+            }))
+        })
+        .await;
+        let (manager, handle) =
+            CallTraceManager::new_with_tracer(rpc.url(), TracerKind::PreStateDiff);
+        tokio::spawn(manager);
+
+        let block = 1;
+        handle
+            .inject_code(block, target, code.clone(), HashMap::new())
+            .await
+            .unwrap();
+
+        let call = TransactionRequest::default().to(target);
+        for _ in 0..2 {
+            handle.add_trace(call.clone(), block).await.unwrap();
+        }
+
+        // Both traces ran against the injected code, which survived the first one
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+        assert_eq!(diffs[&target].code, Some(code));
+        assert_eq!(
+            diffs[&target].state_diff.as_ref().unwrap()[&slot],
+            B256::from(U256::from(2))
+        );
+        assert_eq!(rpc.call_count("debug_traceCall"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_peek_accumulated_diffs() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        let block = 1;
+        assert!(handle
+            .peek_accumulated_diffs(block)
+            .await
+            .unwrap()
+            .is_empty());
+
+        handle
+            .add_trace(counter_call(INCREMENT), block)
+            .await
+            .unwrap();
+
+        // Wait until the trace has been merged
+        let diffs = wait_until(
+            || handle.peek_accumulated_diffs(block),
+            |diffs| !diffs.is_empty(),
+        )
+        .await;
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(1)));
+
+        // The peeked diffs were kept, so the next traces build on top of them
+        for _ in 0..2 {
+            handle
+                .add_trace(counter_call(INCREMENT), block)
+                .await
+                .unwrap();
+        }
+
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(3)));
+        assert_eq!(rpc.call_count("debug_traceCall"), 3);
+
+        // Fetching consumed them
+        assert!(handle
+            .peek_accumulated_diffs(block)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_diffs() {
+        let rpc = spawn_counter_rpc_with_delay(Duration::from_millis(50)).await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        let block = 1;
+        assert_eq!(
+            handle.subscribe_diffs(block).await.unwrap().count().await,
+            0
+        );
+
+        for _ in 0..3 {
+            handle
+                .add_trace(counter_call(INCREMENT), block)
+                .await
+                .unwrap();
+        }
+
+        // One snapshot per merged trace, and the stream ends with the last one
+        let slots = handle
+            .subscribe_diffs(block)
+            .await
+            .unwrap()
+            .map(|diffs| diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO])
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            slots,
+            (1..=3u64)
+                .map(|value| B256::from(U256::from(value)))
+                .collect::<Vec<_>>()
+        );
+
+        // Subscribing doesn't consume the diffs
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(3)));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_diff_subscriber_is_removed() {
+        let rpc = stalled_rpc().await;
+        let (mut manager, _handle) = CallTraceManager::new(rpc.url());
+
+        // Trace id 0 in flight, and another one queued behind it
+        let block = 1;
+        for _ in 0..2 {
+            add_trace(&mut manager, counter_call(INCREMENT), block);
+        }
+
+        let (tx, rx) = mpsc::channel(1);
+        manager.handle_new_trace_command(TraceCommand::SubscribeDiffs { block, tx });
+        assert_eq!(manager.diff_subscribers[&block].len(), 1);
+        drop(rx);
+
+        // The block is still pending, but the subscriber is gone
+        manager.handle_trace_result(block, 0, Ok(counter_trace(1)));
+        assert!(manager.is_block_pending(block));
+        assert!(manager.diff_subscribers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_trace_options_config() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) =
+            CallTraceManager::new_with_tracer(rpc.url(), TracerKind::PreStateDiff);
+        let options = TraceOptionsConfig {
+            memory: true,
+            gas_cap: Some(100_000),
+            timeout: Some("60s".to_string()),
+            ..Default::default()
+        };
+        tokio::spawn(manager.with_trace_options(options));
+
+        let block = 1;
+        handle
+            .add_trace(counter_call(INCREMENT).gas_limit(1_000_000), block)
+            .await
+            .unwrap();
+        handle.fetch_accumulated_diffs(block).await.unwrap();
+
+        let calls = rpc.calls("debug_traceCall");
+        let (transaction, opts) = (&calls[0][0], &calls[0][2]);
+        assert_eq!(transaction["gas"], json!("0x186a0"));
+        assert_eq!(opts["enableMemory"], json!(true));
+        assert_eq!(opts["disableMemory"], json!(false));
+        assert_eq!(opts["disableStack"], json!(true));
+        assert_eq!(opts["disableStorage"], json!(false));
+        assert_eq!(opts["timeout"], json!("60s"));
+    }
+
+    #[test]
+    fn test_default_trace_options() {
+        let opts = get_trace_options_with_override(
+            TracerKind::PreStateDiff.tracer_type(&TransactionRequest::default()),
+            &TraceOptionsConfig::default(),
+            None,
+        );
+
+        let config = opts.tracing_options.config;
+        assert_eq!(config.disable_storage, Some(false));
+        assert_eq!(config.disable_memory, Some(true));
+        assert_eq!(config.disable_stack, Some(true));
+        assert_eq!(config.disable_return_data, Some(true));
+        assert_eq!(config.enable_memory, None);
+        assert_eq!(opts.tracing_options.timeout, None);
+    }
+
+    #[tokio::test]
+    async fn test_diff_mode_accumulates_post_state() {
+        let initial = U256::from(1_000_000);
+        let value = U256::from(1_000);
+        let recipient = Address::repeat_byte(0x11);
+
+        // A transfer from the sender, which also clears a slot of the recipient
+        let slot = B256::with_last_byte(1);
+        let rpc = MockRpcServer::spawn(move |_, _| {
+            let (sender, to) = (SENDER.to_string(), recipient.to_string());
+            let storage = json!({ slot.to_string(): B256::with_last_byte(7) });
+
+            Ok(json!({
+                "pre": {
+                    &sender: { "balance": initial, "nonce": 0 },
+                    &to: { "balance": "0x0", "storage": storage },
+                },
+                "post": {
+                    &sender: { "balance": initial - value, "nonce": 1 },
+                    &to: { "balance": value },
+                },
+            }))
+        })
+        .await;
+        let (manager, handle) =
+            CallTraceManager::new_with_tracer(rpc.url(), TracerKind::PreStateDiff);
+        let options = TraceOptionsConfig {
+            diff_mode: true,
+            ..Default::default()
+        };
+        tokio::spawn(manager.with_trace_options(options));
+
+        let block = 1;
+        let transfer = TransactionRequest::default()
+            .from(SENDER)
+            .to(recipient)
+            .value(value);
+        handle.add_trace(transfer, block).await.unwrap();
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+
+        let calls = rpc.calls("debug_traceCall");
+        assert_eq!(calls[0][2]["tracerConfig"]["diffMode"], json!(true));
+
+        assert_eq!(diffs[&SENDER].balance, Some(initial - value));
+        assert_eq!(diffs[&SENDER].nonce, Some(U64::from(1)));
+        assert_eq!(diffs[&recipient].balance, Some(value));
+        assert_eq!(
+            diffs[&recipient].state_diff,
+            Some(HashMap::from([(slot, B256::ZERO)]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_accumulated_diffs_range() {
+        let rpc = spawn_counter_rpc_with_delay(Duration::from_millis(50)).await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        // Nothing traced in the range yet
+        assert!(handle
+            .fetch_accumulated_diffs_range(1, 3)
+            .await
+            .unwrap()
+            .is_empty());
+
+        for block in [1, 2, 3, 2, 5] {
+            handle
+                .add_trace(counter_call(INCREMENT), block)
+                .await
+                .unwrap();
+        }
+
+        // Resolves once all the blocks of the range are done, without the ones outside
+        let diffs = handle.fetch_accumulated_diffs_range(1, 3).await.unwrap();
+        let slots = diffs
+            .iter()
+            .map(|(block, diffs)| {
+                let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+                (*block, slot)
+            })
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(
+            slots,
+            BTreeMap::from([
+                (1, B256::from(U256::from(1))),
+                (2, B256::from(U256::from(2))),
+                (3, B256::from(U256::from(1))),
+            ])
+        );
+
+        // The diffs were not consumed
+        let diffs = handle.fetch_accumulated_diffs(2).await.unwrap();
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(2)));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_traces_are_deduplicated() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        // The same transaction re-broadcast while its trace is in flight
+        let block = 1;
+        let transaction = counter_call(INCREMENT).from(SENDER).nonce(0);
+        for _ in 0..2 {
+            handle.add_trace(transaction.clone(), block).await.unwrap();
+        }
+
+        // And again once it has been merged
+        wait_until(
+            || handle.peek_accumulated_diffs(block),
+            |diffs| !diffs.is_empty(),
+        )
+        .await;
+        handle.add_trace(transaction.clone(), block).await.unwrap();
+
+        // The increment was applied once
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(1)));
+        assert_eq!(rpc.call_count("debug_traceCall"), 1);
+
+        // Fetching consumed the bundle, so the transaction can be traced again
+        handle.add_trace(transaction, block).await.unwrap();
+        handle.fetch_accumulated_diffs(block).await.unwrap();
+        assert_eq!(rpc.call_count("debug_traceCall"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_calls_are_traced() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        // Without a nonce, the same call made twice isn't a re-submission, even with a
+        // sender
+        let block = 1;
+        let calls = [
+            counter_call(INCREMENT),
+            counter_call(INCREMENT).from(SENDER),
+        ];
+        for call in calls.iter().flat_map(|call| [call, call]) {
+            handle.add_trace(call.clone(), block).await.unwrap();
+        }
+
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(4)));
+        assert_eq!(rpc.call_count("debug_traceCall"), 4);
+    }
+
+    #[tokio::test]
+    async fn test_remove_merged_trace() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        let block = 1;
+        let transactions = (0..3)
+            .map(|nonce| counter_call(INCREMENT).nonce(nonce))
+            .collect::<Vec<_>>();
+        for transaction in &transactions {
+            handle.add_trace(transaction.clone(), block).await.unwrap();
+        }
+
+        // Wait for all the traces to be merged before removing the second one
+        handle
+            .fetch_accumulated_diffs_range(block, block)
+            .await
+            .unwrap();
+        handle
+            .remove_trace(block, trace_request_hash(&transactions[1]))
+            .await
+            .unwrap();
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+
+        // The third increment ran on top of the second one, so it is traced again
+        // without it, seeing the counter left by the first increment only
+        let overrides = rpc
+            .calls("debug_traceCall")
+            .iter()
+            .map(counter_override)
+            .collect::<Vec<_>>();
+        assert_eq!(overrides, vec![None, Some(1), Some(2), Some(1)]);
+
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(2)));
+    }
+
+    #[tokio::test]
+    async fn test_future_block_traces_are_buffered() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        handle.update_head(9).await.unwrap();
+
+        let block = 10;
+        for _ in 0..2 {
+            handle
+                .add_trace(counter_call(INCREMENT), block)
+                .await
+                .unwrap();
+        }
+
+        // The block is in the future, so nothing is traced yet
+        let res = handle
+            .fetch_accumulated_diffs_timeout(block, Duration::from_millis(100))
+            .await;
+        assert!(res.is_err());
+        assert_eq!(rpc.call_count("debug_traceCall"), 0);
+
+        // Once the head reaches the block, the buffered traces are processed in order
+        handle.update_head(block).await.unwrap();
+
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(2)));
+        assert_eq!(rpc.call_count("debug_traceCall"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_pending_block_target() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        tokio::spawn(manager);
+
+        // The pending block can't be resolved without a head
+        assert_eq!(
+            handle.add_pending_trace(counter_call(INCREMENT)).await,
+            Err(PendingTraceError::UnknownHead)
+        );
+
+        handle.update_head(9).await.unwrap();
+        let block = handle
+            .add_pending_trace(counter_call(INCREMENT))
+            .await
+            .unwrap();
+        assert_eq!(block, 10);
+        assert!(handle.block_status(10).await.unwrap().pending);
+
+        // The trace stays on the resolved block when the head advances, and a later
+        // request resolves to the new pending block
+        handle.update_head(10).await.unwrap();
+        let next = handle
+            .add_pending_trace(counter_call(INCREMENT))
+            .await
+            .unwrap();
+        assert_eq!(next, 11);
+
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(1)));
+        assert!(!handle.block_status(block).await.unwrap().pending);
+        assert!(handle.block_status(next).await.unwrap().pending);
+    }
+
+    #[tokio::test]
+    async fn test_block_status() {
+        let rpc = stalled_rpc().await;
+        let (mut manager, _handle) = CallTraceManager::new(rpc.url());
+        manager.set_head(1);
+
+        // The first trace is stalled in flight, the next ones wait behind it
+        add_trace(&mut manager, counter_call(INCREMENT), 1);
+        add_trace(&mut manager, counter_call(RESET), 1);
+        add_trace(&mut manager, counter_call([0; 4]), 1);
+        // Buffered until the head reaches the block
+        add_trace(&mut manager, counter_call(INCREMENT), 3);
+
+        let (fetch_tx, _fetch_rx) = oneshot::channel();
+        manager.handle_new_trace_command(TraceCommand::FetchAccumulatedDiffs {
+            block: 1,
+            id: 0,
+            res: fetch_tx,
+        });
+        let (validation_tx, _validation_rx) = oneshot::channel();
+        manager.handle_new_trace_command(TraceCommand::ValidateBundle {
+            block: 1,
+            res: validation_tx,
+        });
+
+        let status = |manager: &mut CallTraceManager, block| {
+            let (res_tx, mut res_rx) = oneshot::channel();
+            manager.handle_new_trace_command(TraceCommand::BlockStatus { block, res: res_tx });
+            res_rx.try_recv().unwrap()
+        };
+
+        let expected = BlockTraceStatus {
+            in_flight: 1,
+            queued: 2,
+            has_diffs: false,
+            waiters: 2,
+            failed: false,
+            pending: false,
+        };
+        assert_eq!(status(&mut manager, 1), expected);
+        assert!(!expected.is_complete());
+
+        // Seeded state shows up as diffs, without changing the pipeline
+        manager.handle_new_trace_command(TraceCommand::SeedDiff {
+            block: 1,
+            overrides: StateOverride::from([(COUNTER, AccountOverride::default())]),
+        });
+        assert_eq!(
+            status(&mut manager, 1),
+            BlockTraceStatus {
+                has_diffs: true,
+                ..expected
+            }
+        );
+
+        assert_eq!(
+            status(&mut manager, 3),
+            BlockTraceStatus {
+                queued: 1,
+                ..Default::default()
+            }
+        );
+        assert!(status(&mut manager, 2).is_complete());
+    }
+
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn test_accumulated_diffs_shared_with_traces() {
+        use metrics_util::debugging::{DebugValue, DebuggingRecorder, Snapshotter};
+
+        let rpc = spawn_counter_rpc().await;
+        let (mut manager, _handle) = counter_manager(&rpc);
+        manager.set_head(1);
+
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+
+        async fn drive(manager: &mut CallTraceManager, recorder: &DebuggingRecorder) {
+            while let Some(res) = manager.pending_traces.next().await {
+                let (block, id, result) = res.unwrap();
+                metrics::with_local_recorder(recorder, || {
+                    manager.handle_trace_result(block, id, result)
+                });
+            }
+        }
+        let diff_copies = |snapshotter: &Snapshotter| {
+            snapshotter
+                .snapshot()
+                .into_vec()
+                .into_iter()
+                .find_map(|(key, _, _, value)| match value {
+                    DebugValue::Counter(value) if key.key().name() == TRACE_DIFF_COPIES_TOTAL => {
+                        Some(value)
+                    }
+                    _ => None,
+                })
+                .unwrap_or_default()
+        };
+
+        // A 50-transaction bundle, each trace running on top of the previous ones
+        for _ in 0..50 {
+            add_trace(&mut manager, counter_call(INCREMENT), 1);
+        }
+        drive(&mut manager, &recorder).await;
+
+        let calls = rpc.calls("debug_traceCall");
+        assert_eq!(calls.len(), 50);
+        assert_eq!(counter_override(&calls[49]), Some(49));
+
+        // The trace tasks were done with the diffs when their results were merged,
+        // so the diffs were never copied
+        assert_eq!(diff_copies(&snapshotter), 0);
+
+        // Seeding while a trace still holds the diffs copies them, once
+        add_trace(&mut manager, counter_call(INCREMENT), 1);
+        metrics::with_local_recorder(&recorder, || {
+            manager.handle_new_trace_command(TraceCommand::SeedDiff {
+                block: 1,
+                overrides: StateOverride::from([(SENDER, AccountOverride::default())]),
+            })
+        });
+        assert_eq!(diff_copies(&snapshotter), 1);
+
+        drive(&mut manager, &recorder).await;
+        assert_eq!(diff_copies(&snapshotter), 1);
+        assert_eq!(
+            counter_override(&rpc.calls("debug_traceCall")[50]),
+            Some(50)
+        );
+        assert!(manager.accumulated_state_diffs[&1].contains_key(&SENDER));
+    }
+
+    #[tokio::test]
+    async fn test_head_polling_flushes_future_traces() {
+        let rpc = MockRpcServer::spawn(|method, _| match method {
+            "eth_getBlockByNumber" => Ok(json!({
+                "number": "0xa",
+                "hash": B256::ZERO,
+                "parentHash": B256::ZERO,
+            })),
+            _ => Ok(Value::Null),
+        })
+        .await;
+        let (manager, _handle) = CallTraceManager::new(rpc.url());
+        let mut manager = manager.with_head_poll_interval(Duration::from_millis(10));
+        manager.set_head(9);
+
+        let block = 10;
+        add_trace(&mut manager, counter_call(INCREMENT), block);
+        assert_eq!(manager.future_queue[&block].len(), 1);
+        assert!(manager.in_flight_blocks.is_empty());
+
+        // Drive the actor until the polled head reaches the buffered block
+        let _ = tokio::time::timeout(Duration::from_millis(500), &mut manager).await;
+
+        assert_eq!(manager.head, Some(block));
+        assert!(manager.future_queue.is_empty());
+        assert_eq!(rpc.call_count("debug_traceCall"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_block_ticker_flushes_future_traces() {
+        let rpc = MockRpcServer::spawn(|method, _| match method {
+            "eth_blockNumber" => Ok(json!("0xa")),
+            _ => Ok(Value::Null),
+        })
+        .await;
+        let ticker = BlockTicker::polling(RpcClient::new(rpc.url()), Duration::from_millis(10));
+        let (manager, _handle) = CallTraceManager::new(rpc.url());
+        let mut manager = manager.with_block_ticker(ticker);
+        manager.set_head(9);
+
+        let block = 10;
+        add_trace(&mut manager, counter_call(INCREMENT), block);
+        assert_eq!(manager.future_queue[&block].len(), 1);
+
+        // Drive the actor until the ticker yields the buffered block
+        let _ = tokio::time::timeout(Duration::from_millis(500), &mut manager).await;
+
+        assert_eq!(manager.head, Some(block));
+        assert!(manager.future_queue.is_empty());
+        assert_eq!(rpc.call_count("debug_traceCall"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_simulation_target_saturates() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(Value::Null)).await;
+        let (manager, _handle) = CallTraceManager::new(rpc.url());
+        let mut manager = manager.with_head_offset(2);
+        assert_eq!(manager.simulation_target(), None);
+
+        manager.set_head(u64::MAX - 1);
+        assert_eq!(manager.simulation_target(), Some(u64::MAX));
+    }
+
+    #[tokio::test]
+    async fn test_trace_at_head_with_offset_runs_on_head_state() {
+        let rpc = spawn_counter_rpc().await;
+        let (manager, handle) = counter_manager(&rpc);
+        let mut manager = manager.with_head_offset(2);
+        manager.set_head(9);
+        tokio::spawn(manager);
+
+        let block = handle
+            .add_trace_at_head(counter_call(INCREMENT))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(block, 11);
+
+        // The trace isn't buffered until the head reaches the target block
+        let diffs = handle.fetch_accumulated_diffs(block).await.unwrap();
+        let slot = diffs[&COUNTER].state_diff.as_ref().unwrap()[&B256::ZERO];
+        assert_eq!(slot, B256::from(U256::from(1)));
+
+        let params = &rpc.calls("debug_traceCall")[0];
+        assert_eq!(params[1], json!("0x9"));
+        assert_eq!(params[2]["blockOverrides"]["number"], json!("0xb"));
     }
 }
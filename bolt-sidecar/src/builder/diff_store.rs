@@ -0,0 +1,97 @@
+//! Persistence of the state diffs accumulated by the
+//! [CallTraceManager](super::call_trace_manager::CallTraceManager), so that a restart
+//! doesn't force re-tracing every in-progress bundle.
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use alloy_primitives::BlockNumber;
+use alloy_rpc_types::state::StateOverride;
+
+/// A storage backend for the accumulated state diffs, keyed by block.
+///
+/// Every [DiffStore::save] replaces the previous snapshot. The periodic snapshots are
+/// saved on a blocking thread, one at a time, so implementations may block.
+pub trait DiffStore: Debug + Send + Sync {
+    /// Replace the stored snapshot with the given diffs, shared with the actor.
+    fn save(&self, diffs: &HashMap<BlockNumber, Arc<StateOverride>>) -> io::Result<()>;
+
+    /// Load the last saved snapshot. Returns an empty map if nothing was saved yet.
+    fn load(&self) -> io::Result<HashMap<BlockNumber, StateOverride>>;
+}
+
+/// A [DiffStore] that keeps the snapshot as a JSON file.
+///
+/// The file is written to a temporary file first and then renamed, so that a crash
+/// while saving never leaves a truncated snapshot behind.
+#[derive(Debug, Clone)]
+pub struct FileDiffStore {
+    path: PathBuf,
+}
+
+impl FileDiffStore {
+    /// Create a new store that keeps the snapshot at the given path.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl DiffStore for FileDiffStore {
+    fn save(&self, diffs: &HashMap<BlockNumber, Arc<StateOverride>>) -> io::Result<()> {
+        let diffs = diffs
+            .iter()
+            .map(|(block, diffs)| (block, diffs.as_ref()))
+            .collect::<HashMap<_, _>>();
+
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(&diffs)?)?;
+        fs::rename(tmp, &self.path)
+    }
+
+    fn load(&self) -> io::Result<HashMap<BlockNumber, StateOverride>> {
+        match fs::read(&self.path) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{Address, B256, U256, U64};
+    use alloy_rpc_types::state::AccountOverride;
+
+    use super::*;
+
+    #[test]
+    fn test_file_diff_store_roundtrip() {
+        let path = std::env::temp_dir().join(format!("bolt-diffs-{}.json", std::process::id()));
+        let store = FileDiffStore::new(&path);
+
+        // Nothing saved yet
+        assert!(store.load().unwrap().is_empty());
+
+        let account = AccountOverride {
+            nonce: Some(U64::from(1)),
+            balance: Some(U256::from(100)),
+            state_diff: Some([(B256::ZERO, B256::with_last_byte(1))].into()),
+            ..Default::default()
+        };
+        let diff = StateOverride::from([(Address::ZERO, account)]);
+
+        store
+            .save(&HashMap::from([(10, Arc::new(diff.clone()))]))
+            .unwrap();
+        assert_eq!(store.load().unwrap(), HashMap::from([(10, diff)]));
+
+        fs::remove_file(path).unwrap();
+    }
+}
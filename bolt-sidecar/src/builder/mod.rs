@@ -38,7 +38,17 @@ pub mod state_root;
 
 /// Deprecated simulation manager. TODO: remove
 pub mod call_trace_manager;
-pub use call_trace_manager::{CallTraceHandle, CallTraceManager};
+
+/// Persistence of the state diffs accumulated by the call trace manager.
+#[cfg(feature = "diff-store")]
+pub mod diff_store;
+pub use call_trace_manager::{
+    trace_request_hash, BlockTarget, BlockTraceStatus, BundleConflict, BundleValidation,
+    CallTraceHandle, CallTraceManager, PendingTraceError, SenderSummary, TouchedAccounts,
+    TraceActorGone, TraceError, TraceOptionsConfig, TracerKind, DEFAULT_CHANNEL_CAPACITY,
+    DEFAULT_MAX_CONCURRENT_TRACES, DEFAULT_MAX_TRACKED_BLOCKS, DEFAULT_TRACE_CACHE_SIZE,
+    DIFF_SUBSCRIPTION_CAPACITY,
+};
 
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
@@ -7,7 +7,7 @@ use ethereum_consensus::ssz::prelude::{HashTreeRoot, MerkleizationError};
 use tree_hash::TreeHash;
 use tree_hash_derive::TreeHash;
 
-use crate::ChainConfig;
+use crate::{primitives::SignedBuilderBid, ChainConfig};
 
 /// Sign a SSZ object a BLS secret key, using the Application Builder domain
 /// for signing arbitrary builder-api messages in the out-of-protocol specifications.
@@ -47,13 +47,33 @@ pub fn verify_signed_builder_message<T: HashTreeRoot>(
     })
 }
 
+/// Verify the signature of a [SignedBuilderBid] against the builder public key
+/// contained in the bid message, using the Application Builder domain.
+///
+/// This should be used before trusting any bid received from a relay.
+pub fn verify_signed_builder_bid(
+    chain: &ChainConfig,
+    signed_bid: &SignedBuilderBid,
+) -> Result<(), ethereum_consensus::Error> {
+    let invalid_signature =
+        || ethereum_consensus::Error::Bls(ethereum_consensus::crypto::BlsError::InvalidSignature);
+
+    // compat: convert from ethereum consensus to blst and alloy types
+    let pubkey = PublicKey::from_bytes(signed_bid.message.public_key.as_ref())
+        .map_err(|_| invalid_signature())?;
+    let signature =
+        BlsSignature::try_from(signed_bid.signature.as_ref()).map_err(|_| invalid_signature())?;
+
+    verify_signed_builder_message(chain, &pubkey, &signed_bid.message, &signature)
+}
+
 /// Verify a BLS signature for a given message and public key.
 pub fn verify_signature(
     pubkey: &PublicKey,
     msg: &[u8],
     signature: &BlsSignature,
 ) -> Result<(), blst::BLST_ERROR> {
-    let sig = blst::min_pk::Signature::from_bytes(&signature.0).expect("valid signature bytes");
+    let sig = blst::min_pk::Signature::from_bytes(&signature.0)?;
 
     let res = sig.verify(true, msg, BLS_DST_SIG, &[], pubkey, true);
     if res == BLST_ERROR::BLST_SUCCESS {
@@ -115,7 +135,17 @@ pub fn compute_builder_domain(
 
 #[cfg(test)]
 mod tests {
-    use crate::{builder::signature::compute_builder_domain, ChainConfig};
+    use alloy_primitives::U256;
+    use ethereum_consensus::crypto::PublicKey;
+
+    use crate::{
+        builder::signature::{
+            compute_builder_domain, sign_builder_message, verify_signed_builder_bid,
+        },
+        primitives::{BuilderBid, SignedBuilderBid},
+        test_util::test_bls_secret_key,
+        ChainConfig,
+    };
 
     #[test]
     fn test_compute_builder_domain() {
@@ -143,4 +173,28 @@ mod tests {
             helder.builder_domain()
         );
     }
+
+    #[test]
+    fn test_verify_signed_builder_bid() {
+        let chain = ChainConfig::mainnet();
+        let sk = test_bls_secret_key();
+        let pubkey = sk.sk_to_pk().to_bytes();
+
+        let message = BuilderBid {
+            value: U256::from(1),
+            public_key: PublicKey::try_from(pubkey.as_slice()).unwrap(),
+            ..Default::default()
+        };
+        let signature = sign_builder_message(&chain, &sk, &message).unwrap();
+        let mut signed_bid = SignedBuilderBid { message, signature };
+
+        assert!(verify_signed_builder_bid(&chain, &signed_bid).is_ok());
+
+        // The signature is bound to the signing domain of the chain
+        assert!(verify_signed_builder_bid(&ChainConfig::holesky(), &signed_bid).is_err());
+
+        // Tampering with the bid invalidates the signature
+        signed_bid.message.value = U256::from(2);
+        assert!(verify_signed_builder_bid(&chain, &signed_bid).is_err());
+    }
 }
@@ -43,13 +43,14 @@ mod tests {
             .collect::<Vec<_>>();
 
         for tx in tx_requests.iter() {
-            call_trace_handler.add_trace(tx.clone(), block_number).await;
+            call_trace_handler
+                .add_trace(tx.clone(), block_number)
+                .await?;
         }
 
         let diffs = call_trace_handler
             .fetch_accumulated_diffs(block_number)
-            .await
-            .unwrap();
+            .await?;
 
         println!("Touched accounts: {:?}", diffs.keys().len());
 
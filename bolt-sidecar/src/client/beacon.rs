@@ -0,0 +1,496 @@
+//! A typed client of the standard Beacon API of the consensus client, with the queries
+//! the sidecar needs to know which slots its validators propose: proposer duties,
+//! validator statuses, head events, and the genesis and spec of the chain.
+//!
+//! It builds on the [BeaconClient] of `beacon-api-client`, which handles the requests,
+//! the API errors and the event streams, and exposes the responses with the alloy types
+//! used by the rest of the sidecar.
+
+use std::{collections::HashMap, fmt, time::Duration};
+
+use alloy_primitives::{FixedBytes, B256};
+use alloy_rpc_types_beacon::{events::HeadEvent, BlsPublicKey};
+use ethereum_consensus::serde::as_str;
+use futures::{Stream, StreamExt};
+use reqwest::Url;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use super::BeaconClient;
+use crate::{primitives::Slot, state::head_tracker::NewHeadsTopic};
+
+/// The timeout of the queries, not applied to the event streams.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Errors returned by the [BeaconApiClient].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum BeaconApiError {
+    /// The request failed, the beacon node answered with an error, or the response
+    /// could not be decoded.
+    #[error("Beacon API request failed: {0}")]
+    Client(#[from] beacon_api_client::Error),
+    /// The beacon node didn't answer the query in time.
+    #[error("Beacon API request timed out")]
+    Timeout,
+    /// An event of the stream could not be read or decoded.
+    #[error("Failed to read Beacon API event: {0}")]
+    Event(String),
+}
+
+/// A validator, identified either by its index or by its public key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidatorId {
+    /// The index of the validator in the beacon state.
+    Index(u64),
+    /// The BLS public key of the validator.
+    PublicKey(BlsPublicKey),
+}
+
+impl fmt::Display for ValidatorId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "{index}"),
+            Self::PublicKey(pubkey) => write!(f, "{pubkey}"),
+        }
+    }
+}
+
+impl From<u64> for ValidatorId {
+    fn from(index: u64) -> Self {
+        Self::Index(index)
+    }
+}
+
+impl From<BlsPublicKey> for ValidatorId {
+    fn from(pubkey: BlsPublicKey) -> Self {
+        Self::PublicKey(pubkey)
+    }
+}
+
+/// The duty of a validator to propose the block of a slot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProposerDuty {
+    /// The public key of the proposer.
+    pub pubkey: BlsPublicKey,
+    /// The index of the proposer.
+    #[serde(with = "as_str")]
+    pub validator_index: u64,
+    /// The slot to propose in.
+    #[serde(with = "as_str")]
+    pub slot: Slot,
+}
+
+/// The proposer duties of an epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProposerDuties {
+    /// The block root the duties depend on: they must be fetched again if it is
+    /// reorged out.
+    pub dependent_root: B256,
+    /// The duties of every slot of the epoch.
+    #[serde(rename = "data")]
+    pub duties: Vec<ProposerDuty>,
+}
+
+impl ProposerDuties {
+    /// Returns the duties of the given validators, in slot order.
+    pub fn for_validators<'a>(
+        &'a self,
+        validator_indexes: &'a [u64],
+    ) -> impl Iterator<Item = &'a ProposerDuty> + 'a {
+        self.duties
+            .iter()
+            .filter(|duty| validator_indexes.contains(&duty.validator_index))
+    }
+}
+
+/// The status of a validator, as defined by the Beacon API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidatorStatus {
+    /// The deposit was processed, but the validator is not eligible for activation yet.
+    PendingInitialized,
+    /// The validator is eligible and waits in the activation queue.
+    PendingQueued,
+    /// The validator is active, and not exiting.
+    ActiveOngoing,
+    /// The validator is active, and its voluntary exit is scheduled.
+    ActiveExiting,
+    /// The validator is active, and its exit is scheduled after being slashed.
+    ActiveSlashed,
+    /// The validator exited without being slashed, its balance is not withdrawable yet.
+    ExitedUnslashed,
+    /// The validator exited after being slashed, its balance is not withdrawable yet.
+    ExitedSlashed,
+    /// The balance of the exited validator is withdrawable.
+    WithdrawalPossible,
+    /// The balance of the exited validator was withdrawn.
+    WithdrawalDone,
+}
+
+impl ValidatorStatus {
+    /// Returns `true` if the validator is active, i.e. may be assigned proposer duties.
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self,
+            Self::ActiveOngoing | Self::ActiveExiting | Self::ActiveSlashed
+        )
+    }
+}
+
+/// A validator and its status in the beacon state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validator {
+    /// The index of the validator.
+    #[serde(with = "as_str")]
+    pub index: u64,
+    /// The balance of the validator, in gwei.
+    #[serde(with = "as_str")]
+    pub balance: u64,
+    /// The status of the validator.
+    pub status: ValidatorStatus,
+    /// The validator record of the beacon state.
+    pub validator: ValidatorRecord,
+}
+
+/// The record of a validator in the beacon state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorRecord {
+    /// The BLS public key of the validator.
+    pub pubkey: BlsPublicKey,
+    /// The commitment to the credentials the balance is withdrawn with.
+    pub withdrawal_credentials: B256,
+    /// The balance at stake, in gwei, rounded down and capped at the maximum.
+    #[serde(with = "as_str")]
+    pub effective_balance: u64,
+    /// Whether the validator was slashed.
+    pub slashed: bool,
+    /// The epoch at which the validator became eligible for activation.
+    #[serde(with = "as_str")]
+    pub activation_eligibility_epoch: u64,
+    /// The epoch at which the validator was, or will be, activated.
+    #[serde(with = "as_str")]
+    pub activation_epoch: u64,
+    /// The epoch at which the validator exits, `u64::MAX` if not exiting.
+    #[serde(with = "as_str")]
+    pub exit_epoch: u64,
+    /// The epoch from which the balance is withdrawable, `u64::MAX` if not exiting.
+    #[serde(with = "as_str")]
+    pub withdrawable_epoch: u64,
+}
+
+/// The genesis of the beacon chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Genesis {
+    /// The UNIX timestamp of the genesis, in seconds.
+    #[serde(with = "as_str")]
+    pub genesis_time: u64,
+    /// The root of the validators at genesis, part of the signing domains.
+    pub genesis_validators_root: B256,
+    /// The fork version at genesis.
+    pub genesis_fork_version: FixedBytes<4>,
+}
+
+/// The configuration of the beacon node: the preset, config and constants of the
+/// chain, e.g. `SECONDS_PER_SLOT` or `SLOTS_PER_EPOCH`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BeaconSpec(pub HashMap<String, Value>);
+
+impl BeaconSpec {
+    /// Returns the value of the given key, if it is a string.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key)?.as_str()
+    }
+
+    /// Returns the value of the given key, if it is a decimal integer.
+    pub fn get_u64(&self, key: &str) -> Option<u64> {
+        self.get(key)?.parse().ok()
+    }
+}
+
+/// The `data` envelope of most Beacon API responses.
+#[derive(Debug, Serialize, Deserialize)]
+struct DataResponse<T> {
+    data: T,
+}
+
+/// A typed client of the Beacon API of a beacon node, wrapping a [BeaconClient].
+#[derive(Debug, Clone)]
+pub struct BeaconApiClient {
+    client: BeaconClient,
+}
+
+impl From<BeaconClient> for BeaconApiClient {
+    fn from(client: BeaconClient) -> Self {
+        Self { client }
+    }
+}
+
+impl BeaconApiClient {
+    /// Create a new client for the beacon node at the given URL.
+    pub fn new<U: Into<Url>>(url: U) -> Self {
+        Self::from(BeaconClient::new(url.into()))
+    }
+
+    /// Returns the underlying [BeaconClient], for the queries not covered here.
+    pub fn inner(&self) -> &BeaconClient {
+        &self.client
+    }
+
+    /// Get the proposer duties of the given epoch.
+    pub async fn proposer_duties(&self, epoch: u64) -> Result<ProposerDuties, BeaconApiError> {
+        self.get(&format!("eth/v1/validator/duties/proposer/{epoch}"))
+            .await
+    }
+
+    /// Get the given validator and its status at the head of the chain.
+    pub async fn validator(&self, id: ValidatorId) -> Result<Validator, BeaconApiError> {
+        let path = format!("eth/v1/beacon/states/head/validators/{id}");
+        let res: DataResponse<_> = self.get(&path).await?;
+
+        Ok(res.data)
+    }
+
+    /// Get the given validators and their status at the head of the chain. Unknown
+    /// validators are omitted.
+    pub async fn validators(&self, ids: &[ValidatorId]) -> Result<Vec<Validator>, BeaconApiError> {
+        // Without ids, the beacon node would return every validator
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids = ids.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let path = format!("eth/v1/beacon/states/head/validators?id={}", ids.join(","));
+        let res: DataResponse<_> = self.get(&path).await?;
+
+        Ok(res.data)
+    }
+
+    /// Get the genesis of the chain.
+    pub async fn genesis(&self) -> Result<Genesis, BeaconApiError> {
+        let res: DataResponse<_> = self.get("eth/v1/beacon/genesis").await?;
+
+        Ok(res.data)
+    }
+
+    /// Get the configuration of the beacon node.
+    pub async fn spec(&self) -> Result<BeaconSpec, BeaconApiError> {
+        let res: DataResponse<_> = self.get("eth/v1/config/spec").await?;
+
+        Ok(res.data)
+    }
+
+    /// Subscribe to the new heads of the chain, through the event stream of the
+    /// [BeaconClient].
+    pub async fn head_events(
+        &self,
+    ) -> Result<impl Stream<Item = Result<HeadEvent, BeaconApiError>> + Send, BeaconApiError> {
+        let events = self.client.get_events::<NewHeadsTopic>().await?;
+
+        Ok(events.map(|event| event.map_err(|err| BeaconApiError::Event(err.to_string()))))
+    }
+
+    /// Get the given path of the Beacon API, relative to the URL of the beacon node.
+    /// Event streams are not subject to the timeout of the queries.
+    async fn get<T: Serialize + DeserializeOwned>(&self, path: &str) -> Result<T, BeaconApiError> {
+        tokio::time::timeout(DEFAULT_TIMEOUT, self.client.get(path))
+            .await
+            .map_err(|_| BeaconApiError::Timeout)?
+            .map_err(BeaconApiError::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{extract::Path, http::StatusCode, response::IntoResponse, routing::get, Router};
+    use futures::StreamExt;
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn head_event(slot: u64) -> Value {
+        json!({
+            "slot": slot.to_string(),
+            "block": B256::repeat_byte(1),
+            "state": B256::repeat_byte(2),
+            "epoch_transition": false,
+            "previous_duty_dependent_root": B256::repeat_byte(3),
+            "current_duty_dependent_root": B256::repeat_byte(4),
+            "execution_optimistic": false,
+        })
+    }
+
+    fn validator(index: u64) -> Value {
+        json!({
+            "index": index.to_string(),
+            "balance": "32000000000",
+            "status": "active_ongoing",
+            "validator": {
+                "pubkey": BlsPublicKey::repeat_byte(index as u8),
+                "withdrawal_credentials": B256::repeat_byte(9),
+                "effective_balance": "32000000000",
+                "slashed": false,
+                "activation_eligibility_epoch": "0",
+                "activation_epoch": "0",
+                "exit_epoch": u64::MAX.to_string(),
+                "withdrawable_epoch": u64::MAX.to_string(),
+            },
+        })
+    }
+
+    /// Spawn a mock beacon node serving the endpoints of the client.
+    async fn spawn_beacon_node() -> Url {
+        let router = Router::new()
+            .route(
+                "/eth/v1/validator/duties/proposer/:epoch",
+                get(|Path(epoch): Path<u64>| async move {
+                    let duties = (0..32)
+                        .map(|i| {
+                            json!({
+                                "pubkey": BlsPublicKey::repeat_byte(i as u8),
+                                "validator_index": (100 + i % 4).to_string(),
+                                "slot": (epoch * 32 + i).to_string(),
+                            })
+                        })
+                        .collect::<Vec<_>>();
+
+                    axum::Json(json!({
+                        "dependent_root": B256::repeat_byte(4),
+                        "execution_optimistic": false,
+                        "data": duties,
+                    }))
+                }),
+            )
+            .route(
+                "/eth/v1/beacon/states/head/validators/:id",
+                get(|Path(id): Path<String>| async move {
+                    match id.as_str() {
+                        "7" => axum::Json(json!({ "data": validator(7) })).into_response(),
+                        _ => (
+                            StatusCode::NOT_FOUND,
+                            axum::Json(json!({ "code": 404, "message": "Validator not found" })),
+                        )
+                            .into_response(),
+                    }
+                }),
+            )
+            .route(
+                "/eth/v1/beacon/states/head/validators",
+                get(|| async { axum::Json(json!({ "data": [validator(1), validator(2)] })) }),
+            )
+            .route(
+                "/eth/v1/beacon/genesis",
+                get(|| async {
+                    axum::Json(json!({
+                        "data": {
+                            "genesis_time": "1695902400",
+                            "genesis_validators_root": B256::repeat_byte(5),
+                            "genesis_fork_version": "0x01017000",
+                        }
+                    }))
+                }),
+            )
+            .route(
+                "/eth/v1/config/spec",
+                get(|| async {
+                    axum::Json(json!({
+                        "data": { "SECONDS_PER_SLOT": "12", "CONFIG_NAME": "holesky" }
+                    }))
+                }),
+            )
+            .route(
+                "/eth/v1/events",
+                get(|| async {
+                    // A keep-alive comment, then two events with CRLF line endings
+                    let body = format!(
+                        ": keep-alive\n\nevent: head\r\ndata: {}\r\n\r\nevent: head\ndata: {}\n\n",
+                        head_event(64),
+                        head_event(65),
+                    );
+                    ([("content-type", "text/event-stream")], body)
+                }),
+            );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        format!("http://{addr}").parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_proposer_duties_of_our_validators() {
+        let client = BeaconApiClient::new(spawn_beacon_node().await);
+
+        let duties = client.proposer_duties(2).await.unwrap();
+        assert_eq!(duties.dependent_root, B256::repeat_byte(4));
+        assert_eq!(duties.duties.len(), 32);
+
+        let slots = duties
+            .for_validators(&[101, 103])
+            .map(|duty| duty.slot)
+            .collect::<Vec<_>>();
+        assert_eq!(slots.len(), 16);
+        assert_eq!(slots[..2], [65, 67]);
+    }
+
+    #[tokio::test]
+    async fn test_validator_status() {
+        let client = BeaconApiClient::new(spawn_beacon_node().await);
+
+        let validator = client.validator(ValidatorId::Index(7)).await.unwrap();
+        assert_eq!(validator.index, 7);
+        assert!(validator.status.is_active());
+        assert_eq!(validator.validator.pubkey, BlsPublicKey::repeat_byte(7));
+        assert_eq!(validator.validator.exit_epoch, u64::MAX);
+
+        let validators = client
+            .validators(&[1.into(), BlsPublicKey::repeat_byte(2).into()])
+            .await
+            .unwrap();
+        assert_eq!(validators.len(), 2);
+
+        // No query is sent without validators
+        let client = BeaconApiClient::new(Url::parse("http://127.0.0.1:1").unwrap());
+        assert!(client.validators(&[]).await.unwrap().is_empty());
+
+        let client = BeaconApiClient::new(spawn_beacon_node().await);
+        let err = client.validator(ValidatorId::Index(8)).await.unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                BeaconApiError::Client(beacon_api_client::Error::Api(error))
+                    if error.to_string().contains("Validator not found")
+            ),
+            "error: {err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_genesis_and_spec() {
+        let client = BeaconApiClient::new(spawn_beacon_node().await);
+
+        let genesis = client.genesis().await.unwrap();
+        assert_eq!(genesis.genesis_time, 1695902400);
+        assert_eq!(genesis.genesis_fork_version, FixedBytes([1, 1, 0x70, 0]));
+
+        let spec = client.spec().await.unwrap();
+        assert_eq!(spec.get_u64("SECONDS_PER_SLOT"), Some(12));
+        assert_eq!(spec.get("CONFIG_NAME"), Some("holesky"));
+        assert_eq!(spec.get_u64("CONFIG_NAME"), None);
+    }
+
+    #[tokio::test]
+    async fn test_head_events() {
+        let client = BeaconApiClient::new(spawn_beacon_node().await);
+
+        let events = client.head_events().await.unwrap();
+        let slots = events
+            .take(2)
+            .map(|event| event.unwrap().slot)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(slots, [64, 65]);
+    }
+}
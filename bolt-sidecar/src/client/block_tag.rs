@@ -0,0 +1,32 @@
+//! Resolution of the block targeted by the [RpcClient](super::rpc::RpcClient) methods,
+//! which accept either a block number or a block tag such as `safe` or `finalized`.
+
+use alloy_eips::BlockNumberOrTag;
+
+/// A block targeted by a call, resolved to the block parameter of the JSON-RPC request.
+///
+/// Implemented for [BlockNumberOrTag] to target the `latest`, `safe`, `finalized`,
+/// `pending` or `earliest` block, for a block number, and for an optional block number
+/// where `None` targets the latest block.
+pub trait IntoBlockTag {
+    /// Returns the block parameter of the request.
+    fn into_block_tag(self) -> BlockNumberOrTag;
+}
+
+impl IntoBlockTag for BlockNumberOrTag {
+    fn into_block_tag(self) -> BlockNumberOrTag {
+        self
+    }
+}
+
+impl IntoBlockTag for u64 {
+    fn into_block_tag(self) -> BlockNumberOrTag {
+        BlockNumberOrTag::Number(self)
+    }
+}
+
+impl IntoBlockTag for Option<u64> {
+    fn into_block_tag(self) -> BlockNumberOrTag {
+        self.map_or(BlockNumberOrTag::Latest, BlockNumberOrTag::Number)
+    }
+}
@@ -0,0 +1,102 @@
+//! Size-capped reading of HTTP response bodies, so that a pathological response (e.g.
+//! the trace of a transaction with a massive storage footprint) fails instead of being
+//! buffered entirely in memory.
+
+use std::task::{Context, Poll};
+
+use alloy_json_rpc::{RequestPacket, ResponsePacket, RpcError};
+use alloy_transport::{TransportError, TransportErrorKind, TransportFut};
+use bytes::{Bytes, BytesMut};
+use reqwest::{Client, Response, Url};
+use tower::Service;
+
+use super::http::{send_request, HttpOptions};
+
+/// Error returned when a response body exceeds
+/// [RpcClientConfig::max_response_bytes](super::rpc::RpcClientConfig::max_response_bytes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("response body exceeds the limit of {limit} bytes")]
+pub struct ResponseTooLargeError {
+    /// The maximum size of a response body, in bytes.
+    pub limit: usize,
+}
+
+/// Returns `true` if the response was dropped because its body was too large.
+pub(crate) fn is_response_too_large(err: &TransportError) -> bool {
+    matches!(
+        err,
+        RpcError::Transport(TransportErrorKind::Custom(err)) if err.is::<ResponseTooLargeError>()
+    )
+}
+
+/// Reads the body of the given response, failing with a [ResponseTooLargeError] as soon
+/// as it exceeds the given limit, if any.
+///
+/// The limit applies to the decompressed body: the [Client] decompresses the chunks as
+/// they are read, so a small compressed body can't expand past the limit in memory.
+pub(crate) async fn read_body(
+    mut res: Response,
+    limit: Option<usize>,
+) -> Result<Bytes, TransportError> {
+    let Some(limit) = limit else {
+        return res.bytes().await.map_err(TransportErrorKind::custom);
+    };
+    let too_large = || TransportErrorKind::custom(ResponseTooLargeError { limit });
+
+    // Only known for uncompressed bodies, the length is dropped when decompressing
+    if res.content_length().is_some_and(|len| len > limit as u64) {
+        return Err(too_large());
+    }
+
+    let mut body = BytesMut::new();
+    while let Some(chunk) = res.chunk().await.map_err(TransportErrorKind::custom)? {
+        if body.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body.freeze())
+}
+
+/// An HTTP transport that fails with a [ResponseTooLargeError] instead of buffering
+/// response bodies larger than the given limit.
+#[derive(Debug, Clone)]
+pub struct LimitedHttp {
+    client: Client,
+    url: Url,
+    opts: HttpOptions,
+}
+
+impl LimitedHttp {
+    /// Create a new transport for the given URL, with the given response size limit.
+    pub fn new(client: Client, url: Url, max_response_bytes: usize) -> Self {
+        Self {
+            client,
+            url,
+            opts: HttpOptions {
+                max_response_bytes: Some(max_response_bytes),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl Service<RequestPacket> for LimitedHttp {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        Box::pin(send_request(
+            self.client.clone(),
+            self.url.clone(),
+            self.opts.clone(),
+            req,
+        ))
+    }
+}
@@ -0,0 +1,358 @@
+//! A [tower] layer that stops sending requests to an execution node after repeated
+//! failures, to fail fast instead of waiting for timeouts while the node is down.
+//! Used by the [RpcClient](super::rpc::RpcClient).
+
+use std::{
+    future::poll_fn,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use alloy_json_rpc::{RequestPacket, ResponsePacket, RpcError};
+use alloy_transport::{TransportError, TransportErrorKind, TransportFut};
+use parking_lot::Mutex;
+use tower::{Layer, Service};
+
+use super::retry::is_retryable;
+
+/// The thresholds of the circuit breaker of an [RpcClient](super::rpc::RpcClient).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreakerConfig {
+    /// The number of consecutive transport failures that opens the circuit.
+    pub failure_threshold: u32,
+    /// The time window, from the first of the consecutive failures, in which the
+    /// threshold must be reached. Older failures are forgotten.
+    pub window: Duration,
+    /// How long the circuit stays open before a probe request is let through.
+    pub cooldown: Duration,
+}
+
+impl CircuitBreakerConfig {
+    /// Create a new configuration opening the circuit after `failure_threshold`
+    /// consecutive failures within `window`, for `cooldown`.
+    pub fn new(failure_threshold: u32, window: Duration, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold,
+            window,
+            cooldown,
+        }
+    }
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self::new(5, Duration::from_secs(30), Duration::from_secs(10))
+    }
+}
+
+/// Error returned without sending the request while the circuit breaker is open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("circuit breaker open after repeated RPC failures, next probe in {retry_in:?}")]
+pub struct CircuitOpenError {
+    /// The time left until a probe request is let through. Zero while a probe is
+    /// already in flight.
+    pub retry_in: Duration,
+}
+
+/// Returns `true` if the request was not sent because the circuit breaker is open.
+pub(crate) fn is_circuit_open(err: &TransportError) -> bool {
+    matches!(
+        err,
+        RpcError::Transport(TransportErrorKind::Custom(err)) if err.is::<CircuitOpenError>()
+    )
+}
+
+/// The state of a circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CircuitState {
+    /// Requests are sent, and the consecutive failures are counted from the first one.
+    Closed {
+        failures: u32,
+        since: Option<Instant>,
+    },
+    /// Requests fail fast until the cooldown has elapsed.
+    Open { until: Instant },
+    /// A single probe request is sent: the circuit closes if the node answers it,
+    /// and opens again otherwise.
+    HalfOpen { probing: bool },
+}
+
+/// A circuit breaker shared between the clones of a [CircuitBreakerService].
+#[derive(Debug)]
+struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: Mutex<CircuitState>,
+}
+
+impl CircuitBreaker {
+    fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(CircuitState::Closed {
+                failures: 0,
+                since: None,
+            }),
+        }
+    }
+
+    /// Checks whether a request can be sent. Returns whether it is the probe of a
+    /// half-open circuit, or the error to fail fast with.
+    fn acquire(&self, now: Instant) -> Result<bool, CircuitOpenError> {
+        let mut state = self.state.lock();
+
+        match *state {
+            CircuitState::Closed { .. } => Ok(false),
+            CircuitState::Open { until } if now < until => Err(CircuitOpenError {
+                retry_in: until - now,
+            }),
+            CircuitState::Open { .. } | CircuitState::HalfOpen { probing: false } => {
+                tracing::debug!("Circuit breaker half-open, probing the RPC");
+                *state = CircuitState::HalfOpen { probing: true };
+                Ok(true)
+            }
+            CircuitState::HalfOpen { probing: true } => Err(CircuitOpenError {
+                retry_in: Duration::ZERO,
+            }),
+        }
+    }
+
+    /// Records the outcome of a sent request. Only transport failures count: any
+    /// answer of the node, including a JSON-RPC error, closes the circuit.
+    fn record(&self, failed: bool, now: Instant) {
+        let mut state = self.state.lock();
+        let open = CircuitState::Open {
+            until: now + self.config.cooldown,
+        };
+
+        *state = match (*state, failed) {
+            (CircuitState::Closed { failures: 0, .. }, false) => return,
+            (_, false) => {
+                tracing::debug!("Circuit breaker closed");
+                CircuitState::Closed {
+                    failures: 0,
+                    since: None,
+                }
+            }
+            (CircuitState::Closed { failures, since }, true) => {
+                let (failures, since) = match since {
+                    Some(since) if now.duration_since(since) <= self.config.window => {
+                        (failures + 1, since)
+                    }
+                    _ => (1, now),
+                };
+
+                if failures >= self.config.failure_threshold.max(1) {
+                    tracing::warn!(failures, cooldown = ?self.config.cooldown, "Circuit breaker open");
+                    open
+                } else {
+                    CircuitState::Closed {
+                        failures,
+                        since: Some(since),
+                    }
+                }
+            }
+            // A request sent before the circuit opened doesn't extend the cooldown
+            (CircuitState::Open { until }, true) => CircuitState::Open { until },
+            (CircuitState::HalfOpen { .. }, true) => {
+                tracing::warn!(cooldown = ?self.config.cooldown, "Circuit breaker probe failed");
+                open
+            }
+        };
+    }
+
+    /// Lets another probe through if the current one was dropped before completing.
+    fn release_probe(&self) {
+        let mut state = self.state.lock();
+        if *state == (CircuitState::HalfOpen { probing: true }) {
+            *state = CircuitState::HalfOpen { probing: false };
+        }
+    }
+}
+
+/// Releases the probe of a half-open circuit if the request is dropped before
+/// its outcome is recorded.
+struct ProbeGuard(Option<Arc<CircuitBreaker>>);
+
+impl Drop for ProbeGuard {
+    fn drop(&mut self) {
+        if let Some(breaker) = self.0.take() {
+            breaker.release_probe();
+        }
+    }
+}
+
+/// A [Layer] that wraps a transport in a [CircuitBreakerService].
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerLayer {
+    breaker: Option<Arc<CircuitBreaker>>,
+}
+
+impl CircuitBreakerLayer {
+    /// Create a new circuit breaker layer. Requests are always sent if `None`.
+    pub fn new(config: Option<CircuitBreakerConfig>) -> Self {
+        Self {
+            breaker: config.map(|config| Arc::new(CircuitBreaker::new(config))),
+        }
+    }
+}
+
+impl<S> Layer<S> for CircuitBreakerLayer {
+    type Service = CircuitBreakerService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CircuitBreakerService {
+            inner,
+            breaker: self.breaker.clone(),
+        }
+    }
+}
+
+/// A transport service that fails fast with a [CircuitOpenError] after repeated
+/// transient failures (see [is_retryable]), until a probe request succeeds again.
+///
+/// JSON-RPC application errors and 4xx HTTP responses mean that the node is up,
+/// so they never open the circuit.
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerService<S> {
+    inner: S,
+    breaker: Option<Arc<CircuitBreaker>>,
+}
+
+impl<S> Service<RequestPacket> for CircuitBreakerService<S>
+where
+    S: Service<
+            RequestPacket,
+            Response = ResponsePacket,
+            Error = TransportError,
+            Future = TransportFut<'static>,
+        > + Clone
+        + Send
+        + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        let Some(breaker) = self.breaker.clone() else {
+            return self.inner.call(req);
+        };
+
+        let mut inner = self.inner.clone();
+        Box::pin(async move {
+            let is_probe = breaker
+                .acquire(Instant::now())
+                .map_err(TransportErrorKind::custom)?;
+            let mut guard = ProbeGuard(is_probe.then(|| breaker.clone()));
+
+            poll_fn(|cx| inner.poll_ready(cx)).await?;
+            let res = inner.call(req).await;
+
+            let failed = res.as_ref().is_err_and(is_retryable);
+            breaker.record(failed, Instant::now());
+            guard.0 = None;
+
+            res
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOLDOWN: Duration = Duration::from_secs(10);
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig::new(
+            3,
+            Duration::from_secs(5),
+            COOLDOWN,
+        ))
+    }
+
+    #[test]
+    fn test_circuit_breaker_transitions() {
+        let breaker = breaker();
+        let start = Instant::now();
+
+        // Closed: the failures are counted until the threshold
+        for _ in 0..2 {
+            assert_eq!(breaker.acquire(start), Ok(false));
+            breaker.record(true, start);
+        }
+        assert_eq!(breaker.acquire(start), Ok(false));
+        breaker.record(true, start);
+
+        // Open: fail fast until the cooldown has elapsed
+        let now = start + Duration::from_secs(4);
+        assert_eq!(
+            breaker.acquire(now),
+            Err(CircuitOpenError {
+                retry_in: Duration::from_secs(6)
+            })
+        );
+
+        // Half-open: a single probe goes through, and reopens the circuit on failure
+        let now = start + COOLDOWN;
+        assert_eq!(breaker.acquire(now), Ok(true));
+        assert_eq!(
+            breaker.acquire(now),
+            Err(CircuitOpenError {
+                retry_in: Duration::ZERO
+            })
+        );
+        breaker.record(true, now);
+        assert!(breaker.acquire(now + Duration::from_secs(1)).is_err());
+
+        // A successful probe closes the circuit
+        let now = now + COOLDOWN;
+        assert_eq!(breaker.acquire(now), Ok(true));
+        breaker.record(false, now);
+        assert_eq!(breaker.acquire(now), Ok(false));
+        assert_eq!(breaker.acquire(now), Ok(false));
+    }
+
+    #[test]
+    fn test_circuit_breaker_counts_consecutive_failures_in_window() {
+        let breaker = breaker();
+        let start = Instant::now();
+
+        // A success resets the count
+        breaker.record(true, start);
+        breaker.record(true, start);
+        breaker.record(false, start);
+        breaker.record(true, start);
+        breaker.record(true, start);
+        assert_eq!(breaker.acquire(start), Ok(false));
+
+        // Failures spread over more than the window don't open the circuit
+        let later = start + Duration::from_secs(6);
+        breaker.record(true, later);
+        assert_eq!(breaker.acquire(later), Ok(false));
+
+        breaker.record(true, later);
+        breaker.record(true, later);
+        assert!(breaker.acquire(later).is_err());
+    }
+
+    #[test]
+    fn test_dropped_probe_is_released() {
+        let breaker = Arc::new(breaker());
+        let start = Instant::now();
+        for _ in 0..3 {
+            breaker.record(true, start);
+        }
+
+        let now = start + COOLDOWN;
+        assert_eq!(breaker.acquire(now), Ok(true));
+        drop(ProbeGuard(Some(breaker.clone())));
+
+        assert_eq!(breaker.acquire(now), Ok(true));
+    }
+}
@@ -0,0 +1,619 @@
+//! Typed errors for the [RpcClient] calls, carrying the failed JSON-RPC method and the
+//! block, address or hash it was called with.
+//!
+//! The methods of [RpcClient] return alloy's [TransportResult], which doesn't tell which
+//! call failed. [RpcClient::contextual] exposes the same calls returning an
+//! [RpcClientError] instead, without breaking the existing method signatures.
+
+use std::{collections::HashMap, fmt};
+
+use alloy_eips::BlockNumberOrTag;
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use alloy_rpc_types::{
+    state::StateOverride, Block, EIP1186AccountProofResponse, Filter, Log, TransactionReceipt,
+    TransactionRequest,
+};
+use alloy_rpc_types_trace::geth::{GethDebugTracingCallOptions, GethTrace};
+use alloy_transport::{TransportError, TransportResult};
+
+use super::{
+    body_limit::is_response_too_large,
+    circuit_breaker::is_circuit_open,
+    revert::{decode_revert_reason, RevertReason},
+    rpc::RpcClient,
+};
+use crate::primitives::AccountState;
+
+/// The parameters of a failed [RpcClient] call, to identify what failed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RpcErrorContext {
+    /// The block the call was made on, if any. `None` for the latest block.
+    pub block: Option<u64>,
+    /// The address the call was about, if any.
+    pub address: Option<Address>,
+    /// The transaction or block hash the call was about, if any.
+    pub hash: Option<B256>,
+}
+
+impl RpcErrorContext {
+    fn block(block: Option<u64>) -> Self {
+        Self {
+            block,
+            ..Default::default()
+        }
+    }
+
+    fn address(address: Address, block: Option<u64>) -> Self {
+        Self {
+            block,
+            address: Some(address),
+            ..Default::default()
+        }
+    }
+
+    fn hash(hash: B256) -> Self {
+        Self {
+            hash: Some(hash),
+            ..Default::default()
+        }
+    }
+}
+
+/// Formats the set parameters as ` (block: 1, address: 0x..)`, or nothing if none is set.
+impl fmt::Display for RpcErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut fields = Vec::new();
+        if let Some(block) = self.block {
+            fields.push(format!("block: {block}"));
+        }
+        if let Some(address) = self.address {
+            fields.push(format!("address: {address}"));
+        }
+        if let Some(hash) = self.hash {
+            fields.push(format!("hash: {hash}"));
+        }
+
+        if fields.is_empty() {
+            return Ok(());
+        }
+        write!(f, " ({})", fields.join(", "))
+    }
+}
+
+/// Error returned by the [RpcClient] calls made through [RpcClient::contextual].
+#[derive(Debug, thiserror::Error)]
+pub enum RpcClientError {
+    /// A call failed. Carries the JSON-RPC method and the parameters it was called with.
+    #[error("{method} failed{context}: {source}")]
+    Request {
+        /// The JSON-RPC method(s) of the failed call.
+        method: &'static str,
+        /// The parameters of the failed call.
+        context: RpcErrorContext,
+        /// The transport error of the call.
+        #[source]
+        source: TransportError,
+    },
+    /// A call was not sent, as the circuit breaker of the client is open after repeated
+    /// failures of the node. See
+    /// [RpcClientConfig::circuit_breaker](super::rpc::RpcClientConfig::circuit_breaker).
+    #[error("{method} not sent{context}: {source}")]
+    CircuitOpen {
+        /// The JSON-RPC method(s) of the call.
+        method: &'static str,
+        /// The parameters of the call.
+        context: RpcErrorContext,
+        /// The [CircuitOpenError](super::circuit_breaker::CircuitOpenError) of the call.
+        #[source]
+        source: TransportError,
+    },
+    /// The response of a call was dropped, as its body exceeds the configured limit. See
+    /// [RpcClientConfig::max_response_bytes](super::rpc::RpcClientConfig::max_response_bytes).
+    #[error("{method} response too large{context}: {source}")]
+    ResponseTooLarge {
+        /// The JSON-RPC method(s) of the call.
+        method: &'static str,
+        /// The parameters of the call.
+        context: RpcErrorContext,
+        /// The [ResponseTooLargeError](super::body_limit::ResponseTooLargeError) of the call.
+        #[source]
+        source: TransportError,
+    },
+    /// A call reverted, with the reason decoded from the revert data of the error
+    /// response, e.g. for `eth_call` and `debug_traceCall`.
+    #[error("{method} reverted{context}: {reason}")]
+    Reverted {
+        /// The JSON-RPC method(s) of the call.
+        method: &'static str,
+        /// The parameters of the call.
+        context: RpcErrorContext,
+        /// The decoded revert reason.
+        reason: RevertReason,
+        /// The `execution reverted` error response of the call.
+        #[source]
+        source: TransportError,
+    },
+    /// A transport error without context.
+    #[error(transparent)]
+    Transport(#[from] TransportError),
+}
+
+impl RpcClientError {
+    /// Returns the JSON-RPC method of the failed call, if known.
+    pub fn method(&self) -> Option<&'static str> {
+        match self {
+            Self::Request { method, .. }
+            | Self::CircuitOpen { method, .. }
+            | Self::ResponseTooLarge { method, .. }
+            | Self::Reverted { method, .. } => Some(method),
+            Self::Transport(_) => None,
+        }
+    }
+
+    /// Returns the parameters of the failed call, if known.
+    pub fn context(&self) -> Option<&RpcErrorContext> {
+        match self {
+            Self::Request { context, .. }
+            | Self::CircuitOpen { context, .. }
+            | Self::ResponseTooLarge { context, .. }
+            | Self::Reverted { context, .. } => Some(context),
+            Self::Transport(_) => None,
+        }
+    }
+
+    /// Returns the underlying transport error.
+    pub fn transport_error(&self) -> &TransportError {
+        match self {
+            Self::Request { source, .. }
+            | Self::CircuitOpen { source, .. }
+            | Self::ResponseTooLarge { source, .. }
+            | Self::Reverted { source, .. }
+            | Self::Transport(source) => source,
+        }
+    }
+
+    /// Returns the decoded revert reason, if the call reverted.
+    pub fn revert_reason(&self) -> Option<&RevertReason> {
+        match self {
+            Self::Reverted { reason, .. } => Some(reason),
+            _ => None,
+        }
+    }
+}
+
+/// Attaches the method and parameters of a call to its transport error, telling apart
+/// the calls that were not sent because the circuit breaker is open, the responses
+/// that were dropped because they are too large and the calls that reverted.
+fn with_context<T>(
+    result: TransportResult<T>,
+    method: &'static str,
+    context: RpcErrorContext,
+) -> Result<T, RpcClientError> {
+    result.map_err(|source| {
+        if is_circuit_open(&source) {
+            RpcClientError::CircuitOpen {
+                method,
+                context,
+                source,
+            }
+        } else if is_response_too_large(&source) {
+            RpcClientError::ResponseTooLarge {
+                method,
+                context,
+                source,
+            }
+        } else if let Some(reason) = decode_revert_reason(&source) {
+            RpcClientError::Reverted {
+                method,
+                context,
+                reason,
+                source,
+            }
+        } else {
+            RpcClientError::Request {
+                method,
+                context,
+                source,
+            }
+        }
+    })
+}
+
+/// A view of an [RpcClient] whose calls return an [RpcClientError] with the method and
+/// parameters of the failed call. Created with [RpcClient::contextual].
+#[derive(Debug, Clone, Copy)]
+pub struct ContextualRpcClient<'a>(&'a RpcClient);
+
+impl RpcClient {
+    /// Returns a view of this client whose calls return an [RpcClientError] with the
+    /// method and parameters of the failed call, instead of a bare [TransportError].
+    pub fn contextual(&self) -> ContextualRpcClient<'_> {
+        ContextualRpcClient(self)
+    }
+}
+
+impl ContextualRpcClient<'_> {
+    /// See [RpcClient::get_chain_id].
+    pub async fn get_chain_id(&self) -> Result<u64, RpcClientError> {
+        with_context(
+            self.0.get_chain_id().await,
+            "eth_chainId",
+            Default::default(),
+        )
+    }
+
+    /// See [RpcClient::get_head].
+    pub async fn get_head(&self) -> Result<u64, RpcClientError> {
+        with_context(
+            self.0.get_head().await,
+            "eth_blockNumber",
+            Default::default(),
+        )
+    }
+
+    /// See [RpcClient::get_basefee].
+    pub async fn get_basefee(&self, block_number: Option<u64>) -> Result<u128, RpcClientError> {
+        with_context(
+            self.0.get_basefee(block_number).await,
+            "eth_feeHistory",
+            RpcErrorContext::block(block_number),
+        )
+    }
+
+    /// See [RpcClient::get_account_state].
+    pub async fn get_account_state(
+        &self,
+        address: &Address,
+        block_number: Option<u64>,
+    ) -> Result<AccountState, RpcClientError> {
+        with_context(
+            self.0.get_account_state(address, block_number).await,
+            "eth_getBalance,eth_getTransactionCount",
+            RpcErrorContext::address(*address, block_number),
+        )
+    }
+
+    /// See [RpcClient::get_account_states].
+    pub async fn get_account_states(
+        &self,
+        addresses: &[Address],
+        block_number: Option<u64>,
+    ) -> Result<HashMap<Address, AccountState>, RpcClientError> {
+        with_context(
+            self.0.get_account_states(addresses, block_number).await,
+            "eth_getBalance,eth_getTransactionCount",
+            RpcErrorContext::block(block_number),
+        )
+    }
+
+    /// See [RpcClient::get_balance_history].
+    pub async fn get_balance_history(
+        &self,
+        address: Address,
+        blocks: &[u64],
+    ) -> Result<Vec<(u64, U256)>, RpcClientError> {
+        with_context(
+            self.0.get_balance_history(address, blocks).await,
+            "eth_getBalance",
+            RpcErrorContext::address(address, None),
+        )
+    }
+
+    /// See [RpcClient::get_block].
+    pub async fn get_block(
+        &self,
+        block_number: Option<u64>,
+        full: bool,
+    ) -> Result<Block, RpcClientError> {
+        with_context(
+            self.0.get_block(block_number, full).await,
+            "eth_getBlockByNumber",
+            RpcErrorContext::block(block_number),
+        )
+    }
+
+    /// See [RpcClient::get_transaction_receipt].
+    pub async fn get_transaction_receipt(
+        &self,
+        hash: B256,
+    ) -> Result<Option<TransactionReceipt>, RpcClientError> {
+        with_context(
+            self.0.get_transaction_receipt(hash).await,
+            "eth_getTransactionReceipt",
+            RpcErrorContext::hash(hash),
+        )
+    }
+
+    /// See [RpcClient::get_proof].
+    pub async fn get_proof(
+        &self,
+        address: Address,
+        storage_keys: Vec<B256>,
+        block_number: Option<u64>,
+    ) -> Result<EIP1186AccountProofResponse, RpcClientError> {
+        with_context(
+            self.0.get_proof(address, storage_keys, block_number).await,
+            "eth_getProof",
+            RpcErrorContext::address(address, block_number),
+        )
+    }
+
+    /// See [RpcClient::get_proof_batched]. The proofs may be on different blocks and
+    /// accounts, so the context is empty.
+    pub async fn get_proof_batched(
+        &self,
+        opts: Vec<(Address, Vec<B256>, BlockNumberOrTag)>,
+    ) -> Result<Vec<EIP1186AccountProofResponse>, RpcClientError> {
+        with_context(
+            self.0.get_proof_batched(opts).await,
+            "eth_getProof",
+            Default::default(),
+        )
+    }
+
+    /// See [RpcClient::get_code].
+    pub async fn get_code(
+        &self,
+        address: Address,
+        block_number: Option<u64>,
+    ) -> Result<Bytes, RpcClientError> {
+        with_context(
+            self.0.get_code(address, block_number).await,
+            "eth_getCode",
+            RpcErrorContext::address(address, block_number),
+        )
+    }
+
+    /// See [RpcClient::get_storage_at].
+    pub async fn get_storage_at(
+        &self,
+        address: Address,
+        slot: B256,
+        block_number: Option<u64>,
+    ) -> Result<B256, RpcClientError> {
+        with_context(
+            self.0.get_storage_at(address, slot, block_number).await,
+            "eth_getStorageAt",
+            RpcErrorContext::address(address, block_number),
+        )
+    }
+
+    /// See [RpcClient::get_logs].
+    pub async fn get_logs(&self, filter: Filter) -> Result<Vec<Log>, RpcClientError> {
+        with_context(
+            self.0.get_logs(filter).await,
+            "eth_getLogs",
+            Default::default(),
+        )
+    }
+
+    /// See [RpcClient::estimate_gas]. The context address is the sender of the transaction.
+    pub async fn estimate_gas(
+        &self,
+        tx: TransactionRequest,
+        block_number: Option<u64>,
+    ) -> Result<u64, RpcClientError> {
+        let context = RpcErrorContext {
+            address: tx.from,
+            ..RpcErrorContext::block(block_number)
+        };
+
+        with_context(
+            self.0.estimate_gas(tx, block_number).await,
+            "eth_estimateGas",
+            context,
+        )
+    }
+
+    /// See [RpcClient::call]. The context address is the sender of the transaction.
+    pub async fn call(
+        &self,
+        tx: TransactionRequest,
+        block_number: Option<u64>,
+        state_override: Option<StateOverride>,
+    ) -> Result<Bytes, RpcClientError> {
+        let context = RpcErrorContext {
+            address: tx.from,
+            ..RpcErrorContext::block(block_number)
+        };
+
+        with_context(
+            self.0.call(tx, block_number, state_override).await,
+            "eth_call",
+            context,
+        )
+    }
+
+    /// See [RpcClient::send_raw_transaction]. The context hash is the hash of the
+    /// transaction, i.e. of its encoding.
+    pub async fn send_raw_transaction(&self, raw: Bytes) -> Result<B256, RpcClientError> {
+        let context = RpcErrorContext::hash(keccak256(&raw));
+
+        with_context(
+            self.0.send_raw_transaction(raw).await,
+            "eth_sendRawTransaction",
+            context,
+        )
+    }
+
+    /// See [RpcClient::debug_trace_call]. The context address is the sender of the
+    /// transaction.
+    pub async fn debug_trace_call(
+        &self,
+        tx: TransactionRequest,
+        block_number: Option<u64>,
+        opts: Option<GethDebugTracingCallOptions>,
+    ) -> Result<GethTrace, RpcClientError> {
+        let context = RpcErrorContext {
+            address: tx.from,
+            ..RpcErrorContext::block(block_number)
+        };
+
+        with_context(
+            self.0.debug_trace_call(tx, block_number, opts).await,
+            "debug_traceCall",
+            context,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_json_rpc::RpcError;
+    use alloy_transport::TransportErrorKind;
+
+    use super::*;
+    use crate::test_util::MockRpcServer;
+
+    async fn failing_rpc() -> MockRpcServer {
+        MockRpcServer::spawn(|_, _| {
+            Err(serde_json::json!({ "code": -32000, "message": "header not found" }))
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_error_context_is_populated() {
+        let rpc = failing_rpc().await;
+        let client = RpcClient::new(rpc.url());
+
+        let address = Address::repeat_byte(0x11);
+        let err = client
+            .contextual()
+            .get_storage_at(address, B256::ZERO, Some(5))
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.method(), Some("eth_getStorageAt"));
+        assert_eq!(
+            err.context(),
+            Some(&RpcErrorContext {
+                block: Some(5),
+                address: Some(address),
+                hash: None,
+            })
+        );
+        assert!(err.to_string().starts_with(&format!(
+            "eth_getStorageAt failed (block: 5, address: {address}): "
+        )));
+        assert!(err.to_string().contains("header not found"));
+
+        let hash = B256::repeat_byte(0x22);
+        let err = client
+            .contextual()
+            .get_transaction_receipt(hash)
+            .await
+            .unwrap_err();
+        assert_eq!(err.method(), Some("eth_getTransactionReceipt"));
+        assert_eq!(err.context(), Some(&RpcErrorContext::hash(hash)));
+
+        let raw = Bytes::from_static(&[0x02, 0xc0]);
+        let err = client
+            .contextual()
+            .send_raw_transaction(raw.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(err.method(), Some("eth_sendRawTransaction"));
+        assert_eq!(err.context(), Some(&RpcErrorContext::hash(keccak256(&raw))));
+
+        let err = client
+            .contextual()
+            .get_account_states(&[address], Some(5))
+            .await
+            .unwrap_err();
+        assert_eq!(err.method(), Some("eth_getBalance,eth_getTransactionCount"));
+        assert_eq!(err.context(), Some(&RpcErrorContext::block(Some(5))));
+
+        let err = client
+            .contextual()
+            .get_proof_batched(vec![(address, vec![], BlockNumberOrTag::Latest)])
+            .await
+            .unwrap_err();
+        assert_eq!(err.method(), Some("eth_getProof"));
+        assert_eq!(err.context(), Some(&RpcErrorContext::default()));
+
+        let sender = Address::repeat_byte(0x33);
+        let tx = TransactionRequest::default().from(sender);
+        let err = client
+            .contextual()
+            .debug_trace_call(tx, None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.method(), Some("debug_traceCall"));
+        assert_eq!(err.context(), Some(&RpcErrorContext::address(sender, None)));
+        assert!(err
+            .to_string()
+            .starts_with("debug_traceCall failed (address: "));
+    }
+
+    #[tokio::test]
+    async fn test_revert_reason_is_decoded() {
+        // revert("not allowed")
+        let data = concat!(
+            "0x08c379a0",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "000000000000000000000000000000000000000000000000000000000000000b",
+            "6e6f7420616c6c6f776564000000000000000000000000000000000000000000",
+        );
+        let rpc = MockRpcServer::spawn(move |_, _| {
+            Err(serde_json::json!({
+                "code": 3,
+                "message": "execution reverted: not allowed",
+                "data": data,
+            }))
+        })
+        .await;
+        let client = RpcClient::new(rpc.url());
+
+        let tx = TransactionRequest::default().to(Address::repeat_byte(0x11));
+        let err = client
+            .contextual()
+            .call(tx.clone(), Some(5), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RpcClientError::Reverted { .. }));
+        assert_eq!(
+            err.revert_reason(),
+            Some(&RevertReason::Error("not allowed".into()))
+        );
+        assert_eq!(err.to_string(), "eth_call reverted (block: 5): not allowed");
+
+        let err = client
+            .contextual()
+            .debug_trace_call(tx, None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.method(), Some("debug_traceCall"));
+        assert_eq!(
+            err.revert_reason(),
+            Some(&RevertReason::Error("not allowed".into()))
+        );
+
+        // Errors without revert data are not annotated
+        let rpc = failing_rpc().await;
+        let client = RpcClient::new(rpc.url());
+        let err = client
+            .contextual()
+            .call(TransactionRequest::default(), None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RpcClientError::Request { .. }));
+        assert_eq!(err.revert_reason(), None);
+    }
+
+    #[test]
+    fn test_error_from_transport_error() {
+        let err = RpcClientError::from(TransportErrorKind::backend_gone());
+
+        assert_eq!(err.method(), None);
+        assert_eq!(err.context(), None);
+        assert!(matches!(
+            err.transport_error(),
+            RpcError::Transport(TransportErrorKind::BackendGone)
+        ));
+        assert_eq!(
+            err.to_string(),
+            TransportErrorKind::backend_gone().to_string()
+        );
+    }
+}
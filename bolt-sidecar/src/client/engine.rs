@@ -0,0 +1,203 @@
+//! A client of the authenticated Engine API of the execution client, used to have the
+//! node build and validate payloads, e.g. a fallback block when relays fail to honor
+//! the constraints of the proposer.
+
+use alloy_primitives::B256;
+use alloy_rpc_types_engine::{
+    ExecutionPayloadEnvelopeV3, ExecutionPayloadEnvelopeV4, ExecutionPayloadV3, ForkchoiceState,
+    ForkchoiceUpdated, PayloadAttributes, PayloadId, PayloadStatus,
+};
+use alloy_transport::TransportResult;
+use reqwest::Url;
+use reth_rpc_layer::JwtSecret;
+
+use super::rpc::{RpcClient, RpcClientConfig};
+
+/// A client of the Engine API, authenticating every request with a JWT.
+///
+/// It is an [RpcClient] under the hood, with the same retries and transport options.
+#[derive(Debug, Clone)]
+pub struct EngineClient(RpcClient);
+
+impl EngineClient {
+    /// Create a new client for the Engine API at the given URL, authenticated with the
+    /// given secret, with the default [RpcClientConfig].
+    pub fn new<U: Into<Url>>(url: U, jwt_secret: JwtSecret) -> Self {
+        Self::new_with_config(url, jwt_secret, RpcClientConfig::default())
+    }
+
+    /// Create a new client for the Engine API at the given URL, authenticated with the
+    /// given secret, with the given transport configuration.
+    pub fn new_with_config<U: Into<Url>>(
+        url: U,
+        jwt_secret: JwtSecret,
+        config: RpcClientConfig,
+    ) -> Self {
+        Self(
+            RpcClient::builder(url)
+                .config(config)
+                .jwt_secret(jwt_secret)
+                .build(),
+        )
+    }
+
+    /// Update the fork choice of the node with `engine_forkchoiceUpdatedV3`. If payload
+    /// attributes are given, the node starts building a payload on top of the new head,
+    /// to be retrieved with the returned payload ID.
+    pub async fn fork_choice_updated_v3(
+        &self,
+        state: ForkchoiceState,
+        attributes: Option<PayloadAttributes>,
+    ) -> TransportResult<ForkchoiceUpdated> {
+        self.0
+            .request("engine_forkchoiceUpdatedV3", (state, attributes))
+            .await
+    }
+
+    /// Get the Deneb payload built by the node for the given payload ID, with its blobs
+    /// bundle and value, with `engine_getPayloadV3`.
+    pub async fn get_payload_v3(
+        &self,
+        payload_id: PayloadId,
+    ) -> TransportResult<ExecutionPayloadEnvelopeV3> {
+        self.0.request("engine_getPayloadV3", (payload_id,)).await
+    }
+
+    /// Get the Electra payload built by the node for the given payload ID, with its
+    /// blobs bundle and value, with `engine_getPayloadV4`.
+    pub async fn get_payload_v4(
+        &self,
+        payload_id: PayloadId,
+    ) -> TransportResult<ExecutionPayloadEnvelopeV4> {
+        self.0.request("engine_getPayloadV4", (payload_id,)).await
+    }
+
+    /// Have the node validate and execute the given Deneb payload with
+    /// `engine_newPayloadV3`, given the versioned hashes of its blobs and the root of
+    /// its parent beacon block.
+    pub async fn new_payload_v3(
+        &self,
+        payload: ExecutionPayloadV3,
+        versioned_hashes: Vec<B256>,
+        parent_beacon_block_root: B256,
+    ) -> TransportResult<PayloadStatus> {
+        self.0
+            .request(
+                "engine_newPayloadV3",
+                (payload, versioned_hashes, parent_beacon_block_root),
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{Address, U256};
+    use alloy_rpc_types_engine::PayloadStatusEnum;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::test_util::MockRpcServer;
+
+    const PAYLOAD_ID: &str = "0x0000000000000001";
+
+    fn execution_payload(block_hash: B256) -> Value {
+        json!({
+            "parentHash": B256::repeat_byte(1),
+            "feeRecipient": Address::repeat_byte(2),
+            "stateRoot": B256::repeat_byte(3),
+            "receiptsRoot": B256::repeat_byte(4),
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "prevRandao": B256::repeat_byte(5),
+            "blockNumber": "0x64",
+            "gasLimit": "0x1c9c380",
+            "gasUsed": "0x0",
+            "timestamp": "0x66a0b1c0",
+            "extraData": "0x",
+            "baseFeePerGas": "0x7",
+            "blockHash": block_hash,
+            "transactions": [],
+            "withdrawals": [],
+            "blobGasUsed": "0x0",
+            "excessBlobGas": "0x0",
+        })
+    }
+
+    #[tokio::test]
+    async fn test_build_and_validate_payload() {
+        let block_hash = B256::repeat_byte(6);
+        let rpc = MockRpcServer::spawn(move |method, _| match method {
+            "engine_forkchoiceUpdatedV3" => Ok(json!({
+                "payloadStatus": { "status": "VALID", "latestValidHash": B256::repeat_byte(1) },
+                "payloadId": PAYLOAD_ID,
+            })),
+            "engine_getPayloadV3" => Ok(json!({
+                "executionPayload": execution_payload(block_hash),
+                "blockValue": "0x2386f26fc10000",
+                "blobsBundle": { "commitments": [], "proofs": [], "blobs": [] },
+                "shouldOverrideBuilder": false,
+            })),
+            "engine_newPayloadV3" => Ok(json!({
+                "status": "VALID",
+                "latestValidHash": block_hash,
+            })),
+            _ => unreachable!(),
+        })
+        .await;
+
+        let secret = JwtSecret::from_hex("42".repeat(32)).unwrap();
+        let client = EngineClient::new(rpc.url(), secret.clone());
+
+        let state = ForkchoiceState {
+            head_block_hash: B256::repeat_byte(1),
+            safe_block_hash: B256::repeat_byte(1),
+            finalized_block_hash: B256::ZERO,
+        };
+        let attributes = PayloadAttributes {
+            timestamp: 0x66a0b1c0,
+            prev_randao: B256::repeat_byte(5),
+            suggested_fee_recipient: Address::repeat_byte(2),
+            withdrawals: Some(Vec::new()),
+            parent_beacon_block_root: Some(B256::repeat_byte(7)),
+        };
+        let updated = client
+            .fork_choice_updated_v3(state, Some(attributes))
+            .await
+            .unwrap();
+        assert_eq!(updated.payload_status.status, PayloadStatusEnum::Valid);
+
+        let payload_id = updated.payload_id.unwrap();
+        let envelope = client.get_payload_v3(payload_id).await.unwrap();
+        let payload = envelope.execution_payload;
+        assert_eq!(payload.payload_inner.payload_inner.block_hash, block_hash);
+        assert_eq!(envelope.block_value, U256::from(10u64.pow(16)));
+
+        let status = client
+            .new_payload_v3(payload, Vec::new(), B256::repeat_byte(7))
+            .await
+            .unwrap();
+        assert_eq!(status.status, PayloadStatusEnum::Valid);
+        assert_eq!(status.latest_valid_hash, Some(block_hash));
+
+        // The requests follow the positional parameters of the spec
+        let params = &rpc.calls("engine_forkchoiceUpdatedV3")[0];
+        assert_eq!(params[0]["headBlockHash"], json!(B256::repeat_byte(1)));
+        assert_eq!(
+            params[1]["parentBeaconBlockRoot"],
+            json!(B256::repeat_byte(7))
+        );
+        assert_eq!(rpc.calls("engine_getPayloadV3")[0], json!([PAYLOAD_ID]));
+        let params = &rpc.calls("engine_newPayloadV3")[0];
+        assert_eq!(params[0]["blockHash"], json!(block_hash));
+        assert_eq!(params[1], json!([]));
+        assert_eq!(params[2], json!(B256::repeat_byte(7)));
+
+        // Every request carries a valid token
+        for headers in rpc.headers() {
+            let auth = headers["authorization"].to_str().unwrap();
+            secret
+                .validate(auth.strip_prefix("Bearer ").unwrap())
+                .unwrap();
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! The execution layer JSON-RPC methods the sidecar components depend on, as a trait,
+//! so that they can be driven by a backend other than the [RpcClient], e.g. an
+//! in-memory mock scripting responses and errors in tests.
+
+use std::{fmt::Debug, sync::Arc};
+
+use alloy_eips::BlockNumberOrTag;
+use alloy_primitives::{Address, Bytes, B256, U64};
+use alloy_rpc_types::{state::StateOverride, EIP1186AccountProofResponse, TransactionRequest};
+use alloy_rpc_types_trace::geth::{GethDebugTracingCallOptions, GethTrace};
+use alloy_transport::TransportResult;
+use serde::Deserialize;
+
+use super::rpc::RpcClient;
+use crate::primitives::AccountState;
+
+/// An execution client backend, implemented by the [RpcClient] and shared behind an [Arc].
+///
+/// The methods mirror the ones of the [RpcClient] with the same name, with the block
+/// given as a [BlockNumberOrTag].
+#[async_trait::async_trait]
+pub trait ExecutionRpc: Debug + Send + Sync {
+    /// Get the chain ID of the endpoint with `eth_chainId`.
+    async fn get_chain_id(&self) -> TransportResult<u64>;
+
+    /// Get the number of the latest block with `eth_blockNumber`.
+    async fn get_head(&self) -> TransportResult<u64>;
+
+    /// Get the number, hash and parent hash of the given block with `eth_getBlockByNumber`.
+    async fn get_block_hash(&self, block: BlockNumberOrTag) -> TransportResult<BlockHashes>;
+
+    /// Get the balance and nonce of the given account at the given block.
+    async fn get_account_state(
+        &self,
+        address: Address,
+        block: BlockNumberOrTag,
+    ) -> TransportResult<AccountState>;
+
+    /// Get the code of the given account at the given block with `eth_getCode`.
+    async fn get_code(&self, address: Address, block: BlockNumberOrTag) -> TransportResult<Bytes>;
+
+    /// Get the value of the given storage slot of an account at the given block with
+    /// `eth_getStorageAt`.
+    async fn get_storage_at(
+        &self,
+        address: Address,
+        slot: B256,
+        block: BlockNumberOrTag,
+    ) -> TransportResult<B256>;
+
+    /// Get the account and storage values of the given account, with their Merkle proof,
+    /// at the given block with `eth_getProof`.
+    async fn get_proof(
+        &self,
+        address: Address,
+        storage_keys: Vec<B256>,
+        block: BlockNumberOrTag,
+    ) -> TransportResult<EIP1186AccountProofResponse>;
+
+    /// Trace the given transaction on top of the given block with `debug_traceCall`.
+    async fn debug_trace_call(
+        &self,
+        tx: TransactionRequest,
+        block: BlockNumberOrTag,
+        opts: Option<GethDebugTracingCallOptions>,
+    ) -> TransportResult<GethTrace>;
+
+    /// Trace the given transaction on top of the given block with `debug_traceCall`, with
+    /// the given state overrides replacing the ones of the options.
+    ///
+    /// The overrides are shared with the caller. By default they are copied into the
+    /// options: backends able to serialize them in place should override this method.
+    async fn debug_trace_call_with_shared_state(
+        &self,
+        tx: TransactionRequest,
+        block: BlockNumberOrTag,
+        state_override: Arc<StateOverride>,
+        opts: Option<GethDebugTracingCallOptions>,
+    ) -> TransportResult<GethTrace> {
+        let opts = opts
+            .unwrap_or_default()
+            .with_state_overrides(StateOverride::clone(&state_override));
+
+        self.debug_trace_call(tx, block, Some(opts)).await
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: ExecutionRpc + ?Sized> ExecutionRpc for Arc<T> {
+    async fn get_chain_id(&self) -> TransportResult<u64> {
+        (**self).get_chain_id().await
+    }
+
+    async fn get_head(&self) -> TransportResult<u64> {
+        (**self).get_head().await
+    }
+
+    async fn get_block_hash(&self, block: BlockNumberOrTag) -> TransportResult<BlockHashes> {
+        (**self).get_block_hash(block).await
+    }
+
+    async fn get_account_state(
+        &self,
+        address: Address,
+        block: BlockNumberOrTag,
+    ) -> TransportResult<AccountState> {
+        (**self).get_account_state(address, block).await
+    }
+
+    async fn get_code(&self, address: Address, block: BlockNumberOrTag) -> TransportResult<Bytes> {
+        (**self).get_code(address, block).await
+    }
+
+    async fn get_storage_at(
+        &self,
+        address: Address,
+        slot: B256,
+        block: BlockNumberOrTag,
+    ) -> TransportResult<B256> {
+        (**self).get_storage_at(address, slot, block).await
+    }
+
+    async fn get_proof(
+        &self,
+        address: Address,
+        storage_keys: Vec<B256>,
+        block: BlockNumberOrTag,
+    ) -> TransportResult<EIP1186AccountProofResponse> {
+        (**self).get_proof(address, storage_keys, block).await
+    }
+
+    async fn debug_trace_call(
+        &self,
+        tx: TransactionRequest,
+        block: BlockNumberOrTag,
+        opts: Option<GethDebugTracingCallOptions>,
+    ) -> TransportResult<GethTrace> {
+        (**self).debug_trace_call(tx, block, opts).await
+    }
+
+    async fn debug_trace_call_with_shared_state(
+        &self,
+        tx: TransactionRequest,
+        block: BlockNumberOrTag,
+        state_override: Arc<StateOverride>,
+        opts: Option<GethDebugTracingCallOptions>,
+    ) -> TransportResult<GethTrace> {
+        (**self)
+            .debug_trace_call_with_shared_state(tx, block, state_override, opts)
+            .await
+    }
+}
+
+/// The number, hash and parent hash of a block, as returned by `eth_getBlockByNumber`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockHashes {
+    /// The number of the block
+    pub number: U64,
+    /// The hash of the block
+    pub hash: B256,
+    /// The hash of the parent of the block
+    pub parent_hash: B256,
+}
+
+#[async_trait::async_trait]
+impl ExecutionRpc for RpcClient {
+    async fn get_chain_id(&self) -> TransportResult<u64> {
+        RpcClient::get_chain_id(self).await
+    }
+
+    async fn get_head(&self) -> TransportResult<u64> {
+        RpcClient::get_head(self).await
+    }
+
+    async fn get_block_hash(&self, block: BlockNumberOrTag) -> TransportResult<BlockHashes> {
+        self.request("eth_getBlockByNumber", (block, false)).await
+    }
+
+    async fn get_account_state(
+        &self,
+        address: Address,
+        block: BlockNumberOrTag,
+    ) -> TransportResult<AccountState> {
+        RpcClient::get_account_state(self, &address, block).await
+    }
+
+    async fn get_code(&self, address: Address, block: BlockNumberOrTag) -> TransportResult<Bytes> {
+        RpcClient::get_code(self, address, block).await
+    }
+
+    async fn get_storage_at(
+        &self,
+        address: Address,
+        slot: B256,
+        block: BlockNumberOrTag,
+    ) -> TransportResult<B256> {
+        RpcClient::get_storage_at(self, address, slot, block).await
+    }
+
+    async fn get_proof(
+        &self,
+        address: Address,
+        storage_keys: Vec<B256>,
+        block: BlockNumberOrTag,
+    ) -> TransportResult<EIP1186AccountProofResponse> {
+        RpcClient::get_proof(self, address, storage_keys, block).await
+    }
+
+    async fn debug_trace_call(
+        &self,
+        tx: TransactionRequest,
+        block: BlockNumberOrTag,
+        opts: Option<GethDebugTracingCallOptions>,
+    ) -> TransportResult<GethTrace> {
+        RpcClient::debug_trace_call(self, tx, block, opts).await
+    }
+
+    async fn debug_trace_call_with_shared_state(
+        &self,
+        tx: TransactionRequest,
+        block: BlockNumberOrTag,
+        state_override: Arc<StateOverride>,
+        opts: Option<GethDebugTracingCallOptions>,
+    ) -> TransportResult<GethTrace> {
+        RpcClient::debug_trace_call_with_shared_state(self, tx, block, state_override, opts).await
+    }
+}
@@ -0,0 +1,249 @@
+//! A transport that fails over across multiple execution client endpoints.
+//! Used by the [RpcClient](super::rpc::RpcClient) to keep working when the primary node is down.
+
+use std::{
+    future::{poll_fn, Future},
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use alloy_json_rpc::{RequestPacket, ResponsePacket};
+use alloy_transport::{BoxTransport, TransportError, TransportFut, TransportResult};
+use parking_lot::Mutex;
+use tower::Service;
+
+use super::retry::{is_idempotent, is_retryable};
+
+/// How the [FailoverTransport] picks the endpoint to send requests to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailoverPolicy {
+    /// Prefer the first endpoint: after failing over, stay on the backup until
+    /// the cooldown has elapsed, then return to the primary.
+    StickyPrimary {
+        /// How long to stay on a backup before trying the primary again.
+        cooldown: Duration,
+    },
+    /// Stay on the last working endpoint, and move to the next one on failure.
+    RoundRobin,
+}
+
+impl Default for FailoverPolicy {
+    fn default() -> Self {
+        Self::StickyPrimary {
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+/// The endpoint currently in use and the health of every endpoint.
+#[derive(Debug)]
+struct FailoverState {
+    active: usize,
+    /// When the active endpoint last moved away from another one.
+    failed_over_at: Option<Instant>,
+    /// Whether each endpoint is healthy. All the endpoints are healthy until told
+    /// otherwise with [Failover::set_health].
+    healthy: Vec<bool>,
+}
+
+/// The state machine picking the endpoint to send requests to, shared between clones
+/// of a [FailoverTransport] or of a [MultiRpcClient](super::multi::MultiRpcClient).
+///
+/// Requests go to the active endpoint, and fail over to the next healthy one on
+/// transient errors. How the active endpoint is picked after failing over depends on
+/// the [FailoverPolicy].
+#[derive(Debug, Clone)]
+pub(crate) struct Failover {
+    policy: FailoverPolicy,
+    state: Arc<Mutex<FailoverState>>,
+}
+
+impl Failover {
+    /// Create a new state machine over the given number of endpoints, in order of
+    /// preference.
+    pub(crate) fn new(count: usize, policy: FailoverPolicy) -> Self {
+        Self {
+            policy,
+            state: Arc::new(Mutex::new(FailoverState {
+                active: 0,
+                failed_over_at: None,
+                healthy: vec![true; count],
+            })),
+        }
+    }
+
+    /// Set how the active endpoint is picked after failing over.
+    pub(crate) fn set_policy(&mut self, policy: FailoverPolicy) {
+        self.policy = policy;
+    }
+
+    /// Returns the endpoint to try first.
+    pub(crate) fn active(&self) -> usize {
+        let mut state = self.state.lock();
+
+        // Return to the most preferred healthy endpoint once the cooldown has elapsed
+        if let FailoverPolicy::StickyPrimary { cooldown } = self.policy {
+            if state
+                .failed_over_at
+                .is_some_and(|at| at.elapsed() >= cooldown)
+            {
+                if let Some(preferred) = state.healthy.iter().position(|healthy| *healthy) {
+                    state.active = preferred;
+                }
+                // Keep checking for the primary while it is unhealthy
+                if state.active == 0 {
+                    state.failed_over_at = None;
+                }
+            }
+        }
+
+        state.active
+    }
+
+    /// Update the health of every endpoint, and move away from the active endpoint if
+    /// it is not healthy anymore. If no endpoint is healthy, the active one is kept.
+    ///
+    /// Returns the endpoints moved from and to, if the active endpoint changed.
+    pub(crate) fn set_health(&self, healthy: Vec<bool>) -> Option<(usize, usize)> {
+        let mut state = self.state.lock();
+        state.healthy = healthy;
+
+        let active = state.active;
+        if state.healthy[active] {
+            return None;
+        }
+
+        let next = next_healthy(&state.healthy, active)?;
+        state.active = next;
+        state.failed_over_at = Some(Instant::now());
+        Some((active, next))
+    }
+
+    /// Send a request with the given function to the active endpoint, failing over to
+    /// the next healthy endpoints on transient errors, i.e. connection failures, timeouts
+    /// and 5xx HTTP responses. Every endpoint is tried at most once. If no endpoint is
+    /// healthy, all of them are tried in order.
+    ///
+    /// Requests that are not `idempotent` are never sent to another endpoint, as the
+    /// failed one may have executed them (e.g. added the transaction to its pool before
+    /// timing out). The following requests still move away from it.
+    pub(crate) async fn request<T, F, Fut>(&self, idempotent: bool, mut f: F) -> TransportResult<T>
+    where
+        F: FnMut(usize) -> Fut,
+        Fut: Future<Output = TransportResult<T>>,
+    {
+        let start = self.active();
+
+        let candidates: Vec<_> = {
+            let state = self.state.lock();
+            let count = state.healthy.len();
+            let healthy: Vec<_> = (0..count)
+                .map(|offset| (start + offset) % count)
+                .filter(|index| state.healthy[*index])
+                .collect();
+
+            if healthy.is_empty() {
+                (0..count).map(|offset| (start + offset) % count).collect()
+            } else {
+                healthy
+            }
+        };
+
+        let (last, rest) = candidates.split_last().expect("At least one endpoint");
+        for index in rest {
+            match f(*index).await {
+                Err(err) if is_retryable(&err) && !idempotent => {
+                    tracing::warn!(?err, endpoint = index, "RPC endpoint failed, not resending");
+                    self.fail_over(*index);
+                    return Err(err);
+                }
+                Err(err) if is_retryable(&err) => {
+                    tracing::warn!(?err, endpoint = index, "RPC endpoint failed, failing over");
+                    self.fail_over(*index);
+                }
+                res => return res,
+            }
+        }
+
+        f(*last).await
+    }
+
+    /// Moves to the healthy endpoint after the given failed one, if any.
+    fn fail_over(&self, failed: usize) {
+        let mut state = self.state.lock();
+
+        // Another request may have failed over already
+        if state.active == failed {
+            if let Some(next) = next_healthy(&state.healthy, failed) {
+                state.active = next;
+                state.failed_over_at = Some(Instant::now());
+            }
+        }
+    }
+}
+
+/// Returns the first healthy endpoint after the given one, wrapping around.
+fn next_healthy(healthy: &[bool], after: usize) -> Option<usize> {
+    let count = healthy.len();
+
+    (1..count)
+        .map(|offset| (after + offset) % count)
+        .find(|index| healthy[*index])
+}
+
+/// A transport that sends requests to the active endpoint, and fails over to the next
+/// one on transient errors, i.e. connection failures, timeouts and 5xx HTTP responses.
+///
+/// JSON-RPC application errors are deterministic, so they are returned as is.
+/// Every endpoint is tried at most once per request, and requests sending a transaction
+/// are never resent to another endpoint.
+#[derive(Debug, Clone)]
+pub struct FailoverTransport {
+    transports: Arc<Vec<BoxTransport>>,
+    failover: Failover,
+}
+
+impl FailoverTransport {
+    /// Create a new failover transport over the given endpoints, in order of preference.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no transport is given.
+    pub fn new(transports: Vec<BoxTransport>, policy: FailoverPolicy) -> Self {
+        assert!(!transports.is_empty(), "At least one endpoint is required");
+
+        Self {
+            failover: Failover::new(transports.len(), policy),
+            transports: Arc::new(transports),
+        }
+    }
+}
+
+impl Service<RequestPacket> for FailoverTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Readiness is checked on the endpoint actually used by each request
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        let this = self.clone();
+
+        Box::pin(async move {
+            this.failover
+                .request(is_idempotent(&req), |index| {
+                    let mut transport = this.transports[index].clone();
+                    let req = req.clone();
+                    async move {
+                        poll_fn(|cx| transport.poll_ready(cx)).await?;
+                        transport.call(req).await
+                    }
+                })
+                .await
+        })
+    }
+}
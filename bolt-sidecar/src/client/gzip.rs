@@ -0,0 +1,62 @@
+//! An HTTP transport that gzip-compresses request bodies, to cut the transfer time
+//! of large payloads (e.g. `trace_callMany` or `eth_getProof` batches) over remote links.
+
+use std::task::{Context, Poll};
+
+use alloy_json_rpc::{RequestPacket, ResponsePacket};
+use alloy_transport::{TransportError, TransportFut};
+use reqwest::{Client, Url};
+use tower::Service;
+
+use super::http::{send_request, HttpOptions};
+
+/// An HTTP transport that sends request bodies with `Content-Encoding: gzip`.
+///
+/// Not all execution clients decompress request bodies, so this is opt-in. Response
+/// decompression is handled by the [Client], which must be built with gzip enabled.
+#[derive(Debug, Clone)]
+pub struct GzipHttp {
+    client: Client,
+    url: Url,
+    opts: HttpOptions,
+}
+
+impl GzipHttp {
+    /// Create a new transport for the given URL.
+    pub fn new(client: Client, url: Url) -> Self {
+        Self {
+            client,
+            url,
+            opts: HttpOptions {
+                gzip: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Fail with a [ResponseTooLargeError](super::body_limit::ResponseTooLargeError)
+    /// instead of buffering decompressed response bodies larger than the given limit.
+    pub fn with_max_response_bytes(mut self, max_response_bytes: Option<usize>) -> Self {
+        self.opts.max_response_bytes = max_response_bytes;
+        self
+    }
+}
+
+impl Service<RequestPacket> for GzipHttp {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        Box::pin(send_request(
+            self.client.clone(),
+            self.url.clone(),
+            self.opts.clone(),
+            req,
+        ))
+    }
+}
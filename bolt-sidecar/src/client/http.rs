@@ -0,0 +1,75 @@
+//! The HTTP requests of the transports that customize them: JWT authentication
+//! ([JwtHttp](super::jwt::JwtHttp)), request body compression
+//! ([GzipHttp](super::gzip::GzipHttp)) and response size limits
+//! ([LimitedHttp](super::body_limit::LimitedHttp)).
+
+use std::io::Write;
+
+use alloy_json_rpc::{RequestPacket, ResponsePacket};
+use alloy_transport::{TransportError, TransportErrorKind};
+use flate2::{write::GzEncoder, Compression};
+use reqwest::{
+    header::{AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE},
+    Client, Url,
+};
+use reth_rpc_layer::{secret_to_bearer_header, JwtSecret};
+
+use super::body_limit::read_body;
+
+/// The options of the HTTP requests sent with [send_request].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HttpOptions {
+    /// The secret to sign a fresh JWT with for every request, if any.
+    pub(crate) jwt_secret: Option<JwtSecret>,
+    /// Whether to send the request bodies with `Content-Encoding: gzip`.
+    pub(crate) gzip: bool,
+    /// The maximum size of a response body in bytes, after decompression, if any.
+    pub(crate) max_response_bytes: Option<usize>,
+}
+
+/// Sends the given JSON-RPC request to the given URL with the given options, and
+/// parses its response.
+///
+/// Response decompression is handled by the [Client], which must be built with gzip
+/// enabled to accept compressed responses.
+pub(crate) async fn send_request(
+    client: Client,
+    url: Url,
+    opts: HttpOptions,
+    req: RequestPacket,
+) -> Result<ResponsePacket, TransportError> {
+    let mut body = serde_json::to_vec(&req).map_err(TransportError::ser_err)?;
+    let mut request = client.post(url).header(CONTENT_TYPE, "application/json");
+
+    if let Some(secret) = &opts.jwt_secret {
+        request = request.header(AUTHORIZATION, secret_to_bearer_header(secret));
+    }
+
+    if opts.gzip {
+        let mut encoder = GzEncoder::new(Vec::with_capacity(body.len() / 4), Compression::fast());
+        encoder
+            .write_all(&body)
+            .map_err(TransportErrorKind::custom)?;
+        body = encoder.finish().map_err(TransportErrorKind::custom)?;
+        request = request.header(CONTENT_ENCODING, "gzip");
+    }
+
+    let res = request
+        .body(body)
+        .send()
+        .await
+        .map_err(TransportErrorKind::custom)?;
+
+    let status = res.status();
+    let body = read_body(res, opts.max_response_bytes).await?;
+
+    if !status.is_success() {
+        return Err(TransportErrorKind::http_error(
+            status.as_u16(),
+            String::from_utf8_lossy(&body).into_owned(),
+        ));
+    }
+
+    serde_json::from_slice(&body)
+        .map_err(|err| TransportError::deser_err(err, String::from_utf8_lossy(&body)))
+}
@@ -0,0 +1,71 @@
+//! An HTTP transport that authenticates every request with a JWT, as required by
+//! the Engine API and other authenticated execution client endpoints.
+
+use std::task::{Context, Poll};
+
+use alloy_json_rpc::{RequestPacket, ResponsePacket};
+use alloy_transport::{TransportError, TransportFut};
+use reqwest::{Client, Url};
+use reth_rpc_layer::JwtSecret;
+use tower::Service;
+
+use super::http::{send_request, HttpOptions};
+
+/// An HTTP transport that attaches a freshly signed `Authorization: Bearer` token
+/// to every request.
+///
+/// The HS256 token only carries the `iat` claim, which execution clients accept
+/// within a window of a few seconds: signing it per request means it never expires.
+#[derive(Debug, Clone)]
+pub struct JwtHttp {
+    client: Client,
+    url: Url,
+    opts: HttpOptions,
+}
+
+impl JwtHttp {
+    /// Create a new transport for the given URL and JWT secret.
+    pub fn new(client: Client, url: Url, secret: JwtSecret) -> Self {
+        Self {
+            client,
+            url,
+            opts: HttpOptions {
+                jwt_secret: Some(secret),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Fail with a [ResponseTooLargeError](super::body_limit::ResponseTooLargeError)
+    /// instead of buffering response bodies larger than the given limit.
+    pub fn with_max_response_bytes(mut self, max_response_bytes: Option<usize>) -> Self {
+        self.opts.max_response_bytes = max_response_bytes;
+        self
+    }
+
+    /// Whether to send request bodies with `Content-Encoding: gzip`, as with
+    /// [GzipHttp](super::gzip::GzipHttp).
+    pub fn with_gzip(mut self, gzip: bool) -> Self {
+        self.opts.gzip = gzip;
+        self
+    }
+}
+
+impl Service<RequestPacket> for JwtHttp {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        Box::pin(send_request(
+            self.client.clone(),
+            self.url.clone(),
+            self.opts.clone(),
+            req,
+        ))
+    }
+}
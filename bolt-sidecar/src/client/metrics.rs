@@ -0,0 +1,110 @@
+//! A [tower] layer that records latency and error metrics for every JSON-RPC request
+//! made by the [RpcClient](super::rpc::RpcClient). Only available with the `metrics` feature.
+//!
+//! The following metrics are recorded with the [metrics] crate macros, so they can be
+//! exposed with any compatible exporter (e.g. `metrics-exporter-prometheus`):
+//!
+//! - `bolt_sidecar_rpc_request_duration_seconds` (histogram, label `method`): the latency of
+//!   each request, including retries. Batches are labeled with the method of their calls,
+//!   or `batch` if they mix several methods.
+//! - `bolt_sidecar_rpc_requests_total` (counter, labels `method` and `status`): the number
+//!   of requests, with `status` either `success` or `error`. A batch counts as an error if
+//!   any of its calls failed.
+//! - `bolt_sidecar_rpc_batch_size` (histogram, label `method`): the number of calls in each
+//!   batch, e.g. for `eth_getProof` batches sent by `get_proof_batched`.
+
+use std::{
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use alloy_json_rpc::{RequestPacket, ResponsePacket};
+use alloy_transport::{TransportError, TransportFut};
+use tower::{Layer, Service};
+
+/// The latency histogram of the RPC requests.
+pub const RPC_REQUEST_DURATION: &str = "bolt_sidecar_rpc_request_duration_seconds";
+/// The counter of the RPC requests, by method and status.
+pub const RPC_REQUESTS_TOTAL: &str = "bolt_sidecar_rpc_requests_total";
+/// The histogram of the number of calls in each batch.
+pub const RPC_BATCH_SIZE: &str = "bolt_sidecar_rpc_batch_size";
+
+/// A [Layer] that wraps a transport in a [MetricsService].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsLayer;
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = MetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetricsService { inner }
+    }
+}
+
+/// A transport service that records the latency and outcome of every request.
+#[derive(Debug, Clone)]
+pub struct MetricsService<S> {
+    inner: S,
+}
+
+impl<S> Service<RequestPacket> for MetricsService<S>
+where
+    S: Service<
+            RequestPacket,
+            Response = ResponsePacket,
+            Error = TransportError,
+            Future = TransportFut<'static>,
+        > + Send,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        let method = method_label(&req);
+
+        if let RequestPacket::Batch(calls) = &req {
+            metrics::histogram!(RPC_BATCH_SIZE, "method" => method.clone())
+                .record(calls.len() as f64);
+        }
+
+        let start = Instant::now();
+        let fut = self.inner.call(req);
+
+        Box::pin(async move {
+            let res = fut.await;
+
+            metrics::histogram!(RPC_REQUEST_DURATION, "method" => method.clone())
+                .record(start.elapsed().as_secs_f64());
+
+            let status = match &res {
+                Ok(ResponsePacket::Single(res)) if !res.payload.is_error() => "success",
+                Ok(ResponsePacket::Batch(res)) if !res.iter().any(|res| res.payload.is_error()) => {
+                    "success"
+                }
+                _ => "error",
+            };
+            metrics::counter!(RPC_REQUESTS_TOTAL, "method" => method, "status" => status)
+                .increment(1);
+
+            res
+        })
+    }
+}
+
+/// Returns the method of the request, or `batch` for batches mixing several methods.
+fn method_label(req: &RequestPacket) -> String {
+    match req {
+        RequestPacket::Single(call) => call.method().to_string(),
+        RequestPacket::Batch(calls) => match calls.split_first() {
+            Some((first, rest)) if rest.iter().all(|call| call.method() == first.method()) => {
+                first.method().to_string()
+            }
+            _ => "batch".to_string(),
+        },
+    }
+}
@@ -1,7 +1,29 @@
+pub mod beacon;
+pub mod block_tag;
+pub mod body_limit;
+pub mod circuit_breaker;
 pub mod commit_boost;
+pub mod context;
+pub mod engine;
+pub mod execution;
+pub mod failover;
+pub mod gzip;
+mod http;
+pub mod jwt;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub mod mevboost;
+pub mod multi;
+pub mod multicall;
+pub mod proof;
 pub mod pubsub;
+pub mod rate_limit;
+pub mod retry;
+pub mod revert;
 pub mod rpc;
+pub mod simulate;
+pub mod slot_clock;
+pub mod ticker;
 
 // Re-export the beacon_api_client
 pub use beacon_api_client::mainnet::Client as BeaconClient;
@@ -0,0 +1,430 @@
+//! An execution client over multiple endpoints, which fails over on transport errors
+//! like the [FailoverTransport](super::failover::FailoverTransport), with the same state
+//! machine, and also moves away from the endpoints that are syncing or fall behind the
+//! highest head.
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use alloy_eips::BlockNumberOrTag;
+use alloy_primitives::{Address, Bytes, B256};
+use alloy_rpc_types::{state::StateOverride, EIP1186AccountProofResponse, TransactionRequest};
+use alloy_rpc_types_trace::geth::{GethDebugTracingCallOptions, GethTrace};
+use alloy_transport::TransportResult;
+use futures::future::join_all;
+use reqwest::Url;
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+
+use super::{
+    execution::{BlockHashes, ExecutionRpc},
+    failover::{Failover, FailoverPolicy},
+    retry::is_idempotent_method,
+    rpc::RpcClient,
+};
+use crate::primitives::AccountState;
+
+/// The default number of blocks an endpoint can lag behind the highest head
+/// before it is considered unhealthy.
+pub const DEFAULT_MAX_HEAD_LAG: u64 = 2;
+
+/// The health of an endpoint of a [MultiRpcClient], as of its last health check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndpointHealth {
+    /// The index of the endpoint, in order of preference.
+    pub index: usize,
+    /// The head of the endpoint, or `None` if it is unreachable.
+    pub head: Option<u64>,
+    /// Whether the endpoint is still syncing.
+    pub is_syncing: bool,
+    /// Whether the endpoint is reachable, synced and within the maximum lag of the
+    /// highest head of all the endpoints.
+    pub healthy: bool,
+}
+
+/// An execution client over multiple endpoints, in order of preference.
+///
+/// Requests made with [MultiRpcClient::request] (or through its [ExecutionRpc] methods)
+/// go to the active endpoint, and fail over to the next healthy one on connection
+/// failures, timeouts and 5xx responses. The health checks of
+/// [MultiRpcClient::check_health] additionally move away from the endpoints that are
+/// syncing or lag more than the maximum head lag behind the highest head, so that the
+/// requests never run on stale state. How the active endpoint is picked after failing
+/// over depends on the [FailoverPolicy].
+#[derive(Debug, Clone)]
+pub struct MultiRpcClient {
+    clients: Arc<Vec<RpcClient>>,
+    failover: Failover,
+    max_head_lag: u64,
+}
+
+impl MultiRpcClient {
+    /// Create a new client over the given HTTP endpoints, with the default
+    /// [RpcClientConfig](super::rpc::RpcClientConfig).
+    ///
+    /// # Panics
+    ///
+    /// Panics if no URL is given.
+    pub fn new(urls: Vec<Url>) -> Self {
+        Self::from_clients(urls.into_iter().map(RpcClient::new).collect())
+    }
+
+    /// Create a new client over the given, already configured clients.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no client is given.
+    pub fn from_clients(clients: Vec<RpcClient>) -> Self {
+        assert!(!clients.is_empty(), "At least one endpoint is required");
+
+        Self {
+            failover: Failover::new(clients.len(), FailoverPolicy::default()),
+            clients: Arc::new(clients),
+            max_head_lag: DEFAULT_MAX_HEAD_LAG,
+        }
+    }
+
+    /// Set how the active endpoint is picked after failing over.
+    /// Defaults to [FailoverPolicy::StickyPrimary].
+    pub fn with_policy(mut self, policy: FailoverPolicy) -> Self {
+        self.failover.set_policy(policy);
+        self
+    }
+
+    /// Set the number of blocks an endpoint can lag behind the highest head before it
+    /// is considered unhealthy. Defaults to [DEFAULT_MAX_HEAD_LAG].
+    pub fn with_max_head_lag(mut self, max_head_lag: u64) -> Self {
+        self.max_head_lag = max_head_lag;
+        self
+    }
+
+    /// Returns the client of the endpoint to send requests to.
+    pub fn active(&self) -> &RpcClient {
+        &self.clients[self.active_index()]
+    }
+
+    /// Returns the index of the endpoint to send requests to.
+    pub fn active_index(&self) -> usize {
+        self.failover.active()
+    }
+
+    /// Probe the head and sync status of every endpoint, and move away from the active
+    /// endpoint if it is not healthy anymore. If no endpoint is healthy, the active
+    /// endpoint is kept.
+    pub async fn check_health(&self) -> Vec<EndpointHealth> {
+        let results = join_all(self.clients.iter().map(RpcClient::health_check)).await;
+
+        let highest_head = results
+            .iter()
+            .filter_map(|res| res.as_ref().ok())
+            .filter(|health| !health.is_syncing)
+            .map(|health| health.head)
+            .max();
+
+        let report: Vec<_> = results
+            .into_iter()
+            .enumerate()
+            .map(|(index, res)| {
+                let (head, is_syncing) = match &res {
+                    Ok(health) => (Some(health.head), health.is_syncing),
+                    Err(err) => {
+                        tracing::warn!(?err, endpoint = index, "RPC endpoint health check failed");
+                        (None, false)
+                    }
+                };
+                let healthy = !is_syncing
+                    && head.is_some_and(|head| {
+                        highest_head.is_some_and(|highest| {
+                            head.saturating_add(self.max_head_lag) >= highest
+                        })
+                    });
+
+                EndpointHealth {
+                    index,
+                    head,
+                    is_syncing,
+                    healthy,
+                }
+            })
+            .collect();
+
+        let healthy = report.iter().map(|health| health.healthy).collect();
+        if let Some((from, to)) = self.failover.set_health(healthy) {
+            tracing::warn!(
+                from,
+                to,
+                head = ?report[from].head,
+                ?highest_head,
+                "RPC endpoint unhealthy, failing over"
+            );
+        }
+
+        report
+    }
+
+    /// Run [MultiRpcClient::check_health] on the given interval in the background,
+    /// until the returned task is aborted.
+    pub fn spawn_health_checks(&self, interval: Duration) -> JoinHandle<()> {
+        let this = self.clone();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+                this.check_health().await;
+            }
+        })
+    }
+
+    /// Send a request for the given JSON-RPC method with the given function to the active
+    /// endpoint, failing over to the next healthy endpoints on transient errors. Every
+    /// endpoint is tried at most once. If no endpoint is healthy, all of them are tried
+    /// in order.
+    ///
+    /// Non-idempotent methods like `eth_sendRawTransaction` are never sent to another
+    /// endpoint, as the failed one may have executed them before failing.
+    pub async fn request<T, F, Fut>(&self, method: &str, f: F) -> TransportResult<T>
+    where
+        F: Fn(RpcClient) -> Fut,
+        Fut: Future<Output = TransportResult<T>>,
+    {
+        self.failover
+            .request(is_idempotent_method(method), |index| {
+                f(self.clients[index].clone())
+            })
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl ExecutionRpc for MultiRpcClient {
+    async fn get_chain_id(&self) -> TransportResult<u64> {
+        self.request("eth_chainId", |rpc| async move { rpc.get_chain_id().await })
+            .await
+    }
+
+    async fn get_head(&self) -> TransportResult<u64> {
+        self.request("eth_blockNumber", |rpc| async move { rpc.get_head().await })
+            .await
+    }
+
+    async fn get_block_hash(&self, block: BlockNumberOrTag) -> TransportResult<BlockHashes> {
+        self.request("eth_getBlockByNumber", move |rpc| async move {
+            ExecutionRpc::get_block_hash(&rpc, block).await
+        })
+        .await
+    }
+
+    async fn get_account_state(
+        &self,
+        address: Address,
+        block: BlockNumberOrTag,
+    ) -> TransportResult<AccountState> {
+        self.request("eth_getBalance", move |rpc| async move {
+            rpc.get_account_state(&address, block).await
+        })
+        .await
+    }
+
+    async fn get_code(&self, address: Address, block: BlockNumberOrTag) -> TransportResult<Bytes> {
+        self.request("eth_getCode", move |rpc| async move {
+            rpc.get_code(address, block).await
+        })
+        .await
+    }
+
+    async fn get_storage_at(
+        &self,
+        address: Address,
+        slot: B256,
+        block: BlockNumberOrTag,
+    ) -> TransportResult<B256> {
+        self.request("eth_getStorageAt", move |rpc| async move {
+            rpc.get_storage_at(address, slot, block).await
+        })
+        .await
+    }
+
+    async fn get_proof(
+        &self,
+        address: Address,
+        storage_keys: Vec<B256>,
+        block: BlockNumberOrTag,
+    ) -> TransportResult<EIP1186AccountProofResponse> {
+        self.request("eth_getProof", |rpc| {
+            let storage_keys = storage_keys.clone();
+            async move { rpc.get_proof(address, storage_keys, block).await }
+        })
+        .await
+    }
+
+    async fn debug_trace_call(
+        &self,
+        tx: TransactionRequest,
+        block: BlockNumberOrTag,
+        opts: Option<GethDebugTracingCallOptions>,
+    ) -> TransportResult<GethTrace> {
+        self.request("debug_traceCall", |rpc| {
+            let (tx, opts) = (tx.clone(), opts.clone());
+            async move { rpc.debug_trace_call(tx, block, opts).await }
+        })
+        .await
+    }
+
+    async fn debug_trace_call_with_shared_state(
+        &self,
+        tx: TransactionRequest,
+        block: BlockNumberOrTag,
+        state_override: Arc<StateOverride>,
+        opts: Option<GethDebugTracingCallOptions>,
+    ) -> TransportResult<GethTrace> {
+        self.request("debug_traceCall", |rpc| {
+            let (tx, state_override, opts) = (tx.clone(), state_override.clone(), opts.clone());
+            async move {
+                rpc.debug_trace_call_with_shared_state(tx, block, state_override, opts)
+                    .await
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use axum::http::StatusCode;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{client::rpc::RpcClientConfig, test_util::MockRpcServer};
+
+    /// Spawns a mock endpoint at the given head, which is synced unless `syncing` is set.
+    async fn spawn_endpoint(head: Arc<AtomicU64>, syncing: bool) -> MockRpcServer {
+        MockRpcServer::spawn(move |method, _| match method {
+            "eth_syncing" if syncing => {
+                Ok(json!({ "currentBlock": "0x1", "highestBlock": "0x100" }))
+            }
+            "eth_syncing" => Ok(Value::Bool(false)),
+            _ => Ok(json!(format!("{:#x}", head.load(Ordering::Relaxed)))),
+        })
+        .await
+    }
+
+    fn no_retry_client(rpc: &MockRpcServer) -> RpcClient {
+        let config = RpcClientConfig {
+            max_retries: 0,
+            ..Default::default()
+        };
+        RpcClient::new_with_config(rpc.url(), config)
+    }
+
+    #[tokio::test]
+    async fn test_fail_over_on_transport_errors() {
+        let primary = spawn_endpoint(Arc::new(AtomicU64::new(16)), false).await;
+        let backup = spawn_endpoint(Arc::new(AtomicU64::new(16)), false).await;
+        let client =
+            MultiRpcClient::from_clients(vec![no_retry_client(&primary), no_retry_client(&backup)])
+                .with_policy(FailoverPolicy::RoundRobin);
+
+        primary.fail_next(1, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(ExecutionRpc::get_head(&client).await.unwrap(), 16);
+        assert_eq!(client.active_index(), 1);
+
+        // The following requests stay on the backup
+        assert_eq!(ExecutionRpc::get_head(&client).await.unwrap(), 16);
+        assert_eq!(primary.call_count("eth_blockNumber"), 1);
+        assert_eq!(backup.call_count("eth_blockNumber"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_no_fail_over_of_transactions() {
+        let primary = MockRpcServer::spawn_with_delay(Duration::from_millis(500), |_, _| {
+            Ok(json!(B256::ZERO))
+        })
+        .await;
+        let backup = MockRpcServer::spawn(|_, _| Ok(json!(B256::ZERO))).await;
+        let config = RpcClientConfig {
+            timeout: Duration::from_millis(100),
+            max_retries: 0,
+            ..Default::default()
+        };
+        let client = MultiRpcClient::from_clients(vec![
+            RpcClient::new_with_config(primary.url(), config),
+            RpcClient::new_with_config(backup.url(), config),
+        ]);
+
+        // The primary may have received the transaction before timing out
+        let res = client
+            .request("eth_sendRawTransaction", |rpc| async move {
+                rpc.send_raw_transaction(Bytes::new()).await
+            })
+            .await;
+        assert!(res.is_err());
+        assert_eq!(primary.call_count("eth_sendRawTransaction"), 1);
+        assert_eq!(backup.call_count("eth_sendRawTransaction"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_fail_over_from_lagging_endpoint() {
+        let primary_head = Arc::new(AtomicU64::new(10));
+        let primary = spawn_endpoint(primary_head.clone(), false).await;
+        let backup = spawn_endpoint(Arc::new(AtomicU64::new(20)), false).await;
+        let client = MultiRpcClient::new(vec![primary.url(), backup.url()])
+            .with_policy(FailoverPolicy::StickyPrimary {
+                cooldown: Duration::ZERO,
+            })
+            .with_max_head_lag(2);
+
+        // The primary is 10 blocks behind: requests shift to the backup
+        let report = client.check_health().await;
+        assert_eq!(
+            report,
+            vec![
+                EndpointHealth {
+                    index: 0,
+                    head: Some(10),
+                    is_syncing: false,
+                    healthy: false,
+                },
+                EndpointHealth {
+                    index: 1,
+                    head: Some(20),
+                    is_syncing: false,
+                    healthy: true,
+                },
+            ]
+        );
+        assert_eq!(client.active_index(), 1);
+        assert_eq!(ExecutionRpc::get_head(&client).await.unwrap(), 20);
+        assert_eq!(primary.call_count("eth_blockNumber"), 1);
+
+        // Once it is back within the lag, the primary is preferred again
+        primary_head.store(18, Ordering::Relaxed);
+        assert!(client
+            .check_health()
+            .await
+            .iter()
+            .all(|health| health.healthy));
+        assert_eq!(client.active_index(), 0);
+        assert_eq!(ExecutionRpc::get_head(&client).await.unwrap(), 18);
+    }
+
+    #[tokio::test]
+    async fn test_fail_over_from_syncing_endpoint() {
+        let head = Arc::new(AtomicU64::new(20));
+        let primary = spawn_endpoint(head.clone(), true).await;
+        let backup = spawn_endpoint(head, false).await;
+        let client = MultiRpcClient::new(vec![primary.url(), backup.url()]);
+
+        let report = client.check_health().await;
+        assert!(report[0].is_syncing);
+        assert!(!report[0].healthy);
+        assert_eq!(client.active_index(), 1);
+
+        // Unhealthy endpoints are skipped, even when the active one fails
+        backup.fail_next(4, StatusCode::BAD_GATEWAY);
+        assert!(ExecutionRpc::get_head(&client).await.is_err());
+        assert_eq!(primary.call_count("eth_blockNumber"), 1);
+    }
+}
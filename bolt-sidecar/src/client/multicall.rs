@@ -0,0 +1,336 @@
+//! A builder of batched account state queries, created with [RpcClient::new_batch].
+//! The calls are sent in batches and their results are returned in the order they
+//! were queued, already deserialized.
+
+use std::{collections::HashMap, ops::Deref};
+
+use alloy_eips::BlockNumberOrTag;
+use alloy_primitives::{Address, Bytes, B256, U256, U64};
+use alloy_rpc_client::Waiter;
+use alloy_transport::TransportResult;
+
+use super::rpc::RpcClient;
+
+/// An account state query of a [MultiCall].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StateCall {
+    /// `eth_getBalance` of the account.
+    Balance(Address),
+    /// `eth_getTransactionCount` of the account.
+    Nonce(Address),
+    /// `eth_getCode` of the account.
+    Code(Address),
+    /// `eth_getStorageAt` of the given slot of the account.
+    Storage(Address, B256),
+}
+
+/// The result of a [StateCall].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateValue {
+    /// The balance of the account.
+    Balance(U256),
+    /// The nonce of the account.
+    Nonce(u64),
+    /// The code of the account.
+    Code(Bytes),
+    /// The value of the storage slot.
+    Storage(B256),
+}
+
+/// The pending response of a [StateCall] in a sent batch.
+enum StateWaiter {
+    Balance(Waiter<U256>),
+    Nonce(Waiter<U64>),
+    Code(Waiter<Bytes>),
+    Storage(Waiter<U256>),
+}
+
+impl StateWaiter {
+    async fn value(self) -> TransportResult<StateValue> {
+        Ok(match self {
+            Self::Balance(waiter) => StateValue::Balance(waiter.await?),
+            Self::Nonce(waiter) => StateValue::Nonce(waiter.await?.to()),
+            Self::Code(waiter) => StateValue::Code(waiter.await?),
+            Self::Storage(waiter) => StateValue::Storage(B256::from(waiter.await?)),
+        })
+    }
+}
+
+/// A builder of account state queries of mixed types, sent in batches of at most
+/// [RpcClientConfig::max_batch_size](super::rpc::RpcClientConfig::max_batch_size) calls.
+/// All the calls target the same block, the latest one by default.
+#[derive(Debug)]
+#[must_use = "the calls are only sent with `send`"]
+pub struct MultiCall<'a> {
+    client: &'a RpcClient,
+    batch_size: usize,
+    block: BlockNumberOrTag,
+    calls: Vec<StateCall>,
+}
+
+impl<'a> MultiCall<'a> {
+    pub(crate) fn new(client: &'a RpcClient, batch_size: usize) -> Self {
+        Self {
+            client,
+            batch_size,
+            block: BlockNumberOrTag::Latest,
+            calls: Vec::new(),
+        }
+    }
+
+    /// Set the block that the calls target.
+    pub fn with_block(mut self, block: BlockNumberOrTag) -> Self {
+        self.block = block;
+        self
+    }
+
+    /// Queue the given call.
+    pub fn call(mut self, call: StateCall) -> Self {
+        self.calls.push(call);
+        self
+    }
+
+    /// Queue an `eth_getBalance` call for the given account.
+    pub fn balance(self, address: Address) -> Self {
+        self.call(StateCall::Balance(address))
+    }
+
+    /// Queue an `eth_getTransactionCount` call for the given account.
+    pub fn nonce(self, address: Address) -> Self {
+        self.call(StateCall::Nonce(address))
+    }
+
+    /// Queue an `eth_getCode` call for the given account.
+    pub fn code(self, address: Address) -> Self {
+        self.call(StateCall::Code(address))
+    }
+
+    /// Queue an `eth_getStorageAt` call for the given slot of an account.
+    pub fn storage(self, address: Address, slot: B256) -> Self {
+        self.call(StateCall::Storage(address, slot))
+    }
+
+    /// Returns the number of queued calls.
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Returns `true` if no call is queued.
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Send the queued calls and wait for their results.
+    ///
+    /// If any of the calls fails, the whole request fails, like the other batched
+    /// methods of the [RpcClient].
+    pub async fn send(self) -> TransportResult<MultiCallResults> {
+        let mut values = Vec::with_capacity(self.calls.len());
+
+        for chunk in self.calls.chunks(self.batch_size) {
+            let mut batch = self.client.deref().new_batch();
+            let tag = self.block;
+
+            let mut waiters = Vec::with_capacity(chunk.len());
+            for call in chunk {
+                let waiter = match *call {
+                    StateCall::Balance(address) => StateWaiter::Balance(
+                        batch
+                            .add_call("eth_getBalance", &(address, tag))
+                            .expect("Correct parameters"),
+                    ),
+                    StateCall::Nonce(address) => StateWaiter::Nonce(
+                        batch
+                            .add_call("eth_getTransactionCount", &(address, tag))
+                            .expect("Correct parameters"),
+                    ),
+                    StateCall::Code(address) => StateWaiter::Code(
+                        batch
+                            .add_call("eth_getCode", &(address, tag))
+                            .expect("Correct parameters"),
+                    ),
+                    StateCall::Storage(address, slot) => {
+                        let slot = U256::from_be_bytes(slot.0);
+                        StateWaiter::Storage(
+                            batch
+                                .add_call("eth_getStorageAt", &(address, slot, tag))
+                                .expect("Correct parameters"),
+                        )
+                    }
+                };
+                waiters.push(waiter);
+            }
+
+            batch.send().await?;
+
+            for waiter in waiters {
+                values.push(waiter.value().await?);
+            }
+        }
+
+        Ok(MultiCallResults::new(self.calls, values))
+    }
+}
+
+/// The results of a [MultiCall], in the order the calls were queued.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MultiCallResults {
+    calls: Vec<StateCall>,
+    values: Vec<StateValue>,
+    /// The index of the result of each call. A call queued several times
+    /// points to its first result.
+    index: HashMap<StateCall, usize>,
+}
+
+impl MultiCallResults {
+    fn new(calls: Vec<StateCall>, values: Vec<StateValue>) -> Self {
+        let mut index = HashMap::with_capacity(calls.len());
+        for (i, call) in calls.iter().enumerate() {
+            index.entry(*call).or_insert(i);
+        }
+
+        Self {
+            calls,
+            values,
+            index,
+        }
+    }
+
+    /// Returns the results, in the order the calls were queued.
+    pub fn values(&self) -> &[StateValue] {
+        &self.values
+    }
+
+    /// Returns an iterator over the calls and their results, in the order they were queued.
+    pub fn iter(&self) -> impl Iterator<Item = (&StateCall, &StateValue)> {
+        self.calls.iter().zip(&self.values)
+    }
+
+    /// Returns the number of results.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if no call was queued.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the balance of the given account, if it was queued.
+    pub fn balance(&self, address: Address) -> Option<U256> {
+        self.find(StateCall::Balance(address))
+            .map(|value| match value {
+                StateValue::Balance(balance) => *balance,
+                _ => unreachable!("balance call with a non-balance value"),
+            })
+    }
+
+    /// Returns the nonce of the given account, if it was queued.
+    pub fn nonce(&self, address: Address) -> Option<u64> {
+        self.find(StateCall::Nonce(address))
+            .map(|value| match value {
+                StateValue::Nonce(nonce) => *nonce,
+                _ => unreachable!("nonce call with a non-nonce value"),
+            })
+    }
+
+    /// Returns the code of the given account, if it was queued.
+    pub fn code(&self, address: Address) -> Option<&Bytes> {
+        self.find(StateCall::Code(address))
+            .map(|value| match value {
+                StateValue::Code(code) => code,
+                _ => unreachable!("code call with a non-code value"),
+            })
+    }
+
+    /// Returns the value of the given storage slot of an account, if it was queued.
+    pub fn storage(&self, address: Address, slot: B256) -> Option<B256> {
+        self.find(StateCall::Storage(address, slot))
+            .map(|value| match value {
+                StateValue::Storage(value) => *value,
+                _ => unreachable!("storage call with a non-storage value"),
+            })
+    }
+
+    fn find(&self, call: StateCall) -> Option<&StateValue> {
+        self.index.get(&call).map(|&i| &self.values[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use alloy_primitives::hex;
+    use reqwest::Url;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{client::rpc::RpcClientConfig, test_util::launch_anvil};
+
+    #[tokio::test]
+    async fn test_multicall_mixed_calls() {
+        let anvil = launch_anvil();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let config = RpcClientConfig {
+            max_batch_size: 3,
+            ..Default::default()
+        };
+        let client = RpcClient::new_with_config(anvil_url, config);
+
+        let funded = anvil.addresses()[0];
+        let contract = Address::with_last_byte(0x42);
+        let code = Bytes::from_static(&hex!("4760005260206000f3"));
+        let slot = B256::with_last_byte(1);
+        let value = B256::with_last_byte(7);
+
+        client
+            .request::<_, Value>("anvil_setCode", (contract, &code))
+            .await
+            .unwrap();
+        client
+            .request::<_, Value>("anvil_setNonce", (contract, U64::from(5)))
+            .await
+            .unwrap();
+        client
+            .request::<_, Value>("anvil_setStorageAt", (contract, slot, value))
+            .await
+            .unwrap();
+
+        let results = client
+            .new_batch()
+            .balance(funded)
+            .code(contract)
+            .nonce(funded)
+            .storage(contract, slot)
+            .nonce(contract)
+            .code(funded)
+            .storage(contract, B256::ZERO)
+            .send()
+            .await
+            .unwrap();
+
+        // Spread over three batches, in the order the calls were queued
+        let funded_balance = U256::from(10000000000000000000000u128);
+        assert_eq!(
+            results.values(),
+            [
+                StateValue::Balance(funded_balance),
+                StateValue::Code(code.clone()),
+                StateValue::Nonce(0),
+                StateValue::Storage(value),
+                StateValue::Nonce(5),
+                StateValue::Code(Bytes::new()),
+                StateValue::Storage(B256::ZERO),
+            ]
+        );
+
+        assert_eq!(results.balance(funded), Some(funded_balance));
+        assert_eq!(results.balance(contract), None);
+        assert_eq!(results.nonce(contract), Some(5));
+        assert_eq!(results.code(contract), Some(&code));
+        assert_eq!(results.storage(contract, slot), Some(value));
+
+        assert!(client.new_batch().send().await.unwrap().is_empty());
+    }
+}
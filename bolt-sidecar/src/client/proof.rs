@@ -0,0 +1,399 @@
+//! Local verification of the EIP-1186 proofs returned by `eth_getProof`, so that the
+//! account state fetched from an RPC doesn't have to be trusted blindly.
+
+use alloy_primitives::{b256, keccak256, Bytes, B256, U256, U64};
+use alloy_rlp::{Decodable, Header, EMPTY_LIST_CODE, EMPTY_STRING_CODE};
+use alloy_rpc_types::EIP1186AccountProofResponse;
+
+/// The root of an empty Merkle-Patricia trie, i.e. `keccak256(rlp(""))`.
+const EMPTY_ROOT_HASH: B256 =
+    b256!("56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421");
+
+/// The code hash of an account without code, i.e. `keccak256("")`.
+const KECCAK_EMPTY: B256 =
+    b256!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470");
+
+/// Error returned by [verify_account_proof].
+#[derive(Debug, thiserror::Error)]
+pub enum ProofError {
+    /// The proof ended before reaching the value, or its absence.
+    #[error("missing proof node with hash {0}")]
+    MissingNode(B256),
+    /// A proof node doesn't hash to the reference of its parent (or to the root).
+    #[error("proof node hash mismatch: expected {expected}, got {got}")]
+    NodeHashMismatch {
+        /// The hash referenced by the parent node, or the root
+        expected: B256,
+        /// The hash of the node in the proof
+        got: B256,
+    },
+    /// A proof node isn't a valid branch, extension or leaf node.
+    #[error("invalid trie node in proof")]
+    InvalidNode,
+    /// The proof has more nodes than the path to the value.
+    #[error("unexpected trailing nodes in proof")]
+    TrailingNodes,
+    /// A proof node or value isn't valid RLP.
+    #[error("invalid RLP in proof: {0}")]
+    Rlp(#[from] alloy_rlp::Error),
+    /// An account field of the response doesn't match the proven account.
+    #[error("account {field} doesn't match the proof")]
+    AccountMismatch {
+        /// The mismatching field of the response
+        field: &'static str,
+    },
+    /// A storage value of the response doesn't match the proven value.
+    #[error("storage slot {key} holds {proven} in the proof, not {claimed}")]
+    StorageMismatch {
+        /// The storage key
+        key: B256,
+        /// The value proven by the storage proof
+        proven: U256,
+        /// The value in the response
+        claimed: U256,
+    },
+}
+
+/// Verifies an `eth_getProof` response against the given state root, i.e. the state root
+/// of the block the proof was requested at.
+///
+/// The account proof must prove the nonce, balance, code hash and storage hash of the
+/// response (or the absence of the account, for an empty one), and every storage proof
+/// must prove its value (or its absence, for a zero value) against the storage hash.
+pub fn verify_account_proof(
+    proof: &EIP1186AccountProofResponse,
+    state_root: B256,
+) -> Result<(), ProofError> {
+    let account =
+        match verify_trie_proof(state_root, proof.address.as_slice(), &proof.account_proof)? {
+            Some(mut value) => TrieAccount::decode(&mut value)?,
+            None => TrieAccount::default(),
+        };
+
+    if account.nonce != proof.nonce {
+        return Err(ProofError::AccountMismatch { field: "nonce" });
+    }
+    if account.balance != proof.balance {
+        return Err(ProofError::AccountMismatch { field: "balance" });
+    }
+    if account.storage_root != proof.storage_hash {
+        return Err(ProofError::AccountMismatch {
+            field: "storage hash",
+        });
+    }
+    if account.code_hash != proof.code_hash {
+        return Err(ProofError::AccountMismatch { field: "code hash" });
+    }
+
+    for storage in &proof.storage_proof {
+        let key = storage.key.0;
+        let proven = match verify_trie_proof(proof.storage_hash, key.as_slice(), &storage.proof)? {
+            Some(mut value) => U256::decode(&mut value)?,
+            None => U256::ZERO,
+        };
+
+        if proven != storage.value {
+            return Err(ProofError::StorageMismatch {
+                key,
+                proven,
+                claimed: storage.value,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// The account as stored in the state trie.
+#[derive(Debug)]
+struct TrieAccount {
+    nonce: U64,
+    balance: U256,
+    storage_root: B256,
+    code_hash: B256,
+}
+
+/// An account that is not in the state trie.
+impl Default for TrieAccount {
+    fn default() -> Self {
+        Self {
+            nonce: U64::ZERO,
+            balance: U256::ZERO,
+            storage_root: EMPTY_ROOT_HASH,
+            code_hash: KECCAK_EMPTY,
+        }
+    }
+}
+
+impl TrieAccount {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProofError> {
+        let header = Header::decode(buf)?;
+        if !header.list || header.payload_length != buf.len() {
+            return Err(ProofError::InvalidNode);
+        }
+
+        Ok(Self {
+            nonce: U64::from(u64::decode(buf)?),
+            balance: U256::decode(buf)?,
+            storage_root: B256::decode(buf)?,
+            code_hash: B256::decode(buf)?,
+        })
+    }
+}
+
+/// A reference to a trie node: either its hash, or the node itself if its encoding is
+/// shorter than 32 bytes.
+enum NodeRef<'a> {
+    Hash(B256),
+    Inline(&'a [u8]),
+}
+
+/// Walks the proof of the given key from the root, and returns the value stored at the
+/// key, or `None` if the proof shows that the key is not in the trie.
+fn verify_trie_proof<'a>(
+    root: B256,
+    key: &[u8],
+    proof: &'a [Bytes],
+) -> Result<Option<&'a [u8]>, ProofError> {
+    // An empty trie has no node to prove anything with
+    if root == EMPTY_ROOT_HASH && proof.iter().all(|node| node[..] == [EMPTY_STRING_CODE]) {
+        return Ok(None);
+    }
+
+    let path = to_nibbles(keccak256(key).as_slice());
+    let mut path = path.as_slice();
+    let mut nodes = proof.iter();
+    let mut next = NodeRef::Hash(root);
+
+    let value = loop {
+        let node = match next {
+            NodeRef::Hash(hash) => {
+                let node = nodes.next().ok_or(ProofError::MissingNode(hash))?;
+                let got = keccak256(node);
+                if got != hash {
+                    return Err(ProofError::NodeHashMismatch {
+                        expected: hash,
+                        got,
+                    });
+                }
+                &node[..]
+            }
+            NodeRef::Inline(node) => node,
+        };
+
+        match decode_node(node)?[..] {
+            [ref children @ .., value] if children.len() == 16 => {
+                let Some((&nibble, rest)) = path.split_first() else {
+                    break Some(decode_string(value)?).filter(|value| !value.is_empty());
+                };
+                path = rest;
+
+                match decode_child(children[nibble as usize])? {
+                    Some(child) => next = child,
+                    None => break None,
+                }
+            }
+            [encoded_path, value] => {
+                let (is_leaf, node_path) = decode_path(decode_string(encoded_path)?)?;
+
+                // The key diverges from the path of the node, so it's not in the trie
+                let Some(rest) = path.strip_prefix(node_path.as_slice()) else {
+                    break None;
+                };
+                path = rest;
+
+                if is_leaf {
+                    break Some(decode_string(value)?).filter(|_| path.is_empty());
+                }
+                next = decode_child(value)?.ok_or(ProofError::InvalidNode)?;
+            }
+            _ => return Err(ProofError::InvalidNode),
+        }
+    };
+
+    if nodes.next().is_some() {
+        return Err(ProofError::TrailingNodes);
+    }
+    Ok(value)
+}
+
+/// Splits an RLP-encoded trie node into the raw encodings of its items.
+fn decode_node(mut node: &[u8]) -> Result<Vec<&[u8]>, ProofError> {
+    let header = Header::decode(&mut node)?;
+    if !header.list || header.payload_length != node.len() {
+        return Err(ProofError::InvalidNode);
+    }
+
+    let mut items = Vec::with_capacity(17);
+    while !node.is_empty() {
+        let mut payload = node;
+        let item_header = Header::decode(&mut payload)?;
+        let len = node.len() - payload.len() + item_header.payload_length;
+        if len > node.len() {
+            return Err(alloy_rlp::Error::InputTooShort.into());
+        }
+
+        let (item, rest) = node.split_at(len);
+        items.push(item);
+        node = rest;
+    }
+
+    Ok(items)
+}
+
+/// Returns the payload of an RLP-encoded string.
+fn decode_string(mut item: &[u8]) -> Result<&[u8], ProofError> {
+    let header = Header::decode(&mut item)?;
+    if header.list || header.payload_length != item.len() {
+        return Err(ProofError::InvalidNode);
+    }
+    Ok(item)
+}
+
+/// Decodes the reference to a child node, or `None` for an empty branch slot.
+fn decode_child(item: &[u8]) -> Result<Option<NodeRef<'_>>, ProofError> {
+    if item.first().is_some_and(|&byte| byte >= EMPTY_LIST_CODE) {
+        return Ok(Some(NodeRef::Inline(item)));
+    }
+
+    match decode_string(item)? {
+        [] => Ok(None),
+        hash if hash.len() == 32 => Ok(Some(NodeRef::Hash(B256::from_slice(hash)))),
+        _ => Err(ProofError::InvalidNode),
+    }
+}
+
+/// Decodes the hex-prefix encoded path of a leaf or extension node, returning whether
+/// the node is a leaf and the nibbles of its path.
+fn decode_path(encoded: &[u8]) -> Result<(bool, Vec<u8>), ProofError> {
+    let (&first, rest) = encoded.split_first().ok_or(ProofError::InvalidNode)?;
+    let flag = first >> 4;
+    if flag > 3 {
+        return Err(ProofError::InvalidNode);
+    }
+
+    let mut nibbles = Vec::with_capacity(rest.len() * 2 + 1);
+    if flag & 1 == 1 {
+        nibbles.push(first & 0x0f);
+    }
+    nibbles.extend(to_nibbles(rest));
+
+    Ok((flag & 2 == 2, nibbles))
+}
+
+fn to_nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .iter()
+        .flat_map(|byte| [byte >> 4, byte & 0x0f])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::address;
+
+    use super::*;
+
+    /// The proof of the Uniswap V3 positions NFT contract on mainnet, without storage keys.
+    fn account_proof() -> EIP1186AccountProofResponse {
+        serde_json::from_str(include_str!(
+            "../../testdata/eth_getProof_uniswap_v3_positions.json"
+        ))
+        .unwrap()
+    }
+
+    /// The root of the state trie of [account_proof].
+    const ACCOUNT_PROOF_ROOT: B256 =
+        b256!("471374b211dfc7de94e14882f279b2cf6b66eefc10f4215fffcb077f8a59d97a");
+
+    /// A contract proof with an empty storage slot 0.
+    fn storage_exclusion_proof() -> EIP1186AccountProofResponse {
+        serde_json::from_str(include_str!(
+            "../../testdata/eth_getProof_storage_exclusion.json"
+        ))
+        .unwrap()
+    }
+
+    /// The root of the state trie of [storage_exclusion_proof].
+    const STORAGE_EXCLUSION_PROOF_ROOT: B256 =
+        b256!("57e6e864257daf9d96aaca31edd0cfe4e3892f09061e727c57ab56197dd59287");
+
+    #[test]
+    fn test_verify_account_proof() {
+        let proof = account_proof();
+        assert_eq!(
+            proof.address,
+            address!("c36442b4a4522e871399cd717abdd847ab11fe88")
+        );
+        verify_account_proof(&proof, ACCOUNT_PROOF_ROOT).unwrap();
+
+        let proof = storage_exclusion_proof();
+        assert_eq!(proof.storage_proof.len(), 1);
+        verify_account_proof(&proof, STORAGE_EXCLUSION_PROOF_ROOT).unwrap();
+    }
+
+    #[test]
+    fn test_verify_tampered_account_proof() {
+        // Wrong state root
+        let proof = account_proof();
+        assert!(matches!(
+            verify_account_proof(&proof, STORAGE_EXCLUSION_PROOF_ROOT),
+            Err(ProofError::NodeHashMismatch { .. })
+        ));
+
+        // Tampered account fields
+        let mut proof = account_proof();
+        proof.balance = U256::from(1);
+        assert!(matches!(
+            verify_account_proof(&proof, ACCOUNT_PROOF_ROOT),
+            Err(ProofError::AccountMismatch { field: "balance" })
+        ));
+
+        let mut proof = account_proof();
+        proof.code_hash = KECCAK_EMPTY;
+        assert!(matches!(
+            verify_account_proof(&proof, ACCOUNT_PROOF_ROOT),
+            Err(ProofError::AccountMismatch { field: "code hash" })
+        ));
+
+        // Tampered leaf node, whose hash doesn't match its parent's reference anymore
+        let mut proof = account_proof();
+        let mut leaf = proof.account_proof.pop().unwrap().to_vec();
+        *leaf.last_mut().unwrap() ^= 1;
+        proof.account_proof.push(Bytes::from(leaf));
+        assert!(matches!(
+            verify_account_proof(&proof, ACCOUNT_PROOF_ROOT),
+            Err(ProofError::NodeHashMismatch { .. })
+        ));
+
+        // Truncated proof
+        let mut proof = account_proof();
+        proof.account_proof.pop();
+        assert!(matches!(
+            verify_account_proof(&proof, ACCOUNT_PROOF_ROOT),
+            Err(ProofError::MissingNode(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_tampered_storage_proof() {
+        // A value claimed for an empty slot
+        let mut proof = storage_exclusion_proof();
+        proof.storage_proof[0].value = U256::from(1);
+        assert!(matches!(
+            verify_account_proof(&proof, STORAGE_EXCLUSION_PROOF_ROOT),
+            Err(ProofError::StorageMismatch {
+                proven: U256::ZERO,
+                ..
+            })
+        ));
+
+        // A storage proof that doesn't match the proven storage hash
+        let mut proof = storage_exclusion_proof();
+        proof.storage_proof[0].proof.remove(0);
+        assert!(matches!(
+            verify_account_proof(&proof, STORAGE_EXCLUSION_PROOF_ROOT),
+            Err(ProofError::NodeHashMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,170 @@
+//! A [tower] layer that throttles outgoing requests with a token bucket, to stay under
+//! the request caps of hosted providers. Used by the [RpcClient](super::rpc::RpcClient).
+
+use std::{
+    future::poll_fn,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use alloy_json_rpc::{RequestPacket, ResponsePacket};
+use alloy_transport::{TransportError, TransportFut};
+use parking_lot::Mutex;
+use tokio::time::Instant;
+use tower::{Layer, Service};
+
+/// The rate limit of the requests sent to an execution client.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// The sustained number of requests per second.
+    pub requests_per_second: f64,
+    /// The maximum number of requests that can be sent at once after a quiet period.
+    pub burst: u32,
+    /// Whether a batch counts as one request per call, or as a single request.
+    /// Defaults to `true`, as most providers meter every call of a batch.
+    pub count_batch_calls: bool,
+}
+
+impl RateLimit {
+    /// Create a new rate limit of `requests_per_second` with the given burst,
+    /// counting every call of a batch as a request.
+    ///
+    /// The rate must be finite and positive, see [RateLimit::is_valid].
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        Self {
+            requests_per_second,
+            burst,
+            count_batch_calls: true,
+        }
+    }
+
+    /// Returns `true` if the rate is finite and positive. Other rates can't be
+    /// enforced, so the [RateLimitLayer] ignores them.
+    pub fn is_valid(&self) -> bool {
+        self.requests_per_second.is_finite() && self.requests_per_second > 0.0
+    }
+}
+
+/// A token bucket shared between the clones of a [RateLimitService].
+#[derive(Debug)]
+struct TokenBucket {
+    limit: RateLimit,
+    /// The available tokens, negative when requests are waiting for their turn,
+    /// and the last time they were refilled.
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            state: Mutex::new((limit.burst as f64, Instant::now())),
+        }
+    }
+
+    /// Reserves the given number of tokens, and returns how long to wait before
+    /// they are available. Requests larger than the burst are allowed, and simply
+    /// wait for the tokens they borrowed.
+    fn reserve(&self, tokens: usize) -> Duration {
+        let rate = self.limit.requests_per_second;
+        let mut state = self.state.lock();
+
+        let now = Instant::now();
+        let refilled = state.0 + now.duration_since(state.1).as_secs_f64() * rate;
+        state.0 = refilled.min(self.limit.burst as f64) - tokens as f64;
+        state.1 = now;
+
+        if state.0 >= 0.0 {
+            Duration::ZERO
+        } else {
+            // The layer only accepts valid rates, but an overflow must not panic
+            Duration::try_from_secs_f64(-state.0 / rate).unwrap_or(Duration::ZERO)
+        }
+    }
+}
+
+/// A [Layer] that wraps a transport in a [RateLimitService].
+#[derive(Debug, Clone)]
+pub struct RateLimitLayer {
+    bucket: Option<Arc<TokenBucket>>,
+}
+
+impl RateLimitLayer {
+    /// Create a new rate limiting layer. Requests are not throttled if `None`, or if
+    /// the rate limit is invalid (e.g. zero or NaN requests per second).
+    pub fn new(limit: Option<RateLimit>) -> Self {
+        let limit = limit.filter(|limit| {
+            if !limit.is_valid() {
+                tracing::warn!(?limit, "Ignoring invalid RPC rate limit");
+            }
+            limit.is_valid()
+        });
+
+        Self {
+            bucket: limit.map(|limit| Arc::new(TokenBucket::new(limit))),
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            bucket: self.bucket.clone(),
+        }
+    }
+}
+
+/// A transport service that waits for the rate limit before sending every request.
+/// Throttled requests are delayed rather than failed.
+#[derive(Debug, Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    bucket: Option<Arc<TokenBucket>>,
+}
+
+impl<S> Service<RequestPacket> for RateLimitService<S>
+where
+    S: Service<
+            RequestPacket,
+            Response = ResponsePacket,
+            Error = TransportError,
+            Future = TransportFut<'static>,
+        > + Clone
+        + Send
+        + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        let Some(bucket) = &self.bucket else {
+            return self.inner.call(req);
+        };
+
+        let tokens = match &req {
+            RequestPacket::Batch(calls) if bucket.limit.count_batch_calls => calls.len(),
+            _ => 1,
+        };
+        let wait = bucket.reserve(tokens);
+
+        let mut inner = self.inner.clone();
+        Box::pin(async move {
+            if !wait.is_zero() {
+                tracing::trace!(?wait, tokens, "Waiting for the RPC rate limit");
+                tokio::time::sleep(wait).await;
+            }
+
+            poll_fn(|cx| inner.poll_ready(cx)).await?;
+            inner.call(req).await
+        })
+    }
+}
@@ -0,0 +1,369 @@
+//! A [tower] layer that retries transient transport failures with exponential backoff
+//! and optional jitter. Used by the [RpcClient](super::rpc::RpcClient) to survive flaky
+//! or rate-limiting execution nodes.
+
+use std::{
+    future::poll_fn,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use alloy_json_rpc::{RequestPacket, ResponsePacket, RpcError, SerializedRequest};
+use alloy_transport::{TransportError, TransportErrorKind, TransportFut};
+use tower::{Layer, Service};
+
+use super::rpc::is_log_query_limit_exceeded;
+
+/// Retry policy for transient transport failures.
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    /// The maximum number of retries after the first attempt.
+    max_retries: u32,
+    /// The backoff before the first retry. It is doubled after every further attempt.
+    backoff: Duration,
+    /// Whether to randomize every backoff between half and all of its exponential value.
+    jitter: bool,
+    /// Whether to also retry the requests rejected by the rate limit of the node.
+    retry_rate_limited: bool,
+}
+
+impl RetryPolicy {
+    /// The backoff to wait for before the given retry attempt (0-indexed).
+    fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self.backoff.saturating_mul(2u32.saturating_pow(attempt));
+        if !self.jitter {
+            return backoff;
+        }
+
+        let half = backoff / 2;
+        half + half.mul_f64(rand::random::<f64>())
+    }
+
+    /// Returns `true` if a request failing with the given error should be retried.
+    /// Transient failures are only retried for idempotent requests, see [is_idempotent].
+    fn should_retry(&self, err: &TransportError, idempotent: bool) -> bool {
+        (idempotent && is_retryable(err)) || (self.retry_rate_limited && is_rate_limited(err))
+    }
+}
+
+/// The methods whose requests are not sent again after a transient failure: the node
+/// may have executed the request before the failure (e.g. added the transaction to its
+/// pool before the response timed out), and the retry would then be rejected.
+const NON_IDEMPOTENT_METHODS: &[&str] = &["eth_sendRawTransaction", "eth_sendTransaction"];
+
+/// Returns `true` if the method is not one of the [NON_IDEMPOTENT_METHODS].
+pub(crate) fn is_idempotent_method(method: &str) -> bool {
+    !NON_IDEMPOTENT_METHODS.contains(&method)
+}
+
+/// Returns `true` if none of the calls of the request is a [NON_IDEMPOTENT_METHODS] one.
+pub(crate) fn is_idempotent(req: &RequestPacket) -> bool {
+    let is_idempotent = |call: &SerializedRequest| is_idempotent_method(call.method());
+
+    match req {
+        RequestPacket::Single(call) => is_idempotent(call),
+        RequestPacket::Batch(calls) => calls.iter().all(is_idempotent),
+    }
+}
+
+/// A [Layer] that wraps a transport in a [RetryService].
+#[derive(Debug, Clone)]
+pub struct RetryLayer {
+    policy: RetryPolicy,
+}
+
+impl RetryLayer {
+    /// Create a new retry layer, retrying transient failures up to `max_retries` times
+    /// with an exponential backoff starting at `backoff`, without jitter.
+    pub fn new(max_retries: u32, backoff: Duration) -> Self {
+        Self {
+            policy: RetryPolicy {
+                max_retries,
+                backoff,
+                jitter: false,
+                retry_rate_limited: false,
+            },
+        }
+    }
+
+    /// Randomize every backoff between half and all of its exponential value, so that
+    /// the clients of a struggling node don't retry in lockstep.
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.policy.jitter = jitter;
+        self
+    }
+
+    /// Also retry the requests rejected by the rate limit of the node, see
+    /// [is_rate_limited].
+    pub fn with_rate_limited_retries(mut self, retry_rate_limited: bool) -> Self {
+        self.policy.retry_rate_limited = retry_rate_limited;
+        self
+    }
+}
+
+impl<S> Layer<S> for RetryLayer {
+    type Service = RetryService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RetryService {
+            inner,
+            policy: self.policy,
+        }
+    }
+}
+
+/// A transport service that retries requests failing with a transient error,
+/// i.e. connection failures, timeouts and 5xx HTTP responses (see [is_retryable]),
+/// and optionally the requests rejected by the rate limit of the node.
+///
+/// Other JSON-RPC application errors and 4xx HTTP responses are never retried, and
+/// neither are the transient failures of the requests sending transactions.
+#[derive(Debug, Clone)]
+pub struct RetryService<S> {
+    inner: S,
+    policy: RetryPolicy,
+}
+
+impl<S> Service<RequestPacket> for RetryService<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        let mut inner = self.inner.clone();
+        let policy = self.policy;
+        let idempotent = is_idempotent(&req);
+
+        Box::pin(async move {
+            let mut attempt = 0;
+
+            loop {
+                poll_fn(|cx| inner.poll_ready(cx)).await?;
+
+                match inner.call(req.clone()).await {
+                    Err(err)
+                        if attempt < policy.max_retries
+                            && policy.should_retry(&err, idempotent) =>
+                    {
+                        let backoff = policy.backoff(attempt);
+                        tracing::debug!(?err, attempt, ?backoff, "Retrying transient RPC failure");
+
+                        tokio::time::sleep(backoff).await;
+                        attempt += 1;
+                    }
+                    res => return res,
+                }
+            }
+        })
+    }
+}
+
+/// Returns `true` if the error is worth retrying, i.e. if sending the same request
+/// again (to the same or another endpoint) may succeed. This is the single source of
+/// truth for the retry and failover logic of the client.
+///
+/// | Error                                          | Retryable |
+/// |------------------------------------------------|-----------|
+/// | Connection failure, request error or timeout   | yes       |
+/// | I/O error                                      | yes       |
+/// | Pubsub backend gone                            | yes       |
+/// | HTTP 5xx                                       | yes       |
+/// | HTTP 4xx (including 429)                       | no        |
+/// | JSON-RPC error response (e.g. reverts)         | no        |
+/// | (De)serialization error                        | no        |
+/// | Anything else                                  | no        |
+///
+/// JSON-RPC error responses are deterministic (e.g. "execution reverted" or "nonce
+/// too low"): retrying them wastes the retry budget and delays the actual result.
+///
+/// Rate-limited requests (see [is_rate_limited]) are not transient failures of the
+/// node, and should neither trigger a failover nor trip the circuit breaker. The
+/// [RetryService] can still be configured to retry them after the backoff.
+pub(crate) fn is_retryable(err: &TransportError) -> bool {
+    match err {
+        RpcError::Transport(TransportErrorKind::HttpError(e)) => e.status >= 500,
+        RpcError::Transport(TransportErrorKind::BackendGone) => true,
+        RpcError::Transport(TransportErrorKind::Custom(e)) => {
+            if let Some(e) = e.downcast_ref::<reqwest::Error>() {
+                return e.is_timeout() || e.is_connect() || e.is_request();
+            }
+
+            e.is::<std::io::Error>()
+        }
+        _ => false,
+    }
+}
+
+/// Returns `true` if the request was rejected by the rate limit of the node: an HTTP
+/// 429 response, or a JSON-RPC error with code 429 or -32005 ("limit exceeded"), as
+/// returned by hosted providers over their quota.
+///
+/// Infura also returns -32005 for log queries exceeding its result limit, which
+/// are rejected again on every retry: those are not rate limits.
+pub(crate) fn is_rate_limited(err: &TransportError) -> bool {
+    match err {
+        RpcError::Transport(TransportErrorKind::HttpError(e)) => e.status == 429,
+        RpcError::ErrorResp(payload) => match payload.code {
+            429 => true,
+            -32005 => !is_log_query_limit_exceeded(err),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, time::Duration};
+
+    use alloy_json_rpc::ErrorPayload;
+
+    use super::*;
+
+    fn rpc_error(code: i64, message: &str) -> TransportError {
+        RpcError::ErrorResp(ErrorPayload {
+            code,
+            message: message.into(),
+            data: None,
+        })
+    }
+
+    #[test]
+    fn test_exponential_backoff() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            backoff: Duration::from_millis(100),
+            jitter: false,
+            retry_rate_limited: false,
+        };
+
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+    }
+
+    #[test]
+    fn test_jittered_backoff() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            backoff: Duration::from_millis(100),
+            jitter: true,
+            retry_rate_limited: false,
+        };
+
+        for _ in 0..100 {
+            let backoff = policy.backoff(2);
+            assert!(backoff >= Duration::from_millis(200));
+            assert!(backoff <= Duration::from_millis(400));
+        }
+    }
+
+    #[test]
+    fn test_rate_limited_retries() {
+        let mut policy = RetryPolicy {
+            max_retries: 3,
+            backoff: Duration::from_millis(100),
+            jitter: false,
+            retry_rate_limited: false,
+        };
+        let rate_limited = [
+            TransportErrorKind::http_error(429, String::new()),
+            rpc_error(429, "Too Many Requests"),
+            rpc_error(-32005, "daily request count exceeded, request rate limited"),
+        ];
+
+        for err in &rate_limited {
+            assert!(is_rate_limited(err));
+            assert!(!is_retryable(err));
+            assert!(!policy.should_retry(err, true));
+        }
+
+        policy.retry_rate_limited = true;
+        for err in &rate_limited {
+            assert!(policy.should_retry(err, true));
+            // The node rejected the request before executing it
+            assert!(policy.should_retry(err, false));
+        }
+        assert!(!policy.should_retry(&rpc_error(-32000, "execution reverted"), true));
+        let log_limit = rpc_error(-32005, "query returned more than 10000 results");
+        assert!(!is_rate_limited(&log_limit));
+        assert!(!policy.should_retry(&log_limit, true));
+        assert!(!policy.should_retry(&TransportErrorKind::http_error(403, String::new()), true));
+        assert!(!policy.should_retry(&TransportErrorKind::http_error(502, String::new()), false));
+    }
+
+    #[test]
+    fn test_is_retryable_http_status() {
+        for status in [500, 502, 503, 504] {
+            assert!(is_retryable(&TransportErrorKind::http_error(
+                status,
+                String::new()
+            )));
+        }
+        for status in [400, 401, 404, 413, 429] {
+            assert!(!is_retryable(&TransportErrorKind::http_error(
+                status,
+                String::new()
+            )));
+        }
+    }
+
+    #[test]
+    fn test_json_rpc_errors_are_not_retryable() {
+        assert!(!is_retryable(&rpc_error(-32000, "execution reverted")));
+        assert!(!is_retryable(&rpc_error(-32000, "nonce too low")));
+        assert!(!is_retryable(&rpc_error(
+            -32601,
+            "the method does not exist"
+        )));
+
+        let err = serde_json::from_str::<u64>("\"0x1\"").unwrap_err();
+        assert!(!is_retryable(&TransportError::deser_err(err, "\"0x1\"")));
+    }
+
+    #[test]
+    fn test_io_errors_are_retryable() {
+        let err = io::Error::from(io::ErrorKind::ConnectionReset);
+        assert!(is_retryable(&TransportErrorKind::custom(err)));
+        assert!(is_retryable(&TransportErrorKind::backend_gone()));
+
+        assert!(!is_retryable(&TransportErrorKind::custom_str(
+            "unknown failure"
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_reqwest_errors_are_retryable() {
+        // Refused connection
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let err = reqwest::get(format!("http://{addr}")).await.unwrap_err();
+        assert!(is_retryable(&TransportErrorKind::custom(err)));
+
+        // Timeout, with a server that accepts the connection but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let err = reqwest::Client::new()
+            .get(format!("http://{addr}"))
+            .timeout(Duration::from_millis(50))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(err.is_timeout());
+        assert!(is_retryable(&TransportErrorKind::custom(err)));
+    }
+}
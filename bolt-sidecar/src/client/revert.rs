@@ -0,0 +1,229 @@
+//! Decoding of the revert reason carried in the data of `execution reverted` errors,
+//! so that a reverting commitment can be rejected with a human-readable message.
+
+use std::fmt;
+
+use alloy_json_rpc::RpcError;
+use alloy_primitives::{Bytes, U256};
+use alloy_transport::TransportError;
+
+/// The selector of `Error(string)`, emitted by `require` and `revert` with a message.
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// The selector of `Panic(uint256)`, emitted by failed assertions, arithmetic overflows,
+/// out-of-bounds accesses, etc.
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// The reason why a call reverted, decoded from its revert data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevertReason {
+    /// An `Error(string)` with the given message.
+    Error(String),
+    /// A `Panic(uint256)` with the given code, see [RevertReason::panic_description].
+    Panic(U256),
+    /// A custom error, or malformed standard error: the raw revert data, selector included.
+    Custom(Bytes),
+}
+
+impl RevertReason {
+    /// Decode the given revert data. Returns `None` if the data is too short to carry
+    /// a selector, e.g. for a bare `revert()`.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let (selector, args) = data.split_first_chunk::<4>()?;
+
+        let reason = match *selector {
+            ERROR_SELECTOR => decode_string(args).map(Self::Error),
+            PANIC_SELECTOR => (args.len() == 32).then(|| Self::Panic(U256::from_be_slice(args))),
+            _ => None,
+        };
+
+        Some(reason.unwrap_or_else(|| Self::Custom(Bytes::copy_from_slice(data))))
+    }
+
+    /// Returns the meaning of the code of a [RevertReason::Panic], as documented by
+    /// Solidity, or `None` if the reason is not a panic or the code is unknown.
+    pub fn panic_description(&self) -> Option<&'static str> {
+        let Self::Panic(code) = self else {
+            return None;
+        };
+
+        let description = match u64::try_from(*code).ok()? {
+            0x00 => "generic compiler panic",
+            0x01 => "assertion failed",
+            0x11 => "arithmetic overflow or underflow",
+            0x12 => "division or modulo by zero",
+            0x21 => "invalid enum value",
+            0x22 => "invalid storage byte array encoding",
+            0x31 => "pop on empty array",
+            0x32 => "array index out of bounds",
+            0x41 => "out of memory",
+            0x51 => "call to uninitialized function",
+            _ => return None,
+        };
+        Some(description)
+    }
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error(message) => write!(f, "{message}"),
+            Self::Panic(code) => match self.panic_description() {
+                Some(description) => write!(f, "panic {code:#x}: {description}"),
+                None => write!(f, "panic {code:#x}"),
+            },
+            Self::Custom(data) => write!(f, "custom error {data}"),
+        }
+    }
+}
+
+/// Decode the revert reason from the data of a JSON-RPC error response, e.g. the
+/// `execution reverted` error of `eth_call` or `eth_estimateGas`.
+///
+/// Returns `None` if the error is not a JSON-RPC `execution reverted` error response
+/// (code 3, or a message starting with `execution reverted`), or if it doesn't carry
+/// hex-encoded revert data. Other errors may carry hex data that is not revert data.
+pub fn decode_revert_reason(err: &TransportError) -> Option<RevertReason> {
+    let RpcError::ErrorResp(payload) = err else {
+        return None;
+    };
+
+    let is_revert = payload.code == 3
+        || payload
+            .message
+            .to_lowercase()
+            .starts_with("execution reverted");
+    if !is_revert {
+        return None;
+    }
+
+    let data: Bytes = serde_json::from_str(payload.data.as_deref()?.get()).ok()?;
+    RevertReason::decode(&data)
+}
+
+/// Decode the ABI-encoded arguments of `Error(string)`: the offset of the string,
+/// then its length and its UTF-8 bytes, padded to 32 bytes.
+fn decode_string(args: &[u8]) -> Option<String> {
+    let word = |offset: usize| -> Option<usize> {
+        let word = args.get(offset..offset.checked_add(32)?)?;
+        usize::try_from(U256::from_be_slice(word)).ok()
+    };
+
+    let offset = word(0)?;
+    let len = word(offset)?;
+    let start = offset.checked_add(32)?;
+    let bytes = args.get(start..start.checked_add(len)?)?;
+
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_json_rpc::ErrorPayload;
+    use alloy_transport::TransportErrorKind;
+    use serde_json::value::RawValue;
+
+    use super::*;
+
+    /// Returns the `execution reverted` error response of Geth with the given data.
+    fn reverted(data: &str) -> TransportError {
+        RpcError::ErrorResp(ErrorPayload {
+            code: 3,
+            message: "execution reverted".into(),
+            data: Some(RawValue::from_string(format!("\"{data}\"")).unwrap()),
+        })
+    }
+
+    #[test]
+    fn test_decode_error_string() {
+        // require(false, "Not enough Ether provided.")
+        let err = reverted(concat!(
+            "0x08c379a0",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "000000000000000000000000000000000000000000000000000000000000001a",
+            "4e6f7420656e6f7567682045746865722070726f76696465642e000000000000",
+        ));
+
+        let reason = decode_revert_reason(&err).unwrap();
+        assert_eq!(
+            reason,
+            RevertReason::Error("Not enough Ether provided.".into())
+        );
+        assert_eq!(reason.to_string(), "Not enough Ether provided.");
+    }
+
+    #[test]
+    fn test_decode_panic() {
+        // Arithmetic overflow
+        let err = reverted(concat!(
+            "0x4e487b71",
+            "0000000000000000000000000000000000000000000000000000000000000011",
+        ));
+
+        let reason = decode_revert_reason(&err).unwrap();
+        assert_eq!(reason, RevertReason::Panic(U256::from(0x11)));
+        assert_eq!(
+            reason.to_string(),
+            "panic 0x11: arithmetic overflow or underflow"
+        );
+    }
+
+    #[test]
+    fn test_decode_custom_and_malformed_errors() {
+        // error InsufficientBalance(uint256)
+        let data = concat!(
+            "0xcf479181",
+            "0000000000000000000000000000000000000000000000000000000000000001",
+        );
+        let reason = decode_revert_reason(&reverted(data)).unwrap();
+        assert_eq!(reason, RevertReason::Custom(data.parse().unwrap()));
+
+        // Truncated Error(string): the length exceeds the data
+        let data = concat!(
+            "0x08c379a0",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "00000000000000000000000000000000000000000000000000000000000000ff",
+        );
+        let reason = decode_revert_reason(&reverted(data)).unwrap();
+        assert!(matches!(reason, RevertReason::Custom(_)));
+
+        // No revert data at all
+        assert_eq!(decode_revert_reason(&reverted("0x")), None);
+        let err = RpcError::ErrorResp(ErrorPayload {
+            code: -32000,
+            message: "nonce too low".into(),
+            data: None,
+        });
+        assert_eq!(decode_revert_reason(&err), None);
+        assert_eq!(
+            decode_revert_reason(&TransportErrorKind::custom_str("timeout")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_only_reverts_are_decoded() {
+        let data = concat!(
+            "0x08c379a0",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "0000000000000000000000000000000000000000000000000000000000000002",
+            "6e6f000000000000000000000000000000000000000000000000000000000000",
+        );
+        let error = |code: i64, message: &str| {
+            RpcError::ErrorResp(ErrorPayload {
+                code,
+                message: message.into(),
+                data: Some(RawValue::from_string(format!("\"{data}\"")).unwrap()),
+            })
+        };
+
+        // Hex data on another error, e.g. the hash of a known transaction
+        assert_eq!(decode_revert_reason(&error(-32000, "already known")), None);
+
+        // Nodes that don't set code 3 still report the revert in the message
+        let reason = decode_revert_reason(&error(-32000, "execution reverted: no"));
+        assert_eq!(reason, Some(RevertReason::Error("no".into())));
+        let reason = decode_revert_reason(&error(3, "reverted"));
+        assert_eq!(reason, Some(RevertReason::Error("no".into())));
+    }
+}
@@ -1,208 +1,3891 @@
 //! This module contains the `RpcClient` struct, which is a wrapper around the `alloy_rpc_client`.
-//! It provides a simple interface to interact with the Execution layer JSON-RPC API.
+//! It provides a simple interface to interact with the Execution layer JSON-RPC API,
+//! over HTTP, WebSocket or IPC.
 
 use alloy_rpc_types_trace::geth::{GethDebugTracingCallOptions, GethTrace};
-use futures::future::join_all;
+use futures::{future::join_all, stream, Future, Stream, StreamExt};
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     ops::{Deref, DerefMut},
+    path::PathBuf,
+    slice::Chunks,
+    sync::{Arc, OnceLock},
+    time::Duration,
 };
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
 
 use alloy::ClientBuilder;
-use alloy_eips::BlockNumberOrTag;
-use alloy_primitives::{Address, B256, U256, U64};
+use alloy_eips::{eip4844::calc_blob_gasprice, BlockNumberOrTag};
+use alloy_json_rpc::{RpcError, RpcParam, RpcReturn};
+use alloy_primitives::{Address, Bytes, B256, U128, U256, U64};
+use alloy_pubsub::{PubSubConnect, PubSubFrontend};
 use alloy_rpc_client::{self as alloy, Waiter};
-use alloy_rpc_types::{Block, EIP1186AccountProofResponse, FeeHistory, TransactionRequest};
+use alloy_rpc_types::{
+    state::StateOverride, AccessListWithGasUsed, Block, BlockOverrides,
+    EIP1186AccountProofResponse, FeeHistory, Filter, Header, Log, Transaction, TransactionReceipt,
+    TransactionRequest,
+};
 use alloy_rpc_types_trace::parity::{TraceResults, TraceType};
-use alloy_transport::TransportResult;
+use alloy_transport::{
+    utils::guess_local_url, BoxTransport, Transport, TransportError, TransportErrorKind,
+    TransportResult,
+};
 use alloy_transport_http::Http;
-use reqwest::{Client, Url};
+use alloy_transport_ipc::IpcConnect;
+use alloy_transport_ws::WsConnect;
+use reqwest::{header::HeaderMap, Client, Url};
+use reth_rpc_layer::JwtSecret;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
+use super::{
+    block_tag::IntoBlockTag,
+    body_limit::LimitedHttp,
+    circuit_breaker::{CircuitBreakerConfig, CircuitBreakerLayer, CircuitBreakerService},
+    failover::{FailoverPolicy, FailoverTransport},
+    gzip::GzipHttp,
+    jwt::JwtHttp,
+    multicall::MultiCall,
+    rate_limit::{RateLimit, RateLimitLayer, RateLimitService},
+    retry::{RetryLayer, RetryService},
+    simulate::{SimBlock, SimulatePayload, SimulatedBlock},
+};
 use crate::primitives::AccountState;
 
-/// An HTTP-based JSON-RPC client that supports batching.
+/// Configuration for the [RpcClient] transport.
+#[derive(Debug, Clone, Copy)]
+pub struct RpcClientConfig {
+    /// The timeout of every single HTTP request. Defaults to 10 seconds.
+    pub timeout: Duration,
+    /// The maximum number of retries of a request failing with a transient error
+    /// (connection failure, timeout or 5xx response). Defaults to 3.
+    pub max_retries: u32,
+    /// The backoff before the first retry, doubled after every further attempt.
+    /// Defaults to 100 milliseconds.
+    pub backoff: Duration,
+    /// Whether to randomize every backoff between half and all of its exponential
+    /// value, so that concurrent requests don't retry in lockstep. Defaults to `true`.
+    pub jitter: bool,
+    /// Whether to also retry the requests rejected by the rate limit of the node (HTTP
+    /// 429 or its JSON-RPC equivalents), within `max_retries`. Defaults to `true`.
+    pub retry_rate_limited: bool,
+    /// The maximum number of calls sent in a single JSON-RPC batch. Larger batched
+    /// requests are split into chunks, as many providers reject oversized batches.
+    /// Defaults to 50.
+    pub max_batch_size: usize,
+    /// Whether to gzip-compress request bodies and accept gzip-compressed responses.
+    /// Only enable it for nodes that decompress request bodies. Defaults to `false`.
+    pub gzip: bool,
+    /// The rate limit of the requests, including retries. Throttled requests wait for
+    /// their turn instead of failing. Defaults to `None`, i.e. no limit.
+    pub rate_limit: Option<RateLimit>,
+    /// The circuit breaker failing calls fast with
+    /// [CircuitOpenError](super::circuit_breaker::CircuitOpenError) after repeated
+    /// transport failures, until the node answers a probe again. Defaults to `None`,
+    /// i.e. every call is sent.
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// The maximum size of an HTTP response body in bytes, after decompression. Larger
+    /// responses fail with a [ResponseTooLargeError](super::body_limit::ResponseTooLargeError)
+    /// as soon as the limit is exceeded, instead of being buffered entirely. Only applies
+    /// to HTTP endpoints. Defaults to `None`, i.e. no limit.
+    pub max_response_bytes: Option<usize>,
+}
+
+impl Default for RpcClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            max_retries: 3,
+            backoff: Duration::from_millis(100),
+            jitter: true,
+            retry_rate_limited: true,
+            max_batch_size: 50,
+            gzip: false,
+            rate_limit: None,
+            circuit_breaker: None,
+            max_response_bytes: None,
+        }
+    }
+}
+
+/// A builder of HTTP [RpcClient]s, combining the transport options of the
+/// [RpcClientConfig] with authentication, failover and archive fallback.
+///
+/// The transport layers are composed in a fixed order, from the outermost:
+/// circuit breaker, retries, rate limit, failover across the endpoints, and finally
+/// the HTTP transport of each endpoint, with its headers, JWT, compression and
+/// response size limit.
+#[derive(Debug, Clone)]
+pub struct RpcClientBuilder {
+    url: Url,
+    failover_urls: Vec<Url>,
+    failover_policy: FailoverPolicy,
+    headers: HeaderMap,
+    jwt_secret: Option<JwtSecret>,
+    archive_url: Option<Url>,
+    config: RpcClientConfig,
+}
+
+impl RpcClientBuilder {
+    /// Create a new builder for the given endpoint, with the default [RpcClientConfig].
+    pub fn new<U: Into<Url>>(url: U) -> Self {
+        Self {
+            url: url.into(),
+            failover_urls: Vec::new(),
+            failover_policy: FailoverPolicy::default(),
+            headers: HeaderMap::new(),
+            jwt_secret: None,
+            archive_url: None,
+            config: RpcClientConfig::default(),
+        }
+    }
+
+    /// Set the whole transport configuration at once, replacing the options set so far.
+    pub fn config(mut self, config: RpcClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Set the timeout of every single HTTP request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+
+    /// Set the maximum number of retries of a request failing with a transient error.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.config.max_retries = max_retries;
+        self
+    }
+
+    /// Set the backoff before the first retry, doubled after every further attempt.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.config.backoff = backoff;
+        self
+    }
+
+    /// Whether to randomize every backoff between half and all of its exponential value.
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.config.jitter = jitter;
+        self
+    }
+
+    /// Whether to also retry the requests rejected by the rate limit of the node.
+    pub fn retry_rate_limited(mut self, retry_rate_limited: bool) -> Self {
+        self.config.retry_rate_limited = retry_rate_limited;
+        self
+    }
+
+    /// Set the maximum number of calls sent in a single JSON-RPC batch.
+    pub fn max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.config.max_batch_size = max_batch_size;
+        self
+    }
+
+    /// Set the headers (e.g. an API key) sent on every request, to every endpoint.
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Authenticate every request with a fresh JWT signed with the given secret,
+    /// as required by the Engine API.
+    pub fn jwt(mut self, jwt_secret: [u8; 32]) -> Self {
+        let secret = JwtSecret::from_hex(hex::encode(jwt_secret)).expect("32-byte JWT secret");
+        self.jwt_secret = Some(secret);
+        self
+    }
+
+    /// Authenticate every request with a fresh JWT signed with the given secret, e.g.
+    /// as read from the `jwt.hex` file of the execution client. See [RpcClientBuilder::jwt].
+    pub fn jwt_secret(mut self, jwt_secret: JwtSecret) -> Self {
+        self.jwt_secret = Some(jwt_secret);
+        self
+    }
+
+    /// Set the backup endpoints to fail over to, in order of preference after the
+    /// primary one, on connection failures, timeouts and 5xx responses.
+    pub fn failover_urls(mut self, urls: Vec<Url>) -> Self {
+        self.failover_urls = urls;
+        self
+    }
+
+    /// Set how the endpoint to send requests to is picked when failing over.
+    /// Defaults to [FailoverPolicy::StickyPrimary].
+    pub fn failover_policy(mut self, policy: FailoverPolicy) -> Self {
+        self.failover_policy = policy;
+        self
+    }
+
+    /// Set the rate limit of the requests, including retries.
+    pub fn rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.config.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Fail calls fast after repeated transport failures, until the node answers again.
+    pub fn circuit_breaker(mut self, circuit_breaker: CircuitBreakerConfig) -> Self {
+        self.config.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// Set the maximum size of an HTTP response body in bytes, after decompression.
+    pub fn max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.config.max_response_bytes = Some(max_response_bytes);
+        self
+    }
+
+    /// Retry the queries of historical state on the given archive node, as with
+    /// [RpcClient::with_archive]. The archive node is queried with the same
+    /// [RpcClientConfig], but without the headers and JWT of the other endpoints.
+    pub fn archive_url<U: Into<Url>>(mut self, url: U) -> Self {
+        self.archive_url = Some(url.into());
+        self
+    }
+
+    /// Whether to gzip-compress request bodies and accept gzip-compressed responses.
+    pub fn gzip(mut self, gzip: bool) -> Self {
+        self.config.gzip = gzip;
+        self
+    }
+
+    /// Build the [RpcClient].
+    pub fn build(self) -> RpcClient {
+        let config = self.config;
+        let urls: Vec<_> = std::iter::once(self.url)
+            .chain(self.failover_urls)
+            .collect();
+        let is_local = urls.iter().all(guess_local_url);
+
+        let http_client = Client::builder()
+            .timeout(config.timeout)
+            .default_headers(self.headers)
+            .gzip(config.gzip)
+            .build()
+            .expect("Failed to build HTTP client");
+
+        let mut transports: Vec<_> = urls
+            .into_iter()
+            .map(|url| match &self.jwt_secret {
+                Some(secret) => JwtHttp::new(http_client.clone(), url, secret.clone())
+                    .with_gzip(config.gzip)
+                    .with_max_response_bytes(config.max_response_bytes)
+                    .boxed(),
+                None => http_transport(http_client.clone(), url, &config),
+            })
+            .collect();
+
+        let transport = if transports.len() == 1 {
+            transports.remove(0)
+        } else {
+            FailoverTransport::new(transports, self.failover_policy).boxed()
+        };
+
+        let client = RpcClient::from_transport(transport, None, is_local, config);
+        match self.archive_url {
+            Some(url) => client.with_archive(url),
+            None => client,
+        }
+    }
+}
+
+/// Options for reading the base fee through `eth_feeHistory`.
+#[derive(Debug, Clone, Copy)]
+pub struct BaseFeeOpts {
+    /// The number of blocks of fee history to request. Defaults to 1.
+    pub block_count: u64,
+    /// Whether to average the base fees of all the requested blocks, to smooth over
+    /// noisy single-block reads. Defaults to `false`.
+    pub average: bool,
+}
+
+impl Default for BaseFeeOpts {
+    fn default() -> Self {
+        Self {
+            block_count: 1,
+            average: false,
+        }
+    }
+}
+
+/// The health of an execution client endpoint, as returned by [RpcClient::health_check].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RpcHealth {
+    /// Whether the node is still syncing. A syncing node serves stale state.
+    pub is_syncing: bool,
+    /// The latest block number known to the node.
+    pub head: u64,
+    /// The sync progress, if the node is syncing.
+    pub sync_progress: Option<SyncProgress>,
+}
+
+/// The progress of a syncing execution client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncProgress {
+    /// The block the node is currently importing.
+    pub current_block: u64,
+    /// The highest block known to the node.
+    pub highest_block: u64,
+}
+
+/// The response of `eth_syncing`: `false` when synced, the sync progress otherwise.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SyncingResponse {
+    Syncing {
+        #[serde(rename = "currentBlock")]
+        current_block: U64,
+        #[serde(rename = "highestBlock")]
+        highest_block: U64,
+    },
+    NotSyncing(bool),
+}
+
+/// An execution client endpoint, parsed from a connection string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RpcEndpoint {
+    /// An `http://` or `https://` URL.
+    Http(Url),
+    /// A `ws://` or `wss://` URL.
+    Ws(Url),
+    /// The path to an IPC socket.
+    Ipc(PathBuf),
+}
+
+impl RpcEndpoint {
+    /// Parse an endpoint from the given connection string, dispatching on its URL scheme.
+    /// Strings that are not URLs are interpreted as IPC socket paths.
+    pub fn parse(s: &str) -> TransportResult<Self> {
+        let Ok(url) = Url::parse(s) else {
+            return Ok(Self::Ipc(PathBuf::from(s)));
+        };
+
+        match url.scheme() {
+            "http" | "https" => Ok(Self::Http(url)),
+            "ws" | "wss" => Ok(Self::Ws(url)),
+            "file" => url
+                .to_file_path()
+                .map(Self::Ipc)
+                .map_err(|_| TransportErrorKind::custom_str("invalid IPC path")),
+            scheme => Err(TransportErrorKind::custom_str(&format!(
+                "unsupported RPC scheme: {scheme}"
+            ))),
+        }
+    }
+}
+
+/// Error returned when a chunk of a batched request fails. It is wrapped in a
+/// custom [TransportErrorKind], and identifies the addresses of the calls in the chunk.
+#[derive(Debug, thiserror::Error)]
+#[error("batch request failed for addresses {addresses:?}: {source}")]
+pub struct BatchChunkError {
+    /// The addresses of the calls in the failed chunk.
+    pub addresses: Vec<Address>,
+    /// The error of the failed chunk.
+    #[source]
+    pub source: TransportError,
+}
+
+/// Error returned by [RpcClient::get_logs] when the provider rejects a log query
+/// because it matches too many logs or spans too many blocks. It is wrapped in a
+/// custom [TransportErrorKind]: the query should be split into narrower block ranges.
+#[derive(Debug, thiserror::Error)]
+#[error("log query exceeds the provider limits, narrow the block range: {source}")]
+pub struct LogQueryLimitError {
+    /// The error returned by the provider.
+    #[source]
+    pub source: TransportError,
+}
+
+/// The reason why a node rejected a transaction sent with [RpcClient::send_raw_transaction].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectionReason {
+    /// The fee is too low to enter the pool or to replace the pending transaction.
+    Underpriced,
+    /// The nonce was already used by a mined transaction.
+    NonceTooLow,
+    /// The nonce is too far ahead of the account nonce.
+    NonceTooHigh,
+    /// The sender can't pay for the gas and value of the transaction.
+    InsufficientFunds,
+    /// The transaction is already in the pool.
+    AlreadyKnown,
+}
+
+impl RejectionReason {
+    /// The error messages returned by execution clients for each reason, in lowercase.
+    const MESSAGES: &'static [(&'static str, Self)] = &[
+        ("underpriced", Self::Underpriced),
+        ("fee too low", Self::Underpriced),
+        ("nonce too low", Self::NonceTooLow),
+        ("nonce too high", Self::NonceTooHigh),
+        ("insufficient funds", Self::InsufficientFunds),
+        ("already known", Self::AlreadyKnown),
+        ("known transaction", Self::AlreadyKnown),
+        ("already imported", Self::AlreadyKnown),
+    ];
+
+    /// Returns the reason of the given `eth_sendRawTransaction` error, if known.
+    fn from_error(err: &TransportError) -> Option<Self> {
+        let RpcError::ErrorResp(payload) = err else {
+            return None;
+        };
+
+        let message = payload.message.to_lowercase();
+        Self::MESSAGES
+            .iter()
+            .find(|(signature, _)| message.contains(signature))
+            .map(|(_, reason)| *reason)
+    }
+}
+
+/// Error returned by [RpcClient::send_raw_transaction] when the node rejects the
+/// transaction for a known reason. It is wrapped in a custom [TransportErrorKind].
+#[derive(Debug, thiserror::Error)]
+#[error("transaction rejected ({reason:?}): {source}")]
+pub struct TransactionRejected {
+    /// Why the transaction was rejected.
+    pub reason: RejectionReason,
+    /// The error returned by the node.
+    #[source]
+    pub source: TransportError,
+}
+
+/// The error messages returned by execution clients and providers when a log query
+/// exceeds their limits, in lowercase:
+///
+/// - Geth and Infura: `query returned more than 10000 results`
+/// - Alchemy: `log response size exceeded`
+/// - Erigon and Reth: `query exceeds max results` and `exceed max(imum) block range`
+/// - QuickNode and Ankr: `block range is too (large|wide)`
+const LOG_QUERY_LIMIT_ERRORS: &[&str] = &[
+    "query returned more than",
+    "log response size exceeded",
+    "query exceeds max results",
+    "exceed max block range",
+    "exceed maximum block range",
+    "block range is too large",
+    "block range is too wide",
+];
+
+/// Returns `true` if the error means that the provider rejected a log query for
+/// exceeding its result or block range limits.
+pub(crate) fn is_log_query_limit_exceeded(err: &TransportError) -> bool {
+    let RpcError::ErrorResp(payload) = err else {
+        return false;
+    };
+
+    let message = payload.message.to_lowercase();
+    LOG_QUERY_LIMIT_ERRORS
+        .iter()
+        .any(|signature| message.contains(signature))
+}
+
+/// State overrides shared through an [Arc], serialized as the [StateOverride] itself.
+#[derive(Debug, Clone)]
+struct SharedStateOverride(Arc<StateOverride>);
+
+impl Serialize for SharedStateOverride {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+/// The options of `debug_traceCall`, serialized as [GethDebugTracingCallOptions] with
+/// the given shared state overrides.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SharedStateCallOptions {
+    #[serde(flatten)]
+    opts: GethDebugTracingCallOptions,
+    state_overrides: SharedStateOverride,
+}
+
+/// The overrides object of `trace_callMany`, passed as third parameter.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TraceCallManyOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state_overrides: Option<StateOverride>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_overrides: Option<BlockOverrides>,
+}
+
+/// The error messages returned by execution clients when the state of the requested
+/// block is not available anymore, in lowercase:
+///
+/// - Geth: `missing trie node`, `historical state ... is not available` and
+///   `required historical state unavailable`
+/// - Erigon: `old data not available due to pruning`
+/// - Reth: `state at block #... is pruned`
+/// - Nethermind: `no state available for block`
+/// - Besu: `world state unavailable`
+const STATE_UNAVAILABLE_ERRORS: &[&str] = &[
+    "missing trie node",
+    "historical state",
+    "old data not available due to pruning",
+    "is pruned",
+    "no state available",
+    "world state unavailable",
+];
+
+/// Returns `true` if the error means that the node doesn't have the state of the
+/// requested block anymore, so that the query should be retried on an archive node.
+pub fn is_state_unavailable(err: &TransportError) -> bool {
+    let RpcError::ErrorResp(payload) = err else {
+        return false;
+    };
+
+    let message = payload.message.to_lowercase();
+    STATE_UNAVAILABLE_ERRORS
+        .iter()
+        .any(|signature| message.contains(signature))
+}
+
+/// The JSON-RPC error code of calls to a method the node doesn't know about.
+const METHOD_NOT_FOUND_CODE: i64 = -32601;
+
+/// The error messages returned by execution clients for unsupported methods, in
+/// lowercase, for the ones that don't use the standard error code.
+const METHOD_UNSUPPORTED_ERRORS: &[&str] = &[
+    "method not found",
+    "does not exist/is not available",
+    "not supported",
+    "unsupported method",
+];
+
+/// Returns `true` if the error means that the node doesn't support the called method.
+fn is_method_unsupported(err: &TransportError) -> bool {
+    let RpcError::ErrorResp(payload) = err else {
+        return false;
+    };
+
+    let message = payload.message.to_lowercase();
+    payload.code == METHOD_NOT_FOUND_CODE
+        || METHOD_UNSUPPORTED_ERRORS
+            .iter()
+            .any(|signature| message.contains(signature))
+}
+
+/// The subset of block header fields needed to compute the blob base fee.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlobGasHeader {
+    excess_blob_gas: Option<U64>,
+}
+
+/// The transport service stack of the [RpcClient].
+#[cfg(not(feature = "metrics"))]
+type RpcService = CircuitBreakerService<RetryService<RateLimitService<BoxTransport>>>;
+
+/// The transport service stack of the [RpcClient], instrumented with metrics.
+#[cfg(feature = "metrics")]
+type RpcService = super::metrics::MetricsService<
+    CircuitBreakerService<RetryService<RateLimitService<BoxTransport>>>,
+>;
+
+/// A JSON-RPC client that supports batching, over HTTP, WebSocket or IPC.
 /// Implements all methods that are relevant to Bolt state.
 #[derive(Clone, Debug)]
-pub struct RpcClient(alloy::RpcClient<Http<Client>>);
+pub struct RpcClient {
+    client: alloy::RpcClient<RpcService>,
+    /// The pubsub frontend, used to manage subscriptions. Only set for WS and IPC endpoints.
+    pubsub: Option<PubSubFrontend>,
+    /// The configuration of the transport, also used for the archive node.
+    config: RpcClientConfig,
+    /// The maximum number of calls sent in a single batch.
+    max_batch_size: usize,
+    /// The chain ID of the endpoint, once fetched. Shared between clones.
+    chain_id: Arc<OnceLock<u64>>,
+    /// The archive node to retry historical state queries on, if any.
+    archive: Option<Arc<RpcClient>>,
+}
 
 impl RpcClient {
-    /// Create a new `RpcClient` with the given URL.
+    /// Create a new HTTP `RpcClient` with the given URL and the default [RpcClientConfig].
     pub fn new<U: Into<Url>>(url: U) -> Self {
-        let client = ClientBuilder::default().http(url.into());
+        Self::builder(url).build()
+    }
 
-        Self(client)
+    /// Create a new [RpcClientBuilder] for the given endpoint, to configure the client
+    /// with multiple options at once.
+    pub fn builder<U: Into<Url>>(url: U) -> RpcClientBuilder {
+        RpcClientBuilder::new(url)
     }
 
-    /// Get the basefee of the latest block.
-    pub async fn get_basefee(&self, block_number: Option<u64>) -> TransportResult<u128> {
-        let tag = block_number.map_or(BlockNumberOrTag::Latest, BlockNumberOrTag::Number);
+    /// Create a new HTTP `RpcClient` with the given URL and transport configuration.
+    pub fn new_with_config<U: Into<Url>>(url: U, config: RpcClientConfig) -> Self {
+        Self::builder(url).config(config).build()
+    }
 
-        let fee_history: FeeHistory = self
-            .0
-            .request("eth_feeHistory", (U64::from(1), tag, &[] as &[f64]))
-            .await?;
+    /// Create a new HTTP `RpcClient` that sends the given headers (e.g. an API key)
+    /// on every request, including batches.
+    pub fn new_with_headers<U: Into<Url>>(url: U, headers: HeaderMap) -> Self {
+        Self::new_with_headers_and_config(url, headers, RpcClientConfig::default())
+    }
 
-        Ok(fee_history.latest_block_base_fee().unwrap())
+    /// Create a new HTTP `RpcClient` with the given headers and transport configuration.
+    pub fn new_with_headers_and_config<U: Into<Url>>(
+        url: U,
+        headers: HeaderMap,
+        config: RpcClientConfig,
+    ) -> Self {
+        Self::builder(url).headers(headers).config(config).build()
     }
 
-    /// Get the latest block number
-    pub async fn get_head(&self) -> TransportResult<u64> {
-        let result: U64 = self.0.request("eth_blockNumber", ()).await?;
+    /// Create a new HTTP `RpcClient` that sends its requests with the given, fully
+    /// configured [Client], e.g. with custom root certificates, client certificates for
+    /// mTLS, proxies, timeouts or connection pool settings.
+    pub fn from_reqwest<U: Into<Url>>(client: Client, url: U) -> Self {
+        Self::from_reqwest_with_config(client, url, RpcClientConfig::default())
+    }
 
-        Ok(result.to())
+    /// Create a new HTTP `RpcClient` that sends its requests with the given [Client],
+    /// with the given transport configuration.
+    ///
+    /// The [RpcClientConfig::timeout] is ignored: the timeouts are the ones of the given
+    /// client. If [RpcClientConfig::gzip] is set, the client should have gzip enabled to
+    /// decompress the responses.
+    pub fn from_reqwest_with_config<U: Into<Url>>(
+        client: Client,
+        url: U,
+        config: RpcClientConfig,
+    ) -> Self {
+        let url = url.into();
+        let is_local = guess_local_url(&url);
+
+        let transport = http_transport(client, url, &config);
+        Self::from_transport(transport, None, is_local, config)
     }
 
-    /// Gets the latest account state for the given address
-    pub async fn get_account_state(
-        &self,
-        address: &Address,
-        block_number: Option<u64>,
-    ) -> TransportResult<AccountState> {
-        let mut batch = self.0.new_batch();
+    /// Create a new HTTP `RpcClient` over multiple endpoints, in order of preference.
+    /// Requests (including batches) fail over to the next endpoint on connection failures,
+    /// timeouts and 5xx responses, according to the given [FailoverPolicy].
+    ///
+    /// # Panics
+    ///
+    /// Panics if no URL is given.
+    pub fn new_failover(urls: Vec<Url>, policy: FailoverPolicy) -> Self {
+        let mut urls = urls.into_iter();
+        let primary = urls.next().expect("At least one URL");
 
-        let tag = block_number.map_or(BlockNumberOrTag::Latest, BlockNumberOrTag::Number);
+        Self::builder(primary)
+            .failover_urls(urls.collect())
+            .failover_policy(policy)
+            .build()
+    }
 
-        let balance = batch
-            .add_call("eth_getBalance", &(address, tag))
-            .expect("Correct parameters");
+    /// Create a new HTTP `RpcClient` for an authenticated endpoint (e.g. the Engine API),
+    /// signing a fresh JWT with the given secret for every request.
+    pub fn new_with_jwt<U: Into<Url>>(url: U, jwt_secret: [u8; 32]) -> Self {
+        Self::builder(url).jwt(jwt_secret).build()
+    }
 
-        let tx_count = batch
-            .add_call("eth_getTransactionCount", &(address, tag))
-            .expect("Correct parameters");
+    /// Connect to the given endpoint with the default [RpcClientConfig]. The transport
+    /// is chosen from the URL scheme (`http`, `https`, `ws`, `wss`), or IPC for file paths.
+    pub async fn connect(endpoint: &str) -> TransportResult<Self> {
+        Self::connect_with_config(endpoint, RpcClientConfig::default()).await
+    }
 
-        // After the batch is complete, we can get the results.
-        // Note that requests may error separately!
-        batch.send().await?;
+    /// Connect to the given endpoint with the given transport configuration.
+    /// The request timeout only applies to HTTP endpoints.
+    pub async fn connect_with_config(
+        endpoint: &str,
+        config: RpcClientConfig,
+    ) -> TransportResult<Self> {
+        match RpcEndpoint::parse(endpoint)? {
+            RpcEndpoint::Http(url) => Ok(Self::new_with_config(url, config)),
+            RpcEndpoint::Ws(url) => {
+                let is_local = guess_local_url(&url);
+                let pubsub = WsConnect::new(url.to_string()).into_service().await?;
 
-        let tx_count: U64 = tx_count.await?;
-        let balance: U256 = balance.await?;
+                Ok(Self::from_transport(
+                    pubsub.clone().boxed(),
+                    Some(pubsub),
+                    is_local,
+                    config,
+                ))
+            }
+            RpcEndpoint::Ipc(path) => {
+                let pubsub = IpcConnect::new(path).into_service().await?;
 
-        Ok(AccountState {
-            balance,
-            transaction_count: tx_count.to(),
-        })
+                Ok(Self::from_transport(
+                    pubsub.clone().boxed(),
+                    Some(pubsub),
+                    true,
+                    config,
+                ))
+            }
+        }
     }
 
-    /// Get the block with the given number. If `None`, the latest block is returned.
-    pub async fn get_block(&self, block_number: Option<u64>, full: bool) -> TransportResult<Block> {
-        let tag = block_number.map_or(BlockNumberOrTag::Latest, BlockNumberOrTag::Number);
+    fn from_transport(
+        transport: BoxTransport,
+        pubsub: Option<PubSubFrontend>,
+        is_local: bool,
+        config: RpcClientConfig,
+    ) -> Self {
+        let builder = ClientBuilder::default();
+
+        // Outermost, so that the recorded latency includes the retries
+        #[cfg(feature = "metrics")]
+        let builder = builder.layer(super::metrics::MetricsLayer);
 
-        self.0.request("eth_getBlockByNumber", (tag, full)).await
+        let client = builder
+            // Outside the retries, so that a call failing after all its retries counts
+            // as a single failure, and fast-failed calls are not retried
+            .layer(CircuitBreakerLayer::new(config.circuit_breaker))
+            .layer(
+                RetryLayer::new(config.max_retries, config.backoff)
+                    .with_jitter(config.jitter)
+                    .with_rate_limited_retries(config.retry_rate_limited),
+            )
+            // Innermost, so that every retry waits for the rate limit
+            .layer(RateLimitLayer::new(config.rate_limit))
+            .transport(transport, is_local);
+
+        Self {
+            client,
+            pubsub,
+            config,
+            max_batch_size: config.max_batch_size.max(1),
+            chain_id: Default::default(),
+            archive: None,
+        }
     }
 
-    /// Returns the account and storage values of the specified account including the Merkle-proof.
-    /// If the block number is `None`, the latest block is used.
-    pub async fn get_proof(
+    /// Retry the queries of historical state on the given archive node when this
+    /// endpoint doesn't have the state anymore (e.g. a full node with pruned state).
+    ///
+    /// This applies to `eth_getProof` (including [RpcClient::get_proof_batched]),
+    /// `eth_getCode`, `eth_getStorageAt`, `eth_call`, `eth_createAccessList`,
+    /// `debug_traceCall` and `trace_callMany`. The fallback is triggered by the "missing
+    /// trie node" and "state pruned" errors of the main execution clients.
+    ///
+    /// The archive node is queried over HTTP with the same [RpcClientConfig] as this client.
+    pub fn with_archive<U: Into<Url>>(mut self, url: U) -> Self {
+        self.archive = Some(Arc::new(RpcClient::new_with_config(url, self.config)));
+        self
+    }
+
+    /// Splits the given items into the chunks sent as a single batch, so that each batch
+    /// holds at most [RpcClientConfig::max_batch_size] calls with `calls_per_item` calls
+    /// per item. A chunk holds at least one item.
+    fn batch_chunks<'a, T>(&self, items: &'a [T], calls_per_item: usize) -> Chunks<'a, T> {
+        items.chunks((self.max_batch_size / calls_per_item).max(1))
+    }
+
+    /// Send a request that depends on the state at a given block, retrying it on
+    /// the archive node if this endpoint doesn't have that state.
+    async fn state_request<P: RpcParam, R: RpcReturn>(
         &self,
-        address: Address,
-        storage_keys: Vec<B256>,
-        block_number: Option<u64>,
-    ) -> TransportResult<EIP1186AccountProofResponse> {
-        let tag = block_number.map_or(BlockNumberOrTag::Latest, BlockNumberOrTag::Number);
-        let params = (address, storage_keys, tag);
+        method: &'static str,
+        params: P,
+    ) -> TransportResult<R> {
+        match (
+            self.client.request(method, params.clone()).await,
+            &self.archive,
+        ) {
+            (Err(err), Some(archive)) if is_state_unavailable(&err) => {
+                tracing::debug!(
+                    ?err,
+                    method,
+                    "Historical state unavailable, retrying on archive node"
+                );
+                archive.client.request(method, params).await
+            }
+            (res, _) => res,
+        }
+    }
 
-        self.0.request("eth_getProof", params).await
+    /// Subscribe to new blocks with `eth_subscribe("newHeads")`. Only available on
+    /// WS and IPC endpoints. The yielded blocks only contain the header fields.
+    ///
+    /// The underlying connection is re-established (and the subscription renewed) by the
+    /// transport on disconnects. If that fails, the stream yields an error and then ends.
+    pub async fn subscribe_new_heads(
+        &self,
+    ) -> TransportResult<impl Stream<Item = TransportResult<Block>> + 'static> {
+        self.subscribe("newHeads").await
     }
 
-    /// Perform multiple `eth_getProof` calls in a single batch.
-    pub async fn get_proof_batched(
+    /// Subscribe to the hashes of the transactions entering the mempool of the node with
+    /// `eth_subscribe("newPendingTransactions")`. Only available on WS and IPC endpoints.
+    ///
+    /// Disconnects are handled as for [RpcClient::subscribe_new_heads].
+    pub async fn subscribe_pending_transactions(
         &self,
-        opts: Vec<(Address, Vec<B256>, BlockNumberOrTag)>,
-    ) -> TransportResult<Vec<EIP1186AccountProofResponse>> {
-        let mut batch = self.0.new_batch();
+    ) -> TransportResult<impl Stream<Item = TransportResult<B256>> + 'static> {
+        self.subscribe("newPendingTransactions").await
+    }
 
-        let mut proofs: Vec<Waiter<EIP1186AccountProofResponse>> = Vec::new();
+    /// Subscribe to the given `eth_subscribe` kind, deserializing every notification.
+    /// Lagging behind the notifications skips them with a warning.
+    async fn subscribe<T: DeserializeOwned + 'static>(
+        &self,
+        kind: &'static str,
+    ) -> TransportResult<impl Stream<Item = TransportResult<T>> + 'static> {
+        let pubsub = self
+            .pubsub
+            .as_ref()
+            .ok_or_else(TransportErrorKind::pubsub_unavailable)?;
 
-        for params in opts {
-            proofs.push(
-                batch
-                    .add_call("eth_getProof", &params)
-                    .expect("Correct parameters"),
-            );
-        }
+        let id: U256 = self.client.request("eth_subscribe", (kind,)).await?;
+        let subscription = pubsub.get_subscription(id).await?;
 
-        batch.send().await?;
+        Ok(stream::unfold(
+            Some(subscription),
+            move |subscription| async move {
+                let mut subscription = subscription?;
 
-        // Important: join_all will preserve the order of the proofs
-        join_all(proofs)
-            .await
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>()
+                loop {
+                    match subscription.recv().await {
+                        Ok(raw) => {
+                            let item =
+                                serde_json::from_str(raw.get()).map_err(TransportErrorKind::custom);
+                            return Some((item, Some(subscription)));
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::warn!(skipped, kind, "Subscription lagged behind");
+                        }
+                        Err(RecvError::Closed) => {
+                            return Some((Err(TransportErrorKind::backend_gone()), None));
+                        }
+                    }
+                }
+            },
+        ))
     }
 
-    /// Performs multiple call traces on top of the same block. i.e. transaction n will be executed
-    /// on top of a pending block with all n-1 transactions applied (traced) first.
+    /// Get the basefee of the given block or block tag, or of the latest block if `None`.
     ///
-    /// Note: Allows tracing dependent transactions, hence all transactions are traced in sequence
-    pub async fn trace_call_many(
+    /// Returns an error if the node doesn't return any base fee in the fee history.
+    pub async fn get_basefee(&self, block_number: impl IntoBlockTag) -> TransportResult<u128> {
+        self.get_basefee_with_opts(block_number, BaseFeeOpts::default())
+            .await
+    }
+
+    /// Get the basefee of the latest block, reading a window of `opts.block_count` blocks
+    /// of fee history ending at the given block. If `opts.average` is set, the mean base fee
+    /// of the window is returned instead of the base fee of the last block.
+    pub async fn get_basefee_with_opts(
         &self,
-        calls: Vec<(TransactionRequest, HashSet<TraceType>)>,
-        block_number: Option<u64>,
-    ) -> TransportResult<Vec<TraceResults>> {
-        let tag = block_number.map_or(BlockNumberOrTag::Latest, BlockNumberOrTag::Number);
-        let params = (calls, tag);
+        block_number: impl IntoBlockTag,
+        opts: BaseFeeOpts,
+    ) -> TransportResult<u128> {
+        let fee_history = self
+            .get_fee_history(opts.block_count.max(1), block_number, &[])
+            .await?;
+
+        // The last element of `base_fee_per_gas` is the base fee of the next block,
+        // which is not part of the requested window.
+        let window = fee_history
+            .base_fee_per_gas
+            .split_last()
+            .map(|(_, window)| window)
+            .unwrap_or_default();
+
+        if opts.average && !window.is_empty() {
+            return Ok(window.iter().sum::<u128>() / window.len() as u128);
+        }
 
-        self.0.request("trace_callMany", params).await
+        fee_history
+            .latest_block_base_fee()
+            .ok_or_else(|| TransportErrorKind::custom_str("missing base fee in fee history"))
     }
 
-    /// Performs the `debug_traceCall` JSON-RPC method.
-    pub async fn debug_trace_call(
+    /// Get the fee history of the `block_count` blocks ending at `newest_block` (or the
+    /// latest block if `None`) with `eth_feeHistory`.
+    ///
+    /// For every block, the reward matrix holds the priority fees paid at each of the given
+    /// percentiles of gas used, which must be monotonically increasing between 0 and 100.
+    pub async fn get_fee_history(
         &self,
-        tx: TransactionRequest,
-        block_number: Option<u64>,
-        opts: Option<GethDebugTracingCallOptions>,
-    ) -> TransportResult<GethTrace> {
-        let tag = block_number.map_or(BlockNumberOrTag::Latest, BlockNumberOrTag::Number);
-        let params = (tx, tag, opts);
+        block_count: u64,
+        newest_block: impl IntoBlockTag,
+        reward_percentiles: &[f64],
+    ) -> TransportResult<FeeHistory> {
+        let tag = newest_block.into_block_tag();
 
-        self.0.request("debug_traceCall", params).await
+        self.client
+            .request(
+                "eth_feeHistory",
+                (U64::from(block_count), tag, reward_percentiles),
+            )
+            .await
     }
-}
 
-impl Deref for RpcClient {
-    type Target = alloy::RpcClient<Http<Client>>;
+    /// Get the blob base fee of the given block, or of the latest block if `None`.
+    ///
+    /// The fee is read from the `baseFeePerBlobGas` field of `eth_feeHistory`. If the node
+    /// doesn't return it, it is computed from the excess blob gas of the block header instead.
+    /// Returns an error if the block predates Cancun.
+    pub async fn get_blob_basefee(&self, block_number: impl IntoBlockTag) -> TransportResult<u128> {
+        let tag = block_number.into_block_tag();
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+        let fee_history = self.get_fee_history(1, tag, &[]).await?;
+
+        // As with `base_fee_per_gas`, the last element is the blob base fee of the next block.
+        // Pre-Cancun blocks are reported with a blob base fee of zero.
+        if let Some(blob_basefee) = fee_history.base_fee_per_blob_gas.iter().rev().nth(1) {
+            if *blob_basefee == 0 {
+                return Err(pre_cancun_error());
+            }
+
+            return Ok(*blob_basefee);
+        }
+
+        let header: Option<BlobGasHeader> = self
+            .client
+            .request("eth_getBlockByNumber", (tag, false))
+            .await?;
+        let header = header.ok_or_else(|| TransportErrorKind::custom_str("block not found"))?;
+
+        let excess_blob_gas = header.excess_blob_gas.ok_or_else(pre_cancun_error)?;
+
+        Ok(calc_blob_gasprice(excess_blob_gas.to()))
     }
-}
 
-impl DerefMut for RpcClient {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+    /// Get the chain ID of the endpoint with `eth_chainId`. The result is cached,
+    /// so only the first call hits the network.
+    pub async fn get_chain_id(&self) -> TransportResult<u64> {
+        if let Some(chain_id) = self.chain_id.get() {
+            return Ok(*chain_id);
+        }
+
+        let chain_id: U64 = self.client.request("eth_chainId", ()).await?;
+
+        Ok(*self.chain_id.get_or_init(|| chain_id.to()))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::str::FromStr;
+    /// Returns the chain ID of the endpoint if it was already fetched.
+    pub fn cached_chain_id(&self) -> Option<u64> {
+        self.chain_id.get().copied()
+    }
 
-    use alloy_consensus::constants::ETH_TO_WEI;
-    use alloy_primitives::{uint, Uint};
-    use alloy_rpc_types::EIP1186AccountProofResponse;
-    use reth_primitives::B256;
+    /// Get the latest block number
+    pub async fn get_head(&self) -> TransportResult<u64> {
+        let result: U64 = self.client.request("eth_blockNumber", ()).await?;
 
-    use crate::test_util::launch_anvil;
+        Ok(result.to())
+    }
 
-    use super::*;
+    /// Check that the endpoint is reachable, and whether it is synced, with `eth_syncing`
+    /// and `eth_blockNumber` in a single batch.
+    ///
+    /// Returns an error if the endpoint is unreachable, and `Ok` with `is_syncing: true`
+    /// if it is reachable but still syncing.
+    pub async fn health_check(&self) -> TransportResult<RpcHealth> {
+        let mut batch = self.client.new_batch();
 
-    #[tokio::test]
-    async fn test_rpc_client() {
-        let anvil = launch_anvil();
-        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
-        let client = RpcClient::new(anvil_url);
+        let syncing = batch
+            .add_call("eth_syncing", &())
+            .expect("Correct parameters");
+        let head = batch
+            .add_call("eth_blockNumber", &())
+            .expect("Correct parameters");
 
-        let addr = anvil.addresses().first().unwrap();
+        batch.send().await?;
 
-        let account_state = client.get_account_state(addr, None).await.unwrap();
+        let syncing: SyncingResponse = syncing.await?;
+        let head: U64 = head.await?;
 
-        // Accounts in Anvil start with 10_000 ETH
+        let sync_progress = match syncing {
+            SyncingResponse::Syncing {
+                current_block,
+                highest_block,
+            } => Some(SyncProgress {
+                current_block: current_block.to(),
+                highest_block: highest_block.to(),
+            }),
+            // Some clients answer `true` without details while syncing
+            SyncingResponse::NotSyncing(syncing) => syncing.then_some(SyncProgress {
+                current_block: head.to(),
+                highest_block: head.to(),
+            }),
+        };
+
+        Ok(RpcHealth {
+            is_syncing: sync_progress.is_some(),
+            head: head.to(),
+            sync_progress,
+        })
+    }
+
+    /// Get the gas estimate of the given transaction with `eth_estimateGas`.
+    /// If the block number is `None`, the latest block is used.
+    pub async fn estimate_gas(
+        &self,
+        tx: TransactionRequest,
+        block_number: impl IntoBlockTag,
+    ) -> TransportResult<u64> {
+        let tag = block_number.into_block_tag();
+        let result: U64 = self.client.request("eth_estimateGas", (tx, tag)).await?;
+
+        Ok(result.to())
+    }
+
+    /// Get the suggested priority fee per gas with `eth_maxPriorityFeePerGas`.
+    pub async fn get_max_priority_fee(&self) -> TransportResult<u128> {
+        let result: U128 = self.client.request("eth_maxPriorityFeePerGas", ()).await?;
+
+        Ok(result.to())
+    }
+
+    /// Create a new batch of account state queries, such as balances, nonces, code
+    /// and storage slots, whose results are returned in the order they were queued.
+    pub fn new_batch(&self) -> MultiCall<'_> {
+        MultiCall::new(self, self.max_batch_size)
+    }
+
+    /// Gets the account state for the given address at the given block or block tag,
+    /// or at the latest block if `None`.
+    pub async fn get_account_state(
+        &self,
+        address: &Address,
+        block_number: impl IntoBlockTag,
+    ) -> TransportResult<AccountState> {
+        let mut batch = self.client.new_batch();
+
+        let tag = block_number.into_block_tag();
+
+        let balance = batch
+            .add_call("eth_getBalance", &(address, tag))
+            .expect("Correct parameters");
+
+        let tx_count = batch
+            .add_call("eth_getTransactionCount", &(address, tag))
+            .expect("Correct parameters");
+
+        // After the batch is complete, we can get the results.
+        // Note that requests may error separately!
+        batch.send().await?;
+
+        let tx_count: U64 = tx_count.await?;
+        let balance: U256 = balance.await?;
+
+        Ok(AccountState {
+            balance,
+            transaction_count: tx_count.to(),
+            code_hash: None,
+            storage_root: None,
+        })
+    }
+
+    /// Gets the latest account state for the given address, including its code hash
+    /// and storage root, which are sourced from `eth_getProof` in the same batch.
+    pub async fn get_account_state_full(
+        &self,
+        address: &Address,
+        block_number: impl IntoBlockTag,
+    ) -> TransportResult<AccountState> {
+        let mut batch = self.client.new_batch();
+
+        let tag = block_number.into_block_tag();
+
+        let balance = batch
+            .add_call("eth_getBalance", &(address, tag))
+            .expect("Correct parameters");
+
+        let tx_count = batch
+            .add_call("eth_getTransactionCount", &(address, tag))
+            .expect("Correct parameters");
+
+        let proof = batch
+            .add_call("eth_getProof", &(address, Vec::<B256>::new(), tag))
+            .expect("Correct parameters");
+
+        batch.send().await?;
+
+        let tx_count: U64 = tx_count.await?;
+        let balance: U256 = balance.await?;
+        let proof: EIP1186AccountProofResponse = proof.await?;
+
+        Ok(AccountState {
+            balance,
+            transaction_count: tx_count.to(),
+            code_hash: Some(proof.code_hash),
+            storage_root: Some(proof.storage_hash),
+        })
+    }
+
+    /// Gets the account states of multiple addresses in batches of at most
+    /// [RpcClientConfig::max_batch_size] calls, two per address. Duplicate addresses are
+    /// only fetched once. If the block number is `None`, the latest block is used.
+    ///
+    /// If any of the calls fails, the whole request fails: callers validating a bundle need
+    /// the state of every sender, and a partial result would silently skip some of them.
+    ///
+    /// Each result is resolved from the response carrying the id of its call, so a node
+    /// reordering the batch responses can't swap the states of two addresses.
+    pub async fn get_account_states(
+        &self,
+        addresses: &[Address],
+        block_number: impl IntoBlockTag,
+    ) -> TransportResult<HashMap<Address, AccountState>> {
+        let addresses: Vec<_> = addresses
+            .iter()
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+
+        if addresses.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let tag = block_number.into_block_tag();
+        let mut states = HashMap::with_capacity(addresses.len());
+
+        for chunk in self.batch_chunks(&addresses, 2) {
+            let mut batch = self.client.new_batch();
+
+            let mut balances: Vec<Waiter<U256>> = Vec::with_capacity(chunk.len());
+            let mut tx_counts: Vec<Waiter<U64>> = Vec::with_capacity(chunk.len());
+
+            for address in chunk {
+                balances.push(
+                    batch
+                        .add_call("eth_getBalance", &(address, tag))
+                        .expect("Correct parameters"),
+                );
+                tx_counts.push(
+                    batch
+                        .add_call("eth_getTransactionCount", &(address, tag))
+                        .expect("Correct parameters"),
+                );
+            }
+
+            batch.send().await?;
+
+            // Important: join_all will preserve the order of the results
+            let (balances, tx_counts) = tokio::join!(join_all(balances), join_all(tx_counts));
+
+            for (address, (balance, tx_count)) in
+                chunk.iter().zip(balances.into_iter().zip(tx_counts))
+            {
+                let state = AccountState {
+                    balance: balance?,
+                    transaction_count: tx_count?.to(),
+                    code_hash: None,
+                    storage_root: None,
+                };
+                states.insert(*address, state);
+            }
+        }
+
+        Ok(states)
+    }
+
+    /// Gets the balance of the given address at each of the given blocks, with one
+    /// `eth_getBalance` call per block sent in batches of at most
+    /// [RpcClientConfig::max_batch_size] calls. The results are paired with their
+    /// block number, in the order of the given blocks.
+    pub async fn get_balance_history(
+        &self,
+        address: Address,
+        blocks: &[u64],
+    ) -> TransportResult<Vec<(u64, U256)>> {
+        let mut balances = Vec::with_capacity(blocks.len());
+
+        for chunk in self.batch_chunks(blocks, 1) {
+            let mut batch = self.client.new_batch();
+
+            let mut waiters: Vec<Waiter<U256>> = Vec::with_capacity(chunk.len());
+            for block in chunk {
+                waiters.push(
+                    batch
+                        .add_call(
+                            "eth_getBalance",
+                            &(address, BlockNumberOrTag::Number(*block)),
+                        )
+                        .expect("Correct parameters"),
+                );
+            }
+
+            batch.send().await?;
+
+            // Important: join_all will preserve the order of the balances
+            for (block, balance) in chunk.iter().zip(join_all(waiters).await) {
+                balances.push((*block, balance?));
+            }
+        }
+
+        Ok(balances)
+    }
+
+    /// Returns the nonce of the given address including its pending transactions, i.e.
+    /// the nonce of its next transaction, using the `pending` block tag.
+    pub async fn get_pending_nonce(&self, address: Address) -> TransportResult<u64> {
+        let nonce: U64 = self
+            .client
+            .request(
+                "eth_getTransactionCount",
+                (address, BlockNumberOrTag::Pending),
+            )
+            .await?;
+
+        Ok(nonce.to())
+    }
+
+    /// Sends the given signed and EIP-2718 encoded transaction to the node, and returns
+    /// its hash.
+    ///
+    /// If the node rejects the transaction for a known reason (e.g. underpriced or nonce
+    /// too low), a [TransactionRejected] error is returned with the reason.
+    ///
+    /// Transient failures (e.g. timeouts) are not retried, as the node may have added the
+    /// transaction to its pool anyway: the retry would then be rejected as already known.
+    pub async fn send_raw_transaction(&self, raw: Bytes) -> TransportResult<B256> {
+        self.client
+            .request("eth_sendRawTransaction", (raw,))
+            .await
+            .map_err(|err| match RejectionReason::from_error(&err) {
+                Some(reason) => TransportErrorKind::custom(TransactionRejected {
+                    reason,
+                    source: err,
+                }),
+                None => err,
+            })
+    }
+
+    /// Get the block with the given number or tag, e.g. [BlockNumberOrTag::Finalized] for
+    /// reorg-resistant decisions. If `None`, the latest block is returned.
+    pub async fn get_block(
+        &self,
+        block_number: impl IntoBlockTag,
+        full: bool,
+    ) -> TransportResult<Block> {
+        let tag = block_number.into_block_tag();
+
+        self.client
+            .request("eth_getBlockByNumber", (tag, full))
+            .await
+    }
+
+    /// Get the block with the given hash, or `None` if it is unknown.
+    pub async fn get_block_by_hash(
+        &self,
+        hash: B256,
+        full: bool,
+    ) -> TransportResult<Option<Block>> {
+        self.client
+            .request("eth_getBlockByHash", (hash, full))
+            .await
+    }
+
+    /// Get the header of the block with the given number or tag, without its transactions.
+    /// If `None`, the header of the latest block is returned.
+    pub async fn get_header(&self, block_number: impl IntoBlockTag) -> TransportResult<Header> {
+        self.get_block(block_number, false)
+            .await
+            .map(|block| block.header)
+    }
+
+    /// Returns the receipt of the transaction with the given hash,
+    /// or `None` if the transaction is unknown or not mined yet.
+    pub async fn get_transaction_receipt(
+        &self,
+        hash: B256,
+    ) -> TransportResult<Option<TransactionReceipt>> {
+        self.client
+            .request("eth_getTransactionReceipt", (hash,))
+            .await
+    }
+
+    /// Returns the transaction with the given hash, or `None` if it is unknown.
+    pub async fn get_transaction_by_hash(
+        &self,
+        hash: B256,
+    ) -> TransportResult<Option<Transaction>> {
+        self.client
+            .request("eth_getTransactionByHash", (hash,))
+            .await
+    }
+
+    /// Returns the logs matching the given filter, e.g. the events of a contract
+    /// with the given topics in a block range.
+    ///
+    /// If the provider rejects the query for matching too many logs or spanning too
+    /// many blocks, a [LogQueryLimitError] is returned, so that the caller can retry
+    /// with a narrower block range.
+    pub async fn get_logs(&self, filter: Filter) -> TransportResult<Vec<Log>> {
+        self.client
+            .request("eth_getLogs", (filter,))
+            .await
+            .map_err(|err| {
+                if is_log_query_limit_exceeded(&err) {
+                    TransportErrorKind::custom(LogQueryLimitError { source: err })
+                } else {
+                    err
+                }
+            })
+    }
+
+    /// Perform multiple `eth_getTransactionReceipt` calls in batches of at most
+    /// [RpcClientConfig::max_batch_size] calls. The order of the results matches the
+    /// order of the given hashes, with `None` for the transactions that are not mined.
+    pub async fn get_receipts_batched(
+        &self,
+        hashes: &[B256],
+    ) -> TransportResult<Vec<Option<TransactionReceipt>>> {
+        let mut receipts = Vec::with_capacity(hashes.len());
+
+        for chunk in self.batch_chunks(hashes, 1) {
+            let mut batch = self.client.new_batch();
+
+            let mut waiters: Vec<Waiter<Option<TransactionReceipt>>> =
+                Vec::with_capacity(chunk.len());
+
+            for hash in chunk {
+                waiters.push(
+                    batch
+                        .add_call("eth_getTransactionReceipt", &(hash,))
+                        .expect("Correct parameters"),
+                );
+            }
+
+            batch.send().await?;
+
+            // Important: join_all will preserve the order of the receipts
+            for receipt in join_all(waiters).await {
+                receipts.push(receipt?);
+            }
+        }
+
+        Ok(receipts)
+    }
+
+    /// Returns the receipts of all the transactions of the given block, in block order.
+    /// If the block number is `None`, the latest block is used.
+    ///
+    /// Uses `eth_getBlockReceipts`, and falls back to batched `eth_getTransactionReceipt`
+    /// calls over the transactions of the block on nodes that don't support it.
+    pub async fn get_block_receipts(
+        &self,
+        block_number: impl IntoBlockTag,
+    ) -> TransportResult<Vec<TransactionReceipt>> {
+        let tag = block_number.into_block_tag();
+
+        match self.client.request("eth_getBlockReceipts", (tag,)).await {
+            Err(err) if is_method_unsupported(&err) => {
+                tracing::debug!(
+                    ?err,
+                    "eth_getBlockReceipts unsupported, fetching receipts one by one"
+                );
+            }
+            res => return res,
+        }
+
+        let block = self.get_block(tag, false).await?;
+        let hashes = block.transactions.hashes().copied().collect::<Vec<_>>();
+
+        self.get_receipts_batched(&hashes)
+            .await?
+            .into_iter()
+            .zip(hashes)
+            .map(|(receipt, hash)| {
+                receipt.ok_or_else(|| {
+                    TransportErrorKind::custom_str(&format!(
+                        "missing receipt of transaction {hash}"
+                    ))
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the account and storage values of the specified account including the Merkle-proof.
+    /// If the block number is `None`, the latest block is used.
+    pub async fn get_proof(
+        &self,
+        address: Address,
+        storage_keys: Vec<B256>,
+        block_number: impl IntoBlockTag,
+    ) -> TransportResult<EIP1186AccountProofResponse> {
+        let tag = block_number.into_block_tag();
+        let params = (address, storage_keys, tag);
+
+        self.state_request("eth_getProof", params).await
+    }
+
+    /// Perform multiple `eth_getProof` calls in batches of at most
+    /// [RpcClientConfig::max_batch_size] calls, sent sequentially.
+    ///
+    /// The order of the results matches the order of the given options. If a chunk fails,
+    /// a [BatchChunkError] with the addresses of that chunk is returned.
+    ///
+    /// The responses of a batch are matched to their calls by JSON-RPC id, not by
+    /// position, so the order holds even if the node answers the batch out of order.
+    pub async fn get_proof_batched(
+        &self,
+        opts: Vec<(Address, Vec<B256>, BlockNumberOrTag)>,
+    ) -> TransportResult<Vec<EIP1186AccountProofResponse>> {
+        let mut proofs = Vec::with_capacity(opts.len());
+
+        for chunk in self.batch_chunks(&opts, 1) {
+            proofs.extend(self.get_proof_chunk(chunk).await?);
+        }
+
+        Ok(proofs)
+    }
+
+    /// Perform multiple `eth_getProof` calls in batches of at most `chunk_size` calls,
+    /// with up to `max_concurrency` batches in flight at once. Against a latency-bound
+    /// endpoint, large sets of proofs complete faster than with
+    /// [RpcClient::get_proof_batched], at the cost of more concurrent load on the node.
+    ///
+    /// The order of the results matches the order of the given options. If a chunk fails,
+    /// a [BatchChunkError] with the addresses of that chunk is returned, and the batches
+    /// in flight are dropped. A `chunk_size` or `max_concurrency` of 0 is treated as 1.
+    pub async fn get_proof_batched_concurrent(
+        &self,
+        opts: Vec<(Address, Vec<B256>, BlockNumberOrTag)>,
+        chunk_size: usize,
+        max_concurrency: usize,
+    ) -> TransportResult<Vec<EIP1186AccountProofResponse>> {
+        let mut proofs = Vec::with_capacity(opts.len());
+
+        // Buffered in order, so the chunks are reassembled in the order of the options
+        let mut chunks = stream::iter(opts.chunks(chunk_size.max(1)))
+            .map(|chunk| self.get_proof_chunk(chunk))
+            .buffered(max_concurrency.max(1));
+
+        while let Some(chunk_proofs) = chunks.next().await {
+            proofs.extend(chunk_proofs?);
+        }
+
+        Ok(proofs)
+    }
+
+    /// Perform the given `eth_getProof` calls in a single batch, retrying on the archive
+    /// node if the historical state is unavailable. Errors are wrapped in a
+    /// [BatchChunkError] with the addresses of the chunk.
+    async fn get_proof_chunk(
+        &self,
+        chunk: &[(Address, Vec<B256>, BlockNumberOrTag)],
+    ) -> TransportResult<Vec<EIP1186AccountProofResponse>> {
+        let chunk_proofs = match (self.send_proof_batch(chunk).await, &self.archive) {
+            (Err(err), Some(archive)) if is_state_unavailable(&err) => {
+                tracing::debug!(
+                    ?err,
+                    "Historical state unavailable, retrying on archive node"
+                );
+                archive.send_proof_batch(chunk).await
+            }
+            (res, _) => res,
+        };
+
+        chunk_proofs.map_err(|source| {
+            TransportErrorKind::custom(BatchChunkError {
+                addresses: chunk.iter().map(|(address, _, _)| *address).collect(),
+                source,
+            })
+        })
+    }
+
+    /// Perform the given `eth_getProof` calls in a single batch.
+    async fn send_proof_batch(
+        &self,
+        opts: &[(Address, Vec<B256>, BlockNumberOrTag)],
+    ) -> TransportResult<Vec<EIP1186AccountProofResponse>> {
+        let mut batch = self.client.new_batch();
+
+        let mut proofs: Vec<Waiter<EIP1186AccountProofResponse>> = Vec::new();
+
+        for params in opts {
+            proofs.push(
+                batch
+                    .add_call("eth_getProof", params)
+                    .expect("Correct parameters"),
+            );
+        }
+
+        batch.send().await?;
+
+        // Important: join_all will preserve the order of the proofs
+        join_all(proofs)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+    }
+
+    /// Returns the code of the given account. If the block number is `None`,
+    /// the latest block is used.
+    pub async fn get_code(
+        &self,
+        address: Address,
+        block_number: impl IntoBlockTag,
+    ) -> TransportResult<Bytes> {
+        let tag = block_number.into_block_tag();
+
+        self.state_request("eth_getCode", (address, tag)).await
+    }
+
+    /// Returns the value of the given storage slot of an account. If the block number
+    /// is `None`, the latest block is used.
+    pub async fn get_storage_at(
+        &self,
+        address: Address,
+        slot: B256,
+        block_number: impl IntoBlockTag,
+    ) -> TransportResult<B256> {
+        let tag = block_number.into_block_tag();
+        let slot = U256::from_be_bytes(slot.0);
+
+        self.state_request::<_, U256>("eth_getStorageAt", (address, slot, tag))
+            .await
+            .map(B256::from)
+    }
+
+    /// Perform multiple `eth_getCode` calls in batches of at most
+    /// [RpcClientConfig::max_batch_size] calls, sent sequentially. The order of the
+    /// results matches the order of the given options.
+    pub async fn get_code_batched(
+        &self,
+        opts: Vec<(Address, BlockNumberOrTag)>,
+    ) -> TransportResult<Vec<Bytes>> {
+        let mut codes = Vec::with_capacity(opts.len());
+
+        for chunk in self.batch_chunks(&opts, 1) {
+            let mut batch = self.client.new_batch();
+
+            let mut waiters: Vec<Waiter<Bytes>> = Vec::with_capacity(chunk.len());
+
+            for params in chunk {
+                waiters.push(
+                    batch
+                        .add_call("eth_getCode", params)
+                        .expect("Correct parameters"),
+                );
+            }
+
+            batch.send().await?;
+
+            // Important: join_all will preserve the order of the codes
+            for code in join_all(waiters).await {
+                codes.push(code?);
+            }
+        }
+
+        Ok(codes)
+    }
+
+    /// Get the code of multiple accounts on the same block, batched like
+    /// [RpcClient::get_code_batched]. The order of the results matches the order of the
+    /// given addresses. If the block number is `None`, the latest block is used.
+    pub async fn get_codes(
+        &self,
+        addresses: &[Address],
+        block_number: impl IntoBlockTag,
+    ) -> TransportResult<Vec<Bytes>> {
+        let tag = block_number.into_block_tag();
+
+        self.get_code_batched(addresses.iter().map(|address| (*address, tag)).collect())
+            .await
+    }
+
+    /// Perform multiple `eth_getProof` calls in a single batch, aborting
+    /// as soon as the given cancellation token fires.
+    pub async fn get_proof_batched_with_cancel(
+        &self,
+        opts: Vec<(Address, Vec<B256>, BlockNumberOrTag)>,
+        cancel: &CancellationToken,
+    ) -> TransportResult<Vec<EIP1186AccountProofResponse>> {
+        with_cancel(cancel, self.get_proof_batched(opts)).await
+    }
+
+    /// Performs multiple call traces on top of the same block. i.e. transaction n will be executed
+    /// on top of a pending block with all n-1 transactions applied (traced) first.
+    ///
+    /// Note: Allows tracing dependent transactions, hence all transactions are traced in sequence
+    pub async fn trace_call_many(
+        &self,
+        calls: Vec<(TransactionRequest, HashSet<TraceType>)>,
+        block_number: impl IntoBlockTag,
+    ) -> TransportResult<Vec<TraceResults>> {
+        let tag = block_number.into_block_tag();
+        let params = (calls, tag);
+
+        self.state_request("trace_callMany", params).await
+    }
+
+    /// Performs multiple call traces on top of the same block like [RpcClient::trace_call_many],
+    /// after applying the given state and block overrides. This allows tracing a bundle on
+    /// top of the diffs accumulated by the [CallTraceManager](crate::builder::CallTraceManager).
+    pub async fn trace_call_many_with_overrides(
+        &self,
+        calls: Vec<(TransactionRequest, HashSet<TraceType>)>,
+        block_number: impl IntoBlockTag,
+        state_override: Option<StateOverride>,
+        block_override: Option<BlockOverrides>,
+    ) -> TransportResult<Vec<TraceResults>> {
+        let tag = block_number.into_block_tag();
+        let overrides = TraceCallManyOverrides {
+            state_overrides: state_override,
+            block_overrides: block_override,
+        };
+
+        self.state_request("trace_callMany", (calls, tag, overrides))
+            .await
+    }
+
+    /// Performs the `eth_call` JSON-RPC method, optionally on top of the given state overrides.
+    /// The overrides have the same format as the accumulated state diffs returned by the
+    /// [CallTraceManager](crate::builder::CallTraceManager), so they can be passed in directly.
+    pub async fn call(
+        &self,
+        tx: TransactionRequest,
+        block_number: impl IntoBlockTag,
+        state_override: Option<StateOverride>,
+    ) -> TransportResult<Bytes> {
+        let tag = block_number.into_block_tag();
+
+        match state_override {
+            Some(state_override) => {
+                self.state_request("eth_call", (tx, tag, state_override))
+                    .await
+            }
+            None => self.state_request("eth_call", (tx, tag)).await,
+        }
+    }
+
+    /// Performs the `eth_simulateV1` JSON-RPC method: simulates the given blocks of calls
+    /// atomically on top of the given block, each call seeing the state changes of the
+    /// previous ones. If the block number is `None`, the latest block is used.
+    ///
+    /// Faster than chaining `debug_traceCall`s, but only supported by recent nodes.
+    pub async fn simulate_v1(
+        &self,
+        block_state_calls: Vec<SimBlock>,
+        block_number: impl IntoBlockTag,
+        trace_transfers: bool,
+    ) -> TransportResult<Vec<SimulatedBlock>> {
+        let tag = block_number.into_block_tag();
+        let payload = SimulatePayload {
+            block_state_calls,
+            trace_transfers,
+        };
+
+        self.client.request("eth_simulateV1", (payload, tag)).await
+    }
+
+    /// Performs the `eth_createAccessList` JSON-RPC method, returning the access list
+    /// of the transaction along with its estimated gas usage.
+    /// If the block number is `None`, the latest block is used.
+    pub async fn create_access_list(
+        &self,
+        tx: TransactionRequest,
+        block_number: impl IntoBlockTag,
+    ) -> TransportResult<AccessListWithGasUsed> {
+        let tag = block_number.into_block_tag();
+
+        self.state_request("eth_createAccessList", (tx, tag)).await
+    }
+
+    /// Performs the `debug_traceCall` JSON-RPC method.
+    pub async fn debug_trace_call(
+        &self,
+        tx: TransactionRequest,
+        block_number: impl IntoBlockTag,
+        opts: Option<GethDebugTracingCallOptions>,
+    ) -> TransportResult<GethTrace> {
+        let tag = block_number.into_block_tag();
+        let params = (tx, tag, opts);
+
+        self.state_request("debug_traceCall", params).await
+    }
+
+    /// Traces the given transaction on top of the state of the given block, modified by
+    /// the given state overrides, e.g. to simulate against a known pending state that
+    /// isn't a canonical block. If the block number is `None`, the latest block is used.
+    ///
+    /// The state overrides replace the ones of the tracing options, if any.
+    pub async fn debug_trace_call_at_state(
+        &self,
+        tx: TransactionRequest,
+        block_number: impl IntoBlockTag,
+        state_override: StateOverride,
+        opts: Option<GethDebugTracingCallOptions>,
+    ) -> TransportResult<GethTrace> {
+        let opts = opts
+            .unwrap_or_default()
+            .with_state_overrides(state_override);
+
+        self.debug_trace_call(tx, block_number, Some(opts)).await
+    }
+
+    /// Same as [RpcClient::debug_trace_call_at_state], with the state overrides shared
+    /// with the caller: they are serialized from the [Arc] rather than moved into the
+    /// options, so that a large overlay traced repeatedly (e.g. the accumulated diffs of
+    /// a bundle) is not deep-cloned for every call, nor for the archive fallback.
+    pub async fn debug_trace_call_with_shared_state(
+        &self,
+        tx: TransactionRequest,
+        block_number: impl IntoBlockTag,
+        state_override: Arc<StateOverride>,
+        opts: Option<GethDebugTracingCallOptions>,
+    ) -> TransportResult<GethTrace> {
+        let tag = block_number.into_block_tag();
+        let opts = SharedStateCallOptions {
+            opts: GethDebugTracingCallOptions {
+                state_overrides: None,
+                ..opts.unwrap_or_default()
+            },
+            state_overrides: SharedStateOverride(state_override),
+        };
+
+        self.state_request("debug_traceCall", (tx, tag, opts)).await
+    }
+
+    /// Performs the `debug_traceCall` JSON-RPC method, aborting
+    /// as soon as the given cancellation token fires.
+    pub async fn debug_trace_call_with_cancel(
+        &self,
+        tx: TransactionRequest,
+        block_number: impl IntoBlockTag,
+        opts: Option<GethDebugTracingCallOptions>,
+        cancel: &CancellationToken,
+    ) -> TransportResult<GethTrace> {
+        with_cancel(cancel, self.debug_trace_call(tx, block_number, opts)).await
+    }
+}
+
+/// Returns the HTTP transport matching the compression and response size settings
+/// of the given configuration.
+fn http_transport(client: Client, url: Url, config: &RpcClientConfig) -> BoxTransport {
+    match (config.gzip, config.max_response_bytes) {
+        (true, limit) => GzipHttp::new(client, url)
+            .with_max_response_bytes(limit)
+            .boxed(),
+        (false, Some(limit)) => LimitedHttp::new(client, url, limit).boxed(),
+        (false, None) => Http::with_client(client, url).boxed(),
+    }
+}
+
+fn pre_cancun_error() -> TransportError {
+    TransportErrorKind::custom_str("block predates Cancun, no blob base fee available")
+}
+
+/// Run the given request until completion, or until the cancellation token fires.
+/// In the latter case, the request is dropped and an error is returned.
+async fn with_cancel<T>(
+    cancel: &CancellationToken,
+    request: impl Future<Output = TransportResult<T>>,
+) -> TransportResult<T> {
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => Err(TransportErrorKind::custom_str("request cancelled")),
+        res = request => res,
+    }
+}
+
+impl Deref for RpcClient {
+    type Target = alloy::RpcClient<RpcService>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+impl DerefMut for RpcClient {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.client
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        str::FromStr,
+        time::{Duration, Instant},
+    };
+
+    use alloy_consensus::constants::ETH_TO_WEI;
+    use alloy_eips::eip2718::Encodable2718;
+    use alloy_network::{EthereumWallet, TransactionBuilder};
+    use alloy_primitives::{hex, keccak256, uint, Uint};
+    use alloy_rpc_types::state::AccountOverride;
+    use alloy_rpc_types::EIP1186AccountProofResponse;
+    use alloy_signer_local::PrivateKeySigner;
+    use axum::http::StatusCode;
+    use reth_primitives::B256;
+    use serde_json::Value;
+
+    use crate::{
+        client::{
+            body_limit::is_response_too_large, context::RpcClientError, retry::is_rate_limited,
+        },
+        test_util::{default_test_transaction, launch_anvil, MockRpcServer},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rpc_client() {
+        let anvil = launch_anvil();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        let addr = anvil.addresses().first().unwrap();
+
+        let account_state = client.get_account_state(addr, None).await.unwrap();
+
+        // Accounts in Anvil start with 10_000 ETH
+        assert_eq!(
+            account_state.balance,
+            uint!(10_000U256 * Uint::from(ETH_TO_WEI))
+        );
+
+        assert_eq!(account_state.transaction_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_rpc_client_ws() {
+        let anvil = launch_anvil();
+        let http_client = RpcClient::connect(&anvil.endpoint()).await.unwrap();
+        let ws_client = RpcClient::connect(&anvil.ws_endpoint()).await.unwrap();
+
+        let http_head = http_client.get_head().await.unwrap();
+        let ws_head = ws_client.get_head().await.unwrap();
+        assert!(ws_head >= http_head);
+
+        // Batched requests work over WebSocket too
+        let addr = anvil.addresses().first().unwrap();
+        let account_state = ws_client.get_account_state(addr, None).await.unwrap();
+        assert_eq!(account_state.transaction_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_new_heads() {
+        let anvil = launch_anvil();
+        let client = RpcClient::connect(&anvil.ws_endpoint()).await.unwrap();
+
+        let heads = client.subscribe_new_heads().await.unwrap();
+        let blocks: Vec<Block> = heads.take(3).map(|block| block.unwrap()).collect().await;
+
+        let numbers: Vec<u64> = blocks
+            .iter()
+            .map(|block| block.header.number.unwrap())
+            .collect();
+        assert!(numbers.windows(2).all(|w| w[1] > w[0]));
+        assert!(blocks
+            .iter()
+            .all(|block| block.header.base_fee_per_gas.is_some()));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_pending_transactions() {
+        let anvil = launch_anvil();
+        let client = RpcClient::connect(&anvil.ws_endpoint()).await.unwrap();
+        let mut pending = Box::pin(client.subscribe_pending_transactions().await.unwrap());
+
+        let wallet: PrivateKeySigner = anvil.keys()[0].clone().into();
+        let sender = anvil.addresses()[0];
+        let signer: EthereumWallet = wallet.into();
+        let signed = default_test_transaction(sender, None)
+            .build(&signer)
+            .await
+            .unwrap();
+        let raw = Bytes::from(signed.encoded_2718());
+        let hash = client.send_raw_transaction(raw).await.unwrap();
+
+        let notified = tokio::time::timeout(Duration::from_secs(5), pending.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(notified, hash);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_new_heads_http() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(Value::Null)).await;
+        let client = RpcClient::new(rpc.url());
+
+        // Subscriptions are not supported over HTTP
+        assert!(client.subscribe_new_heads().await.is_err());
+        assert!(client.subscribe_pending_transactions().await.is_err());
+        assert_eq!(rpc.call_count("eth_subscribe"), 0);
+    }
+
+    #[test]
+    fn test_parse_rpc_endpoint() {
+        assert!(matches!(
+            RpcEndpoint::parse("http://localhost:8545"),
+            Ok(RpcEndpoint::Http(_))
+        ));
+        assert!(matches!(
+            RpcEndpoint::parse("https://eth.example.com"),
+            Ok(RpcEndpoint::Http(_))
+        ));
+        assert!(matches!(
+            RpcEndpoint::parse("wss://eth.example.com"),
+            Ok(RpcEndpoint::Ws(_))
+        ));
+        assert_eq!(
+            RpcEndpoint::parse("/tmp/reth.ipc").unwrap(),
+            RpcEndpoint::Ipc(PathBuf::from("/tmp/reth.ipc"))
+        );
+        assert_eq!(
+            RpcEndpoint::parse("file:///tmp/reth.ipc").unwrap(),
+            RpcEndpoint::Ipc(PathBuf::from("/tmp/reth.ipc"))
+        );
+        assert!(RpcEndpoint::parse("ftp://eth.example.com").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_account_states() {
+        let rpc = MockRpcServer::spawn(|method, params| {
+            let address = Address::from_str(params[0].as_str().unwrap()).unwrap();
+            let last_byte = address.0[19];
+
+            match method {
+                "eth_getBalance" => Ok(serde_json::json!(format!("{:#x}", last_byte as u64 * 100))),
+                "eth_getTransactionCount" => Ok(serde_json::json!(format!("{:#x}", last_byte))),
+                _ => unreachable!(),
+            }
+        })
+        .await;
+        let client = RpcClient::new(rpc.url());
+
+        let addresses = [Address::with_last_byte(1), Address::with_last_byte(2)];
+        let states = client
+            .get_account_states(&[addresses[0], addresses[1], addresses[0]], None)
+            .await
+            .unwrap();
+
+        assert_eq!(states.len(), 2);
+        for (i, address) in addresses.iter().enumerate() {
+            let expected = i as u64 + 1;
+            assert_eq!(states[address].balance, U256::from(expected * 100));
+            assert_eq!(states[address].transaction_count, expected);
+        }
+
+        // Duplicates are only fetched once
+        assert_eq!(rpc.call_count("eth_getBalance"), 2);
+        assert_eq!(rpc.call_count("eth_getTransactionCount"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_account_states_reversed_batch() {
+        let rpc = MockRpcServer::spawn(|method, params| {
+            let address = Address::from_str(params[0].as_str().unwrap()).unwrap();
+            let last_byte = address.0[19];
+
+            match method {
+                "eth_getBalance" => Ok(serde_json::json!(format!("{:#x}", last_byte as u64 * 100))),
+                "eth_getTransactionCount" => Ok(serde_json::json!(format!("{:#x}", last_byte))),
+                _ => unreachable!(),
+            }
+        })
+        .await;
+        rpc.reverse_batches();
+        let client = RpcClient::new(rpc.url());
+
+        let addresses = (1..=5).map(Address::with_last_byte).collect::<Vec<_>>();
+        let states = client.get_account_states(&addresses, None).await.unwrap();
+
+        assert_eq!(states.len(), 5);
+        for address in &addresses {
+            let expected = address.0[19] as u64;
+            assert_eq!(states[address].balance, U256::from(expected * 100));
+            assert_eq!(states[address].transaction_count, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_account_states_chunks() {
+        let rpc = MockRpcServer::spawn(|method, params| {
+            let address = Address::from_str(params[0].as_str().unwrap()).unwrap();
+            let last_byte = address.0[19];
+
+            match method {
+                "eth_getBalance" => Ok(serde_json::json!(format!("{:#x}", last_byte as u64 * 100))),
+                "eth_getTransactionCount" => Ok(serde_json::json!(format!("{:#x}", last_byte))),
+                _ => unreachable!(),
+            }
+        })
+        .await;
+        let config = RpcClientConfig {
+            max_batch_size: 4,
+            ..Default::default()
+        };
+        let client = RpcClient::new_with_config(rpc.url(), config);
+
+        let addresses = (1..=5).map(Address::with_last_byte).collect::<Vec<_>>();
+        let states = client.get_account_states(&addresses, None).await.unwrap();
+
+        // Two calls per address: 3 batches of at most 2 addresses
+        assert_eq!(rpc.headers().len(), 3);
+        assert_eq!(states.len(), 5);
+        for address in &addresses {
+            let expected = address.0[19] as u64;
+            assert_eq!(states[address].balance, U256::from(expected * 100));
+            assert_eq!(states[address].transaction_count, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_account_states_partial_failure() {
+        let failing = Address::with_last_byte(2);
+        let rpc = MockRpcServer::spawn(move |method, params| {
+            let address = Address::from_str(params[0].as_str().unwrap()).unwrap();
+
+            if method == "eth_getBalance" && address == failing {
+                Err(serde_json::json!({ "code": -32000, "message": "header not found" }))
+            } else {
+                Ok(serde_json::json!("0x1"))
+            }
+        })
+        .await;
+        let client = RpcClient::new(rpc.url());
+
+        let addresses = [Address::with_last_byte(1), failing];
+        assert!(client.get_account_states(&addresses, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_call_with_state_override() {
+        let anvil = launch_anvil();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        // SELFBALANCE PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+        let code = Bytes::from_static(&hex!("4760005260206000f3"));
+        let target = Address::with_last_byte(0x42);
+        let tx = TransactionRequest::default().to(target);
+
+        let with_balance = |balance: U256| -> StateOverride {
+            HashMap::from([(
+                target,
+                AccountOverride {
+                    code: Some(code.clone()),
+                    balance: Some(balance),
+                    ..Default::default()
+                },
+            )])
+        };
+
+        let res = client
+            .call(tx.clone(), None, Some(with_balance(U256::ZERO)))
+            .await
+            .unwrap();
+        assert_eq!(U256::from_be_slice(&res), U256::ZERO);
+
+        let res = client
+            .call(tx, None, Some(with_balance(U256::from(1000))))
+            .await
+            .unwrap();
+        assert_eq!(U256::from_be_slice(&res), U256::from(1000));
+    }
+
+    #[tokio::test]
+    async fn test_create_access_list() {
+        // Auto-mining, so that the deployment is immediately available
+        let anvil = alloy_node_bindings::Anvil::new().spawn();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        let sender = anvil.addresses()[0];
+        let touched = Address::repeat_byte(0x11);
+
+        // Runtime: PUSH20 <touched> BALANCE PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN,
+        // prefixed with the init code that copies it to memory and returns it.
+        let init_code = Bytes::from_static(&hex!(
+            "601e600c600039601e6000f3"
+            "7311111111111111111111111111111111111111113160005260206000f3"
+        ));
+        let deploy = TransactionRequest::default()
+            .from(sender)
+            .input(init_code.into());
+        let _: B256 = client
+            .request("eth_sendTransaction", (deploy,))
+            .await
+            .unwrap();
+
+        let contract = sender.create(0);
+        let tx = TransactionRequest::default().from(sender).to(contract);
+        let res = client.create_access_list(tx, None).await.unwrap();
+
+        assert!(!res.access_list.0.is_empty());
+        assert!(res.access_list.0.iter().any(|item| item.address == touched));
+        assert!(res.gas_used > U256::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_v1() {
+        let anvil = launch_anvil();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        let sender = anvil.addresses()[0];
+        let transfer = |nonce| {
+            TransactionRequest::default()
+                .from(sender)
+                .to(Address::repeat_byte(0x11))
+                .value(U256::from(1))
+                .nonce(nonce)
+        };
+
+        let block = SimBlock {
+            calls: vec![transfer(0), transfer(1)],
+            ..Default::default()
+        };
+        let res = client.simulate_v1(vec![block], None, true).await.unwrap();
+
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].calls.len(), 2);
+        for call in &res[0].calls {
+            assert!(call.is_success());
+            assert_eq!(call.gas_used, U64::from(21_000));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_simulate_v1_results() {
+        let token = Address::repeat_byte(0x22);
+        let topic = B256::repeat_byte(0x33);
+
+        // A successful call emitting a log, followed by a reverted one
+        let rpc = MockRpcServer::spawn(move |method, _| {
+            assert_eq!(method, "eth_simulateV1");
+
+            Ok(serde_json::json!([{
+                "number": "0x2",
+                "hash": B256::repeat_byte(2),
+                "parentHash": B256::repeat_byte(1),
+                "sha3Uncles": B256::ZERO,
+                "miner": Address::ZERO,
+                "stateRoot": B256::ZERO,
+                "transactionsRoot": B256::ZERO,
+                "receiptsRoot": B256::ZERO,
+                "logsBloom": format!("0x{}", "00".repeat(256)),
+                "difficulty": "0x0",
+                "timestamp": "0x64",
+                "gasLimit": "0x1c9c380",
+                "gasUsed": "0xa410",
+                "extraData": "0x",
+                "mixHash": B256::ZERO,
+                "nonce": "0x0000000000000000",
+                "baseFeePerGas": "0x7",
+                "uncles": [],
+                "transactions": [],
+                "calls": [
+                    {
+                        "returnData": "0x01",
+                        "logs": [{
+                            "address": token,
+                            "topics": [topic],
+                            "data": "0x02",
+                            "blockNumber": "0x2",
+                            "logIndex": "0x0",
+                            "removed": false,
+                        }],
+                        "gasUsed": "0x5208",
+                        "status": "0x1",
+                    },
+                    {
+                        "returnData": "0x",
+                        "logs": [],
+                        "gasUsed": "0x5208",
+                        "status": "0x0",
+                        "error": { "code": 3, "message": "execution reverted: not allowed" },
+                    },
+                ],
+            }]))
+        })
+        .await;
+        let client = RpcClient::new(rpc.url());
+
+        let block = SimBlock {
+            calls: vec![
+                TransactionRequest::default().to(token),
+                TransactionRequest::default().to(Address::repeat_byte(0x11)),
+            ],
+            ..Default::default()
+        };
+        let res = client.simulate_v1(vec![block], None, false).await.unwrap();
+
+        let params = &rpc.calls("eth_simulateV1")[0];
+        assert_eq!(params[0]["traceTransfers"], false);
+        assert_eq!(
+            params[0]["blockStateCalls"][0]["calls"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(params[1], "latest");
+
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].inner.header.number, Some(2));
+
+        let [success, revert] = &res[0].calls[..] else {
+            panic!("expected two call results");
+        };
+
+        assert!(success.is_success());
+        assert_eq!(success.return_data, Bytes::from_static(&[0x01]));
+        assert_eq!(success.gas_used, U64::from(21_000));
+        assert_eq!(success.revert_reason(), None);
+        assert_eq!(success.logs.len(), 1);
+        assert_eq!(success.logs[0].inner.address, token);
+        assert_eq!(success.logs[0].inner.data.topics(), &[topic]);
+        assert_eq!(success.logs[0].inner.data.data, Bytes::from_static(&[0x02]));
+
+        assert!(!revert.is_success());
+        assert!(revert.logs.is_empty());
+        assert_eq!(
+            revert.revert_reason(),
+            Some("execution reverted: not allowed")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trace_call_many_with_overrides() {
+        let rich = Address::repeat_byte(0x11);
+
+        let rpc = MockRpcServer::spawn(|method, _| {
+            assert_eq!(method, "trace_callMany");
+
+            Ok(serde_json::json!([{
+                "output": "0x2a",
+                "stateDiff": null,
+                "trace": [],
+                "vmTrace": null,
+            }]))
+        })
+        .await;
+        let client = RpcClient::new(rpc.url());
+
+        let call = || {
+            vec![(
+                TransactionRequest::default().from(rich),
+                HashSet::from([TraceType::Trace]),
+            )]
+        };
+
+        let res = client
+            .trace_call_many_with_overrides(call(), None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(res[0].output, Bytes::from_static(&[0x2a]));
+
+        let state_override = StateOverride::from([(
+            rich,
+            AccountOverride {
+                balance: Some(U256::from(ETH_TO_WEI)),
+                ..Default::default()
+            },
+        )]);
+        let block_override = BlockOverrides {
+            base_fee: Some(U256::from(7)),
+            ..Default::default()
+        };
+        client
+            .trace_call_many_with_overrides(
+                call(),
+                BlockNumberOrTag::Number(5),
+                Some(state_override),
+                Some(block_override),
+            )
+            .await
+            .unwrap();
+
+        let calls = rpc.calls("trace_callMany");
+
+        // Without overrides, the overrides object is empty
+        assert_eq!(calls[0][1], "latest");
+        assert_eq!(calls[0][2], serde_json::json!({}));
+
+        let overrides = &calls[1][2];
+        assert_eq!(calls[1][1], "0x5");
+        assert_eq!(
+            overrides["stateOverrides"][rich.to_string().to_lowercase()]["balance"],
+            "0xde0b6b3a7640000"
+        );
+        assert_eq!(overrides["blockOverrides"]["baseFee"], "0x7");
+    }
+
+    #[tokio::test]
+    async fn test_send_raw_transaction_and_pending_nonce() {
+        // Mining every second, so that the transaction stays pending for a while
+        let anvil = launch_anvil();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        let wallet: PrivateKeySigner = anvil.keys()[0].clone().into();
+        let sender = anvil.addresses()[0];
+        assert_eq!(client.get_pending_nonce(sender).await.unwrap(), 0);
+
+        let signer: EthereumWallet = wallet.into();
+        let signed = default_test_transaction(sender, None)
+            .build(&signer)
+            .await
+            .unwrap();
+        let raw = Bytes::from(signed.encoded_2718());
+
+        let hash = client.send_raw_transaction(raw).await.unwrap();
+        assert_eq!(hash, *signed.tx_hash());
+
+        // The pending nonce accounts for the transaction before it is mined
+        assert_eq!(client.get_pending_nonce(sender).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_raw_transaction_rejected() {
+        let rpc = MockRpcServer::spawn(|_, _| {
+            Err(serde_json::json!({ "code": -32000, "message": "nonce too low" }))
+        })
+        .await;
+        let client = RpcClient::new(rpc.url());
+
+        let err = client
+            .send_raw_transaction(Bytes::from_static(&[0x02]))
+            .await
+            .unwrap_err();
+        let RpcError::Transport(TransportErrorKind::Custom(err)) = err else {
+            panic!("expected a rejected transaction error, got {err:?}");
+        };
+        let err = err.downcast_ref::<TransactionRejected>().unwrap();
+        assert_eq!(err.reason, RejectionReason::NonceTooLow);
+
+        // Unknown errors are returned as is
+        let rpc = MockRpcServer::spawn(|_, _| {
+            Err(serde_json::json!({ "code": -32000, "message": "intrinsic gas too low" }))
+        })
+        .await;
+        let client = RpcClient::new(rpc.url());
+
+        let err = client
+            .send_raw_transaction(Bytes::from_static(&[0x02]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RpcError::ErrorResp(_)));
+    }
+
+    #[tokio::test]
+    async fn test_send_raw_transaction_is_not_retried() {
+        // The first attempt fails after the node received the transaction, so that
+        // a retry would be rejected as already known
+        let rpc = MockRpcServer::spawn(|_, _| {
+            Err(serde_json::json!({ "code": -32000, "message": "already known" }))
+        })
+        .await;
+        let client = RpcClient::new_with_config(rpc.url(), fast_retry_config());
+
+        rpc.fail_next(1, StatusCode::BAD_GATEWAY);
+        let err = client
+            .send_raw_transaction(Bytes::from_static(&[0x02]))
+            .await
+            .unwrap_err();
+
+        // The transient failure is returned, rather than a rejection of the transaction
+        assert!(matches!(
+            err,
+            RpcError::Transport(TransportErrorKind::HttpError(ref e)) if e.status == 502
+        ));
+        assert_eq!(rpc.call_count("eth_sendRawTransaction"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_balance_history() {
+        // Auto-mining, so that every transfer is included in its own block
+        let anvil = alloy_node_bindings::Anvil::new().spawn();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        let sender = anvil.addresses()[0];
+        let recipient = Address::repeat_byte(0x22);
+
+        for value in [100, 200] {
+            let tx = TransactionRequest::default()
+                .from(sender)
+                .to(recipient)
+                .value(U256::from(value));
+            let _: B256 = client.request("eth_sendTransaction", (tx,)).await.unwrap();
+        }
+        assert_eq!(client.get_head().await.unwrap(), 2);
+
+        let history = client
+            .get_balance_history(recipient, &[2, 0, 1, 2])
+            .await
+            .unwrap();
+        assert_eq!(
+            history,
+            vec![
+                (2, U256::from(300)),
+                (0, U256::ZERO),
+                (1, U256::from(100)),
+                (2, U256::from(300)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_balance_history_empty() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(Value::Null)).await;
+        let client = RpcClient::new(rpc.url());
+
+        let history = client
+            .get_balance_history(Address::ZERO, &[])
+            .await
+            .unwrap();
+        assert!(history.is_empty());
+        assert_eq!(rpc.call_count("eth_getBalance"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_logs() {
+        // Auto-mining, so that the event is immediately included
+        let anvil = alloy_node_bindings::Anvil::new().spawn();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        let sender = anvil.addresses()[0];
+        let topic = B256::repeat_byte(0xaa);
+
+        // Init code: PUSH32 <topic> PUSH1 0 PUSH1 0 LOG1 STOP, emitting the event on deployment
+        let mut init_code = vec![0x7f];
+        init_code.extend_from_slice(topic.as_slice());
+        init_code.extend_from_slice(&hex!("60006000a100"));
+        let deploy = TransactionRequest::default()
+            .from(sender)
+            .input(Bytes::from(init_code).into());
+        let _: B256 = client
+            .request("eth_sendTransaction", (deploy,))
+            .await
+            .unwrap();
+
+        let contract = sender.create(0);
+        let filter = Filter::new()
+            .address(contract)
+            .event_signature(topic)
+            .from_block(0);
+        let logs = client.get_logs(filter).await.unwrap();
+
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].inner.address, contract);
+        assert_eq!(logs[0].inner.data.topics(), &[topic]);
+
+        // Another topic doesn't match
+        let filter = Filter::new()
+            .address(contract)
+            .event_signature(B256::repeat_byte(0xbb))
+            .from_block(0);
+        assert!(client.get_logs(filter).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_logs_limit_exceeded() {
+        let rpc = MockRpcServer::spawn(|_, _| {
+            Err(serde_json::json!({
+                "code": -32005,
+                "message": "query returned more than 10000 results",
+            }))
+        })
+        .await;
+        let client = RpcClient::new(rpc.url());
+
+        let err = client.get_logs(Filter::new()).await.unwrap_err();
+        let RpcError::Transport(TransportErrorKind::Custom(err)) = err else {
+            panic!("expected a log query limit error, got {err:?}");
+        };
+        assert!(err.is::<LogQueryLimitError>());
+        // Infura shares the -32005 code with its rate limit, but the query isn't retried
+        assert_eq!(rpc.call_count("eth_getLogs"), 1);
+
+        // Other errors are returned as is
+        let rpc = MockRpcServer::spawn(|_, _| {
+            Err(serde_json::json!({ "code": -32000, "message": "invalid block range" }))
+        })
+        .await;
+        let client = RpcClient::new(rpc.url());
+
+        let err = client.get_logs(Filter::new()).await.unwrap_err();
+        assert!(matches!(err, RpcError::ErrorResp(_)));
+    }
+
+    #[tokio::test]
+    async fn test_get_transaction_and_receipt() {
+        // Auto-mining, so that the transaction is immediately included
+        let anvil = alloy_node_bindings::Anvil::new().spawn();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        let sender = anvil.addresses()[0];
+        let tx = TransactionRequest::default()
+            .from(sender)
+            .to(anvil.addresses()[1])
+            .value(U256::from(1));
+        let hash: B256 = client.request("eth_sendTransaction", (tx,)).await.unwrap();
+
+        let receipt = client.get_transaction_receipt(hash).await.unwrap().unwrap();
+        assert_eq!(receipt.transaction_hash, hash);
+        assert!(receipt.status());
+
+        let tx = client.get_transaction_by_hash(hash).await.unwrap().unwrap();
+        assert_eq!(tx.hash, hash);
+        assert_eq!(tx.from, sender);
+
+        // Unknown hashes are not an error
+        let unknown = B256::repeat_byte(0x42);
+        assert!(client
+            .get_transaction_receipt(unknown)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(client
+            .get_transaction_by_hash(unknown)
+            .await
+            .unwrap()
+            .is_none());
+
+        let receipts = client.get_receipts_batched(&[unknown, hash]).await.unwrap();
+        assert!(receipts[0].is_none());
+        assert_eq!(receipts[1].as_ref().unwrap().transaction_hash, hash);
+    }
+
+    #[tokio::test]
+    async fn test_get_block_receipts() {
+        let anvil = alloy_node_bindings::Anvil::new().spawn();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        // Include all the transfers in the same block
+        let _: Value = client.request("evm_setAutomine", (false,)).await.unwrap();
+
+        let sender = anvil.addresses()[0];
+        let mut hashes = Vec::new();
+        for nonce in 0..3 {
+            let tx = TransactionRequest::default()
+                .from(sender)
+                .to(Address::repeat_byte(0x11))
+                .value(U256::from(1))
+                .nonce(nonce);
+            let hash: B256 = client.request("eth_sendTransaction", (tx,)).await.unwrap();
+            hashes.push(hash);
+        }
+        let _: Value = client.request("evm_mine", ()).await.unwrap();
+
+        let receipts = client.get_block_receipts(Some(1)).await.unwrap();
+        assert_eq!(
+            receipts
+                .iter()
+                .map(|r| r.transaction_hash)
+                .collect::<Vec<_>>(),
+            hashes
+        );
+        for receipt in &receipts {
+            let expected = client
+                .get_transaction_receipt(receipt.transaction_hash)
+                .await
+                .unwrap();
+            assert_eq!(Some(receipt), expected.as_ref());
+        }
+
+        // The latest block is used by default
+        assert_eq!(client.get_block_receipts(None).await.unwrap(), receipts);
+        assert!(client.get_block_receipts(Some(0)).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_is_method_unsupported() {
+        let error = |code, message: &str| {
+            TransportError::ErrorResp(alloy_json_rpc::ErrorPayload {
+                code,
+                message: message.to_string(),
+                data: None,
+            })
+        };
+
+        assert!(is_method_unsupported(&error(-32601, "Method not found")));
+        assert!(is_method_unsupported(&error(
+            -32000,
+            "the method eth_getBlockReceipts does not exist/is not available"
+        )));
+
+        // Other missing things are not unsupported methods
+        assert!(!is_method_unsupported(&error(
+            -32000,
+            "header for block 0x10 does not exist"
+        )));
+        assert!(!is_method_unsupported(&error(
+            -32000,
+            "account does not exist"
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_get_block_receipts_fallback() {
+        let hashes = [B256::repeat_byte(1), B256::repeat_byte(2)];
+        let rpc = MockRpcServer::spawn(move |method, params| match method {
+            "eth_getBlockReceipts" => Err(serde_json::json!({
+                "code": -32601,
+                "message": "the method eth_getBlockReceipts does not exist/is not available",
+            })),
+            "eth_getBlockByNumber" => {
+                assert_eq!(params[0], "0x1");
+
+                let block: Block = Block {
+                    header: Header {
+                        number: Some(1),
+                        ..Default::default()
+                    },
+                    transactions: hashes.to_vec().into(),
+                    ..Default::default()
+                };
+                Ok(serde_json::to_value(block).unwrap())
+            }
+            "eth_getTransactionReceipt" => Ok(serde_json::json!({
+                "type": "0x2",
+                "status": "0x1",
+                "cumulativeGasUsed": "0x5208",
+                "logs": [],
+                "logsBloom": format!("0x{}", "00".repeat(256)),
+                "transactionHash": params[0],
+                "blockNumber": "0x1",
+                "gasUsed": "0x5208",
+                "effectiveGasPrice": "0x1",
+                "from": Address::ZERO,
+                "to": Address::repeat_byte(0x11),
+                "contractAddress": null,
+            })),
+            _ => panic!("unexpected method {method}"),
+        })
+        .await;
+        let client = RpcClient::new(rpc.url());
+
+        let receipts = client.get_block_receipts(Some(1)).await.unwrap();
+        assert_eq!(
+            receipts
+                .iter()
+                .map(|r| r.transaction_hash)
+                .collect::<Vec<_>>(),
+            hashes
+        );
+        assert_eq!(rpc.call_count("eth_getBlockReceipts"), 1);
+        assert_eq!(rpc.call_count("eth_getTransactionReceipt"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_block_by_hash_and_header() {
+        let anvil = launch_anvil();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        let header = client.get_header(None).await.unwrap();
+        let latest = client.get_block(None, false).await.unwrap();
+        assert_eq!(header.hash, latest.header.hash);
+
+        let genesis = client.get_header(Some(0)).await.unwrap();
+        assert_eq!(genesis.number, Some(0));
+
+        let hash = genesis.hash.unwrap();
+        let block = client
+            .get_block_by_hash(hash, false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(block.header.hash, Some(hash));
+        assert_eq!(block.header.number, Some(0));
+
+        // Unknown hashes are not an error
+        let unknown = B256::repeat_byte(0x42);
+        assert!(client
+            .get_block_by_hash(unknown, true)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_block_by_tag() {
+        let anvil = launch_anvil();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        client
+            .request::<_, Value>("anvil_mine", (U64::from(3),))
+            .await
+            .unwrap();
+        let head = client.get_head().await.unwrap();
+
+        let number = |header: Header| header.number.unwrap();
+        let earliest = client.get_header(BlockNumberOrTag::Earliest).await.unwrap();
+        assert_eq!(number(earliest), 0);
+        let latest = client.get_header(BlockNumberOrTag::Latest).await.unwrap();
+        assert_eq!(number(latest), head);
+        let by_number = client.get_header(head).await.unwrap();
+        assert_eq!(number(by_number), head);
+
+        // Anvil lags the safe and finalized blocks behind the head
+        for tag in [BlockNumberOrTag::Safe, BlockNumberOrTag::Finalized] {
+            let header = client.get_header(tag).await.unwrap();
+            assert!(number(header) <= head, "{tag:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_block_tag_params() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x0"))).await;
+        let client = RpcClient::new(rpc.url());
+        let address = Address::repeat_byte(1);
+
+        let tags = [
+            (BlockNumberOrTag::Latest, "latest"),
+            (BlockNumberOrTag::Safe, "safe"),
+            (BlockNumberOrTag::Finalized, "finalized"),
+            (BlockNumberOrTag::Pending, "pending"),
+            (BlockNumberOrTag::Earliest, "earliest"),
+            (BlockNumberOrTag::Number(16), "0x10"),
+        ];
+        for (tag, _) in tags {
+            client.get_code(address, tag).await.unwrap();
+            client.get_account_state(&address, tag).await.unwrap();
+        }
+
+        let code_calls = rpc.calls("eth_getCode");
+        let balance_calls = rpc.calls("eth_getBalance");
+        for (i, (_, param)) in tags.iter().enumerate() {
+            assert_eq!(code_calls[i][1], *param);
+            assert_eq!(balance_calls[i][1], *param);
+        }
+
+        // The optional block number is still accepted, `None` being the latest block
+        client.get_code(address, None).await.unwrap();
+        client.get_code(address, Some(16)).await.unwrap();
+        client.get_code(address, 16).await.unwrap();
+        let code_calls = rpc.calls("eth_getCode");
+        assert_eq!(code_calls[6][1], "latest");
+        assert_eq!(code_calls[7][1], "0x10");
+        assert_eq!(code_calls[8][1], "0x10");
+    }
+
+    #[tokio::test]
+    async fn test_chain_id_is_cached() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x1"))).await;
+        let client = RpcClient::new(rpc.url());
+
+        assert_eq!(client.cached_chain_id(), None);
+        assert_eq!(client.get_chain_id().await.unwrap(), 1);
+        assert_eq!(client.get_chain_id().await.unwrap(), 1);
+
+        // Clones share the cache
+        let clone = client.clone();
+        assert_eq!(clone.cached_chain_id(), Some(1));
+        assert_eq!(clone.get_chain_id().await.unwrap(), 1);
+
+        assert_eq!(rpc.call_count("eth_chainId"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_gas() {
+        let anvil = launch_anvil();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        let tx = TransactionRequest::default()
+            .from(anvil.addresses()[0])
+            .to(anvil.addresses()[1])
+            .value(U256::from(1));
+
+        // A plain transfer always costs the intrinsic gas
+        assert_eq!(client.estimate_gas(tx, None).await.unwrap(), 21_000);
+    }
+
+    #[tokio::test]
+    async fn test_get_max_priority_fee() {
+        let anvil = launch_anvil();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        assert!(client.get_max_priority_fee().await.unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_account_state_full() {
+        let anvil = launch_anvil();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        let address = anvil.addresses()[0];
+        let state = client.get_account_state(&address, None).await.unwrap();
+        let full = client.get_account_state_full(&address, None).await.unwrap();
+
+        assert_eq!(state.code_hash, None);
+        assert_eq!(full.balance, state.balance);
+        assert_eq!(full.transaction_count, state.transaction_count);
+
+        // An EOA has no code and an empty storage trie, whose root is the hash of
+        // the RLP encoding of an empty string
+        assert_eq!(full.code_hash, Some(keccak256([])));
+        assert_eq!(full.storage_root, Some(keccak256([0x80])));
+    }
+
+    #[tokio::test]
+    async fn test_get_code_and_storage() {
+        // Auto-mining, so that the deployment is immediately available
+        let anvil = alloy_node_bindings::Anvil::new().spawn();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        let sender = anvil.addresses()[0];
+
+        // Init code: PUSH1 42 PUSH1 1 SSTORE, then copies the runtime to memory and returns it.
+        // Runtime: PUSH1 42 PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+        let runtime = hex!("602a60005260206000f3");
+        let init_code = Bytes::from_static(&hex!(
+            "602a600155"
+            "600a6011600039"
+            "600a6000f3"
+            "602a60005260206000f3"
+        ));
+        let deploy = TransactionRequest::default()
+            .from(sender)
+            .input(init_code.into());
+        let _: B256 = client
+            .request("eth_sendTransaction", (deploy,))
+            .await
+            .unwrap();
+
+        let contract = sender.create(0);
+
+        let code = client.get_code(contract, None).await.unwrap();
+        assert_eq!(code, Bytes::from_static(&runtime));
+
+        let slot = B256::from(U256::from(1));
+        let value = client.get_storage_at(contract, slot, None).await.unwrap();
+        assert_eq!(value, B256::from(U256::from(42)));
+
+        // Unset slots read as zero
+        let value = client
+            .get_storage_at(contract, B256::ZERO, None)
+            .await
+            .unwrap();
+        assert_eq!(value, B256::ZERO);
+
+        // The batch preserves the order of the requests
+        let codes = client
+            .get_code_batched(vec![
+                (sender, BlockNumberOrTag::Latest),
+                (contract, BlockNumberOrTag::Latest),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(codes, vec![Bytes::new(), Bytes::from_static(&runtime)]);
+    }
+
+    #[tokio::test]
+    async fn test_get_codes() {
+        let anvil = launch_anvil();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        let addresses = anvil.addresses()[..3].to_vec();
+
+        let codes = client.get_codes(&addresses, None).await.unwrap();
+
+        // Anvil accounts are EOAs, so they don't have any code
+        assert_eq!(codes.len(), addresses.len());
+        assert!(codes.iter().all(|code| code.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn test_get_codes_chunks() {
+        let rpc = MockRpcServer::spawn(|method, params| {
+            assert_eq!(method, "eth_getCode");
+            let address = Address::from_str(params[0].as_str().unwrap()).unwrap();
+            Ok(serde_json::json!(Bytes::from(vec![address.0[19]])))
+        })
+        .await;
+        let config = RpcClientConfig {
+            max_batch_size: 2,
+            ..Default::default()
+        };
+        let client = RpcClient::new_with_config(rpc.url(), config);
+
+        let addresses = (1..=5).map(Address::with_last_byte).collect::<Vec<_>>();
+        let codes = client.get_codes(&addresses, None).await.unwrap();
+
+        assert_eq!(rpc.headers().len(), 3);
+        let expected = (1..=5)
+            .map(|byte| Bytes::from(vec![byte]))
+            .collect::<Vec<_>>();
+        assert_eq!(codes, expected);
+    }
+
+    #[tokio::test]
+    async fn test_get_basefee_with_opts() {
+        let rpc = MockRpcServer::spawn(|method, params| {
+            assert_eq!(method, "eth_feeHistory");
+            assert_eq!(params[0], "0x3");
+
+            Ok(serde_json::json!({
+                "oldestBlock": "0x1",
+                "baseFeePerGas": ["0xa", "0x14", "0x1e", "0x28"],
+                "gasUsedRatio": [0.5, 0.5, 0.5],
+            }))
+        })
+        .await;
+        let client = RpcClient::new(rpc.url());
+
+        let latest = BaseFeeOpts {
+            block_count: 3,
+            average: false,
+        };
         assert_eq!(
-            account_state.balance,
-            uint!(10_000U256 * Uint::from(ETH_TO_WEI))
+            client.get_basefee_with_opts(None, latest).await.unwrap(),
+            30
         );
 
-        assert_eq!(account_state.transaction_count, 0);
+        let average = BaseFeeOpts {
+            block_count: 3,
+            average: true,
+        };
+        assert_eq!(
+            client.get_basefee_with_opts(None, average).await.unwrap(),
+            20
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_fee_history_rewards() {
+        let rpc = MockRpcServer::spawn(|method, _| {
+            assert_eq!(method, "eth_feeHistory");
+
+            Ok(serde_json::json!({
+                "oldestBlock": "0xa",
+                "baseFeePerGas": ["0x64", "0x6e", "0x78"],
+                "gasUsedRatio": [0.25, 0.75],
+                "reward": [["0x1", "0x2", "0x3"], ["0x4", "0x5", "0x6"]],
+            }))
+        })
+        .await;
+        let client = RpcClient::new(rpc.url());
+
+        let history = client
+            .get_fee_history(2, Some(11), &[10.0, 50.0, 90.0])
+            .await
+            .unwrap();
+
+        assert_eq!(history.oldest_block, 10);
+        assert_eq!(history.base_fee_per_gas, vec![100, 110, 120]);
+        assert_eq!(history.gas_used_ratio, vec![0.25, 0.75]);
+
+        // One row per block, one column per percentile
+        let reward = history.reward.unwrap();
+        assert_eq!(reward.len(), 2);
+        assert_eq!(reward[0], vec![1, 2, 3]);
+        assert_eq!(reward[1][2], 6);
+
+        let params = &rpc.calls("eth_feeHistory")[0];
+        assert_eq!(params[0], "0x2");
+        assert_eq!(params[1], "0xb");
+        assert_eq!(params[2], serde_json::json!([10.0, 50.0, 90.0]));
+    }
+
+    #[tokio::test]
+    async fn test_get_basefee_empty_fee_history() {
+        let rpc = MockRpcServer::spawn(|_, _| {
+            Ok(serde_json::json!({
+                "oldestBlock": "0x1",
+                "baseFeePerGas": [],
+                "gasUsedRatio": [],
+            }))
+        })
+        .await;
+        let client = RpcClient::new(rpc.url());
+
+        assert!(client.get_basefee(None).await.is_err());
+
+        let average = BaseFeeOpts {
+            block_count: 3,
+            average: true,
+        };
+        assert!(client.get_basefee_with_opts(None, average).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_blob_basefee_from_fee_history() {
+        let rpc = MockRpcServer::spawn(|method, _| {
+            assert_eq!(method, "eth_feeHistory");
+
+            Ok(serde_json::json!({
+                "oldestBlock": "0x1",
+                "baseFeePerGas": ["0xa", "0xb"],
+                "gasUsedRatio": [0.5],
+                "baseFeePerBlobGas": ["0x3", "0x4"],
+                "blobGasUsedRatio": [0.5],
+            }))
+        })
+        .await;
+        let client = RpcClient::new(rpc.url());
+
+        assert_eq!(client.get_blob_basefee(None).await.unwrap(), 3);
+        assert_eq!(rpc.call_count("eth_getBlockByNumber"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_blob_basefee_from_header() {
+        let rpc = MockRpcServer::spawn(|method, params| match method {
+            "eth_feeHistory" => Ok(serde_json::json!({
+                "oldestBlock": "0x1",
+                "baseFeePerGas": ["0xa", "0xb"],
+                "gasUsedRatio": [0.5],
+            })),
+            "eth_getBlockByNumber" if params[0] == "0x1" => Ok(serde_json::json!({
+                "number": "0x1",
+                "excessBlobGas": "0x32f0ed",
+            })),
+            // Pre-Cancun headers have no excess blob gas
+            "eth_getBlockByNumber" => Ok(serde_json::json!({ "number": "0x2" })),
+            _ => unreachable!(),
+        })
+        .await;
+        let client = RpcClient::new(rpc.url());
+
+        // e^(excess / update fraction) with an excess of exactly one update fraction
+        assert_eq!(client.get_blob_basefee(Some(1)).await.unwrap(), 2);
+        assert!(client.get_blob_basefee(Some(2)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_debug_trace_call_at_state() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(serde_json::json!({}))).await;
+        let client = RpcClient::new(rpc.url());
+
+        let account = Address::repeat_byte(0x11);
+        let state_override = StateOverride::from([(
+            account,
+            AccountOverride {
+                balance: Some(U256::from(100)),
+                ..Default::default()
+            },
+        )]);
+        let opts = GethDebugTracingCallOptions::default().with_state_overrides(
+            StateOverride::from([(Address::repeat_byte(0x22), AccountOverride::default())]),
+        );
+
+        client
+            .debug_trace_call_at_state(
+                TransactionRequest::default(),
+                Some(5),
+                state_override,
+                Some(opts),
+            )
+            .await
+            .unwrap();
+
+        let params = &rpc.calls("debug_traceCall")[0];
+        assert_eq!(params[1], "0x5");
+
+        // The given overrides replace the ones of the options
+        let overrides = params[2]["stateOverrides"].as_object().unwrap();
+        assert_eq!(overrides.len(), 1);
+        assert_eq!(
+            overrides[&account.to_string().to_lowercase()]["balance"],
+            "0x64"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_debug_trace_call_with_cancel() {
+        let rpc =
+            MockRpcServer::spawn_with_delay(Duration::from_secs(10), |_, _| Ok(Value::Null)).await;
+        let client = RpcClient::new(rpc.url());
+
+        let cancel = CancellationToken::new();
+        let canceller = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            canceller.cancel();
+        });
+
+        let start = std::time::Instant::now();
+        let res = client
+            .debug_trace_call_with_cancel(TransactionRequest::default(), None, None, &cancel)
+            .await;
+
+        assert!(res.is_err());
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    fn fast_retry_config() -> RpcClientConfig {
+        RpcClientConfig {
+            timeout: Duration::from_millis(200),
+            max_retries: 2,
+            backoff: Duration::from_millis(10),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_http_errors() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x10"))).await;
+        let client = RpcClient::new_with_config(rpc.url(), fast_retry_config());
+
+        rpc.fail_next(2, StatusCode::BAD_GATEWAY);
+        assert_eq!(client.get_head().await.unwrap(), 16);
+        assert_eq!(rpc.call_count("eth_blockNumber"), 3);
+
+        // Give up after `max_retries`
+        rpc.fail_next(3, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(client.get_head().await.is_err());
+        assert_eq!(rpc.call_count("eth_blockNumber"), 6);
+    }
+
+    #[tokio::test]
+    async fn test_retry_rate_limited_requests() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x10"))).await;
+        let client = RpcClient::new_with_config(rpc.url(), fast_retry_config());
+
+        rpc.fail_next(2, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(client.get_head().await.unwrap(), 16);
+        assert_eq!(rpc.call_count("eth_blockNumber"), 3);
+
+        // Rate-limited requests fail right away when their retries are disabled
+        let config = RpcClientConfig {
+            retry_rate_limited: false,
+            ..fast_retry_config()
+        };
+        let client = RpcClient::new_with_config(rpc.url(), config);
+
+        rpc.fail_next(1, StatusCode::TOO_MANY_REQUESTS);
+        let err = client.get_head().await.unwrap_err();
+        assert!(is_rate_limited(&err));
+        assert_eq!(rpc.call_count("eth_blockNumber"), 4);
+    }
+
+    #[tokio::test]
+    async fn test_health_check_synced() {
+        let anvil = launch_anvil();
+        let anvil_url = Url::from_str(&anvil.endpoint()).unwrap();
+        let client = RpcClient::new(anvil_url);
+
+        let health = client.health_check().await.unwrap();
+        assert!(!health.is_syncing);
+        assert_eq!(health.sync_progress, None);
+        assert!(health.head <= client.get_head().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_health_check_syncing() {
+        let rpc = MockRpcServer::spawn(|method, _| match method {
+            "eth_syncing" => Ok(serde_json::json!({
+                "startingBlock": "0x0",
+                "currentBlock": "0x64",
+                "highestBlock": "0xc8",
+            })),
+            "eth_blockNumber" => Ok(serde_json::json!("0x64")),
+            _ => unreachable!(),
+        })
+        .await;
+        let client = RpcClient::new(rpc.url());
+
+        let health = client.health_check().await.unwrap();
+        assert_eq!(
+            health,
+            RpcHealth {
+                is_syncing: true,
+                head: 100,
+                sync_progress: Some(SyncProgress {
+                    current_block: 100,
+                    highest_block: 200,
+                }),
+            }
+        );
+
+        // An unreachable endpoint is an error, not a syncing node
+        let client = RpcClient::new_with_config(dead_endpoint().await, fast_retry_config());
+        assert!(client.health_check().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_on_client_errors() {
+        let rpc = MockRpcServer::spawn(|_, _| {
+            Err(serde_json::json!({ "code": -32000, "message": "execution reverted" }))
+        })
+        .await;
+        let client = RpcClient::new_with_config(rpc.url(), fast_retry_config());
+
+        // JSON-RPC application errors are not retried
+        assert!(client.get_head().await.is_err());
+        assert_eq!(rpc.call_count("eth_blockNumber"), 1);
+
+        // Neither are 4xx responses
+        rpc.fail_next(1, StatusCode::BAD_REQUEST);
+        assert!(client.get_head().await.is_err());
+        assert_eq!(rpc.call_count("eth_blockNumber"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_jwt_authentication() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x10"))).await;
+        let secret = [0x42; 32];
+        let client = RpcClient::new_with_jwt(rpc.url(), secret);
+
+        assert_eq!(client.get_head().await.unwrap(), 16);
+        assert_eq!(client.get_head().await.unwrap(), 16);
+
+        // Every request carries a valid token
+        let secret = JwtSecret::from_hex(hex::encode(secret)).unwrap();
+        let headers = rpc.headers();
+        assert_eq!(headers.len(), 2);
+        for headers in headers {
+            let auth = headers["authorization"].to_str().unwrap();
+            let token = auth.strip_prefix("Bearer ").unwrap();
+            secret.validate(token).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_headers() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x10"))).await;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "secret".parse().unwrap());
+        let client = RpcClient::new_with_headers(rpc.url(), headers);
+
+        // Single call
+        assert_eq!(client.get_head().await.unwrap(), 16);
+
+        // Batched call
+        let addresses = [Address::repeat_byte(1), Address::repeat_byte(2)];
+        client.get_codes(&addresses, None).await.unwrap();
+
+        let headers = rpc.headers();
+        assert_eq!(headers.len(), 2);
+        assert!(headers.iter().all(|h| h["x-api-key"] == "secret"));
+    }
+
+    #[tokio::test]
+    async fn test_builder_combined_options() {
+        let primary = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x1"))).await;
+        let backup = MockRpcServer::spawn(|method, params| match method {
+            "eth_blockNumber" => Ok(serde_json::json!("0x10")),
+            "eth_getProof" => Ok(mock_proof(params)),
+            _ => Err(serde_json::json!({ "code": -32000, "message": "missing trie node" })),
+        })
+        .await;
+        let archive = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x6001"))).await;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "secret".parse().unwrap());
+        let client = RpcClient::builder(primary.url())
+            .failover_urls(vec![backup.url()])
+            .headers(headers)
+            .gzip(true)
+            .max_retries(0)
+            .max_batch_size(2)
+            .archive_url(archive.url())
+            .build();
+
+        // The primary is down and not retried: the request fails over to the backup
+        primary.fail_next(1, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(client.get_head().await.unwrap(), 16);
+        assert_eq!(primary.call_count("eth_blockNumber"), 1);
+
+        // Historical state is fetched from the archive node
+        let address = Address::repeat_byte(1);
+        let code = client.get_code(address, None).await.unwrap();
+        assert_eq!(code, Bytes::from_static(&[0x60, 0x01]));
+        assert_eq!(archive.call_count("eth_getCode"), 1);
+
+        // Batches are split into chunks of 2 calls
+        let opts = (1..=5)
+            .map(|byte| (Address::repeat_byte(byte), vec![], BlockNumberOrTag::Latest))
+            .collect();
+        client.get_proof_batched(opts).await.unwrap();
+
+        // Every request to the backup carries the headers and a compressed body
+        let headers = backup.headers();
+        assert_eq!(headers.len(), 5);
+        for headers in headers {
+            assert_eq!(headers["x-api-key"], "secret");
+            assert_eq!(headers["content-encoding"], "gzip");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_builder_jwt_with_response_limit() {
+        let code = format!("0x{}", "ab".repeat(1 << 10));
+        let rpc = MockRpcServer::spawn(move |method, _| match method {
+            "eth_getCode" => Ok(Value::String(code.clone())),
+            _ => Ok(serde_json::json!("0x10")),
+        })
+        .await;
+
+        let secret = [0x42; 32];
+        let client = RpcClient::builder(rpc.url())
+            .jwt(secret)
+            .max_response_bytes(512)
+            .timeout(Duration::from_secs(1))
+            .build();
+
+        assert_eq!(client.get_head().await.unwrap(), 16);
+        let err = client
+            .get_code(Address::repeat_byte(1), None)
+            .await
+            .unwrap_err();
+        assert!(is_response_too_large(&err), "error: {err}");
+
+        // Both requests carry a valid token
+        let secret = JwtSecret::from_hex(hex::encode(secret)).unwrap();
+        let headers = rpc.headers();
+        assert_eq!(headers.len(), 2);
+        for headers in headers {
+            let auth = headers["authorization"].to_str().unwrap();
+            secret
+                .validate(auth.strip_prefix("Bearer ").unwrap())
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_builder_jwt_with_gzip() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x10"))).await;
+        rpc.gzip_responses();
+
+        let secret = [0x42; 32];
+        let client = RpcClient::builder(rpc.url()).jwt(secret).gzip(true).build();
+
+        assert_eq!(client.get_head().await.unwrap(), 16);
+
+        // The request is both authenticated and compressed
+        let secret = JwtSecret::from_hex(hex::encode(secret)).unwrap();
+        let headers = rpc.headers();
+        assert_eq!(headers.len(), 1);
+        let auth = headers[0]["authorization"].to_str().unwrap();
+        secret
+            .validate(auth.strip_prefix("Bearer ").unwrap())
+            .unwrap();
+        assert_eq!(headers[0]["content-encoding"], "gzip");
+    }
+
+    #[tokio::test]
+    async fn test_from_reqwest_client() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x10"))).await;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "secret".parse().unwrap());
+        let http_client = Client::builder()
+            .user_agent("bolt-sidecar-test")
+            .default_headers(headers)
+            .timeout(Duration::from_secs(5))
+            .pool_max_idle_per_host(1)
+            .build()
+            .unwrap();
+        let client = RpcClient::from_reqwest(http_client, rpc.url());
+
+        assert_eq!(client.get_head().await.unwrap(), 16);
+
+        // The request went through the given client
+        let headers = rpc.headers();
+        assert_eq!(headers[0]["user-agent"], "bolt-sidecar-test");
+        assert_eq!(headers[0]["x-api-key"], "secret");
+    }
+
+    #[tokio::test]
+    async fn test_gzip_compression() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x10"))).await;
+        rpc.gzip_responses();
+
+        let config = RpcClientConfig {
+            gzip: true,
+            ..Default::default()
+        };
+        let client = RpcClient::new_with_config(rpc.url(), config);
+
+        // Single call
+        assert_eq!(client.get_head().await.unwrap(), 16);
+
+        // Batched call
+        let addresses = [Address::repeat_byte(1), Address::repeat_byte(2)];
+        let codes = client.get_codes(&addresses, None).await.unwrap();
+        assert_eq!(codes.len(), 2);
+
+        let headers = rpc.headers();
+        assert_eq!(headers.len(), 2);
+        for headers in headers {
+            assert_eq!(headers["content-encoding"], "gzip");
+            assert!(headers["accept-encoding"]
+                .to_str()
+                .unwrap()
+                .contains("gzip"));
+        }
+
+        // Compression is opt-in
+        let client = RpcClient::new(rpc.url());
+        assert_eq!(client.get_head().await.unwrap(), 16);
+        let headers = rpc.headers();
+        assert!(!headers[2].contains_key("content-encoding"));
+        assert!(!headers[2].contains_key("accept-encoding"));
+    }
+
+    #[tokio::test]
+    async fn test_max_response_bytes() {
+        // A 2MB code, twice the limit
+        let code = format!("0x{}", "ab".repeat(1 << 20));
+        let rpc = MockRpcServer::spawn(move |method, _| match method {
+            "eth_getCode" => Ok(Value::String(code.clone())),
+            _ => Ok(serde_json::json!("0x10")),
+        })
+        .await;
+        rpc.gzip_responses();
+
+        let address = Address::repeat_byte(1);
+        for gzip in [false, true] {
+            let config = RpcClientConfig {
+                gzip,
+                max_response_bytes: Some(1 << 20),
+                ..Default::default()
+            };
+            let client = RpcClient::new_with_config(rpc.url(), config);
+
+            // Small responses are unaffected
+            assert_eq!(client.get_head().await.unwrap(), 16);
+
+            // The decompressed body is capped as well, not only the bytes on the wire
+            let err = client.get_code(address, None).await.unwrap_err();
+            assert!(is_response_too_large(&err), "gzip: {gzip}, error: {err}");
+
+            let err = client
+                .contextual()
+                .get_code(address, None)
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                err,
+                RpcClientError::ResponseTooLarge {
+                    method: "eth_getCode",
+                    ..
+                }
+            ));
+        }
+
+        // Responses are not capped by default
+        let client = RpcClient::new(rpc.url());
+        assert_eq!(client.get_code(address, None).await.unwrap().len(), 1 << 20);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x10"))).await;
+        let config = RpcClientConfig {
+            rate_limit: Some(RateLimit::new(20.0, 5)),
+            ..Default::default()
+        };
+        let client = RpcClient::new_with_config(rpc.url(), config);
+
+        // The burst goes through immediately
+        let start = Instant::now();
+        join_all((0..5).map(|_| client.get_head())).await;
+        assert!(start.elapsed() < Duration::from_millis(200));
+
+        // The flood is throttled to 20 requests per second, rather than failed
+        let start = Instant::now();
+        let results = join_all((0..20).map(|_| client.get_head())).await;
+        assert!(results.iter().all(Result::is_ok));
+        assert!(start.elapsed() >= Duration::from_millis(950));
+
+        assert_eq!(rpc.call_count("eth_blockNumber"), 25);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_rate_limit_is_ignored() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x10"))).await;
+
+        for requests_per_second in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let limit = RateLimit::new(requests_per_second, 1);
+            assert!(!limit.is_valid());
+
+            let config = RpcClientConfig {
+                rate_limit: Some(limit),
+                ..Default::default()
+            };
+            let client = RpcClient::new_with_config(rpc.url(), config);
+
+            // The requests beyond the burst don't wait for a rate that never refills
+            let start = Instant::now();
+            let results = join_all((0..3).map(|_| client.get_head())).await;
+            assert!(results.iter().all(Result::is_ok));
+            assert!(start.elapsed() < Duration::from_millis(500));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_batches() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x"))).await;
+        let addresses = (1..=10).map(Address::with_last_byte).collect::<Vec<_>>();
+
+        // Every call of a batch counts as a request
+        let config = RpcClientConfig {
+            rate_limit: Some(RateLimit::new(10.0, 10)),
+            ..Default::default()
+        };
+        let client = RpcClient::new_with_config(rpc.url(), config);
+
+        let start = Instant::now();
+        client.get_codes(&addresses, None).await.unwrap();
+        client.get_codes(&addresses, None).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(950));
+
+        // A batch counts as a single request
+        let config = RpcClientConfig {
+            rate_limit: Some(RateLimit {
+                count_batch_calls: false,
+                ..RateLimit::new(10.0, 10)
+            }),
+            ..Default::default()
+        };
+        let client = RpcClient::new_with_config(rpc.url(), config);
+
+        let start = Instant::now();
+        client.get_codes(&addresses, None).await.unwrap();
+        client.get_codes(&addresses, None).await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        let rpc = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x10"))).await;
+        let cooldown = Duration::from_millis(200);
+        let config = RpcClientConfig {
+            max_retries: 0,
+            circuit_breaker: Some(CircuitBreakerConfig::new(
+                2,
+                Duration::from_secs(10),
+                cooldown,
+            )),
+            ..Default::default()
+        };
+        let client = RpcClient::new_with_config(rpc.url(), config);
+
+        // Closed: the failures go through to the node, and open the circuit
+        rpc.fail_next(2, StatusCode::SERVICE_UNAVAILABLE);
+        for _ in 0..2 {
+            let err = client.contextual().get_head().await.unwrap_err();
+            assert!(matches!(err, RpcClientError::Request { .. }));
+        }
+
+        // Open: fail fast without calling the node
+        let err = client.contextual().get_head().await.unwrap_err();
+        assert!(matches!(err, RpcClientError::CircuitOpen { .. }));
+        assert_eq!(err.method(), Some("eth_blockNumber"));
+        assert_eq!(rpc.call_count("eth_blockNumber"), 2);
+
+        // Half-open: the failed probe opens the circuit again
+        tokio::time::sleep(cooldown).await;
+        rpc.fail_next(1, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(client.get_head().await.is_err());
+        let err = client.contextual().get_head().await.unwrap_err();
+        assert!(matches!(err, RpcClientError::CircuitOpen { .. }));
+        assert_eq!(rpc.call_count("eth_blockNumber"), 3);
+
+        // The successful probe closes the circuit
+        tokio::time::sleep(cooldown).await;
+        assert_eq!(client.get_head().await.unwrap(), 16);
+        assert_eq!(client.get_head().await.unwrap(), 16);
+        assert_eq!(rpc.call_count("eth_blockNumber"), 5);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_ignores_json_rpc_errors() {
+        let rpc = MockRpcServer::spawn(|_, _| {
+            Err(serde_json::json!({ "code": -32000, "message": "execution reverted" }))
+        })
+        .await;
+        let config = RpcClientConfig {
+            circuit_breaker: Some(CircuitBreakerConfig::new(
+                2,
+                Duration::from_secs(10),
+                Duration::from_secs(10),
+            )),
+            ..Default::default()
+        };
+        let client = RpcClient::new_with_config(rpc.url(), config);
+
+        for _ in 0..5 {
+            let err = client.contextual().get_head().await.unwrap_err();
+            assert!(matches!(err.transport_error(), RpcError::ErrorResp(_)));
+        }
+        assert_eq!(rpc.call_count("eth_blockNumber"), 5);
+    }
+
+    /// Returns the URL of a local port that refuses connections.
+    async fn dead_endpoint() -> Url {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        format!("http://{addr}").parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_failover_to_backup() {
+        let backup = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x10"))).await;
+        let client = RpcClient::new_failover(
+            vec![dead_endpoint().await, backup.url()],
+            FailoverPolicy::default(),
+        );
+
+        // Single calls and batches both go through the backup
+        assert_eq!(client.get_head().await.unwrap(), 16);
+        let addresses = [Address::repeat_byte(1), Address::repeat_byte(2)];
+        client.get_codes(&addresses, None).await.unwrap();
+
+        assert_eq!(backup.call_count("eth_blockNumber"), 1);
+        assert_eq!(backup.call_count("eth_getCode"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failover_policies() {
+        let primary = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x1"))).await;
+        let backup = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x2"))).await;
+        let urls = vec![primary.url(), backup.url()];
+
+        // Sticky primary: stay on the backup until the cooldown elapses
+        let cooldown = Duration::from_millis(200);
+        let client =
+            RpcClient::new_failover(urls.clone(), FailoverPolicy::StickyPrimary { cooldown });
+        primary.fail_next(1, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(client.get_head().await.unwrap(), 2);
+        assert_eq!(client.get_head().await.unwrap(), 2);
+
+        tokio::time::sleep(cooldown).await;
+        assert_eq!(client.get_head().await.unwrap(), 1);
+
+        // Round robin: stay on the backup
+        let client = RpcClient::new_failover(urls, FailoverPolicy::RoundRobin);
+        primary.fail_next(1, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(client.get_head().await.unwrap(), 2);
+
+        tokio::time::sleep(cooldown).await;
+        assert_eq!(client.get_head().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_no_failover_of_transactions() {
+        let primary = MockRpcServer::spawn_with_delay(Duration::from_millis(500), |_, _| {
+            Ok(serde_json::json!("0x1"))
+        })
+        .await;
+        let backup = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x2"))).await;
+        let client = RpcClientBuilder::new(primary.url())
+            .failover_urls(vec![backup.url()])
+            .failover_policy(FailoverPolicy::RoundRobin)
+            .timeout(Duration::from_millis(100))
+            .max_retries(0)
+            .build();
+
+        // The primary may have received the transaction before timing out
+        assert!(client.send_raw_transaction(Bytes::new()).await.is_err());
+        assert_eq!(primary.call_count("eth_sendRawTransaction"), 1);
+        assert_eq!(backup.call_count("eth_sendRawTransaction"), 0);
+
+        // The following requests still move to the backup
+        assert_eq!(client.get_head().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_no_failover_on_json_rpc_errors() {
+        let primary = MockRpcServer::spawn(|_, _| {
+            Err(serde_json::json!({ "code": -32000, "message": "execution reverted" }))
+        })
+        .await;
+        let backup = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x2"))).await;
+        let client = RpcClient::new_failover(
+            vec![primary.url(), backup.url()],
+            FailoverPolicy::RoundRobin,
+        );
+
+        assert!(client.get_head().await.is_err());
+        assert_eq!(backup.call_count("eth_blockNumber"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_archive_fallback() {
+        let primary = MockRpcServer::spawn(|method, _| match method {
+            "eth_getCode" => Ok(serde_json::json!("0x00")),
+            _ => Err(serde_json::json!({ "code": -32000, "message": "missing trie node 0xabcd (path )" })),
+        })
+        .await;
+        let archive = MockRpcServer::spawn(|_, _| Ok(serde_json::json!("0x6001"))).await;
+        let client = RpcClient::new(primary.url()).with_archive(archive.url());
+
+        // The primary doesn't have the state: the archive answers
+        let tx = TransactionRequest::default().to(Address::repeat_byte(1));
+        let res = client.call(tx, Some(1), None).await.unwrap();
+        assert_eq!(res, Bytes::from_static(&[0x60, 0x01]));
+        assert_eq!(archive.call_count("eth_call"), 1);
+
+        // Successful queries are not sent to the archive
+        client
+            .get_code(Address::repeat_byte(1), Some(1))
+            .await
+            .unwrap();
+        assert_eq!(archive.call_count("eth_getCode"), 0);
+
+        // Neither are other errors
+        let primary = MockRpcServer::spawn(|_, _| {
+            Err(serde_json::json!({ "code": 3, "message": "execution reverted" }))
+        })
+        .await;
+        let client = RpcClient::new(primary.url()).with_archive(archive.url());
+        let tx = TransactionRequest::default().to(Address::repeat_byte(1));
+        assert!(client.call(tx, Some(1), None).await.is_err());
+        assert_eq!(archive.call_count("eth_call"), 1);
+
+        // The archive node is queried with the configuration of the client
+        let primary = MockRpcServer::spawn(|_, _| {
+            Err(serde_json::json!({ "code": -32000, "message": "missing trie node" }))
+        })
+        .await;
+        let config = RpcClientConfig {
+            max_retries: 0,
+            ..Default::default()
+        };
+        let client = RpcClient::new_with_config(primary.url(), config).with_archive(archive.url());
+        archive.fail_next(1, StatusCode::SERVICE_UNAVAILABLE);
+        let err = client
+            .get_code(Address::repeat_byte(1), Some(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            RpcError::Transport(TransportErrorKind::HttpError(_))
+        ));
+        assert_eq!(archive.call_count("eth_getCode"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_request_timeout() {
+        let rpc =
+            MockRpcServer::spawn_with_delay(Duration::from_secs(10), |_, _| Ok(Value::Null)).await;
+        let client = RpcClient::new_with_config(rpc.url(), fast_retry_config());
+
+        let start = std::time::Instant::now();
+        assert!(client.get_head().await.is_err());
+
+        // 3 attempts of 200ms each, plus the backoff
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    /// Returns an empty proof for the address in the `eth_getProof` params.
+    fn mock_proof(params: &Value) -> Value {
+        serde_json::json!({
+            "address": params[0],
+            "balance": "0x0",
+            "codeHash": B256::ZERO,
+            "nonce": "0x0",
+            "storageHash": B256::ZERO,
+            "accountProof": [],
+            "storageProof": [],
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get_proof_batched_chunks_preserve_order() {
+        let rpc = MockRpcServer::spawn(|_, params| Ok(mock_proof(params))).await;
+        let config = RpcClientConfig {
+            max_batch_size: 2,
+            ..Default::default()
+        };
+        let client = RpcClient::new_with_config(rpc.url(), config);
+
+        let addresses = (1..=5).map(Address::repeat_byte).collect::<Vec<_>>();
+        let opts = addresses
+            .iter()
+            .map(|address| (*address, vec![], BlockNumberOrTag::Latest))
+            .collect();
+
+        let proofs = client.get_proof_batched(opts).await.unwrap();
+
+        let proof_addresses = proofs.iter().map(|p| p.address).collect::<Vec<_>>();
+        assert_eq!(proof_addresses, addresses);
+        assert_eq!(rpc.call_count("eth_getProof"), 5);
+    }
+
+    #[tokio::test]
+    async fn test_get_proof_batched_reversed_batch() {
+        let rpc = MockRpcServer::spawn(|_, params| Ok(mock_proof(params))).await;
+        rpc.reverse_batches();
+        let config = RpcClientConfig {
+            max_batch_size: 3,
+            ..Default::default()
+        };
+        let client = RpcClient::new_with_config(rpc.url(), config);
+
+        let addresses = (1..=7).map(Address::repeat_byte).collect::<Vec<_>>();
+        let opts = addresses
+            .iter()
+            .map(|address| (*address, vec![], BlockNumberOrTag::Latest))
+            .collect();
+
+        let proofs = client.get_proof_batched(opts).await.unwrap();
+
+        let proof_addresses = proofs.iter().map(|p| p.address).collect::<Vec<_>>();
+        assert_eq!(proof_addresses, addresses);
+    }
+
+    #[tokio::test]
+    async fn test_get_proof_batched_concurrent() {
+        let delay = Duration::from_millis(200);
+        let rpc = MockRpcServer::spawn_with_delay(delay, |_, params| Ok(mock_proof(params))).await;
+        let client = RpcClient::new(rpc.url());
+
+        let addresses = (1..=11).map(Address::repeat_byte).collect::<Vec<_>>();
+        let opts = addresses
+            .iter()
+            .map(|address| (*address, vec![], BlockNumberOrTag::Latest))
+            .collect::<Vec<_>>();
+
+        // 6 chunks, at most 2 in flight: 3 round trips
+        let start = Instant::now();
+        let proofs = client
+            .get_proof_batched_concurrent(opts, 2, 2)
+            .await
+            .unwrap();
+        let elapsed = start.elapsed();
+
+        let proof_addresses = proofs.iter().map(|p| p.address).collect::<Vec<_>>();
+        assert_eq!(proof_addresses, addresses);
+        assert_eq!(rpc.call_count("eth_getProof"), 11);
+        assert_eq!(rpc.headers().len(), 6);
+        assert!(elapsed >= delay * 3, "concurrency not capped: {elapsed:?}");
+        assert!(
+            elapsed < delay * 6,
+            "chunks not sent concurrently: {elapsed:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_proof_batched_chunk_error() {
+        let rpc = MockRpcServer::spawn(|_, params| Ok(mock_proof(params))).await;
+        let config = RpcClientConfig {
+            max_retries: 0,
+            max_batch_size: 2,
+            ..Default::default()
+        };
+        let client = RpcClient::new_with_config(rpc.url(), config);
+
+        let addresses = (1..=5).map(Address::repeat_byte).collect::<Vec<_>>();
+        let opts = addresses
+            .iter()
+            .map(|address| (*address, vec![], BlockNumberOrTag::Latest))
+            .collect();
+
+        // Only the first chunk fails, and the following ones are not sent
+        rpc.fail_next(1, StatusCode::BAD_GATEWAY);
+        let err = client.get_proof_batched(opts).await.unwrap_err();
+
+        let RpcError::Transport(TransportErrorKind::Custom(err)) = err else {
+            panic!("expected a batch chunk error, got {err:?}");
+        };
+        let err = err.downcast_ref::<BatchChunkError>().unwrap();
+        assert_eq!(err.addresses, addresses[..2]);
+        assert_eq!(rpc.call_count("eth_getProof"), 2);
     }
 
     #[tokio::test]
@@ -211,7 +3894,7 @@ mod tests {
         let rpc_client = RpcClient::new(rpc_url);
 
         let proof: EIP1186AccountProofResponse = rpc_client
-            .0
+            .client
             .request(
                 "eth_getProof",
                 (
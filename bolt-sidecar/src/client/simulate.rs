@@ -0,0 +1,81 @@
+//! Types of the `eth_simulateV1` JSON-RPC method, which simulates multiple blocks of
+//! dependent calls atomically on top of a base block.
+//!
+//! Reference: <https://github.com/ethereum/execution-apis/pull/484>
+
+use alloy_primitives::{Bytes, U64};
+use alloy_rpc_types::{state::StateOverride, Block, BlockOverrides, Log, TransactionRequest};
+use serde::{Deserialize, Serialize};
+
+/// A block of calls to simulate, with optional block and state overrides.
+/// Each call is executed on top of the state left by the previous ones.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimBlock {
+    /// Overrides of the block header fields.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_overrides: Option<BlockOverrides>,
+    /// Overrides of the state before the calls are executed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_overrides: Option<StateOverride>,
+    /// The calls to execute, in order.
+    pub calls: Vec<TransactionRequest>,
+}
+
+/// The payload of an `eth_simulateV1` request.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SimulatePayload {
+    /// The blocks to simulate, in order.
+    pub block_state_calls: Vec<SimBlock>,
+    /// Whether to report ETH transfers as logs.
+    pub trace_transfers: bool,
+}
+
+/// A block simulated by `eth_simulateV1`, with the results of its calls.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimulatedBlock {
+    /// The simulated block.
+    #[serde(flatten)]
+    pub inner: Block,
+    /// The results of the calls, in the same order as the requested calls.
+    pub calls: Vec<SimCallResult>,
+}
+
+/// The result of a single simulated call.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimCallResult {
+    /// The data returned by the call, i.e. the revert data if it failed.
+    pub return_data: Bytes,
+    /// The logs emitted by the call.
+    #[serde(default)]
+    pub logs: Vec<Log>,
+    /// The gas used by the call.
+    pub gas_used: U64,
+    /// The status of the call: 1 on success, 0 on failure.
+    pub status: U64,
+    /// The error of the call, if it failed. The message includes the revert reason.
+    pub error: Option<SimCallError>,
+}
+
+impl SimCallResult {
+    /// Returns `true` if the call succeeded.
+    pub fn is_success(&self) -> bool {
+        self.status == U64::from(1)
+    }
+
+    /// Returns the revert reason (or other error message) of the call, if it failed.
+    pub fn revert_reason(&self) -> Option<&str> {
+        self.error.as_ref().map(|err| err.message.as_str())
+    }
+}
+
+/// The error of a failed simulated call.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimCallError {
+    /// The error code.
+    pub code: i64,
+    /// The error message.
+    pub message: String,
+}
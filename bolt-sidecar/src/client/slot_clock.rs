@@ -0,0 +1,293 @@
+//! Mapping of the beacon chain slots, against which commitments are made, to the
+//! execution blocks that the trace and proof machinery works on.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use alloy_primitives::BlockNumber;
+use alloy_rpc_types::Header;
+use alloy_transport::TransportResult;
+use futures::{future, Stream, StreamExt};
+
+use super::rpc::RpcClient;
+use crate::primitives::Slot;
+
+/// An execution head, along with the slot it was proposed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotHead {
+    /// The slot of the block timestamp.
+    pub slot: Slot,
+    /// The execution block number.
+    pub block: BlockNumber,
+}
+
+/// Converts between beacon chain slots and timestamps, given the genesis time and the
+/// slot duration, and resolves the execution block to target for a given slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotClock {
+    genesis_time: u64,
+    seconds_per_slot: u64,
+}
+
+impl SlotClock {
+    /// Create a new clock from the genesis time (in seconds since the UNIX epoch)
+    /// and the slot duration in seconds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `seconds_per_slot` is zero.
+    pub fn new(genesis_time: u64, seconds_per_slot: u64) -> Self {
+        assert!(seconds_per_slot > 0, "slot duration must be positive");
+
+        Self {
+            genesis_time,
+            seconds_per_slot,
+        }
+    }
+
+    /// Returns the genesis time, in seconds since the UNIX epoch.
+    pub fn genesis_time(&self) -> u64 {
+        self.genesis_time
+    }
+
+    /// Returns the slot duration, in seconds.
+    pub fn seconds_per_slot(&self) -> u64 {
+        self.seconds_per_slot
+    }
+
+    /// Returns the expected timestamp of the given slot, i.e. the start of the slot,
+    /// or `None` if it doesn't fit in a `u64`.
+    pub fn slot_timestamp(&self, slot: Slot) -> Option<u64> {
+        slot.checked_mul(self.seconds_per_slot)?
+            .checked_add(self.genesis_time)
+    }
+
+    /// Returns the slot containing the given timestamp, or `None` if it is before genesis.
+    pub fn slot_at(&self, timestamp: u64) -> Option<Slot> {
+        timestamp
+            .checked_sub(self.genesis_time)
+            .map(|elapsed| elapsed / self.seconds_per_slot)
+    }
+
+    /// Returns the current slot, or `None` if the genesis time is still in the future.
+    pub fn current_slot(&self) -> Option<Slot> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time went backwards")
+            .as_secs();
+
+        self.slot_at(now)
+    }
+
+    /// Returns the execution block to target for the given slot.
+    ///
+    /// This is the first block with a timestamp at or after the start of the slot, so a
+    /// missed slot (without an execution block) maps to the next available block. For a
+    /// slot after the current head, the block is extrapolated from the head, assuming
+    /// that none of the slots in between is missed.
+    pub async fn block_for_slot(
+        &self,
+        rpc: &RpcClient,
+        slot: Slot,
+    ) -> TransportResult<BlockNumber> {
+        let head = rpc.get_header(None).await?;
+        let head_number = head.number.unwrap_or_default();
+        let head_slot = self.slot_at(head.timestamp).unwrap_or_default();
+
+        // A slot whose timestamp overflows is far after the head as well
+        let timestamp = match self.slot_timestamp(slot) {
+            Some(timestamp) if timestamp <= head.timestamp => timestamp,
+            _ => return Ok(head_number.saturating_add(slot - head_slot)),
+        };
+
+        // There is at most one block per slot, so the target block can't be further
+        // from the head than the number of slots in between.
+        let mut low = head_number.saturating_sub(head_slot - slot);
+        let mut high = head_number;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if rpc.get_header(Some(mid)).await?.timestamp >= timestamp {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+
+        Ok(high)
+    }
+
+    /// Subscribe to the new execution heads, yielding each head along with the slot it
+    /// was proposed in. Requires a client with a pubsub transport (WS or IPC).
+    pub async fn subscribe_slot_heads(
+        &self,
+        rpc: &RpcClient,
+    ) -> TransportResult<impl Stream<Item = SlotHead> + 'static> {
+        let heads = rpc.subscribe_new_heads().await?;
+
+        Ok(self.slot_heads(heads.filter_map(|block| future::ready(Some(block.ok()?.header)))))
+    }
+
+    /// Maps the given stream of execution heads to the slots they were proposed in.
+    /// Heads without a block number or before genesis are skipped.
+    pub fn slot_heads(&self, headers: impl Stream<Item = Header>) -> impl Stream<Item = SlotHead> {
+        let clock = *self;
+        headers.filter_map(move |header| future::ready(clock.slot_head(&header)))
+    }
+
+    fn slot_head(&self, header: &Header) -> Option<SlotHead> {
+        Some(SlotHead {
+            slot: self.slot_at(header.timestamp)?,
+            block: header.number?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_rpc_types::Block;
+    use futures::stream;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::test_util::MockRpcServer;
+
+    /// Mainnet beacon chain genesis time.
+    const GENESIS_TIME: u64 = 1_606_824_023;
+
+    /// Spawn a mock RPC serving blocks with the given timestamps, the last one being the head.
+    async fn spawn_chain_rpc(timestamps: Vec<u64>) -> MockRpcServer {
+        MockRpcServer::spawn(move |method, params| {
+            assert_eq!(method, "eth_getBlockByNumber");
+
+            let number = match &params[0] {
+                Value::String(tag) if tag == "latest" => timestamps.len() as u64 - 1,
+                Value::String(hex) => u64::from_str_radix(hex.trim_start_matches("0x"), 16)
+                    .expect("valid block number"),
+                _ => panic!("unexpected block tag"),
+            };
+
+            let block: Block = Block {
+                header: Header {
+                    number: Some(number),
+                    timestamp: timestamps[number as usize],
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            Ok(serde_json::to_value(block).unwrap())
+        })
+        .await
+    }
+
+    #[test]
+    fn test_slot_timestamp_math() {
+        let clock = SlotClock::new(GENESIS_TIME, 12);
+
+        assert_eq!(clock.slot_timestamp(0), Some(GENESIS_TIME));
+        assert_eq!(clock.slot_timestamp(1), Some(GENESIS_TIME + 12));
+        assert_eq!(
+            clock.slot_timestamp(9_000_000),
+            Some(GENESIS_TIME + 108_000_000)
+        );
+
+        // Overflowing timestamps
+        assert_eq!(clock.slot_timestamp(u64::MAX / 12 + 1), None);
+        assert_eq!(clock.slot_timestamp(u64::MAX / 12), None);
+        assert_eq!(
+            SlotClock::new(u64::MAX, 12).slot_timestamp(0),
+            Some(u64::MAX)
+        );
+        assert_eq!(SlotClock::new(u64::MAX, 12).slot_timestamp(1), None);
+
+        assert_eq!(clock.slot_at(GENESIS_TIME - 1), None);
+        assert_eq!(clock.slot_at(GENESIS_TIME), Some(0));
+        assert_eq!(clock.slot_at(GENESIS_TIME + 11), Some(0));
+        assert_eq!(clock.slot_at(GENESIS_TIME + 12), Some(1));
+
+        for slot in [0, 1, 42, 9_000_000] {
+            assert_eq!(
+                clock.slot_at(clock.slot_timestamp(slot).unwrap()),
+                Some(slot)
+            );
+        }
+
+        assert!(clock.current_slot().unwrap() > 9_000_000);
+        assert_eq!(SlotClock::new(u64::MAX, 12).current_slot(), None);
+    }
+
+    #[tokio::test]
+    async fn test_block_for_missed_slot() {
+        let clock = SlotClock::new(GENESIS_TIME, 12);
+
+        // One block per slot, except for the missed slots 3 and 5
+        let slots = [0, 1, 2, 4, 6, 7];
+        let timestamps = slots.iter().map(|s| clock.slot_timestamp(*s).unwrap());
+        let rpc = spawn_chain_rpc(timestamps.collect()).await;
+        let client = RpcClient::new(rpc.url());
+
+        let mut resolved = Vec::new();
+        for slot in 0..=7 {
+            resolved.push(clock.block_for_slot(&client, slot).await.unwrap());
+        }
+        assert_eq!(resolved, vec![0, 1, 2, 3, 3, 4, 4, 5]);
+
+        // Future slots are extrapolated from the head
+        assert_eq!(clock.block_for_slot(&client, 8).await.unwrap(), 6);
+        assert_eq!(clock.block_for_slot(&client, 10).await.unwrap(), 8);
+        assert_eq!(
+            clock.block_for_slot(&client, u64::MAX).await.unwrap(),
+            u64::MAX
+        );
+    }
+
+    #[tokio::test]
+    async fn test_block_for_slot_unaligned_head() {
+        let clock = SlotClock::new(GENESIS_TIME, 12);
+
+        // The head is mined in the middle of slot 2
+        let rpc = spawn_chain_rpc(vec![GENESIS_TIME, GENESIS_TIME + 12, GENESIS_TIME + 30]).await;
+        let client = RpcClient::new(rpc.url());
+
+        assert_eq!(clock.block_for_slot(&client, 1).await.unwrap(), 1);
+        assert_eq!(clock.block_for_slot(&client, 2).await.unwrap(), 2);
+        assert_eq!(clock.block_for_slot(&client, 3).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_slot_heads_map_to_blocks() {
+        let clock = SlotClock::new(GENESIS_TIME, 12);
+
+        // One block per slot, except for the missed slot 3
+        let timestamps = [0, 1, 2, 4, 5]
+            .iter()
+            .map(|s| clock.slot_timestamp(*s).unwrap())
+            .collect::<Vec<_>>();
+        let rpc = spawn_chain_rpc(timestamps.clone()).await;
+        let client = RpcClient::new(rpc.url());
+
+        // Mock the new heads, with a header without a number that is skipped
+        let mut headers = timestamps
+            .iter()
+            .enumerate()
+            .map(|(number, &timestamp)| Header {
+                number: Some(number as u64),
+                timestamp,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        headers.insert(1, Header::default());
+
+        let heads = clock
+            .slot_heads(stream::iter(headers))
+            .collect::<Vec<_>>()
+            .await;
+        let slots = heads.iter().map(|head| head.slot).collect::<Vec<_>>();
+        assert_eq!(slots, vec![0, 1, 2, 4, 5]);
+
+        for head in heads {
+            assert_eq!(
+                clock.block_for_slot(&client, head.slot).await.unwrap(),
+                head.block
+            );
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! A stream of new execution block numbers, used to drive the components that act on
+//! every new block (e.g. flushing the traces buffered for future blocks).
+
+use std::{
+    fmt,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use alloy_primitives::BlockNumber;
+use futures::{
+    future,
+    stream::{self, BoxStream},
+    Stream, StreamExt,
+};
+use tokio::time::MissedTickBehavior;
+
+use super::rpc::RpcClient;
+
+/// A [Stream] that yields every new block number of the chain exactly once, in order.
+///
+/// The heads are either received from a `newHeads` subscription or polled with
+/// `eth_blockNumber`. Duplicate and older heads are skipped, and the heights skipped
+/// by a jump of the head (e.g. when polling slower than the block time) are filled in.
+/// The first yielded block is the head at the time of the first observation.
+///
+/// Polling the ticker is cancel-safe: no block is lost if the `next()` future is dropped.
+pub struct BlockTicker {
+    heads: BoxStream<'static, BlockNumber>,
+    /// The last yielded block number, if any.
+    last: Option<BlockNumber>,
+    /// The highest head observed so far.
+    head: Option<BlockNumber>,
+}
+
+impl fmt::Debug for BlockTicker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockTicker")
+            .field("last", &self.last)
+            .field("head", &self.head)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BlockTicker {
+    /// Create a ticker that subscribes to `newHeads` if the client supports it (WS and
+    /// IPC endpoints), and polls `eth_blockNumber` on the given interval otherwise.
+    /// It also falls back to polling if the subscription ends.
+    pub async fn new(rpc: RpcClient, poll_interval: Duration) -> Self {
+        match rpc.subscribe_new_heads().await {
+            Ok(heads) => {
+                let heads = heads
+                    .filter_map(|block| future::ready(block.ok()?.header.number))
+                    .chain(poll_heads(rpc, poll_interval));
+
+                Self::from_heads(heads.boxed())
+            }
+            Err(_) => Self::polling(rpc, poll_interval),
+        }
+    }
+
+    /// Create a ticker that polls `eth_blockNumber` on the given interval.
+    pub fn polling(rpc: RpcClient, interval: Duration) -> Self {
+        Self::from_heads(poll_heads(rpc, interval).boxed())
+    }
+
+    fn from_heads(heads: BoxStream<'static, BlockNumber>) -> Self {
+        Self {
+            heads,
+            last: None,
+            head: None,
+        }
+    }
+}
+
+impl Stream for BlockTicker {
+    type Item = BlockNumber;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            match (this.last, this.head) {
+                (None, Some(head)) => {
+                    this.last = Some(head);
+                    return Poll::Ready(Some(head));
+                }
+                (Some(last), Some(head)) if head > last => {
+                    this.last = Some(last + 1);
+                    return Poll::Ready(Some(last + 1));
+                }
+                _ => {}
+            }
+
+            match ready!(this.heads.poll_next_unpin(cx)) {
+                Some(head) => this.head = Some(this.head.map_or(head, |h| h.max(head))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+/// Polls the head block number on the given interval. Failed requests are skipped.
+fn poll_heads(rpc: RpcClient, interval: Duration) -> impl Stream<Item = BlockNumber> {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    stream::unfold((rpc, ticker), |(rpc, mut ticker)| async move {
+        loop {
+            ticker.tick().await;
+
+            match rpc.get_head().await {
+                Ok(head) => return Some((head, (rpc, ticker))),
+                Err(err) => tracing::warn!(?err, "Failed to poll the head block number"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::launch_anvil;
+
+    #[tokio::test]
+    async fn test_block_ticker_polling() {
+        // Anvil mines a block every second, polled more often than that
+        let anvil = launch_anvil();
+        let client = RpcClient::new(anvil.endpoint().parse::<reqwest::Url>().unwrap());
+
+        let ticker = BlockTicker::polling(client, Duration::from_millis(100));
+        let blocks = ticker.take(3).collect::<Vec<_>>().await;
+
+        assert!(blocks.windows(2).all(|w| w[1] == w[0] + 1));
+    }
+
+    #[tokio::test]
+    async fn test_block_ticker_subscription() {
+        let anvil = launch_anvil();
+        let client = RpcClient::connect(&anvil.ws_endpoint()).await.unwrap();
+
+        let ticker = BlockTicker::new(client, Duration::from_secs(1)).await;
+        let blocks = ticker.take(3).collect::<Vec<_>>().await;
+
+        assert!(blocks.windows(2).all(|w| w[1] == w[0] + 1));
+    }
+
+    #[tokio::test]
+    async fn test_block_ticker_dedup_and_gaps() {
+        // The polled head repeats itself, jumps ahead and goes back
+        let heads = [10u64, 10, 13, 12, 13, 14];
+
+        let ticker = BlockTicker::from_heads(stream::iter(heads).boxed());
+        let blocks = ticker.collect::<Vec<_>>().await;
+
+        assert_eq!(blocks, vec![10, 11, 12, 13, 14]);
+    }
+}
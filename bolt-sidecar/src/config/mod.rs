@@ -1,11 +1,14 @@
-use std::{fs::read_to_string, path::Path};
+use std::{
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
 
 use alloy_primitives::Address;
 use blst::min_pk::SecretKey;
 use clap::Parser;
 use reqwest::Url;
 
-use crate::crypto::bls::random_bls_secret;
+use crate::crypto::bls::{random_bls_secret, BlsPublicKey};
 
 pub mod chain;
 pub use chain::ChainConfig;
@@ -65,6 +68,15 @@ pub struct Opts {
     /// Commitment signing options.
     #[clap(flatten)]
     pub(super) signing: SigningOpts,
+    /// Path to an append-only log of the issued commitments. If set, the
+    /// commitments are replayed from it on startup.
+    #[clap(long)]
+    pub(super) commitment_log: Option<PathBuf>,
+    /// Hex-encoded BLS public keys allowed to sign the constraints submitted to the
+    /// MEV-Boost proxy, e.g. the delegates of the proposers. If not provided, only the
+    /// constraints signed with the commitment signing key are accepted.
+    #[clap(long, value_parser, num_args = 1.., value_delimiter = ',')]
+    pub(super) constraint_signers: Vec<String>,
 }
 
 /// Configuration options for the sidecar. These are parsed from
@@ -101,6 +113,10 @@ pub struct Config {
     pub builder_private_key: SecretKey,
     /// The chain on which the sidecar is running
     pub chain: ChainConfig,
+    /// Path to the log of the issued commitments, if persistence is enabled
+    pub commitment_log_path: Option<PathBuf>,
+    /// The public keys allowed to sign the constraints submitted to the MEV-Boost proxy
+    pub constraint_signers: Vec<BlsPublicKey>,
 }
 
 impl Default for Config {
@@ -120,6 +136,8 @@ impl Default for Config {
             limits: Limits::default(),
             validator_indexes: Vec::new(),
             chain: ChainConfig::default(),
+            commitment_log_path: None,
+            constraint_signers: Vec::new(),
         }
     }
 }
@@ -210,6 +228,22 @@ impl TryFrom<Opts> for Config {
 
         config.chain = opts.chain;
 
+        config.commitment_log_path = opts.commitment_log;
+
+        config.constraint_signers = opts
+            .constraint_signers
+            .iter()
+            .map(|pk| {
+                BlsPublicKey::from_bytes(&hex::decode(pk.trim_start_matches("0x"))?)
+                    .map_err(|e| eyre::eyre!("Failed decoding BLS public key: {:?}", e))
+            })
+            .collect::<eyre::Result<_>>()?;
+
+        // The constraints signed by the sidecar itself are accepted by default
+        if config.constraint_signers.is_empty() {
+            config.constraint_signers = config.private_key.iter().map(|sk| sk.sk_to_pk()).collect();
+        }
+
         Ok(config)
     }
 }
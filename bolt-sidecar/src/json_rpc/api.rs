@@ -25,6 +25,8 @@ pub enum ApiError {
     DuplicateRequest,
     #[error("signature error: {0}")]
     Signature(#[from] alloy_primitives::SignatureError),
+    #[error("committed tip is not covered by the transaction priority fee")]
+    InsufficientTip,
     #[error("signature pubkey mismatch. expected: {expected}, got: {got}")]
     SignaturePubkeyMismatch { expected: String, got: String },
     #[error("failed to decode RLP: {0}")]
@@ -131,6 +133,12 @@ impl CommitmentsRpc for JsonRpcApi {
             });
         }
 
+        // the tip is bound to the digest signed by the user (not to the signed constraints),
+        // make sure the transaction can actually pay it
+        if !request.validate_tip() {
+            return Err(ApiError::InsufficientTip);
+        }
+
         {
             // check for duplicate requests and update the cache if necessary
             let mut cache = self.cache.write();
@@ -11,12 +11,12 @@ use warp::{http::Method, reject::Rejection, Filter};
 pub mod api;
 
 /// JSON-RPC API specification and utilities.
-mod spec;
+pub mod spec;
 
 use crate::Config;
 
 use self::api::CommitmentsRpc;
-use self::spec::{JsonRpcError, JsonRpcRequest, JsonRpcResponse};
+use self::spec::{JsonRpcError, JsonRpcErrorCode, JsonRpcRequest, JsonRpcResponse};
 
 /// Start the JSON-RPC server. Returns a sender that can be used to send a shutdown signal.
 pub async fn start_rpc_server(
@@ -55,11 +55,10 @@ async fn handle_rpc_request(
 ) -> Result<JsonRpcResponse, warp::Rejection> {
     let req = serde_json::from_slice::<JsonRpcRequest>(&req_bytes).map_err(|e| {
         error!(err = ?e, "failed parsing json rpc request");
-        warp::reject::custom(JsonRpcError {
-            message: "Request parse error".to_string(),
-            code: -32700,
-            data: None,
-        })
+        warp::reject::custom(JsonRpcError::new(
+            JsonRpcErrorCode::ParseError,
+            "Request parse error",
+        ))
     })?;
 
     tracing::debug!(?req, "received rpc request");
@@ -68,11 +67,10 @@ async fn handle_rpc_request(
         "bolt_inclusionPreconfirmation" => rpc_api.request_inclusion_commitment(req.params).await?,
         _ => {
             error!(method = ?req.method, "RPC method not found");
-            return Err(warp::reject::custom(JsonRpcError {
-                message: format!("Method not found: {}", req.method),
-                code: -32601,
-                data: None,
-            }));
+            return Err(warp::reject::custom(JsonRpcError::new(
+                JsonRpcErrorCode::MethodNotFound,
+                format!("Method not found: {}", req.method),
+            )));
         }
     };
 
@@ -87,23 +85,20 @@ async fn handle_rejection(err: warp::Rejection) -> Result<impl warp::Reply, Infa
     if let Some(e) = err.find::<JsonRpcError>() {
         Ok(warp::reply::json(e))
     } else if err.is_not_found() {
-        Ok(warp::reply::json(&JsonRpcError {
-            message: "Resource not found".to_string(),
-            code: -32601,
-            data: None,
-        }))
+        Ok(warp::reply::json(&JsonRpcError::new(
+            JsonRpcErrorCode::MethodNotFound,
+            "Resource not found",
+        )))
     } else if let Some(e) = err.find::<warp::reject::MissingHeader>() {
-        Ok(warp::reply::json(&JsonRpcError {
-            message: format!("Missing header: {}", e.name()),
-            code: -32600,
-            data: None,
-        }))
+        Ok(warp::reply::json(&JsonRpcError::new(
+            JsonRpcErrorCode::InvalidRequest,
+            format!("Missing header: {}", e.name()),
+        )))
     } else {
         error!(?err, "unhandled rejection");
-        Ok(warp::reply::json(&JsonRpcError {
-            message: "Internal error".to_string(),
-            code: -32000,
-            data: None,
-        }))
+        Ok(warp::reply::json(&JsonRpcError::new(
+            JsonRpcErrorCode::ServerError,
+            "Internal error",
+        )))
     }
 }
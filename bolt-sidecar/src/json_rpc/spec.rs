@@ -4,16 +4,96 @@ use tracing::error;
 
 use super::api::ApiError;
 
+/// Standard JSON-RPC error codes used by the sidecar.
+///
+/// The discriminants are stable and match the values sent over the wire,
+/// so they can be relied upon by both handlers and clients.
+///
+/// spec: https://www.jsonrpc.org/specification#error_object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(i64)]
+pub enum JsonRpcErrorCode {
+    /// Invalid JSON was received by the server.
+    ParseError = -32700,
+    /// The JSON sent is not a valid request object.
+    InvalidRequest = -32600,
+    /// The method does not exist or is not available.
+    MethodNotFound = -32601,
+    /// Invalid method parameters.
+    InvalidParams = -32602,
+    /// Internal JSON-RPC error.
+    InternalError = -32603,
+    /// Generic server error, used for all sidecar-specific failures.
+    ServerError = -32000,
+}
+
+impl JsonRpcErrorCode {
+    /// Returns the numeric value of the error code.
+    pub const fn code(&self) -> i64 {
+        *self as i64
+    }
+
+    /// Returns the default message associated with the error code.
+    pub const fn message(&self) -> &'static str {
+        match self {
+            Self::ParseError => "Parse error",
+            Self::InvalidRequest => "Invalid request",
+            Self::MethodNotFound => "Method not found",
+            Self::InvalidParams => "Invalid params",
+            Self::InternalError => "Internal error",
+            Self::ServerError => "Server error",
+        }
+    }
+
+    /// Returns the error code corresponding to the given numeric value, if known.
+    pub const fn from_code(code: i64) -> Option<Self> {
+        match code {
+            -32700 => Some(Self::ParseError),
+            -32600 => Some(Self::InvalidRequest),
+            -32601 => Some(Self::MethodNotFound),
+            -32602 => Some(Self::InvalidParams),
+            -32603 => Some(Self::InternalError),
+            -32000 => Some(Self::ServerError),
+            _ => None,
+        }
+    }
+}
+
 /// Standard JSON-RPC error object
 ///
 /// spec: https://www.jsonrpc.org/specification#error_object
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct JsonRpcError {
+    /// The numeric error code. See [JsonRpcErrorCode] for known values.
     pub code: i64,
+    /// A short description of the error.
     pub message: String,
+    /// Additional information about the error.
     pub data: Option<Value>,
 }
 
+impl JsonRpcError {
+    /// Create a new error with the given code and message.
+    pub fn new(code: JsonRpcErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code: code.code(),
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Returns the typed error code, if it is one of the known [JsonRpcErrorCode]s.
+    pub fn error_code(&self) -> Option<JsonRpcErrorCode> {
+        JsonRpcErrorCode::from_code(self.code)
+    }
+}
+
+impl From<JsonRpcErrorCode> for JsonRpcError {
+    fn from(code: JsonRpcErrorCode) -> Self {
+        Self::new(code, code.message())
+    }
+}
+
 impl warp::reject::Reject for JsonRpcError {}
 
 /// Standard JSON-RPC request object
@@ -21,9 +101,13 @@ impl warp::reject::Reject for JsonRpcError {}
 /// spec: https://www.jsonrpc.org/specification#request_object
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JsonRpcRequest {
+    /// The JSON-RPC protocol version. Must be "2.0".
     pub jsonrpc: String,
+    /// The request identifier.
     pub id: String,
+    /// The name of the method to invoke.
     pub method: String,
+    /// The parameters of the method.
     pub params: serde_json::Value,
 }
 
@@ -32,18 +116,17 @@ pub struct JsonRpcRequest {
 /// spec: https://www.jsonrpc.org/specification#response_object
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JsonRpcResponse {
+    /// The JSON-RPC protocol version. Must be "2.0".
     pub jsonrpc: String,
+    /// The identifier of the request this response belongs to.
     pub id: String,
+    /// The result of the method invocation.
     pub result: serde_json::Value,
 }
 
 impl From<eyre::Report> for JsonRpcError {
     fn from(err: eyre::Report) -> Self {
-        Self {
-            message: err.to_string(),
-            code: -32000,
-            data: None,
-        }
+        Self::new(JsonRpcErrorCode::ServerError, err.to_string())
     }
 }
 
@@ -55,11 +138,7 @@ impl From<JsonRpcError> for warp::reply::Json {
 
 impl From<ApiError> for JsonRpcError {
     fn from(err: ApiError) -> Self {
-        Self {
-            message: err.to_string(),
-            code: -32000,
-            data: None,
-        }
+        Self::new(JsonRpcErrorCode::ServerError, err.to_string())
     }
 }
 
@@ -69,3 +148,36 @@ impl From<ApiError> for warp::Rejection {
         warp::reject::custom(JsonRpcError::from(err))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonRpcError, JsonRpcErrorCode};
+
+    #[test]
+    fn test_error_code_roundtrip() {
+        let codes = [
+            JsonRpcErrorCode::ParseError,
+            JsonRpcErrorCode::InvalidRequest,
+            JsonRpcErrorCode::MethodNotFound,
+            JsonRpcErrorCode::InvalidParams,
+            JsonRpcErrorCode::InternalError,
+            JsonRpcErrorCode::ServerError,
+        ];
+
+        for code in codes {
+            assert_eq!(JsonRpcErrorCode::from_code(code.code()), Some(code));
+        }
+
+        assert_eq!(JsonRpcErrorCode::from_code(1), None);
+    }
+
+    #[test]
+    fn test_error_serialization() {
+        let err = JsonRpcError::from(JsonRpcErrorCode::MethodNotFound);
+        let json = serde_json::to_value(&err).unwrap();
+
+        assert_eq!(json["code"], -32601);
+        assert_eq!(json["message"], "Method not found");
+        assert_eq!(err.error_code(), Some(JsonRpcErrorCode::MethodNotFound));
+    }
+}
@@ -11,7 +11,33 @@ pub use api::{
 };
 
 mod client;
-pub use client::{mevboost::MevBoostClient, rpc::RpcClient, BeaconClient};
+pub use client::{
+    beacon::{
+        BeaconApiClient, BeaconApiError, BeaconSpec, Genesis, ProposerDuties, ProposerDuty,
+        Validator, ValidatorId, ValidatorRecord, ValidatorStatus,
+    },
+    block_tag::IntoBlockTag,
+    body_limit::ResponseTooLargeError,
+    circuit_breaker::{CircuitBreakerConfig, CircuitOpenError},
+    context::{ContextualRpcClient, RpcClientError, RpcErrorContext},
+    engine::EngineClient,
+    execution::{BlockHashes, ExecutionRpc},
+    failover::FailoverPolicy,
+    mevboost::MevBoostClient,
+    multi::{EndpointHealth, MultiRpcClient},
+    multicall::{MultiCall, MultiCallResults, StateCall, StateValue},
+    proof::{verify_account_proof, ProofError},
+    rate_limit::RateLimit,
+    revert::{decode_revert_reason, RevertReason},
+    rpc::{
+        BaseFeeOpts, BatchChunkError, LogQueryLimitError, RejectionReason, RpcClient,
+        RpcClientBuilder, RpcClientConfig, RpcEndpoint, RpcHealth, SyncProgress,
+        TransactionRejected,
+    },
+    slot_clock::{SlotClock, SlotHead},
+    ticker::BlockTicker,
+    BeaconClient,
+};
 
 /// Common types and compatibility utilities
 /// (To be refactored)
@@ -1,6 +1,6 @@
 use std::str::FromStr;
 
-use alloy_primitives::{keccak256, Signature, B256};
+use alloy_primitives::{keccak256, Signature, B256, U256};
 use reth_primitives::TransactionSigned;
 use serde::{de, Deserialize, Deserializer, Serialize};
 
@@ -31,6 +31,11 @@ pub struct InclusionRequest {
         serialize_with = "signature_as_str"
     )]
     pub signature: Signature,
+    /// The tip (in wei) that the user agrees to pay to the proposer for this commitment.
+    /// It is part of the digest signed by the user, so that neither party can dispute it
+    /// later: the sidecar keeps the signed request in its commitment log.
+    #[serde(default, skip_serializing_if = "U256::is_zero")]
+    pub tip: U256,
 }
 
 impl InclusionRequest {
@@ -42,9 +47,18 @@ impl InclusionRequest {
         }
         true
     }
+
+    /// Validates that the transaction can pay the committed tip to the proposer.
+    /// Returns true if the maximum priority fee the transaction can pay covers the tip.
+    pub fn validate_tip(&self) -> bool {
+        let max_priority_fee = self.tx.max_priority_fee_per_gas().unwrap_or(0);
+        let max_tip = U256::from(max_priority_fee) * U256::from(self.tx.gas_limit());
+
+        max_tip >= self.tip
+    }
 }
 
-fn deserialize_tx_signed<'de, D>(deserializer: D) -> Result<TransactionSigned, D::Error>
+pub(crate) fn deserialize_tx_signed<'de, D>(deserializer: D) -> Result<TransactionSigned, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -53,7 +67,10 @@ where
     TransactionSigned::decode_enveloped(&mut data.as_slice()).map_err(de::Error::custom)
 }
 
-fn serialize_tx_signed<S>(tx: &TransactionSigned, serializer: S) -> Result<S::Ok, S::Error>
+pub(crate) fn serialize_tx_signed<S>(
+    tx: &TransactionSigned,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
 {
@@ -89,6 +106,7 @@ impl InclusionRequest {
         let mut data = Vec::new();
         data.extend_from_slice(&self.slot.to_le_bytes());
         data.extend_from_slice(self.tx.hash.as_slice());
+        data.extend_from_slice(&self.tip.to_le_bytes::<32>());
 
         keccak256(&data)
     }
@@ -102,6 +120,8 @@ impl From<InclusionRequest> for CommitmentRequest {
 
 #[cfg(test)]
 mod tests {
+    use alloy_primitives::U256;
+
     use super::{CommitmentRequest, InclusionRequest};
 
     #[test]
@@ -140,4 +160,22 @@ mod tests {
             panic!("Expected Inclusion request");
         }
     }
+
+    #[test]
+    fn test_tip_is_bound_to_digest() {
+        let json_req = r#"{
+            "tx": "0x02f86c870c72dd9d5e883e4d0183408f2382520894d2e2adf7177b7a8afddbc12d1634cf23ea1a71020180c001a08556dcfea479b34675db3fe08e29486fe719c2b22f6b0c1741ecbbdce4575cc6a01cd48009ccafd6b9f1290bbe2ceea268f94101d1d322c787018423ebcbc87ab4",
+            "signature": "0xb8623aae262785bd31d0cc6e368a9b9ab5361002edd58ece424ef5dde0544b32472d954da3f34ca9c2c2201393f9b83cdc959bd416c0af96fe3e0962a08cb92101",
+            "slot": 1,
+            "tip": "0x64"
+        }"#;
+
+        let req: InclusionRequest = serde_json::from_str(json_req).unwrap();
+        assert_eq!(req.tip, U256::from(100));
+
+        let mut other = req.clone();
+        other.tip = U256::from(101);
+
+        assert_ne!(req.digest(), other.digest());
+    }
 }
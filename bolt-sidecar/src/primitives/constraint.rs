@@ -1,8 +1,14 @@
 use alloy_primitives::keccak256;
+use blst::min_pk::Signature;
+use ethereum_consensus::{
+    bellatrix::mainnet::Transaction,
+    crypto::Signature as ConsensusSignature,
+    ssz::prelude::{ssz_rs, List, SimpleSerialize},
+};
 use secp256k1::Message;
 use serde::{Deserialize, Serialize};
 
-use crate::crypto::{ecdsa::SignableECDSA, SignableBLS};
+use crate::crypto::{bls::BlsPublicKey, ecdsa::SignableECDSA, SignableBLS};
 
 use super::InclusionRequest;
 
@@ -28,6 +34,12 @@ impl SignableECDSA for ConstraintsMessage {
 /// that need to be forwarded to the PBS pipeline to inform block production.
 pub type BatchedSignedConstraints = Vec<SignedConstraints>;
 
+/// The maximum number of constraints in a [ConstraintsMessage], in SSZ.
+pub const MAX_CONSTRAINTS_PER_MESSAGE: usize = 256;
+
+/// The maximum number of [SignedConstraints] submitted at once, in SSZ.
+pub const MAX_SIGNED_CONSTRAINTS_PER_BATCH: usize = 64;
+
 /// A container for a list of constraints and the signature of the proposer sidecar.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct SignedConstraints {
@@ -37,7 +49,92 @@ pub struct SignedConstraints {
     pub signature: String,
 }
 
+impl SignedConstraints {
+    /// Returns `true` if the message is signed with the given public key. Malformed
+    /// signatures are never valid.
+    pub fn verify_signature(&self, pubkey: &BlsPublicKey) -> bool {
+        let Ok(signature) = hex::decode(self.signature.trim_start_matches("0x")) else {
+            return false;
+        };
+
+        Signature::from_bytes(&signature)
+            .is_ok_and(|signature| SignableBLS::verify(&self.message, &signature, pubkey))
+    }
+
+    /// Decode a batch of signed constraints from its SSZ encoding, i.e. a list of
+    /// [SszSignedConstraints].
+    pub fn decode_ssz_batch(
+        bytes: &[u8],
+    ) -> Result<BatchedSignedConstraints, ssz_rs::DeserializeError> {
+        let batch = <SszBatchedSignedConstraints as ssz_rs::Deserialize>::deserialize(bytes)?;
+
+        Ok(batch.iter().map(Self::from).collect())
+    }
+}
+
+/// The SSZ encoding of a [BatchedSignedConstraints].
+pub type SszBatchedSignedConstraints = List<SszSignedConstraints, MAX_SIGNED_CONSTRAINTS_PER_BATCH>;
+
+/// The SSZ encoding of a [SignedConstraints], with the raw bytes of the transactions
+/// and of the signature.
+#[derive(Debug, Default, Clone, PartialEq, SimpleSerialize)]
+pub struct SszSignedConstraints {
+    /// The signed constraints.
+    pub message: SszConstraintsMessage,
+    /// The BLS signature of the message by the proposer sidecar.
+    pub signature: ConsensusSignature,
+}
+
+/// The SSZ encoding of a [ConstraintsMessage].
+#[derive(Debug, Default, Clone, PartialEq, SimpleSerialize)]
+pub struct SszConstraintsMessage {
+    /// The validator index of the proposer sidecar.
+    pub validator_index: u64,
+    /// The consensus slot at which the constraints are valid.
+    pub slot: u64,
+    /// The constraints, at most [MAX_CONSTRAINTS_PER_MESSAGE].
+    pub constraints: List<SszConstraint, MAX_CONSTRAINTS_PER_MESSAGE>,
+}
+
+/// The SSZ encoding of a [Constraint]. The optional index is a list of at most one
+/// element.
+#[derive(Debug, Default, Clone, PartialEq, SimpleSerialize)]
+pub struct SszConstraint {
+    /// The raw transaction that needs to be included in the block.
+    pub tx: Transaction,
+    /// The index at which the transaction needs to be included, if any.
+    pub index: List<u64, 1>,
+}
+
+impl From<&SszSignedConstraints> for SignedConstraints {
+    fn from(value: &SszSignedConstraints) -> Self {
+        let constraints = value
+            .message
+            .constraints
+            .iter()
+            .map(|constraint| Constraint {
+                tx: format!("0x{}", hex::encode(constraint.tx.as_ref())),
+                index: constraint.index.first().copied(),
+            })
+            .collect();
+
+        Self {
+            message: ConstraintsMessage {
+                validator_index: value.message.validator_index,
+                slot: value.message.slot,
+                constraints,
+            },
+            signature: format!("0x{}", hex::encode(value.signature.as_ref())),
+        }
+    }
+}
+
 /// A message that contains the constraints that need to be signed by the proposer sidecar.
+///
+/// The tip of the [InclusionRequest] is deliberately left out of the message and of its
+/// signed digest: the constraints are forwarded to the PBS pipeline, whose constraints API
+/// (JSON and SSZ) only carries what builders must enforce. The tip is bound by the user's
+/// signature over [InclusionRequest::digest] instead.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ConstraintsMessage {
     /// The validator index of the proposer sidecar.
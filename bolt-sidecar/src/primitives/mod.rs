@@ -3,7 +3,7 @@
 
 use std::sync::{atomic::AtomicU64, Arc};
 
-use alloy_primitives::U256;
+use alloy_primitives::{B256, U256};
 use ethereum_consensus::{
     crypto::{KzgCommitment, PublicKey as BlsPublicKey, Signature as BlsSignature},
     deneb::{
@@ -37,6 +37,10 @@ pub struct AccountState {
     /// The nonce of the account. This is the number of transactions sent from this account
     pub transaction_count: u64,
     pub balance: U256,
+    /// The hash of the account's code. Only set when fetched with `eth_getProof`.
+    pub code_hash: Option<B256>,
+    /// The root of the account's storage trie. Only set when fetched with `eth_getProof`.
+    pub storage_root: Option<B256>,
 }
 
 #[derive(Debug, Default, Clone, SimpleSerialize, serde::Serialize, serde::Deserialize)]
@@ -0,0 +1,288 @@
+//! Append-only, on-disk log of the commitments issued by the sidecar.
+//!
+//! Every entry is a JSON line that includes the keccak hash of the previous line,
+//! forming a hash chain: editing or removing an entry in the middle of the log
+//! is detected when it is loaded again.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::{keccak256, B256, U256};
+use reth_primitives::TransactionSigned;
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::{
+    commitment::{deserialize_tx_signed, serialize_tx_signed},
+    InclusionRequest,
+};
+
+/// Errors that can occur while reading or writing the [CommitmentLog].
+#[derive(Debug, thiserror::Error)]
+pub enum CommitmentLogError {
+    /// Failed to read or write the log file.
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    /// Failed to encode an entry.
+    #[error("Failed to encode entry: {0}")]
+    Encode(#[from] serde_json::Error),
+    /// Failed to decode an entry.
+    #[error("Invalid entry at line {line}: {source}")]
+    InvalidEntry {
+        /// The line of the entry in the log file, starting at 1.
+        line: usize,
+        /// The decoding error.
+        #[source]
+        source: serde_json::Error,
+    },
+    /// An entry doesn't reference the hash of the previous one.
+    #[error("Broken hash chain at line {line}: the log was tampered with")]
+    BrokenChain {
+        /// The line of the first entry not chained to the previous one, starting at 1.
+        line: usize,
+    },
+}
+
+/// A commitment issued by the sidecar.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommitmentRecord {
+    /// The slot the transaction was committed to.
+    pub slot: u64,
+    /// The hash of the committed transaction.
+    pub tx_hash: B256,
+    /// The tip the user agreed to pay for the commitment.
+    pub tip: U256,
+    /// The signature of the sidecar over the constraints message.
+    pub signature: String,
+    /// The UNIX timestamp (in seconds) at which the commitment was issued.
+    pub timestamp: u64,
+    /// The committed transaction, to rebuild the block templates on startup.
+    #[serde(
+        deserialize_with = "deserialize_tx_signed",
+        serialize_with = "serialize_tx_signed"
+    )]
+    pub tx: TransactionSigned,
+}
+
+impl CommitmentRecord {
+    /// Create a new record for the given request, timestamped now.
+    pub fn new(request: &InclusionRequest, signature: String) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        Self {
+            slot: request.slot,
+            tx_hash: request.tx.hash(),
+            tip: request.tip,
+            signature,
+            timestamp,
+            tx: request.tx.clone(),
+        }
+    }
+}
+
+/// A single line of the log.
+#[derive(Serialize, Deserialize)]
+struct LogEntry {
+    /// The keccak hash of the previous line, or zero for the first entry.
+    prev_hash: B256,
+    #[serde(flatten)]
+    record: CommitmentRecord,
+}
+
+/// An append-only log of the commitments issued by the sidecar.
+#[derive(Debug)]
+pub struct CommitmentLog {
+    path: PathBuf,
+    file: File,
+    /// The hash of the last line of the log.
+    last_hash: B256,
+}
+
+impl CommitmentLog {
+    /// Open the log at the given path, creating it if it doesn't exist,
+    /// and return it along with all the records it already contains.
+    ///
+    /// A partially written last line (e.g. after a crash) is discarded.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<(Self, Vec<CommitmentRecord>), CommitmentLogError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+
+        let mut records = Vec::new();
+        let mut last_hash = B256::ZERO;
+        let mut valid_len = 0;
+
+        for (i, line) in contents.split_inclusive('\n').enumerate() {
+            if !line.ends_with('\n') {
+                tracing::warn!(
+                    ?path,
+                    line = i + 1,
+                    "Discarding partially written commitment"
+                );
+                break;
+            }
+
+            let entry: LogEntry =
+                serde_json::from_str(line).map_err(|source| CommitmentLogError::InvalidEntry {
+                    line: i + 1,
+                    source,
+                })?;
+
+            if entry.prev_hash != last_hash {
+                return Err(CommitmentLogError::BrokenChain { line: i + 1 });
+            }
+
+            last_hash = keccak256(line.trim_end().as_bytes());
+            valid_len += line.len();
+            records.push(entry.record);
+        }
+
+        if valid_len < contents.len() {
+            file.set_len(valid_len as u64)?;
+        }
+
+        Ok((
+            Self {
+                path,
+                file,
+                last_hash,
+            },
+            records,
+        ))
+    }
+
+    /// Append a record to the log, and flush it to disk.
+    pub fn append(&mut self, record: &CommitmentRecord) -> Result<(), CommitmentLogError> {
+        let entry = LogEntry {
+            prev_hash: self.last_hash,
+            record: record.clone(),
+        };
+
+        let line = serde_json::to_string(&entry)?;
+
+        self.file.write_all(format!("{line}\n").as_bytes())?;
+        self.file.sync_data()?;
+
+        self.last_hash = keccak256(line.as_bytes());
+
+        Ok(())
+    }
+
+    /// The path of the log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAW_TX: &str = "02f86c870c72dd9d5e883e4d0183408f2382520894d2e2adf7177b7a8afddbc12d1634cf23ea1a71020180c001a08556dcfea479b34675db3fe08e29486fe719c2b22f6b0c1741ecbbdce4575cc6a01cd48009ccafd6b9f1290bbe2ceea268f94101d1d322c787018423ebcbc87ab4";
+
+    fn temp_log_path() -> PathBuf {
+        std::env::temp_dir().join(format!("bolt-commitments-{}.log", rand::random::<u64>()))
+    }
+
+    fn test_record(slot: u64) -> CommitmentRecord {
+        let raw = hex::decode(RAW_TX).unwrap();
+        let tx = TransactionSigned::decode_enveloped(&mut raw.as_slice()).unwrap();
+
+        CommitmentRecord {
+            slot,
+            tx_hash: tx.hash(),
+            tip: U256::from(slot),
+            signature: format!("0x{:0130x}", slot),
+            timestamp: 1_700_000_000 + slot,
+            tx,
+        }
+    }
+
+    #[test]
+    fn test_commitment_log_replay() {
+        let path = temp_log_path();
+
+        let (mut log, records) = CommitmentLog::open(&path).unwrap();
+        assert!(records.is_empty());
+
+        log.append(&test_record(1)).unwrap();
+        log.append(&test_record(2)).unwrap();
+        drop(log);
+
+        // Reopening the log replays the records, and appending keeps the chain intact
+        let (mut log, records) = CommitmentLog::open(&path).unwrap();
+        assert_eq!(records, vec![test_record(1), test_record(2)]);
+
+        log.append(&test_record(3)).unwrap();
+        drop(log);
+
+        let (_, records) = CommitmentLog::open(&path).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2], test_record(3));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_commitment_log_detects_tampering() {
+        let path = temp_log_path();
+
+        let (mut log, _) = CommitmentLog::open(&path).unwrap();
+        log.append(&test_record(1)).unwrap();
+        log.append(&test_record(2)).unwrap();
+        drop(log);
+
+        // Rewrite the tip of the first commitment
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(
+            &path,
+            contents.replacen("\"tip\":\"0x1\"", "\"tip\":\"0x0\"", 1),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            CommitmentLog::open(&path),
+            Err(CommitmentLogError::BrokenChain { line: 2 })
+        ));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_commitment_log_discards_partial_write() {
+        let path = temp_log_path();
+
+        let (mut log, _) = CommitmentLog::open(&path).unwrap();
+        log.append(&test_record(1)).unwrap();
+        drop(log);
+
+        // Simulate a crash in the middle of a write
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"prev_hash\":").unwrap();
+        drop(file);
+
+        let (mut log, records) = CommitmentLog::open(&path).unwrap();
+        assert_eq!(records, vec![test_record(1)]);
+
+        log.append(&test_record(2)).unwrap();
+        drop(log);
+
+        let (_, records) = CommitmentLog::open(&path).unwrap();
+        assert_eq!(records, vec![test_record(1), test_record(2)]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
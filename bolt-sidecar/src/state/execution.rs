@@ -11,7 +11,7 @@ use crate::{
     primitives::{AccountState, CommitmentRequest, Slot},
 };
 
-use super::fetcher::StateFetcher;
+use super::{fetcher::StateFetcher, CommitmentRecord};
 
 /// Possible commitment validation errors.
 #[derive(Debug, Error)]
@@ -132,11 +132,37 @@ impl<C: StateFetcher> ExecutionState<C> {
             return Err(ValidationError::BaseFeeTooLow(max_basefee as u128));
         }
 
+        self.validate_account_state(sender, &req.tx).await?;
+
+        // Check EIP-4844-specific limits
+        if req.tx.tx_type() == TxType::Eip4844 {
+            if let Some(template) = self.block_templates.get(&req.slot) {
+                if template.blob_count() >= MAX_BLOBS_PER_BLOCK {
+                    return Err(ValidationError::Eip4844Limit);
+                }
+            }
+
+            // TODO: check max_fee_per_blob_gas against the blob_base_fee
+        }
+
+        self.commit_transaction(req.slot, req.tx.clone());
+
+        Ok(())
+    }
+
+    /// Validates the transaction against the account state of its sender, including the
+    /// transactions already committed to the block templates. The account state is
+    /// fetched and cached if it's not known yet.
+    async fn validate_account_state(
+        &mut self,
+        sender: Address,
+        tx: &TransactionSigned,
+    ) -> Result<(), ValidationError> {
         // If we have the account state, use it here
         if let Some(account_state) = self.account_state(&sender) {
             // Validate the transaction against the account state
             tracing::debug!(address = %sender, "Known account state: {account_state:?}");
-            validate_transaction(&account_state, &req.tx)?;
+            validate_transaction(&account_state, tx)?;
         } else {
             tracing::debug!(address = %sender, "Unknown account state");
             // If we don't have the account state, we need to fetch it
@@ -152,22 +178,9 @@ impl<C: StateFetcher> ExecutionState<C> {
             self.account_states.insert(sender, account_state);
 
             // Validate the transaction against the account state
-            validate_transaction(&account_state, &req.tx)?;
+            validate_transaction(&account_state, tx)?;
         }
 
-        // Check EIP-4844-specific limits
-        if req.tx.tx_type() == TxType::Eip4844 {
-            if let Some(template) = self.block_templates.get(&req.slot) {
-                if template.blob_count() >= MAX_BLOBS_PER_BLOCK {
-                    return Err(ValidationError::Eip4844Limit);
-                }
-            }
-
-            // TODO: check max_fee_per_blob_gas against the blob_base_fee
-        }
-
-        self.commit_transaction(req.slot, req.tx.clone());
-
         Ok(())
     }
 
@@ -184,6 +197,38 @@ impl<C: StateFetcher> ExecutionState<C> {
         }
     }
 
+    /// Rebuilds the block templates from commitments issued before a restart, as loaded from
+    /// the [CommitmentLog](super::CommitmentLog). Commitments for slots up to and including
+    /// `head_slot` are skipped, since they are no longer relevant, and so are the ones
+    /// that are not valid against the current account states anymore (e.g. because the
+    /// transaction was already included).
+    ///
+    /// Returns the number of replayed commitments.
+    pub async fn replay_commitments<'a>(
+        &mut self,
+        records: impl IntoIterator<Item = &'a CommitmentRecord>,
+        head_slot: u64,
+    ) -> usize {
+        let mut replayed = 0;
+
+        for record in records.into_iter().filter(|r| r.slot > head_slot) {
+            let Some(sender) = record.tx.recover_signer() else {
+                tracing::warn!(tx_hash = %record.tx_hash, "Skipping commitment with invalid signer");
+                continue;
+            };
+
+            if let Err(e) = self.validate_account_state(sender, &record.tx).await {
+                tracing::warn!(tx_hash = %record.tx_hash, err = ?e, "Skipping invalid commitment");
+                continue;
+            }
+
+            self.commit_transaction(record.slot, record.tx.clone());
+            replayed += 1;
+        }
+
+        replayed
+    }
+
     /// Updates the state corresponding to the provided block number if provided, or latest from EL if `None`.
     pub async fn update_head(&mut self, block_number: Option<u64>) -> Result<(), TransportError> {
         // TODO: invalidate any state that we don't need anymore (will be based on block template)
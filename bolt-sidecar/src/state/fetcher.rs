@@ -2,7 +2,7 @@
 #![allow(unused_variables)]
 #![allow(missing_debug_implementations)]
 
-use std::{collections::HashMap, time::Duration};
+use std::{collections::HashMap, ops::Deref, time::Duration};
 
 use alloy_eips::BlockNumberOrTag;
 use alloy_primitives::{Address, U256, U64};
@@ -66,7 +66,7 @@ impl StateFetcher for StateClient {
         block_number: Option<u64>,
     ) -> Result<StateUpdate, TransportError> {
         // Create a new batch
-        let mut batch = self.client.new_batch();
+        let mut batch = self.client.deref().new_batch();
 
         let tag = block_number.map_or(BlockNumberOrTag::Latest, BlockNumberOrTag::Number);
 
@@ -123,6 +123,8 @@ impl StateFetcher for StateClient {
                 .or_insert(AccountState {
                     transaction_count: nonce.to(),
                     balance: U256::ZERO,
+                    code_hash: None,
+                    storage_root: None,
                 });
         }
 
@@ -137,6 +139,8 @@ impl StateFetcher for StateClient {
                 .or_insert(AccountState {
                     transaction_count: 0,
                     balance,
+                    code_hash: None,
+                    storage_root: None,
                 });
         }
 
@@ -25,6 +25,10 @@ pub use consensus::ConsensusState;
 pub mod head_tracker;
 pub use head_tracker::HeadTracker;
 
+/// Module to persist the issued commitments to disk.
+pub mod commitment_log;
+pub use commitment_log::{CommitmentLog, CommitmentRecord};
+
 /// The deadline for a which a commitment is considered valid.
 #[derive(Debug)]
 pub struct CommitmentDeadline {
@@ -71,7 +75,7 @@ mod tests {
     use alloy_consensus::constants::ETH_TO_WEI;
     use alloy_eips::eip2718::Encodable2718;
     use alloy_network::EthereumWallet;
-    use alloy_primitives::{hex, uint, Uint};
+    use alloy_primitives::{hex, uint, Uint, U256};
     use alloy_provider::{network::TransactionBuilder, Provider, ProviderBuilder};
     use alloy_signer::SignerSync;
     use alloy_signer_local::PrivateKeySigner;
@@ -133,6 +137,7 @@ mod tests {
             slot: 10,
             tx: tx_signed,
             signature: sig,
+            tip: U256::ZERO,
         });
 
         assert!(state.try_commit(&request).await.is_ok());
@@ -167,6 +172,7 @@ mod tests {
             slot: 10,
             tx: tx_signed,
             signature: sig,
+            tip: U256::ZERO,
         });
 
         assert!(matches!(
@@ -205,6 +211,7 @@ mod tests {
             slot: 10,
             tx: tx_signed,
             signature: sig,
+            tip: U256::ZERO,
         });
 
         assert!(matches!(
@@ -244,6 +251,7 @@ mod tests {
             slot: 10,
             tx: tx_signed,
             signature: sig,
+            tip: U256::ZERO,
         });
 
         assert!(matches!(
@@ -281,6 +289,7 @@ mod tests {
             slot: 10,
             tx: tx_signed,
             signature: sig,
+            tip: U256::ZERO,
         });
 
         assert!(state.try_commit(&request).await.is_ok());
@@ -302,4 +311,47 @@ mod tests {
         let transactions_len = state.block_templates().get(&10).unwrap().transactions_len();
         assert!(transactions_len == 0);
     }
+
+    #[tokio::test]
+    async fn test_replay_commitments() {
+        let _ = fmt::try_init();
+
+        let anvil = launch_anvil();
+        let client = StateClient::new(Url::parse(&anvil.endpoint()).unwrap());
+
+        let mut state = ExecutionState::new(client).await.unwrap();
+
+        let wallet: PrivateKeySigner = anvil.keys()[0].clone().into();
+        let sender = anvil.addresses()[0];
+        let sig = wallet.sign_message_sync(&hex!("abcd")).unwrap();
+        let signer: EthereumWallet = wallet.into();
+
+        let mut records = Vec::new();
+        for (slot, nonce) in [(4, 0), (10, 0), (10, 5)] {
+            let tx = default_test_transaction(sender, Some(nonce));
+            let signed = tx.build(&signer).await.unwrap();
+
+            // Trick to parse into the TransactionSigned type
+            let tx_signed_bytes = signed.encoded_2718();
+            let tx_signed =
+                TransactionSigned::decode_enveloped(&mut tx_signed_bytes.as_slice()).unwrap();
+
+            let request = InclusionRequest {
+                slot,
+                tx: tx_signed,
+                signature: sig,
+                tip: U256::ZERO,
+            };
+            records.push(CommitmentRecord::new(&request, String::new()));
+        }
+
+        // The commitment for a past slot and the one with a nonce gap are skipped
+        let replayed = state.replay_commitments(&records, 5).await;
+        assert_eq!(replayed, 1);
+        assert!(!state.block_templates().contains_key(&4));
+        assert_eq!(
+            state.block_templates()[&10].transactions,
+            vec![records[1].tx.clone()]
+        );
+    }
 }
@@ -1,13 +1,42 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, Read, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use alloy_eips::BlockNumberOrTag;
+use alloy_json_rpc::{ErrorPayload, RpcError};
 use alloy_network::TransactionBuilder;
 use alloy_node_bindings::{Anvil, AnvilInstance};
-use alloy_primitives::{Address, U256};
-use alloy_rpc_types::TransactionRequest;
+use alloy_primitives::{Address, B256, U256, U64};
+use alloy_rpc_types::{EIP1186AccountProofResponse, TransactionRequest};
+use alloy_rpc_types_trace::geth::{GethDebugTracingCallOptions, GethTrace};
+use alloy_transport::{TransportError, TransportErrorKind, TransportResult};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
 use blst::min_pk::SecretKey;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use parking_lot::Mutex;
+use reqwest::Url;
 use secp256k1::Message;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::{net::TcpListener, task::JoinHandle};
 
 use crate::{
     crypto::{ecdsa::SignableECDSA, SignableBLS},
-    Config,
+    primitives::AccountState,
+    BlockHashes, Config, ExecutionRpc,
 };
 
 /// The URL of the test execution client HTTP API.
@@ -86,6 +115,391 @@ pub(crate) fn launch_anvil() -> AnvilInstance {
     Anvil::new().block_time(1).chain_id(1337).spawn()
 }
 
+/// A handler for JSON-RPC calls made to the [MockRpcServer]. It receives the method
+/// name and its parameters, and returns either the result or a JSON-RPC error object.
+type MockRpcHandler = dyn Fn(&str, &Value) -> Result<Value, Value> + Send + Sync;
+
+/// A minimal JSON-RPC server that answers every call with the given handler,
+/// and records all the calls it receives. Supports batched requests.
+///
+/// The server is shut down when dropped.
+pub(crate) struct MockRpcServer {
+    url: Url,
+    state: Arc<MockRpcState>,
+    task: JoinHandle<()>,
+}
+
+struct MockRpcState {
+    handler: Box<MockRpcHandler>,
+    calls: Mutex<Vec<(String, Value)>>,
+    /// The headers of every HTTP request received.
+    headers: Mutex<Vec<HeaderMap>>,
+    delay: Duration,
+    /// The number of upcoming HTTP requests to fail, and the status code to fail them with.
+    failures: Mutex<(usize, StatusCode)>,
+    /// Whether to gzip-compress the responses of requests accepting it.
+    gzip_responses: AtomicBool,
+    /// Whether to answer batch requests with the responses in reverse order.
+    reverse_batches: AtomicBool,
+}
+
+impl MockRpcServer {
+    /// Spawn a new mock server on a random local port.
+    pub(crate) async fn spawn<F>(handler: F) -> Self
+    where
+        F: Fn(&str, &Value) -> Result<Value, Value> + Send + Sync + 'static,
+    {
+        Self::spawn_with_delay(Duration::ZERO, handler).await
+    }
+
+    /// Spawn a new mock server on a random local port, which waits
+    /// for the given delay before answering every HTTP request.
+    pub(crate) async fn spawn_with_delay<F>(delay: Duration, handler: F) -> Self
+    where
+        F: Fn(&str, &Value) -> Result<Value, Value> + Send + Sync + 'static,
+    {
+        let state = Arc::new(MockRpcState {
+            handler: Box::new(handler),
+            calls: Mutex::new(Vec::new()),
+            headers: Mutex::new(Vec::new()),
+            delay,
+            failures: Mutex::new((0, StatusCode::INTERNAL_SERVER_ERROR)),
+            gzip_responses: AtomicBool::new(false),
+            reverse_batches: AtomicBool::new(false),
+        });
+
+        let router = Router::new()
+            .route("/", post(handle_mock_rpc_request))
+            .with_state(state.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock server");
+        let addr = listener.local_addr().expect("local address");
+        let task = tokio::spawn(async move {
+            axum::serve(listener, router).await.expect("mock server");
+        });
+
+        Self {
+            url: format!("http://{addr}").parse().expect("valid URL"),
+            state,
+            task,
+        }
+    }
+
+    /// The URL of the mock server.
+    pub(crate) fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    /// Returns the parameters of all the calls made to the given method, in order.
+    pub(crate) fn calls(&self, method: &str) -> Vec<Value> {
+        self.state
+            .calls
+            .lock()
+            .iter()
+            .filter(|(m, _)| m == method)
+            .map(|(_, params)| params.clone())
+            .collect()
+    }
+
+    /// Returns the number of calls made to the given method.
+    pub(crate) fn call_count(&self, method: &str) -> usize {
+        self.calls(method).len()
+    }
+
+    /// Returns the headers of all the HTTP requests received, in order.
+    pub(crate) fn headers(&self) -> Vec<HeaderMap> {
+        self.state.headers.lock().clone()
+    }
+
+    /// Fail the next `count` HTTP requests with the given status code. The calls
+    /// contained in the failed requests are still recorded.
+    pub(crate) fn fail_next(&self, count: usize, status: StatusCode) {
+        *self.state.failures.lock() = (count, status);
+    }
+
+    /// Gzip-compress the responses of the requests with `Accept-Encoding: gzip`.
+    /// Gzip-compressed request bodies are always accepted.
+    pub(crate) fn gzip_responses(&self) {
+        self.state.gzip_responses.store(true, Ordering::Relaxed);
+    }
+
+    /// Answer batch requests with the responses in reverse order, as the JSON-RPC spec
+    /// allows: clients must match the responses to their calls by id.
+    pub(crate) fn reverse_batches(&self) {
+        self.state.reverse_batches.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for MockRpcServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl MockRpcState {
+    fn record(&self, request: &Value) {
+        let method = request["method"].as_str().unwrap_or_default();
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        self.calls.lock().push((method.to_string(), params));
+    }
+
+    /// Returns the status code to fail the current HTTP request with, if any.
+    fn next_failure(&self) -> Option<StatusCode> {
+        let mut failures = self.failures.lock();
+        if failures.0 == 0 {
+            return None;
+        }
+
+        failures.0 -= 1;
+        Some(failures.1)
+    }
+
+    fn respond(&self, request: &Value) -> Value {
+        let method = request["method"].as_str().unwrap_or_default();
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        self.record(request);
+
+        match (self.handler)(method, &params) {
+            Ok(result) => serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": result,
+            }),
+            Err(error) => serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "error": error,
+            }),
+        }
+    }
+}
+
+async fn handle_mock_rpc_request(
+    State(state): State<Arc<MockRpcState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let is_gzip = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains("gzip"))
+    };
+    let gzip_request = is_gzip(header::CONTENT_ENCODING);
+    let gzip_response =
+        is_gzip(header::ACCEPT_ENCODING) && state.gzip_responses.load(Ordering::Relaxed);
+
+    state.headers.lock().push(headers);
+    tokio::time::sleep(state.delay).await;
+
+    let body = if gzip_request {
+        let mut decoded = Vec::new();
+        if GzDecoder::new(&body[..]).read_to_end(&mut decoded).is_err() {
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+        decoded
+    } else {
+        body.to_vec()
+    };
+    let Ok(body) = serde_json::from_slice::<Value>(&body) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
+    if let Some(status) = state.next_failure() {
+        match &body {
+            Value::Array(requests) => requests.iter().for_each(|req| state.record(req)),
+            request => state.record(request),
+        }
+
+        return status.into_response();
+    }
+
+    let response = match body {
+        Value::Array(requests) => {
+            let mut responses: Vec<_> = requests.iter().map(|req| state.respond(req)).collect();
+            if state.reverse_batches.load(Ordering::Relaxed) {
+                responses.reverse();
+            }
+            Value::Array(responses)
+        }
+        request => state.respond(&request),
+    };
+
+    if !gzip_response {
+        return Json(response).into_response();
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(&serde_json::to_vec(&response).expect("serialize response"))
+        .expect("compress response");
+    let body = encoder.finish().expect("compress response");
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/json"),
+            (header::CONTENT_ENCODING, "gzip"),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// A scripted response of a [MockRpc] method.
+#[derive(Debug, Clone)]
+pub(crate) enum MockResponse {
+    /// A successful response, deserialized into the return type of the method. A value
+    /// of the wrong shape fails with a deserialization error, as a malformed response.
+    Ok(Value),
+    /// A JSON-RPC error response with the given code and message.
+    Rpc(i64, String),
+    /// An HTTP error response with the given status code, e.g. 429.
+    Http(u16),
+    /// A request that timed out.
+    Timeout,
+}
+
+impl MockResponse {
+    fn into_result<T: DeserializeOwned>(self) -> TransportResult<T> {
+        match self {
+            Self::Ok(value) => serde_json::from_value(value.clone())
+                .map_err(|err| TransportError::deser_err(err, value.to_string())),
+            Self::Rpc(code, message) => Err(RpcError::ErrorResp(ErrorPayload {
+                code,
+                message: message.into(),
+                data: None,
+            })),
+            Self::Http(status) => Err(TransportErrorKind::http_error(status, String::new())),
+            Self::Timeout => Err(TransportErrorKind::custom(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "request timed out",
+            ))),
+        }
+    }
+}
+
+/// An in-memory [ExecutionRpc] backend answering every JSON-RPC method with the responses
+/// scripted for it, without any network. Counts the calls made to every method.
+///
+/// The responses of a method are returned in the order they were scripted, and the last
+/// one is repeated. Methods without a scripted response fail with a "method not found"
+/// JSON-RPC error.
+#[derive(Debug, Default)]
+pub(crate) struct MockRpc {
+    responses: Mutex<HashMap<String, VecDeque<MockResponse>>>,
+    calls: Mutex<HashMap<String, usize>>,
+}
+
+impl MockRpc {
+    /// Create a new mock without any scripted response.
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Script the next response of the given JSON-RPC method.
+    pub(crate) fn respond(&self, method: &str, response: MockResponse) -> &Self {
+        self.responses
+            .lock()
+            .entry(method.to_string())
+            .or_default()
+            .push_back(response);
+        self
+    }
+
+    /// Returns the number of calls made to the given JSON-RPC method.
+    pub(crate) fn call_count(&self, method: &str) -> usize {
+        self.calls.lock().get(method).copied().unwrap_or_default()
+    }
+
+    fn next<T: DeserializeOwned>(&self, method: &str) -> TransportResult<T> {
+        *self.calls.lock().entry(method.to_string()).or_default() += 1;
+
+        let response = {
+            let mut responses = self.responses.lock();
+            match responses.get_mut(method) {
+                Some(queue) if queue.len() > 1 => queue.pop_front(),
+                Some(queue) => queue.front().cloned(),
+                None => None,
+            }
+        };
+
+        response
+            .unwrap_or_else(|| MockResponse::Rpc(-32601, format!("method {method} not found")))
+            .into_result()
+    }
+}
+
+#[async_trait::async_trait]
+impl ExecutionRpc for MockRpc {
+    async fn get_chain_id(&self) -> TransportResult<u64> {
+        self.next::<U64>("eth_chainId").map(|id| id.to())
+    }
+
+    async fn get_head(&self) -> TransportResult<u64> {
+        self.next::<U64>("eth_blockNumber")
+            .map(|number| number.to())
+    }
+
+    async fn get_block_hash(&self, _block: BlockNumberOrTag) -> TransportResult<BlockHashes> {
+        self.next("eth_getBlockByNumber")
+    }
+
+    async fn get_account_state(
+        &self,
+        _address: Address,
+        _block: BlockNumberOrTag,
+    ) -> TransportResult<AccountState> {
+        let balance = self.next::<U256>("eth_getBalance");
+        let nonce = self.next::<U64>("eth_getTransactionCount");
+
+        Ok(AccountState {
+            balance: balance?,
+            transaction_count: nonce?.to(),
+            code_hash: None,
+            storage_root: None,
+        })
+    }
+
+    async fn get_code(
+        &self,
+        _address: Address,
+        _block: BlockNumberOrTag,
+    ) -> TransportResult<alloy_primitives::Bytes> {
+        self.next("eth_getCode")
+    }
+
+    async fn get_storage_at(
+        &self,
+        _address: Address,
+        _slot: B256,
+        _block: BlockNumberOrTag,
+    ) -> TransportResult<B256> {
+        self.next("eth_getStorageAt")
+    }
+
+    async fn get_proof(
+        &self,
+        _address: Address,
+        _storage_keys: Vec<B256>,
+        _block: BlockNumberOrTag,
+    ) -> TransportResult<EIP1186AccountProofResponse> {
+        self.next("eth_getProof")
+    }
+
+    async fn debug_trace_call(
+        &self,
+        _tx: TransactionRequest,
+        _block: BlockNumberOrTag,
+        _opts: Option<GethDebugTracingCallOptions>,
+    ) -> TransportResult<GethTrace> {
+        self.next("debug_traceCall")
+    }
+}
+
 /// Create a default transaction template to use for tests
 pub(crate) fn default_test_transaction(sender: Address, nonce: Option<u64>) -> TransactionRequest {
     TransactionRequest::default()
@@ -56,6 +56,8 @@ async fn main() -> Result<()> {
         let mut data = Vec::new();
         data.extend_from_slice(&slot_number.to_le_bytes());
         data.extend_from_slice(hex::decode(tx_hash.trim_start_matches("0x"))?.as_slice());
+        // The committed tip (little-endian U256). No tip is paid to the proposer here.
+        data.extend_from_slice(&[0u8; 32]);
         H256::from(ethers::utils::keccak256(data))
     };
 